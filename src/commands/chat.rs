@@ -0,0 +1,452 @@
+// Interactive chat: reads user lines from stdin, keeps the conversation across turns and
+// prints each reply. Lines starting with "/" are commands (/reset, /system, /exit); EOF
+// also ends the session. With --tools every turn runs in an AgentLoop; only the user line
+// and the final answer are kept in the history, not the intermediate tool-call messages.
+// A failed turn is reported and dropped from the history so the user can retry; Ctrl-C
+// ends the session.
+
+use clap::Args;
+use serde::Serialize;
+use std::io::{IsTerminal, Write};
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
+
+use super::super::trickery::r#loop::{AgentLoop, LoopConfig, LoopError, DEFAULT_MAX_ITERATIONS};
+use super::generate::{parse_reasoning_level, parse_temperature};
+use super::{CommandExec, CommandResult};
+use crate::error::{print_error, print_warning};
+use crate::provider::{CompletionRequest, Message, Provider, ProviderError, ReasoningLevel};
+use crate::tools::{ToolConfig, ToolRegistry};
+
+/// Shown for an unknown command
+const CHAT_COMMANDS: &str = "/reset, /system <text>, /exit";
+
+/// Messages of a finished chat session (system prompt first)
+#[derive(Serialize, Debug)]
+#[serde(transparent)]
+pub struct ChatResult {
+    pub messages: Vec<Message>,
+}
+
+impl CommandResult<ChatResult> for ChatResult {
+    fn get_result(&self) -> &ChatResult {
+        self
+    }
+}
+
+#[derive(Args)]
+pub struct ChatArgs {
+    /// System prompt for the session (change it with /system)
+    #[arg(long)]
+    system: Option<String>,
+
+    /// Model to use (e.g., gpt-5.2, gpt-5-mini, o1, o3-mini)
+    #[arg(short, long)]
+    model: Option<String>,
+
+    /// Reasoning level for reasoning models (o-series, gpt-5): low, medium, high
+    #[arg(short, long, value_parser = parse_reasoning_level)]
+    reasoning: Option<ReasoningLevel>,
+
+    /// Maximum tokens in each reply (must be at least 1)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    max_tokens: Option<u32>,
+
+    /// Sampling temperature (0.0-2.0), ignored for reasoning models
+    #[arg(long, value_parser = parse_temperature)]
+    temperature: Option<f32>,
+
+    /// End-user identifier sent with requests for abuse monitoring (OpenAI `user`)
+    #[arg(long, value_name = "ID", env = "TRICKERY_USER")]
+    user: Option<String>,
+
+    /// Let the model call tools; without a name, all built-in tools (can be repeated)
+    #[arg(long = "tools", value_name = "NAME", num_args = 0..=1, value_delimiter = ',')]
+    tools: Option<Vec<String>>,
+
+    /// Also offer an opt-in tool: fetch_url, get_weather, run_shell, validate_json, web_search (can be repeated)
+    #[arg(
+        long = "allow-tool",
+        value_name = "NAME",
+        value_delimiter = ',',
+        requires = "tools"
+    )]
+    allow_tool: Vec<String>,
+
+    /// Maximum agent loop iterations per turn when tools are enabled (default: 10)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), requires = "tools")]
+    max_iterations: Option<u32>,
+}
+
+/// One line typed by the user
+#[derive(Debug, PartialEq)]
+enum ChatInput {
+    /// Text to send to the model
+    Message(String),
+    /// Forget the conversation, keeping the system prompt
+    Reset,
+    /// Replace the system prompt; empty text removes it
+    System(String),
+    Exit,
+    /// A line starting with "/" that is not a known command
+    Unknown(String),
+    Empty,
+}
+
+fn parse_chat_input(line: &str) -> ChatInput {
+    let line = line.trim();
+    let Some(command) = line.strip_prefix('/') else {
+        return if line.is_empty() {
+            ChatInput::Empty
+        } else {
+            ChatInput::Message(line.to_string())
+        };
+    };
+    let (name, rest) = command
+        .split_once(char::is_whitespace)
+        .unwrap_or((command, ""));
+    match name {
+        "reset" => ChatInput::Reset,
+        "system" => ChatInput::System(rest.trim().to_string()),
+        "exit" | "quit" => ChatInput::Exit,
+        _ => ChatInput::Unknown(line.to_string()),
+    }
+}
+
+/// Conversation state of a chat session
+#[derive(Debug, Default)]
+struct ChatSession {
+    system: Option<String>,
+    /// User and assistant messages, oldest first
+    history: Vec<Message>,
+}
+
+impl ChatSession {
+    fn new(system: Option<String>) -> Self {
+        Self {
+            system,
+            history: Vec::new(),
+        }
+    }
+
+    /// Messages to send: the system prompt, then the history
+    fn messages(&self) -> Vec<Message> {
+        self.system
+            .iter()
+            .map(Message::system)
+            .chain(self.history.iter().cloned())
+            .collect()
+    }
+}
+
+/// Runs chat turns against a provider
+struct Chat<'a> {
+    provider: &'a dyn Provider,
+    registry: Option<&'a ToolRegistry>,
+    /// Request settings; messages are filled in per turn
+    template: CompletionRequest,
+    loop_config: LoopConfig,
+    session: ChatSession,
+}
+
+impl Chat<'_> {
+    /// Send `text` with the conversation so far; on success both are added to the history
+    async fn reply(&mut self, text: String) -> Result<String, ProviderError> {
+        self.session.history.push(Message::user(text));
+        let mut request = self.template.clone();
+        request.messages = self.session.messages();
+
+        let result = match self.registry {
+            Some(registry) => AgentLoop::new(self.provider, registry, self.loop_config.clone())
+                .run(request)
+                .await
+                .map(|result| result.content)
+                .map_err(|e| match e {
+                    LoopError::Provider(e) => e,
+                    e => ProviderError::InvalidResponse(e.to_string()),
+                }),
+            None => self
+                .provider
+                .complete_cancellable(request, &self.loop_config.cancellation)
+                .await
+                .and_then(|response| {
+                    response.check_refusal()?;
+                    Ok(response.content.unwrap_or_default())
+                }),
+        };
+        match result {
+            Ok(content) => {
+                self.session.history.push(Message::assistant(&content));
+                Ok(content)
+            }
+            Err(e) => {
+                self.session.history.pop();
+                Err(e)
+            }
+        }
+    }
+
+    /// Read lines from `input` until /exit or EOF, writing replies to `out`.
+    /// With `prompt`, "> " is written to stderr before each line.
+    async fn run(
+        &mut self,
+        input: impl AsyncBufRead + Unpin,
+        out: &mut dyn Write,
+        prompt: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut lines = input.lines();
+        loop {
+            if prompt {
+                eprint!("> ");
+            }
+            let Some(line) = lines.next_line().await? else {
+                break;
+            };
+            match parse_chat_input(&line) {
+                ChatInput::Message(text) => match self.reply(text).await {
+                    Ok(content) => writeln!(out, "{}", content)?,
+                    Err(ProviderError::Cancelled) => return Err(ProviderError::Cancelled.into()),
+                    Err(e) => print_error(&e),
+                },
+                ChatInput::Reset => self.session.history.clear(),
+                ChatInput::System(text) => {
+                    self.session.system = Some(text).filter(|t| !t.is_empty());
+                }
+                ChatInput::Exit => break,
+                ChatInput::Unknown(line) => print_warning(&format!(
+                    "Unknown command '{}' (available: {})",
+                    line, CHAT_COMMANDS
+                )),
+                ChatInput::Empty => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CommandExec<ChatResult> for ChatArgs {
+    async fn exec(
+        &self,
+        context: &impl super::CommandExecutionContext,
+    ) -> Result<Box<dyn CommandResult<ChatResult>>, Box<dyn std::error::Error>> {
+        let cli = context.get_cli();
+        let registry = match self.tools {
+            Some(ref names) => {
+                let config = ToolConfig {
+                    timeout: cli.timeout.map(Duration::from_secs),
+                    ..Default::default()
+                };
+                let mut registry = ToolRegistry::with_builtins_config(&config).select(names)?;
+                for name in &self.allow_tool {
+                    registry.allow(name, &config)?;
+                }
+                Some(registry)
+            }
+            None => None,
+        };
+
+        let mut template = CompletionRequest::new(Vec::new());
+        if let Some(model) = self.model.clone().or(cli.config.model.clone()) {
+            template = template.with_model(model);
+        }
+        if let Some(level) = self.reasoning.or(cli.config.reasoning) {
+            template = template.with_reasoning_level(level);
+        }
+        if let Some(max_tokens) = self.max_tokens.or(cli.config.max_tokens) {
+            template = template.with_max_tokens(max_tokens);
+        }
+        if let Some(temperature) = self.temperature {
+            template = template.with_temperature(temperature);
+        }
+        if let Some(ref user) = self.user {
+            template = template.with_user(user.clone());
+        }
+
+        let provider = cli.build_provider()?;
+        let mut chat = Chat {
+            provider: provider.as_ref(),
+            registry: registry.as_ref(),
+            template,
+            loop_config: LoopConfig {
+                max_iterations: self.max_iterations.unwrap_or(DEFAULT_MAX_ITERATIONS),
+                cancellation: cli.cancellation.clone(),
+                tools_disabled: cli.tools_disabled(),
+                ..LoopConfig::default()
+            },
+            session: ChatSession::new(self.system.clone()),
+        };
+
+        // Replies go to stdout, unless stdout is reserved for the structured transcript
+        let prompt = std::io::stdin().is_terminal();
+        let input = BufReader::new(tokio::io::stdin());
+        if cli.is_interactive() {
+            chat.run(input, &mut std::io::stdout(), prompt).await?;
+        } else {
+            chat.run(input, &mut std::io::stderr(), prompt).await?;
+        }
+
+        Ok(Box::from(ChatResult {
+            messages: chat.session.messages(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::Role;
+    use crate::trickery::r#loop::tests::{text_response, tool_call_response, MockProvider};
+
+    fn chat<'a>(
+        provider: &'a MockProvider,
+        registry: Option<&'a ToolRegistry>,
+        system: Option<&str>,
+    ) -> Chat<'a> {
+        Chat {
+            provider,
+            registry,
+            template: CompletionRequest::new(Vec::new()).with_model("gpt-4.1"),
+            loop_config: LoopConfig::default(),
+            session: ChatSession::new(system.map(str::to_string)),
+        }
+    }
+
+    fn texts(messages: &[Message]) -> Vec<(Role, String)> {
+        messages
+            .iter()
+            .map(|m| (m.role.clone(), m.text_content().unwrap_or_default()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_chat_input() {
+        assert_eq!(
+            parse_chat_input("Hello\n"),
+            ChatInput::Message("Hello".into())
+        );
+        assert_eq!(parse_chat_input("  "), ChatInput::Empty);
+        assert_eq!(parse_chat_input("/reset"), ChatInput::Reset);
+        assert_eq!(parse_chat_input("/exit"), ChatInput::Exit);
+        assert_eq!(parse_chat_input("/quit"), ChatInput::Exit);
+        assert_eq!(
+            parse_chat_input("/system  Be brief. "),
+            ChatInput::System("Be brief.".into())
+        );
+        assert_eq!(
+            parse_chat_input("/system"),
+            ChatInput::System(String::new())
+        );
+        assert_eq!(
+            parse_chat_input("/resets"),
+            ChatInput::Unknown("/resets".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_history_accumulates_across_turns() {
+        let provider = MockProvider::new(vec![text_response("Hi Ada"), text_response("Ada")]);
+        let mut chat = chat(&provider, None, Some("Be brief."));
+        let mut out = Vec::new();
+        chat.run(&b"I'm Ada\n\nWho am I?\n"[..], &mut out, false)
+            .await
+            .unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(), "Hi Ada\nAda\n");
+        let requests = provider.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            texts(&requests[1].messages),
+            vec![
+                (Role::System, "Be brief.".to_string()),
+                (Role::User, "I'm Ada".to_string()),
+                (Role::Assistant, "Hi Ada".to_string()),
+                (Role::User, "Who am I?".to_string()),
+            ]
+        );
+        assert_eq!(requests[1].model.as_deref(), Some("gpt-4.1"));
+        assert_eq!(chat.session.history.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_reset_system_and_exit() {
+        let provider = MockProvider::new(vec![text_response("One"), text_response("Two")]);
+        let mut chat = chat(&provider, None, Some("Be brief."));
+        let input = "First\n/reset\n/system Talk like a pirate\nSecond\n/exit\nNever sent\n";
+        chat.run(input.as_bytes(), &mut Vec::new(), false)
+            .await
+            .unwrap();
+
+        let requests = provider.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            texts(&requests[1].messages),
+            vec![
+                (Role::System, "Talk like a pirate".to_string()),
+                (Role::User, "Second".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_turn_is_dropped() {
+        // The second turn has no scripted response and fails
+        let provider = MockProvider::new(vec![text_response("One")]);
+        let mut chat = chat(&provider, None, None);
+        chat.run(&b"First\nSecond\n"[..], &mut Vec::new(), false)
+            .await
+            .unwrap();
+        assert_eq!(
+            texts(&chat.session.history),
+            vec![
+                (Role::User, "First".to_string()),
+                (Role::Assistant, "One".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tools_run_in_agent_loop() {
+        let provider = MockProvider::new(vec![
+            tool_call_response(&[("call_1", "calculate", r#"{"expression": "6*7"}"#)]),
+            text_response("42"),
+        ]);
+        let registry = ToolRegistry::with_builtins();
+        let mut chat = chat(&provider, Some(&registry), None);
+        let mut out = Vec::new();
+        chat.run(&b"What is 6*7?\n"[..], &mut out, false)
+            .await
+            .unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(), "42\n");
+        assert!(provider.requests.lock().unwrap()[0].tools.is_some());
+        // Only the question and the final answer are kept
+        assert_eq!(chat.session.history.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_chat_flags() {
+        use clap::Parser;
+        let cli = crate::Cli::try_parse_from([
+            "trickery",
+            "chat",
+            "--system",
+            "Be brief.",
+            "--tools",
+            "--allow-tool",
+            "run_shell",
+            "--max-iterations",
+            "3",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(crate::Commands::Chat(args)) => {
+                assert_eq!(args.system.as_deref(), Some("Be brief."));
+                assert_eq!(args.tools, Some(Vec::new()));
+                assert_eq!(args.allow_tool, vec!["run_shell".to_string()]);
+                assert_eq!(args.max_iterations, Some(3));
+            }
+            _ => panic!("Expected Chat command"),
+        }
+        assert!(crate::Cli::try_parse_from(["trickery", "chat", "--max-iterations", "3"]).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+// doctor: checks that the selected provider is configured and reachable.
+// Two checks run in order: configuration (the provider can be built, so its API key and
+// required variables are set) and completion (a minimal request gets an answer). The cache
+// is bypassed so the backend is really called. Failures carry the error formatter's text,
+// including its hints; any failed check makes the exit code 1.
+
+use clap::Args;
+use serde::Serialize;
+use std::time::Instant;
+
+use super::{CommandExec, CommandResult};
+use crate::error::format_error;
+use crate::provider::{CompletionRequest, Message};
+
+/// Prompt of the connectivity check; any answer counts
+const PING_PROMPT: &str = "Reply with OK.";
+/// Enough for a short answer; reasoning models may use it all and still count as reachable
+const PING_MAX_TOKENS: u32 = 16;
+
+/// Outcome of one check
+#[derive(Serialize, Debug, PartialEq)]
+pub struct DoctorCheck {
+    pub name: String,
+    pub ok: bool,
+    /// What was found, or the formatted error
+    pub detail: String,
+}
+
+/// Resolved settings and the outcome of every check
+#[derive(Serialize, Debug)]
+pub struct DoctorResult {
+    /// "ok" when every check passed, otherwise "error"
+    pub status: String,
+    pub provider: String,
+    pub model: Option<String>,
+    pub base_url: Option<String>,
+    pub checks: Vec<DoctorCheck>,
+}
+
+impl CommandResult<DoctorResult> for DoctorResult {
+    fn get_result(&self) -> &DoctorResult {
+        self
+    }
+
+    fn failed(&self) -> bool {
+        self.status != "ok"
+    }
+}
+
+#[derive(Args)]
+pub struct DoctorArgs {
+    /// Model for the test completion (default: config file model, then the provider default)
+    #[arg(short, long)]
+    model: Option<String>,
+}
+
+fn check(name: &str, outcome: Result<String, String>) -> DoctorCheck {
+    let (ok, detail) = match outcome {
+        Ok(detail) => (true, detail),
+        Err(detail) => (false, detail),
+    };
+    DoctorCheck {
+        name: name.to_string(),
+        ok,
+        detail,
+    }
+}
+
+/// Settings, then one line per check; failed checks are followed by the formatted error
+fn format_report(result: &DoctorResult) -> String {
+    let mut lines = vec![
+        format!("Provider: {}", result.provider),
+        format!(
+            "Model:    {}",
+            result.model.as_deref().unwrap_or("(unknown)")
+        ),
+        format!(
+            "Base URL: {}",
+            result.base_url.as_deref().unwrap_or("(unknown)")
+        ),
+        String::new(),
+    ];
+    for check in &result.checks {
+        if check.ok {
+            lines.push(format!("[ok]     {}: {}", check.name, check.detail));
+        } else {
+            lines.push(format!("[failed] {}\n{}", check.name, check.detail));
+        }
+    }
+    lines.join("\n")
+}
+
+impl CommandExec<DoctorResult> for DoctorArgs {
+    async fn exec(
+        &self,
+        context: &impl super::CommandExecutionContext,
+    ) -> Result<Box<dyn CommandResult<DoctorResult>>, Box<dyn std::error::Error>> {
+        let cli = context.get_cli();
+        let mut result = DoctorResult {
+            status: "ok".to_string(),
+            provider: cli.provider_name().to_string(),
+            model: self.model.clone().or(cli.config.model.clone()),
+            base_url: None,
+            checks: Vec::new(),
+        };
+
+        match cli.build_provider_with_cache(None) {
+            Err(e) => result
+                .checks
+                .push(check("configuration", Err(format_error(&e)))),
+            Ok(provider) => {
+                result.base_url = provider.base_url().map(str::to_string);
+                if result.model.is_none() {
+                    result.model = provider.default_model().map(str::to_string);
+                }
+                result.checks.push(check(
+                    "configuration",
+                    Ok("provider settings and credentials found".to_string()),
+                ));
+
+                let mut request = CompletionRequest::new(vec![Message::user(PING_PROMPT)])
+                    .with_max_tokens(PING_MAX_TOKENS);
+                if let Some(ref model) = result.model {
+                    request = request.with_model(model.clone());
+                }
+                let started = Instant::now();
+                let outcome = provider
+                    .complete(request)
+                    .await
+                    .map(|_| format!("answered in {} ms", started.elapsed().as_millis()))
+                    .map_err(|e| format_error(&e));
+                result.checks.push(check("completion", outcome));
+            }
+        }
+        if result.checks.iter().any(|c| !c.ok) {
+            result.status = "error".to_string();
+        }
+
+        if cli.is_interactive() {
+            println!("{}", format_report(&result));
+        }
+        Ok(Box::from(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn doctor_cli(global: &[&str], doctor: &[&str]) -> crate::Cli {
+        let mut argv = vec!["trickery", "-o", "json"];
+        argv.extend_from_slice(global);
+        argv.push("doctor");
+        argv.extend_from_slice(doctor);
+        crate::Cli::try_parse_from(argv).unwrap()
+    }
+
+    async fn run(cli: &crate::Cli) -> Box<dyn CommandResult<DoctorResult>> {
+        let Some(crate::Commands::Doctor(ref args)) = cli.command else {
+            panic!("Expected Doctor command");
+        };
+        args.exec(cli).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_doctor_ok() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({"model": "llama3.2", "max_completion_tokens": 16}),
+            ))
+            .with_body(
+                r#"{"choices": [{"message": {"role": "assistant", "content": "OK"}, "finish_reason": "stop"}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let url = server.url();
+        let cli = doctor_cli(
+            &["--provider", "ollama", "--base-url", &url],
+            &["-m", "llama3.2"],
+        );
+        let result = run(&cli).await;
+        let json = serde_json::to_value(result.get_result()).unwrap();
+
+        assert_eq!(json["status"], "ok", "{}", json);
+        assert_eq!(json["provider"], "ollama");
+        assert_eq!(json["model"], "llama3.2");
+        assert_eq!(json["base_url"], url.as_str());
+        assert_eq!(json["checks"][1]["name"], "completion");
+        assert!(!result.failed());
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_doctor_reports_failures() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/chat/completions")
+            .with_status(404)
+            .with_body(r#"{"error": {"message": "model 'nope' not found"}}"#)
+            .create_async()
+            .await;
+
+        let url = server.url();
+        let result = run(&doctor_cli(
+            &["--provider", "ollama", "--base-url", &url],
+            &[],
+        ))
+        .await;
+        let report = result.get_result();
+        assert_eq!(report.status, "error");
+        assert!(report.checks[0].ok);
+        assert!(!report.checks[1].ok);
+        assert!(
+            report.checks[1].detail.contains("model 'nope' not found"),
+            "{}",
+            report.checks[1].detail
+        );
+        assert!(result.failed());
+
+        let result = run(&doctor_cli(&["--provider", "nonexistent"], &[])).await;
+        let report = result.get_result();
+        assert_eq!(report.checks.len(), 1);
+        assert!(report.checks[0].detail.contains("Unknown Provider"));
+        assert!(format_report(report).contains("[failed] configuration"));
+    }
+}
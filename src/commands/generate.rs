@@ -1,17 +1,55 @@
 use clap::{Args, ValueHint};
+use futures::stream::{self, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::fs::read_to_string;
+use tracing::debug;
 
-use super::super::trickery::generate::{generate_from_template, GenerateConfig};
+use super::super::trickery::context::{load_context, DEFAULT_CONTEXT_MAX_BYTES};
+use super::super::trickery::conversation::{load_conversation, save_transcript};
+use super::super::trickery::front_matter::{parse_front_matter, FrontMatter};
+use super::super::trickery::generate::{
+    build_request, describe_request, generate_candidates, generate_from_template,
+    generate_with_tools, strip_code_fences, GenerateConfig, JsonGuard, LoopProgress,
+    SubstitutionMode,
+};
+use super::super::trickery::include::expand_includes;
+use super::super::trickery::r#loop::{LoopConfig, DEFAULT_MAX_ITERATIONS};
+use super::super::trickery::remote::{fetch_template, is_template_url};
+use super::super::trickery::render::markdown_to_plain;
+use super::super::trickery::vars::{collect_variables, parse_key_val, parse_vars_line};
 use super::{CommandExec, CommandResult};
-use crate::provider::ReasoningLevel;
+use crate::config::Config;
+use crate::error::{format_error, print_warning};
+use crate::provider::{
+    CompletionRequest, Message, Provider, ReasoningLevel, ResponseFormat, TokenLogprob, ToolChoice,
+    Usage, MAX_STOP_SEQUENCES,
+};
+use crate::tools::{ToolConfig, ToolError, ToolRegistry};
 use serde_json::Value;
 use std::collections::HashMap;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GenerateResult {
-    output: String,
+    pub output: String,
+    /// All candidates when more than one was requested with -n; `output` is the first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<String>,
+    /// Tokens spent, when the provider reports them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    /// Backend fingerprint, when the provider reports it (compare across seeded runs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_fingerprint: Option<String>,
+    /// Log probabilities of the tokens of `output`, with --logprobs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<Vec<TokenLogprob>>,
+    /// Provider was not called; `output` is the rendered prompt
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
 }
 
 impl CommandResult<GenerateResult> for GenerateResult {
@@ -20,14 +58,57 @@ impl CommandResult<GenerateResult> for GenerateResult {
     }
 }
 
-fn parse_key_val(s: &str) -> Result<(String, Value), String> {
-    let pos = s
-        .find('=')
-        .ok_or_else(|| format!("invalid KEY=VALUE: no `=` found in `{}`", s))?;
-    Ok((
-        s[..pos].to_string(),
-        Value::String(s[pos + 1..].to_string()),
-    ))
+/// Result of the generate command: one result, or each file's output with --input-dir
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+pub enum GenerateCommandOutput {
+    Single(GenerateResult),
+    /// Output per prompt file, keyed by path relative to the input directory
+    Batch(BTreeMap<String, String>),
+    /// With --continue-on-error: outputs of the files that succeeded and errors of the rest
+    BatchSummary(BatchSummary),
+    /// With --jsonl: counts only, the records were streamed to stdout
+    Jsonl(JsonlSummary),
+}
+
+/// Per-file outcome of a batch run, keyed by path relative to the input directory
+#[derive(Serialize, Debug, Default)]
+pub struct BatchSummary {
+    pub outputs: BTreeMap<String, String>,
+    /// Formatted error of each failed file
+    pub failures: BTreeMap<String, String>,
+}
+
+/// One line of --jsonl output: the generated text, or why the input line failed
+#[derive(Serialize, Debug, PartialEq)]
+pub struct JsonlRecord {
+    /// 1-based line number in the input file
+    pub line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Record counts of a --jsonl run
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct JsonlSummary {
+    pub records: usize,
+    pub failures: usize,
+}
+
+impl CommandResult<GenerateCommandOutput> for GenerateCommandOutput {
+    fn get_result(&self) -> &GenerateCommandOutput {
+        self
+    }
+
+    fn failed(&self) -> bool {
+        match self {
+            GenerateCommandOutput::BatchSummary(summary) => !summary.failures.is_empty(),
+            GenerateCommandOutput::Jsonl(summary) => summary.failures > 0,
+            _ => false,
+        }
+    }
 }
 
 #[derive(Args)]
@@ -44,24 +125,239 @@ pub struct GenerateArgs {
     #[arg(short, long = "input", value_name = "INPUT", value_hint = ValueHint::FilePath)]
     pub input_option: Option<String>,
 
+    /// Input prompt as direct text (never treated as a file path)
+    #[arg(short, long, conflicts_with_all = ["input_positional", "input_option"])]
+    pub text: Option<String>,
+
+    /// Run every *.md and *.txt prompt file in this directory (outputs keyed by file path)
+    #[arg(
+        long,
+        value_name = "DIR",
+        value_hint = ValueHint::DirPath,
+        conflicts_with_all = [
+            "input_positional",
+            "input_option",
+            "text",
+            "conversation",
+            "continue_from",
+            "save_transcript",
+            "tools",
+            "count"
+        ]
+    )]
+    pub input_dir: Option<PathBuf>,
+
+    /// Generate once per line of this JSON Lines file (each line an object of variables),
+    /// printing one JSON object per line as results arrive
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        conflicts_with_all = [
+            "input_dir",
+            "conversation",
+            "continue_from",
+            "save_transcript",
+            "tools",
+            "count"
+        ]
+    )]
+    pub jsonl: Option<PathBuf>,
+
+    /// With --input-dir, also run prompt files in subdirectories
+    #[arg(long, requires = "input_dir")]
+    recursive: bool,
+
+    /// With --input-dir, keep going when a file fails and report every failure at the end
+    #[arg(long, requires = "input_dir")]
+    continue_on_error: bool,
+
+    /// With --input-dir, number of files generated at the same time (default: 4)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), requires = "input_dir")]
+    concurrency: Option<u32>,
+
     /// Variables to be used in prompt
     #[arg(short, long="var", value_parser = parse_key_val, number_of_values = 1)]
     pub vars: Vec<(String, Value)>,
 
+    /// JSON or YAML file with variables (overridden by --var)
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub vars_file: Option<PathBuf>,
+
+    /// System prompt sent before the templated prompt
+    #[arg(long, conflicts_with = "system_file")]
+    system: Option<String>,
+
+    /// File containing the system prompt
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    system_file: Option<PathBuf>,
+
+    /// JSON file with prior messages ([{"role": ..., "content": ...}]) sent before the prompt
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    conversation: Option<PathBuf>,
+
+    /// Continue a transcript saved with --save-transcript: its messages are sent verbatim before the prompt
+    #[arg(
+        long = "continue",
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        conflicts_with_all = ["conversation", "system", "system_file"]
+    )]
+    continue_from: Option<PathBuf>,
+
+    /// After the run, write all messages including the reply to FILE as JSON (for --continue)
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, conflicts_with = "count")]
+    save_transcript: Option<PathBuf>,
+
+    /// Fail when a template variable has no value and no default
+    #[arg(long)]
+    strict_vars: bool,
+
     /// Model to use (e.g., gpt-5.2, gpt-5-mini, o1, o3-mini)
     #[arg(short, long)]
     model: Option<String>,
 
-    /// Reasoning level for o1/o3 models: low, medium, high
+    /// Reasoning level for reasoning models (o-series, gpt-5): low, medium, high
     #[arg(short, long, value_parser = parse_reasoning_level)]
     reasoning: Option<ReasoningLevel>,
 
-    /// Maximum tokens in response
-    #[arg(long)]
+    /// Maximum tokens in response (must be at least 1)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
     max_tokens: Option<u32>,
 
-    /// Image files or URLs to include in the prompt (can be specified multiple times)
+    /// Sampling temperature (0.0-2.0), ignored for reasoning models
+    #[arg(long, value_parser = parse_temperature)]
+    temperature: Option<f32>,
+
+    /// Number of candidate completions to generate (best-of-n, 1-10)
+    #[arg(
+        short = 'n',
+        long,
+        value_parser = clap::value_parser!(u32).range(1..=10),
+        conflicts_with = "tools"
+    )]
+    count: Option<u32>,
+
+    /// Stop generating at this sequence (can be repeated, up to 4)
+    #[arg(long, value_name = "SEQ")]
+    stop: Vec<String>,
+
+    /// Seed for reproducible sampling (best effort, provider dependent)
+    #[arg(long)]
+    seed: Option<i64>,
+
+    /// End-user identifier sent with requests for abuse monitoring (OpenAI `user`)
+    #[arg(long, value_name = "ID", env = "TRICKERY_USER")]
+    user: Option<String>,
+
+    /// Penalize tokens by how often they already appeared (-2.0 to 2.0), ignored for reasoning models
+    #[arg(long, value_name = "N", allow_negative_numbers = true, value_parser = parse_penalty)]
+    frequency_penalty: Option<f32>,
+
+    /// Penalize tokens that already appeared at all (-2.0 to 2.0), ignored for reasoning models
+    #[arg(long, value_name = "N", allow_negative_numbers = true, value_parser = parse_penalty)]
+    presence_penalty: Option<f32>,
+
+    /// Bias for a token ID, -100 (ban) to 100 (force), as TOKEN_ID=BIAS (can be repeated)
+    #[arg(long, value_name = "TOKEN_ID=BIAS", value_parser = parse_logit_bias)]
+    logit_bias: Vec<(u32, f32)>,
+
+    /// Remove a code fence (```lang ... ```) wrapping the whole output, then trim it
     #[arg(long)]
+    strip_fences: bool,
+
+    /// Output rendering: markdown or raw (as returned), plain (markdown flattened to plain text)
+    #[arg(long, value_enum, default_value = "markdown")]
+    render: Render,
+
+    /// Include the log probability of each output token in JSON/YAML results
+    #[arg(long, conflicts_with = "tools")]
+    logprobs: bool,
+
+    /// With --logprobs, also return the N most likely alternatives per token (0-20)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(0..=20), requires = "logprobs")]
+    top_logprobs: Option<u8>,
+
+    /// Require a JSON object response
+    #[arg(long, conflicts_with = "json_schema")]
+    json_object: bool,
+
+    /// Require a JSON response conforming to the JSON Schema in this file
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    json_schema: Option<String>,
+
+    /// Fail unless the output (after --strip-fences) parses in this format: json
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    expect: Option<Expect>,
+
+    /// With --expect, on a parse error ask the model once more with the error before failing
+    #[arg(long, requires = "expect")]
+    expect_retry: bool,
+
+    /// Let the model call tools in an agent loop; without a name, all built-in tools (can be repeated)
+    #[arg(long = "tools", value_name = "NAME", num_args = 0..=1, value_delimiter = ',')]
+    tools: Option<Vec<String>>,
+
+    /// Also offer an opt-in tool: fetch_url, get_weather, run_shell, validate_json, web_search (can be repeated)
+    #[arg(
+        long = "allow-tool",
+        value_name = "NAME",
+        value_delimiter = ',',
+        requires = "tools"
+    )]
+    allow_tool: Vec<String>,
+
+    /// With --allow-tool run_shell, only let it run these programs (can be repeated)
+    #[arg(
+        long = "allow-command",
+        value_name = "PROGRAM",
+        value_delimiter = ',',
+        requires = "allow_tool"
+    )]
+    allow_command: Vec<String>,
+
+    /// Tool use: auto, none, required, or a tool name to force that call (first round only)
+    #[arg(long, value_name = "CHOICE", value_parser = parse_tool_choice, requires = "tools")]
+    tool_choice: Option<ToolChoice>,
+
+    /// Maximum agent loop iterations when tools are enabled (default: 10)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), requires = "tools")]
+    max_iterations: Option<u32>,
+
+    /// Stop the agent loop once this many tokens have been used in total
+    #[arg(long, value_name = "N", requires = "tools")]
+    token_budget: Option<u32>,
+
+    /// Keep the agent loop history under N characters by dropping the oldest messages
+    #[arg(long, value_name = "CHARS", value_parser = clap::value_parser!(u64).range(1..), requires = "tools")]
+    context_limit: Option<u64>,
+
+    /// Fail when --max-iterations runs out instead of returning the partial answer
+    #[arg(long, requires = "tools")]
+    fail_on_max_iterations: bool,
+
+    /// Default time zone for the current_time tool: UTC, local or an offset like +02:00
+    #[arg(long, value_name = "TZ", requires = "tools")]
+    timezone: Option<String>,
+
+    /// Default strftime format for the current_time tool (default: RFC 3339)
+    #[arg(long, value_name = "FORMAT", requires = "tools")]
+    time_format: Option<String>,
+
+    /// Write each executed tool call to stderr as a JSON line
+    #[arg(long, requires = "tools")]
+    trace: bool,
+
+    /// Print the answer as it arrives and each tool call as the model makes it (terminal output only)
+    #[arg(long, requires = "tools", conflicts_with_all = ["strip_fences", "render"])]
+    stream: bool,
+
+    /// Text file appended to the prompt in a delimited block headed by its name (can be repeated)
+    #[arg(long, value_name = "PATH", value_hint = ValueHint::FilePath)]
+    context: Vec<PathBuf>,
+
+    /// Image files or URLs to include in the prompt (can be specified multiple times)
+    #[arg(long, visible_alias = "attach", value_name = "PATH|URL", value_hint = ValueHint::FilePath)]
     image: Vec<String>,
 
     /// Image detail level: auto, low, high (default: auto)
@@ -69,21 +365,404 @@ pub struct GenerateArgs {
     image_detail: String,
 }
 
-fn parse_reasoning_level(s: &str) -> Result<ReasoningLevel, String> {
+/// Stop sequences for the request: none, or 1 to MAX_STOP_SEQUENCES non-empty sequences
+fn stop_sequences(stop: &[String]) -> Result<Option<Vec<String>>, String> {
+    if stop.len() > MAX_STOP_SEQUENCES {
+        return Err(format!(
+            "Too many --stop sequences: {}. Use at most {}",
+            stop.len(),
+            MAX_STOP_SEQUENCES
+        ));
+    }
+    if stop.iter().any(String::is_empty) {
+        return Err("--stop sequences must not be empty".to_string());
+    }
+    Ok(Some(stop.to_vec()).filter(|s| !s.is_empty()))
+}
+
+/// Separates candidates printed in interactive mode with -n
+const CANDIDATE_DELIMITER: &str = "\n---\n";
+
+pub(super) fn parse_reasoning_level(s: &str) -> Result<ReasoningLevel, String> {
     s.parse()
 }
 
-/// Resolve input to template content.
-/// If input exists as a file, read from file; otherwise treat as direct text.
-async fn resolve_input(input: &str) -> Result<String, Box<dyn std::error::Error>> {
+pub(super) fn parse_temperature(s: &str) -> Result<f32, String> {
+    let value: f32 = s
+        .parse()
+        .map_err(|_| format!("Invalid temperature: {s}. Use a number from 0.0 to 2.0"))?;
+    if !(0.0..=2.0).contains(&value) {
+        return Err(format!(
+            "Temperature {s} is out of range. Use a number from 0.0 to 2.0"
+        ));
+    }
+    Ok(value)
+}
+
+fn parse_penalty(s: &str) -> Result<f32, String> {
+    let value: f32 = s
+        .parse()
+        .map_err(|_| format!("Invalid penalty: {s}. Use a number from -2.0 to 2.0"))?;
+    if !(-2.0..=2.0).contains(&value) {
+        return Err(format!(
+            "Penalty {s} is out of range. Use a number from -2.0 to 2.0"
+        ));
+    }
+    Ok(value)
+}
+
+/// Parse a `--logit-bias TOKEN_ID=BIAS` flag
+fn parse_logit_bias(s: &str) -> Result<(u32, f32), String> {
+    let (token, bias) = parse_key_val(s)?;
+    let token: u32 = token
+        .parse()
+        .map_err(|_| format!("Invalid token ID '{}' in `{}`", token, s))?;
+    let bias = bias
+        .as_f64()
+        .ok_or_else(|| format!("Invalid bias in `{}`. Use a number from -100 to 100", s))?;
+    if !(-100.0..=100.0).contains(&bias) {
+        return Err(format!(
+            "Bias {} is out of range. Use a number from -100 to 100",
+            bias
+        ));
+    }
+    Ok((token, bias as f32))
+}
+
+fn parse_tool_choice(s: &str) -> Result<ToolChoice, String> {
+    match s {
+        "auto" => Ok(ToolChoice::Auto),
+        "none" => Ok(ToolChoice::None),
+        "required" => Ok(ToolChoice::Required),
+        "" => Err("Tool choice must not be empty".to_string()),
+        name => Ok(ToolChoice::Function(name.to_string())),
+    }
+}
+
+/// Resolve input to template content with includes expanded.
+/// An http(s) URL is fetched; if input exists as a file, read from file; otherwise treat as
+/// direct text. `timeout` is the global --timeout for the fetch.
+async fn resolve_input(
+    input: &str,
+    timeout: Option<Duration>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if is_template_url(input) {
+        return fetch_template(input, timeout).await;
+    }
     let path = Path::new(input);
     if path.exists() {
-        read_to_string(path)
+        let content = read_to_string(path)
             .await
-            .map_err(|e| format!("Failed to read input file '{}': {}", path.display(), e).into())
+            .map_err(|e| format!("Failed to read input file '{}': {}", path.display(), e))?;
+        debug!(path = %path.display(), bytes = content.len(), "loaded template file");
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        Ok(expand_includes(&content, base_dir)?)
     } else {
-        Ok(input.to_string())
+        debug!(bytes = input.len(), "using input as template text");
+        Ok(expand_includes(input, Path::new(""))?)
+    }
+}
+
+/// Where the prompt template comes from
+#[derive(Debug, PartialEq)]
+enum InputSource<'a> {
+    /// Positional or -i value: file path or direct text (auto-detected)
+    Input(&'a str),
+    /// --text value: always direct text
+    Text(&'a str),
+    /// Piped stdin
+    Stdin,
+}
+
+/// Select input source. Precedence: positional/-i, then --text, then piped stdin.
+/// Returns None when nothing is given and stdin is a terminal.
+fn select_input_source<'a>(
+    input: Option<&'a String>,
+    text: Option<&'a String>,
+    stdin_is_terminal: bool,
+) -> Option<InputSource<'a>> {
+    match (input, text) {
+        (Some(input), _) => Some(InputSource::Input(input)),
+        (None, Some(text)) => Some(InputSource::Text(text)),
+        (None, None) if !stdin_is_terminal => Some(InputSource::Stdin),
+        (None, None) => None,
+    }
+}
+
+fn read_stdin() -> Result<String, Box<dyn std::error::Error>> {
+    let mut buffer = String::new();
+    std::io::stdin()
+        .read_to_string(&mut buffer)
+        .map_err(|e| format!("Failed to read prompt from stdin: {}", e))?;
+    Ok(buffer)
+}
+
+const INPUT_REQUIRED: &str =
+    "Input required: use positional arg, -i (file path or text), --text, or pipe via stdin";
+
+/// Load JSON Schema file for structured output
+async fn load_json_schema(path: &str) -> Result<Value, Box<dyn std::error::Error>> {
+    let content = read_to_string(path)
+        .await
+        .map_err(|e| format!("Failed to read JSON schema file '{}': {}", path, e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid JSON schema file '{}': {}", path, e).into())
+}
+
+/// Built-in tools narrowed to `names` (all when empty), plus the `allowed` opt-in tools
+fn select_tools(
+    names: &[String],
+    allowed: &[String],
+    config: &ToolConfig,
+) -> Result<ToolRegistry, ToolError> {
+    let mut registry = ToolRegistry::with_builtins_config(config).select(names)?;
+    for name in allowed {
+        registry.allow(name, config)?;
+    }
+    Ok(registry)
+}
+
+/// Text of the request messages, as sent to the provider
+fn rendered_prompt(request: &CompletionRequest) -> String {
+    request
+        .messages
+        .iter()
+        .filter_map(|m| m.text_content())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Render the request without calling the provider; output is the final prompt
+fn dry_run(
+    template: &str,
+    input_variables: &HashMap<String, Value>,
+    config: GenerateConfig,
+    tools: Option<&ToolRegistry>,
+    interactive: bool,
+) -> Result<GenerateResult, Box<dyn std::error::Error>> {
+    let mut request = build_request(template, input_variables, config)?;
+    if let Some(registry) = tools {
+        request = request.with_tools(registry.definitions());
     }
+
+    if interactive {
+        println!("{}", describe_request(&request));
+    }
+
+    Ok(GenerateResult {
+        output: rendered_prompt(&request),
+        outputs: Vec::new(),
+        usage: None,
+        system_fingerprint: None,
+        logprobs: None,
+        dry_run: true,
+    })
+}
+
+/// Default concurrency for --input-dir
+const DEFAULT_CONCURRENCY: u32 = 4;
+
+/// Prompt files (*.md, *.txt) in `dir`, sorted; with `recursive` also in subdirectories
+fn collect_prompt_files(dir: &Path, recursive: bool) -> Result<Vec<PathBuf>, String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read input directory '{}': {}", dir.display(), e))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|e| format!("Failed to read input directory '{}': {}", dir.display(), e))?
+            .path();
+        if path.is_dir() {
+            if recursive {
+                files.extend(collect_prompt_files(&path, true)?);
+            }
+        } else if matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("md") | Some("txt")
+        ) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Key of a prompt file in batch results: its path relative to `dir`, with `/` separators
+fn file_key(dir: &Path, path: &Path) -> String {
+    path.strip_prefix(dir)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Generate one prompt file; without a provider, return the rendered prompt (dry run)
+async fn generate_file(
+    provider: Option<&dyn Provider>,
+    path: &Path,
+    input_variables: &HashMap<String, Value>,
+    config_for: &impl Fn(FrontMatter) -> GenerateConfig,
+) -> Result<(String, Usage), Box<dyn std::error::Error>> {
+    // Files from --input-dir are local; no fetch timeout applies
+    let raw = resolve_input(&path.to_string_lossy(), None).await?;
+    let (front_matter, template) = parse_front_matter(&raw)?;
+    generate_or_render(
+        provider,
+        &template,
+        input_variables,
+        config_for(front_matter),
+    )
+    .await
+}
+
+/// Complete the template; without a provider, return the rendered prompt (dry run)
+async fn generate_or_render(
+    provider: Option<&dyn Provider>,
+    template: &str,
+    input_variables: &HashMap<String, Value>,
+    config: GenerateConfig,
+) -> Result<(String, Usage), Box<dyn std::error::Error>> {
+    match provider {
+        Some(provider) => {
+            let generated =
+                generate_from_template(provider, template, input_variables, config).await?;
+            Ok((generated.content, generated.usage))
+        }
+        None => {
+            let request = build_request(template, input_variables, config)?;
+            Ok((rendered_prompt(&request), Usage::default()))
+        }
+    }
+}
+
+/// Generate every file, at most `concurrency` at a time; outputs are keyed by `file_key`.
+/// Fails with the first failing file, or with `continue_on_error` records it in `failures`.
+async fn generate_files(
+    provider: Option<&dyn Provider>,
+    dir: &Path,
+    files: &[PathBuf],
+    input_variables: &HashMap<String, Value>,
+    config_for: impl Fn(FrontMatter) -> GenerateConfig,
+    concurrency: usize,
+    continue_on_error: bool,
+) -> Result<(BatchSummary, Usage), Box<dyn std::error::Error>> {
+    let results: Vec<_> = stream::iter(files)
+        .map(|path| generate_file(provider, path, input_variables, &config_for))
+        .buffered(concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut summary = BatchSummary::default();
+    let mut usage = Usage::default();
+    for (path, result) in files.iter().zip(results) {
+        let key = file_key(dir, path);
+        match result {
+            Ok((output, file_usage)) => {
+                usage += &file_usage;
+                summary.outputs.insert(key, output);
+            }
+            Err(e) if continue_on_error => {
+                summary.failures.insert(key, format_error(e.as_ref()));
+            }
+            Err(e) => return Err(format!("Failed to generate '{}': {}", key, e).into()),
+        }
+    }
+    Ok((summary, usage))
+}
+
+/// How generated output is rendered before it is printed or returned
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum Render {
+    /// As returned by the model
+    #[default]
+    Markdown,
+    /// Markdown flattened to plain text
+    Plain,
+    /// As returned by the model, for callers that want to say so explicitly
+    Raw,
+}
+
+/// Output format required by --expect
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Expect {
+    /// Parseable JSON
+    Json,
+}
+
+/// Post-processing from --strip-fences and --render; dry runs show the rendered prompt as is
+#[derive(Clone, Copy, Debug, Default)]
+struct OutputFilter {
+    strip_fences: bool,
+    render: Render,
+}
+
+impl OutputFilter {
+    fn apply(&self, output: &str) -> String {
+        let output = if self.strip_fences {
+            strip_code_fences(output)
+        } else {
+            output.to_string()
+        };
+        match self.render {
+            Render::Plain => markdown_to_plain(&output),
+            Render::Markdown | Render::Raw => output,
+        }
+    }
+}
+
+/// Generate once per JSON Lines input line, writing one record per line to `out` as soon as
+/// it is done. Line variables override `input_variables`. Blank lines are skipped; malformed
+/// lines and failed generations become `error` records and the run goes on.
+async fn generate_records(
+    provider: Option<&dyn Provider>,
+    template: &str,
+    input_variables: &HashMap<String, Value>,
+    config: &GenerateConfig,
+    filter: OutputFilter,
+    input: impl BufRead,
+    out: &mut impl Write,
+) -> Result<(JsonlSummary, Usage), Box<dyn std::error::Error>> {
+    let mut summary = JsonlSummary::default();
+    let mut usage = Usage::default();
+    for (index, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = match parse_vars_line(&line) {
+            Ok(line_variables) => {
+                let mut variables = input_variables.clone();
+                variables.extend(line_variables);
+                generate_or_render(provider, template, &variables, config.clone())
+                    .await
+                    .map_err(|e| format_error(e.as_ref()))
+            }
+            Err(e) => Err(e),
+        };
+        let mut record = JsonlRecord {
+            line: index + 1,
+            output: None,
+            error: None,
+        };
+        match result {
+            Ok((output, record_usage)) => {
+                usage += &record_usage;
+                record.output = Some(if provider.is_some() {
+                    filter.apply(&output)
+                } else {
+                    output
+                });
+            }
+            Err(error) => {
+                summary.failures += 1;
+                record.error = Some(format!("line {}: {}", record.line, error));
+            }
+        }
+        summary.records += 1;
+        writeln!(out, "{}", serde_json::to_string(&record)?)?;
+        out.flush()?;
+    }
+    Ok((summary, usage))
 }
 
 impl GenerateArgs {
@@ -95,45 +774,448 @@ impl GenerateArgs {
     }
 }
 
-impl CommandExec<GenerateResult> for GenerateArgs {
-    async fn exec(
-        &self,
-        context: &impl super::CommandExecutionContext,
-    ) -> Result<Box<dyn CommandResult<GenerateResult>>, Box<dyn std::error::Error>> {
-        let input = self
-            .get_input()
-            .ok_or("Input required: use positional arg or -i (file path or text)")?;
+impl GenerateArgs {
+    fn output_filter(&self) -> OutputFilter {
+        OutputFilter {
+            strip_fences: self.strip_fences,
+            render: self.render,
+        }
+    }
 
-        let template = resolve_input(input).await?;
+    /// Settings shared by every prompt: system prompt, response format, images, stop, seed
+    async fn base_config(&self) -> Result<GenerateConfig, Box<dyn std::error::Error>> {
+        let images: Vec<String> = self.image.clone();
 
-        let input_variables: HashMap<String, Value> = self
-            .vars
-            .iter()
-            .map(|(k, v)| (k.clone(), v.clone()))
-            .collect();
+        let system_prompt = match self.system_file {
+            Some(ref path) => Some(read_to_string(path).await.map_err(|e| {
+                format!(
+                    "Failed to read system prompt file '{}': {}",
+                    path.display(),
+                    e
+                )
+            })?),
+            None => self.system.clone(),
+        };
 
-        let images: Vec<String> = self.image.clone();
+        let context = if self.context.is_empty() {
+            None
+        } else {
+            let block = load_context(&self.context, DEFAULT_CONTEXT_MAX_BYTES)?;
+            if !block.truncated.is_empty() {
+                print_warning(&format!(
+                    "Context files exceed {} bytes; truncated: {}",
+                    DEFAULT_CONTEXT_MAX_BYTES,
+                    block.truncated.join(", ")
+                ));
+            }
+            Some(block.text)
+        };
 
-        let config = GenerateConfig {
-            model: self.model.clone(),
-            reasoning_level: self.reasoning,
-            tools: None,
-            max_tokens: self.max_tokens,
+        let response_format = if let Some(ref path) = self.json_schema {
+            Some(ResponseFormat::JsonSchema {
+                schema: load_json_schema(path).await?,
+            })
+        } else if self.json_object {
+            Some(ResponseFormat::JsonObject)
+        } else {
+            None
+        };
+
+        Ok(GenerateConfig {
+            stop: stop_sequences(&self.stop)?,
+            seed: self.seed,
+            user: self.user.clone(),
+            tool_choice: self.tool_choice.clone(),
+            frequency_penalty: self.frequency_penalty,
+            presence_penalty: self.presence_penalty,
+            logit_bias: (!self.logit_bias.is_empty())
+                .then(|| self.logit_bias.iter().copied().collect()),
+            logprobs: self.logprobs,
+            top_logprobs: self.top_logprobs,
+            response_format,
+            context,
             images: if images.is_empty() {
                 None
             } else {
                 Some(images)
             },
             image_detail: Some(self.image_detail.clone()),
+            substitution_mode: if self.strict_vars {
+                SubstitutionMode::Strict
+            } else {
+                SubstitutionMode::Lenient
+            },
+            system_prompt,
+            json_guard: self.expect.map(|Expect::Json| JsonGuard {
+                strip_fences: self.strip_fences,
+                retry: self.expect_retry,
+            }),
+            ..Default::default()
+        })
+    }
+
+    /// Fill model settings: CLI flags, then template front matter, then the config file
+    fn with_defaults(
+        &self,
+        config: GenerateConfig,
+        front_matter: FrontMatter,
+        defaults: &Config,
+    ) -> GenerateConfig {
+        GenerateConfig {
+            model: self
+                .model
+                .clone()
+                .or(front_matter.model)
+                .or(defaults.model.clone()),
+            reasoning_level: self
+                .reasoning
+                .or(front_matter.reasoning)
+                .or(defaults.reasoning),
+            max_tokens: self
+                .max_tokens
+                .or(front_matter.max_tokens)
+                .or(defaults.max_tokens),
+            temperature: self.temperature.or(front_matter.temperature),
+            ..config
+        }
+    }
+
+    async fn exec_single(
+        &self,
+        context: &impl super::CommandExecutionContext,
+    ) -> Result<GenerateResult, Box<dyn std::error::Error>> {
+        let conversation = match self.conversation {
+            Some(ref path) => Some(load_conversation(path)?),
+            None => None,
+        };
+        let transcript = match self.continue_from {
+            Some(ref path) => Some(load_conversation(path)?),
+            None => None,
         };
 
-        let output = generate_from_template(&template, &input_variables, config).await?;
+        let source = select_input_source(
+            self.get_input(),
+            self.text.as_ref(),
+            std::io::stdin().is_terminal(),
+        );
 
-        if context.get_cli().is_interactive() {
-            println!("{}", output);
+        // With a conversation the prompt is optional
+        let raw = match source {
+            Some(InputSource::Input(input)) => {
+                resolve_input(input, context.get_cli().timeout.map(Duration::from_secs)).await?
+            }
+            Some(InputSource::Text(text)) => expand_includes(text, Path::new(""))?,
+            Some(InputSource::Stdin) => expand_includes(&read_stdin()?, Path::new(""))?,
+            None => String::new(),
+        };
+        if raw.trim().is_empty() && conversation.is_none() {
+            return Err(INPUT_REQUIRED.into());
+        }
+
+        // Front matter provides defaults; CLI flags take precedence
+        let (front_matter, template) = parse_front_matter(&raw)?;
+
+        let input_variables = collect_variables(self.vars_file.as_deref(), &self.vars)?;
+
+        let config = GenerateConfig {
+            conversation,
+            transcript,
+            ..self.with_defaults(
+                self.base_config().await?,
+                front_matter,
+                &context.get_cli().config,
+            )
+        };
+
+        let registry = match self.tools {
+            Some(ref names) => Some(select_tools(
+                names,
+                &self.allow_tool,
+                &ToolConfig {
+                    timezone: self.timezone.clone(),
+                    time_format: self.time_format.clone(),
+                    shell_allowlist: (!self.allow_command.is_empty())
+                        .then(|| self.allow_command.clone()),
+                    timeout: context.get_cli().timeout.map(Duration::from_secs),
+                },
+            )?),
+            None => None,
+        };
+        if let (Some(ToolChoice::Function(name)), Some(registry)) = (&self.tool_choice, &registry) {
+            if !registry.names().contains(name) {
+                return Err(format!(
+                    "--tool-choice '{}' is not among the selected tools ({})",
+                    name,
+                    registry.names().join(", ")
+                )
+                .into());
+            }
+        }
+
+        if context.get_cli().dry_run {
+            return dry_run(
+                &template,
+                &input_variables,
+                config,
+                registry.as_ref(),
+                context.get_cli().is_interactive(),
+            );
+        }
+
+        // Messages as sent; tool-call turns of an agent loop are not part of the transcript
+        let sent_messages = match self.save_transcript {
+            Some(_) => Some(build_request(&template, &input_variables, config.clone())?.messages),
+            None => None,
+        };
+
+        let provider = context.get_cli().build_provider()?;
+        // Streamed text goes straight to stdout; JSON/YAML and file output get the final result
+        let stream = self.stream && context.get_cli().is_interactive();
+        // --trace and --stream print while the loop runs
+        let mut spinner = if self.trace || stream {
+            None
+        } else {
+            context.get_cli().start_spinner()
+        };
+        let (outputs, usage, system_fingerprint, logprobs) = if let Some(ref registry) = registry {
+            let loop_config = LoopConfig {
+                max_iterations: self.max_iterations.unwrap_or(DEFAULT_MAX_ITERATIONS),
+                error_on_max_iterations: self.fail_on_max_iterations,
+                token_budget: self.token_budget,
+                cancellation: context.get_cli().cancellation.clone(),
+                context_limit: self.context_limit.map(|chars| chars as usize),
+                tools_disabled: context.get_cli().tools_disabled(),
+            };
+            let result = generate_with_tools(
+                provider.as_ref(),
+                registry,
+                &template,
+                &input_variables,
+                config,
+                loop_config,
+                LoopProgress {
+                    trace: self.trace,
+                    stream,
+                },
+            )
+            .await?;
+            drop(spinner.take());
+            if result.budget_exhausted {
+                print_warning(&format!(
+                    "Token budget exhausted after {} tokens; output is partial (raise --token-budget)",
+                    result.usage.total_tokens
+                ));
+            } else if result.stopped_early {
+                print_warning(&format!(
+                    "Agent loop stopped after {} iterations without a final answer; output is partial (raise --max-iterations)",
+                    result.iterations
+                ));
+            }
+            (vec![result.content], result.usage, None, None)
+        } else {
+            let generated = generate_candidates(
+                provider.as_ref(),
+                &template,
+                &input_variables,
+                config,
+                self.count.unwrap_or(1),
+            )
+            .await?;
+            let mut usage = Usage::default();
+            for candidate in &generated {
+                usage += &candidate.usage;
+            }
+            let system_fingerprint = generated.iter().find_map(|g| g.system_fingerprint.clone());
+            let logprobs = generated.first().and_then(|g| g.logprobs.clone());
+            (
+                generated.into_iter().map(|g| g.content).collect(),
+                usage,
+                system_fingerprint,
+                logprobs,
+            )
         };
+        drop(spinner);
+        let filter = self.output_filter();
+        let outputs: Vec<String> = outputs.iter().map(|o| filter.apply(o)).collect();
+        // Providers that don't report usage leave it zeroed
+        let usage = Some(usage).filter(|u| u.total_tokens > 0);
+
+        if stream {
+            // The answer was already printed as it arrived
+            println!();
+            if let Some(usage) = usage.as_ref().filter(|_| !context.get_cli().is_quiet()) {
+                eprintln!("{}", usage.summary());
+            }
+        } else if context.get_cli().is_interactive() {
+            println!("{}", outputs.join(CANDIDATE_DELIMITER));
+            if let Some(usage) = usage.as_ref().filter(|_| !context.get_cli().is_quiet()) {
+                eprintln!("{}", usage.summary());
+            }
+        };
+
+        let output = outputs.first().cloned().unwrap_or_default();
+        if let (Some(path), Some(mut messages)) = (&self.save_transcript, sent_messages) {
+            messages.push(Message::assistant(&output));
+            save_transcript(path, &messages)?;
+        }
+        Ok(GenerateResult {
+            output,
+            outputs: if outputs.len() > 1 {
+                outputs
+            } else {
+                Vec::new()
+            },
+            usage,
+            system_fingerprint,
+            logprobs,
+            dry_run: false,
+        })
+    }
+
+    /// Render the template once per record of the JSON Lines file at `path`, each record's
+    /// fields added to the variables, printing one JSON result per line
+    async fn exec_jsonl(
+        &self,
+        path: &Path,
+        context: &impl super::CommandExecutionContext,
+    ) -> Result<GenerateCommandOutput, Box<dyn std::error::Error>> {
+        let cli = context.get_cli();
+        if !cli.is_interactive() {
+            return Err(
+                "--jsonl prints one JSON object per line; -o and --output-file are not supported"
+                    .into(),
+            );
+        }
+        let raw = match select_input_source(
+            self.get_input(),
+            self.text.as_ref(),
+            std::io::stdin().is_terminal(),
+        ) {
+            Some(InputSource::Input(input)) => {
+                resolve_input(input, cli.timeout.map(Duration::from_secs)).await?
+            }
+            Some(InputSource::Text(text)) => expand_includes(text, Path::new(""))?,
+            Some(InputSource::Stdin) => expand_includes(&read_stdin()?, Path::new(""))?,
+            None => String::new(),
+        };
+        if raw.trim().is_empty() {
+            return Err(INPUT_REQUIRED.into());
+        }
+        let (front_matter, template) = parse_front_matter(&raw)?;
+        let input_variables = collect_variables(self.vars_file.as_deref(), &self.vars)?;
+        let config = self.with_defaults(self.base_config().await?, front_matter, &cli.config);
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("Failed to read JSONL file '{}': {}", path.display(), e))?;
+
+        let provider = if cli.dry_run {
+            None
+        } else {
+            Some(cli.build_provider()?)
+        };
+        // Records go to stdout while the run is in progress, so there is no spinner
+        let (summary, usage) = generate_records(
+            provider.as_deref(),
+            &template,
+            &input_variables,
+            &config,
+            self.output_filter(),
+            BufReader::new(file),
+            &mut std::io::stdout(),
+        )
+        .await?;
+
+        if usage.total_tokens > 0 && !cli.is_quiet() {
+            eprintln!("{}", usage.summary());
+        }
+        if summary.failures > 0 {
+            print_warning(&format!(
+                "{} of {} records failed",
+                summary.failures, summary.records
+            ));
+        }
+        Ok(GenerateCommandOutput::Jsonl(summary))
+    }
+
+    /// Run every prompt file in `dir` with the same variables and flags
+    async fn exec_batch(
+        &self,
+        dir: &Path,
+        context: &impl super::CommandExecutionContext,
+    ) -> Result<GenerateCommandOutput, Box<dyn std::error::Error>> {
+        let files = collect_prompt_files(dir, self.recursive)?;
+        if files.is_empty() {
+            return Err(format!("No *.md or *.txt files found in '{}'", dir.display()).into());
+        }
+
+        let input_variables = collect_variables(self.vars_file.as_deref(), &self.vars)?;
+        let base = self.base_config().await?;
+        let defaults = &context.get_cli().config;
+        let config_for = |front_matter| self.with_defaults(base.clone(), front_matter, defaults);
+
+        let provider = if context.get_cli().dry_run {
+            None
+        } else {
+            Some(context.get_cli().build_provider()?)
+        };
+        let spinner = provider
+            .as_ref()
+            .and_then(|_| context.get_cli().start_spinner());
+        let (mut summary, usage) = generate_files(
+            provider.as_deref(),
+            dir,
+            &files,
+            &input_variables,
+            config_for,
+            self.concurrency.unwrap_or(DEFAULT_CONCURRENCY) as usize,
+            self.continue_on_error,
+        )
+        .await?;
+        drop(spinner);
+        if provider.is_some() {
+            let filter = self.output_filter();
+            for output in summary.outputs.values_mut() {
+                *output = filter.apply(output);
+            }
+        }
+
+        if context.get_cli().is_interactive() {
+            for (name, output) in &summary.outputs {
+                println!("==> {} <==\n{}\n", name, output);
+            }
+            for (name, error) in &summary.failures {
+                eprintln!("==> {} <== failed\n{}\n", name, error);
+            }
+            if usage.total_tokens > 0 && !context.get_cli().is_quiet() {
+                eprintln!("{}", usage.summary());
+            }
+            if !summary.failures.is_empty() {
+                print_warning(&format!(
+                    "{} of {} prompt files failed",
+                    summary.failures.len(),
+                    files.len()
+                ));
+            }
+        }
 
-        Ok(Box::from(GenerateResult { output }))
+        Ok(if self.continue_on_error {
+            GenerateCommandOutput::BatchSummary(summary)
+        } else {
+            GenerateCommandOutput::Batch(summary.outputs)
+        })
+    }
+}
+
+impl CommandExec<GenerateCommandOutput> for GenerateArgs {
+    async fn exec(
+        &self,
+        context: &impl super::CommandExecutionContext,
+    ) -> Result<Box<dyn CommandResult<GenerateCommandOutput>>, Box<dyn std::error::Error>> {
+        let output = match (&self.input_dir, &self.jsonl) {
+            (Some(dir), _) => self.exec_batch(dir, context).await?,
+            (None, Some(path)) => self.exec_jsonl(path, context).await?,
+            (None, None) => GenerateCommandOutput::Single(self.exec_single(context).await?),
+        };
+        Ok(Box::from(output))
     }
 }
 
@@ -141,6 +1223,145 @@ impl CommandExec<GenerateResult> for GenerateArgs {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_generate_result_usage_round_trip() {
+        let result = GenerateResult {
+            output: "Hi".to_string(),
+            outputs: Vec::new(),
+            usage: Some(Usage {
+                prompt_tokens: 12,
+                completion_tokens: 6,
+                total_tokens: 18,
+            }),
+            system_fingerprint: None,
+            logprobs: None,
+            dry_run: false,
+        };
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["usage"]["prompt_tokens"], 12);
+        assert_eq!(json["usage"]["completion_tokens"], 6);
+        assert_eq!(json["usage"]["total_tokens"], 18);
+
+        let parsed: GenerateResult = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.usage, result.usage);
+
+        let without = GenerateResult {
+            output: "Hi".to_string(),
+            outputs: Vec::new(),
+            usage: None,
+            system_fingerprint: None,
+            logprobs: None,
+            dry_run: false,
+        };
+        let json = serde_json::to_value(&without).unwrap();
+        assert!(json.get("usage").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_skips_provider() {
+        use clap::Parser;
+        // An unknown provider would fail if the command tried to build it
+        let cli = crate::Cli::try_parse_from([
+            "trickery",
+            "--dry-run",
+            "--provider",
+            "nonexistent",
+            "-o",
+            "json",
+            "generate",
+            "Hello {{ name }}!",
+            "--var",
+            "name=Alice",
+        ])
+        .unwrap();
+        let args = match cli.command {
+            Some(crate::Commands::Generate(ref args)) => args,
+            _ => panic!("Expected Generate command"),
+        };
+
+        let result = args.exec(&cli).await.unwrap();
+        let result = match result.get_result() {
+            GenerateCommandOutput::Single(result) => result,
+            other => panic!("Expected single result, got {:?}", other),
+        };
+        assert!(result.dry_run);
+        assert_eq!(result.output, "Hello Alice!");
+        assert!(result.usage.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_input_url_is_used_as_template() {
+        use clap::Parser;
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/prompts/greet.md")
+            .with_body("---\ntemperature: 0.2\n---\nGreet {{ name }} warmly.")
+            .create_async()
+            .await;
+
+        let url = format!("{}/prompts/greet.md", server.url());
+        let cli = crate::Cli::try_parse_from([
+            "trickery",
+            "--dry-run",
+            "-o",
+            "json",
+            "generate",
+            url.as_str(),
+            "--var",
+            "name=Bob",
+        ])
+        .unwrap();
+        let Some(crate::Commands::Generate(ref args)) = cli.command else {
+            panic!("Expected Generate command");
+        };
+
+        let result = args.exec(&cli).await.unwrap();
+        let GenerateCommandOutput::Single(result) = result.get_result() else {
+            panic!("Expected single result");
+        };
+        assert_eq!(result.output, "Greet Bob warmly.");
+        mock.assert_async().await;
+    }
+
+    #[test]
+    fn test_generate_result_outputs_only_for_multiple_candidates() {
+        let result = GenerateResult {
+            output: "A".to_string(),
+            outputs: vec!["A".to_string(), "B".to_string()],
+            usage: None,
+            system_fingerprint: None,
+            logprobs: None,
+            dry_run: false,
+        };
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["output"], "A");
+        assert_eq!(json["outputs"], serde_json::json!(["A", "B"]));
+
+        let single = GenerateResult {
+            outputs: Vec::new(),
+            ..result
+        };
+        assert!(serde_json::to_value(&single)
+            .unwrap()
+            .get("outputs")
+            .is_none());
+    }
+
+    #[test]
+    fn test_parse_count_flag() {
+        use clap::Parser;
+        let cli = crate::Cli::try_parse_from(["trickery", "generate", "Hi", "-n", "3"]).unwrap();
+        match cli.command {
+            Some(crate::Commands::Generate(args)) => assert_eq!(args.count, Some(3)),
+            _ => panic!("Expected Generate command"),
+        }
+        assert!(crate::Cli::try_parse_from(["trickery", "generate", "Hi", "-n", "0"]).is_err());
+        assert!(
+            crate::Cli::try_parse_from(["trickery", "generate", "Hi", "-n", "2", "--tools"])
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_parse_key_val() {
         let (key, val) = parse_key_val("name=John").unwrap();
@@ -161,6 +1382,742 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_select_input_source_prefers_input() {
+        let input = "prompts/test.md".to_string();
+        let text = "Hello".to_string();
+        assert_eq!(
+            select_input_source(Some(&input), Some(&text), false),
+            Some(InputSource::Input("prompts/test.md"))
+        );
+    }
+
+    #[test]
+    fn test_select_input_source_text_over_stdin() {
+        let text = "Hello".to_string();
+        assert_eq!(
+            select_input_source(None, Some(&text), false),
+            Some(InputSource::Text("Hello"))
+        );
+    }
+
+    #[test]
+    fn test_select_input_source_stdin_when_piped() {
+        assert_eq!(
+            select_input_source(None, None, false),
+            Some(InputSource::Stdin)
+        );
+    }
+
+    #[test]
+    fn test_select_input_source_none_when_terminal() {
+        assert_eq!(select_input_source(None, None, true), None);
+    }
+
+    #[test]
+    fn test_parse_reasoning_flag() {
+        use clap::Parser;
+        let cli = crate::Cli::try_parse_from(["trickery", "generate", "Hi", "-r", "high"]).unwrap();
+        match cli.command {
+            Some(crate::Commands::Generate(args)) => {
+                assert_eq!(args.reasoning, Some(ReasoningLevel::High))
+            }
+            _ => panic!("Expected Generate command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_max_tokens() {
+        use clap::Parser;
+        let cli = crate::Cli::try_parse_from(["trickery", "generate", "Hi", "--max-tokens", "256"])
+            .unwrap();
+        match cli.command {
+            Some(crate::Commands::Generate(args)) => assert_eq!(args.max_tokens, Some(256)),
+            _ => panic!("Expected Generate command"),
+        }
+
+        let result =
+            crate::Cli::try_parse_from(["trickery", "generate", "Hi", "--max-tokens", "0"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_temperature() {
+        assert_eq!(parse_temperature("0").unwrap(), 0.0);
+        assert_eq!(parse_temperature("0.7").unwrap(), 0.7);
+        assert_eq!(parse_temperature("2.0").unwrap(), 2.0);
+        assert!(parse_temperature("2.1").is_err());
+        assert!(parse_temperature("-0.1").is_err());
+        assert!(parse_temperature("warm").is_err());
+    }
+
+    #[test]
+    fn test_parse_penalty() {
+        assert_eq!(parse_penalty("0.5").unwrap(), 0.5);
+        assert_eq!(parse_penalty("-2").unwrap(), -2.0);
+        assert!(parse_penalty("2.5").is_err());
+        assert!(parse_penalty("high").is_err());
+
+        use clap::Parser;
+        let cli = crate::Cli::try_parse_from([
+            "trickery",
+            "generate",
+            "Hi",
+            "--frequency-penalty",
+            "-0.5",
+            "--presence-penalty",
+            "1.2",
+        ])
+        .unwrap();
+        let Some(crate::Commands::Generate(args)) = cli.command else {
+            panic!("Expected Generate command");
+        };
+        assert_eq!(args.frequency_penalty, Some(-0.5));
+        assert_eq!(args.presence_penalty, Some(1.2));
+    }
+
+    #[test]
+    fn test_parse_logit_bias() {
+        assert_eq!(parse_logit_bias("50256=-100").unwrap(), (50256, -100.0));
+        assert_eq!(parse_logit_bias("1734=2.5").unwrap(), (1734, 2.5));
+        assert!(parse_logit_bias("1734=101")
+            .unwrap_err()
+            .contains("out of range"));
+        assert!(parse_logit_bias("hello=1").is_err());
+        assert!(parse_logit_bias("1734=high").is_err());
+        assert!(parse_logit_bias("1734").is_err());
+    }
+
+    #[test]
+    fn test_stop_sequences() {
+        assert_eq!(stop_sequences(&[]).unwrap(), None);
+        let stop = vec!["END".to_string(), "\n\n".to_string()];
+        assert_eq!(stop_sequences(&stop).unwrap(), Some(stop));
+        let err = stop_sequences(&vec!["x".to_string(); 5]).unwrap_err();
+        assert!(err.contains("at most 4"), "{}", err);
+        assert!(stop_sequences(&["".to_string()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_logprobs_flags() {
+        use clap::Parser;
+        let cli = crate::Cli::try_parse_from([
+            "trickery",
+            "generate",
+            "Hi",
+            "--logprobs",
+            "--top-logprobs",
+            "3",
+        ])
+        .unwrap();
+        let Some(crate::Commands::Generate(args)) = cli.command else {
+            panic!("Expected Generate command");
+        };
+        let config = args.base_config().await.unwrap();
+        let request = build_request("Hi", &HashMap::new(), config).unwrap();
+        assert_eq!(request.logprobs, Some(true));
+        assert_eq!(request.top_logprobs, Some(3));
+
+        // --top-logprobs needs --logprobs and is capped at 20
+        for args in [
+            vec!["trickery", "generate", "Hi", "--top-logprobs", "3"],
+            vec![
+                "trickery",
+                "generate",
+                "Hi",
+                "--logprobs",
+                "--top-logprobs",
+                "21",
+            ],
+        ] {
+            assert!(crate::Cli::try_parse_from(args).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_load_json_schema() {
+        use std::io::Write;
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, r#"{{"type": "object"}}"#).unwrap();
+        let schema = load_json_schema(file.path().to_str().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(schema, serde_json::json!({"type": "object"}));
+
+        let mut bad = tempfile::NamedTempFile::new().unwrap();
+        write!(bad, "not json").unwrap();
+        let err = load_json_schema(bad.path().to_str().unwrap())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid JSON schema file"));
+    }
+
+    #[test]
+    fn test_parse_system_flags() {
+        use clap::Parser;
+        let cli =
+            crate::Cli::try_parse_from(["trickery", "generate", "Hi", "--system", "Be brief"])
+                .unwrap();
+        match cli.command {
+            Some(crate::Commands::Generate(args)) => {
+                assert_eq!(args.system.as_deref(), Some("Be brief"))
+            }
+            _ => panic!("Expected Generate command"),
+        }
+
+        let result = crate::Cli::try_parse_from([
+            "trickery",
+            "generate",
+            "Hi",
+            "--system",
+            "Be brief",
+            "--system-file",
+            "system.md",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_json_flags_conflict() {
+        use clap::Parser;
+        let result = crate::Cli::try_parse_from([
+            "trickery",
+            "generate",
+            "Hi",
+            "--json-object",
+            "--json-schema",
+            "schema.json",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_tools_flag() {
+        use clap::Parser;
+        let cli = crate::Cli::try_parse_from([
+            "trickery",
+            "generate",
+            "Hi",
+            "--tools",
+            "calculate",
+            "--tools",
+            "read_file",
+            "--max-iterations",
+            "3",
+        ])
+        .unwrap();
+        match cli.command {
+            Some(crate::Commands::Generate(args)) => {
+                assert_eq!(
+                    args.tools,
+                    Some(vec!["calculate".to_string(), "read_file".to_string()])
+                );
+                assert_eq!(args.max_iterations, Some(3));
+            }
+            _ => panic!("Expected Generate command"),
+        }
+
+        // --max-iterations only makes sense with tools
+        let result =
+            crate::Cli::try_parse_from(["trickery", "generate", "Hi", "--max-iterations", "3"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_empty_tools_flag_uses_all_tools() {
+        use clap::Parser;
+        let cli = crate::Cli::try_parse_from(["trickery", "generate", "Hi", "--tools"]).unwrap();
+        let names = match cli.command {
+            Some(crate::Commands::Generate(args)) => args.tools.unwrap(),
+            _ => panic!("Expected Generate command"),
+        };
+        assert!(names.is_empty());
+        assert_eq!(
+            select_tools(&names, &[], &ToolConfig::default())
+                .unwrap()
+                .names(),
+            ToolRegistry::with_builtins().names()
+        );
+    }
+
+    #[test]
+    fn test_allow_tool_adds_opt_in_tool() {
+        use clap::Parser;
+        let cli = crate::Cli::try_parse_from([
+            "trickery",
+            "generate",
+            "Hi",
+            "--tools",
+            "calculate",
+            "--allow-tool",
+            "run_shell",
+        ])
+        .unwrap();
+        let args = match cli.command {
+            Some(crate::Commands::Generate(args)) => args,
+            _ => panic!("Expected Generate command"),
+        };
+        let registry = select_tools(
+            args.tools.as_deref().unwrap(),
+            &args.allow_tool,
+            &ToolConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(registry.names(), vec!["calculate", "run_shell"]);
+
+        // Opt-in tools only come on top of --tools
+        let result =
+            crate::Cli::try_parse_from(["trickery", "generate", "Hi", "--allow-tool", "run_shell"]);
+        assert!(result.is_err());
+        let result = crate::Cli::try_parse_from([
+            "trickery",
+            "generate",
+            "Hi",
+            "--tools",
+            "--allow-command",
+            "ls",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_unknown_tool_error() {
+        let err = select_tools(&["teleport".to_string()], &[], &ToolConfig::default())
+            .err()
+            .unwrap();
+        assert!(matches!(err, ToolError::NotFound(_)));
+        let message = err.to_string();
+        assert!(message.contains("teleport"));
+        assert!(message.contains(
+            "available: calculate, current_time, date_math, list_dir, read_file, scratchpad, text_stats"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_attach_adds_image_parts() {
+        use crate::provider::ContentPart;
+        use clap::Parser;
+        let dir = tempfile::tempdir().unwrap();
+        let photo = dir.path().join("photo.png");
+        std::fs::write(&photo, [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]).unwrap();
+
+        let cli = crate::Cli::try_parse_from([
+            "trickery",
+            "generate",
+            "What is in these images?",
+            "--attach",
+            photo.to_str().unwrap(),
+            "--attach",
+            "https://example.com/cat.jpg",
+        ])
+        .unwrap();
+        let args = match cli.command {
+            Some(crate::Commands::Generate(ref args)) => args,
+            _ => panic!("Expected Generate command"),
+        };
+
+        let config = args.base_config().await.unwrap();
+        let request = build_request("What is in these images?", &HashMap::new(), config).unwrap();
+        let parts = request.messages.last().unwrap().content.as_ref().unwrap();
+        let urls: Vec<&str> = parts
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::ImageUrl { image_url } => Some(image_url.url.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(urls.len(), 2);
+        assert!(urls[0].starts_with("data:image/png;base64,"));
+        assert_eq!(urls[1], "https://example.com/cat.jpg");
+        assert!(matches!(parts[0], ContentPart::Text { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_save_transcript_then_continue() {
+        use clap::Parser;
+        use mockito::{Matcher, Server};
+
+        let mut server = Server::new_async().await;
+        let reply = |content: &str| {
+            format!(
+                r#"{{"choices": [{{"message": {{"role": "assistant", "content": "{}"}}, "finish_reason": "stop"}}]}}"#,
+                content
+            )
+        };
+        let first = server
+            .mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "messages": [{"role": "user", "content": [{"type": "text", "text": "Name a fruit"}]}]
+            })))
+            .with_body(reply("Banana."))
+            .create_async()
+            .await;
+        let second = server
+            .mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "messages": [
+                    {"role": "user", "content": [{"type": "text", "text": "Name a fruit"}]},
+                    {"role": "assistant", "content": [{"type": "text", "text": "Banana."}]},
+                    {"role": "user", "content": [{"type": "text", "text": "Another one"}]}
+                ]
+            })))
+            .with_body(reply("Mango."))
+            .create_async()
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let transcript = dir.path().join("chat.json");
+        let run = |prompt: &str, continue_from: bool| {
+            let mut argv = vec![
+                "trickery".to_string(),
+                "--provider".to_string(),
+                "ollama".to_string(),
+                "--base-url".to_string(),
+                server.url(),
+                "-o".to_string(),
+                "json".to_string(),
+                "generate".to_string(),
+                prompt.to_string(),
+                "--save-transcript".to_string(),
+                transcript.display().to_string(),
+            ];
+            if continue_from {
+                argv.push("--continue".to_string());
+                argv.push(transcript.display().to_string());
+            }
+            crate::Cli::try_parse_from(argv).unwrap()
+        };
+
+        for (prompt, continue_from) in [("Name a fruit", false), ("Another one", true)] {
+            let cli = run(prompt, continue_from);
+            let args = match cli.command {
+                Some(crate::Commands::Generate(ref args)) => args,
+                _ => panic!("Expected Generate command"),
+            };
+            args.exec(&cli).await.unwrap();
+        }
+        first.assert_async().await;
+        second.assert_async().await;
+
+        let messages = load_conversation(&transcript).unwrap();
+        let texts: Vec<_> = messages.iter().map(|m| m.text_content().unwrap()).collect();
+        assert_eq!(
+            texts,
+            vec!["Name a fruit", "Banana.", "Another one", "Mango."]
+        );
+    }
+
+    /// Provider that answers with the rendered user prompt; prompts containing FAIL get a 500
+    struct EchoProvider;
+
+    #[async_trait::async_trait]
+    impl Provider for EchoProvider {
+        async fn complete(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<crate::provider::CompletionResponse, crate::provider::ProviderError> {
+            let prompt = request
+                .messages
+                .last()
+                .and_then(|m| m.text_content())
+                .unwrap_or_default();
+            if prompt.contains("FAIL") {
+                return Err(crate::provider::ProviderError::Api {
+                    status: 500,
+                    message: "upstream error".to_string(),
+                    details: None,
+                });
+            }
+            let mut response = crate::trickery::r#loop::tests::text_response(&prompt);
+            response.usage.total_tokens = 1;
+            Ok(response)
+        }
+
+        async fn create_response(
+            &self,
+            _request: crate::provider::ResponsesRequest,
+        ) -> Result<crate::provider::ResponsesResponse, crate::provider::ProviderError> {
+            unimplemented!("not used by generate")
+        }
+    }
+
+    fn write_prompt_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b.md"), "B for {{ name }}").unwrap();
+        std::fs::write(dir.path().join("a.txt"), "A for {{ name }}").unwrap();
+        std::fs::write(dir.path().join("notes.json"), "{}").unwrap();
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
+        std::fs::write(dir.path().join("nested/c.md"), "C for {{ name }}").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_collect_prompt_files() {
+        let dir = write_prompt_dir();
+        let keys = |recursive| {
+            collect_prompt_files(dir.path(), recursive)
+                .unwrap()
+                .iter()
+                .map(|p| file_key(dir.path(), p))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(keys(false), ["a.txt", "b.md"]);
+        assert_eq!(keys(true), ["a.txt", "b.md", "nested/c.md"]);
+    }
+
+    #[tokio::test]
+    async fn test_generate_files_keys_every_file() {
+        let dir = write_prompt_dir();
+        let files = collect_prompt_files(dir.path(), true).unwrap();
+        let vars = HashMap::from([("name".to_string(), Value::from("Alice"))]);
+
+        for concurrency in [1, 3] {
+            let (summary, usage) = generate_files(
+                Some(&EchoProvider),
+                dir.path(),
+                &files,
+                &vars,
+                |_| GenerateConfig::default(),
+                concurrency,
+                false,
+            )
+            .await
+            .unwrap();
+            assert!(summary.failures.is_empty());
+            assert_eq!(
+                summary.outputs.into_iter().collect::<Vec<_>>(),
+                [
+                    ("a.txt".to_string(), "A for Alice".to_string()),
+                    ("b.md".to_string(), "B for Alice".to_string()),
+                    ("nested/c.md".to_string(), "C for Alice".to_string()),
+                ]
+            );
+            assert_eq!(usage.total_tokens, 3);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_files_continue_on_error() {
+        let dir = write_prompt_dir();
+        std::fs::write(dir.path().join("c.md"), "FAIL for {{ name }}").unwrap();
+        std::fs::write(dir.path().join("d.md"), "---\nreasoning: extreme\n---\nD").unwrap();
+        let files = collect_prompt_files(dir.path(), false).unwrap();
+        let vars = HashMap::from([("name".to_string(), Value::from("Alice"))]);
+
+        let err = generate_files(
+            Some(&EchoProvider),
+            dir.path(),
+            &files,
+            &vars,
+            |_| GenerateConfig::default(),
+            2,
+            false,
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            err.to_string().starts_with("Failed to generate 'c.md'"),
+            "{}",
+            err
+        );
+
+        let (summary, usage) = generate_files(
+            Some(&EchoProvider),
+            dir.path(),
+            &files,
+            &vars,
+            |_| GenerateConfig::default(),
+            2,
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            summary.outputs.keys().collect::<Vec<_>>(),
+            ["a.txt", "b.md"]
+        );
+        assert_eq!(
+            summary.failures.keys().collect::<Vec<_>>(),
+            ["c.md", "d.md"]
+        );
+        assert!(summary.failures["c.md"].contains("upstream error"));
+        assert!(summary.failures["d.md"].contains("Invalid front matter"));
+        assert_eq!(usage.total_tokens, 2);
+
+        let output = GenerateCommandOutput::BatchSummary(summary);
+        assert!(output.failed());
+        let json = serde_json::to_value(output.get_result()).unwrap();
+        assert_eq!(json["outputs"]["a.txt"], "A for Alice");
+        assert!(json["failures"]["c.md"].is_string());
+        assert!(!GenerateCommandOutput::Batch(BTreeMap::new()).failed());
+    }
+
+    #[tokio::test]
+    async fn test_generate_records_one_output_per_line() {
+        let input = "{\"name\": \"Ada\"}\n\n{name: Bob}\n{\"name\": \"FAIL\"}\n{\"name\": \"Eve\", \"greeting\": \"Hi\"}\n";
+        let vars = HashMap::from([("greeting".to_string(), Value::from("Hello"))]);
+        let mut out = Vec::new();
+
+        let (summary, usage) = generate_records(
+            Some(&EchoProvider),
+            "{{ greeting }} {{ name }}",
+            &vars,
+            &GenerateConfig::default(),
+            OutputFilter::default(),
+            input.as_bytes(),
+            &mut out,
+        )
+        .await
+        .unwrap();
+
+        let records: Vec<Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(records.len(), 4);
+        assert_eq!(
+            records[0],
+            serde_json::json!({"line": 1, "output": "Hello Ada"})
+        );
+        assert_eq!(records[1]["line"], 3);
+        assert!(records[1]["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("line 3: expected a JSON object of variables"));
+        assert!(records[2]["error"]
+            .as_str()
+            .unwrap()
+            .contains("upstream error"));
+        assert_eq!(
+            records[3],
+            serde_json::json!({"line": 5, "output": "Hi Eve"})
+        );
+        assert_eq!(
+            summary,
+            JsonlSummary {
+                records: 4,
+                failures: 2
+            }
+        );
+        assert_eq!(usage.total_tokens, 2);
+        assert!(GenerateCommandOutput::Jsonl(summary).failed());
+    }
+
+    #[test]
+    fn test_jsonl_conflicts() {
+        use clap::Parser;
+        for extra in [["--input-dir", "prompts"], ["--count", "2"]] {
+            let mut argv = vec!["trickery", "generate", "--jsonl", "rows.jsonl"];
+            argv.extend(extra);
+            assert!(crate::Cli::try_parse_from(argv).is_err());
+        }
+        assert!(crate::Cli::try_parse_from([
+            "trickery",
+            "generate",
+            "prompt.md",
+            "--jsonl",
+            "rows.jsonl"
+        ])
+        .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_input_dir_dry_run_json() {
+        use clap::Parser;
+        let dir = write_prompt_dir();
+        let cli = crate::Cli::try_parse_from([
+            "trickery",
+            "--dry-run",
+            "--provider",
+            "nonexistent",
+            "-o",
+            "json",
+            "generate",
+            "--input-dir",
+            dir.path().to_str().unwrap(),
+            "--var",
+            "name=Bob",
+        ])
+        .unwrap();
+        let args = match cli.command {
+            Some(crate::Commands::Generate(ref args)) => args,
+            _ => panic!("Expected Generate command"),
+        };
+
+        let result = args.exec(&cli).await.unwrap();
+        let json = serde_json::to_value(result.get_result()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"a.txt": "A for Bob", "b.md": "B for Bob"})
+        );
+    }
+
+    #[test]
+    fn test_input_dir_conflicts_and_requirements() {
+        use clap::Parser;
+        assert!(
+            crate::Cli::try_parse_from(["trickery", "generate", "Hi", "--input-dir", "."]).is_err()
+        );
+        assert!(crate::Cli::try_parse_from(["trickery", "generate", "--recursive"]).is_err());
+        assert!(
+            crate::Cli::try_parse_from(["trickery", "generate", "--continue-on-error"]).is_err()
+        );
+        assert!(crate::Cli::try_parse_from([
+            "trickery",
+            "generate",
+            "--input-dir",
+            ".",
+            "--concurrency",
+            "0"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_tool_choice() {
+        use clap::Parser;
+        assert_eq!(parse_tool_choice("auto").unwrap(), ToolChoice::Auto);
+        assert_eq!(parse_tool_choice("none").unwrap(), ToolChoice::None);
+        assert_eq!(parse_tool_choice("required").unwrap(), ToolChoice::Required);
+        assert_eq!(
+            parse_tool_choice("calculate").unwrap(),
+            ToolChoice::Function("calculate".to_string())
+        );
+        assert!(parse_tool_choice("").is_err());
+        // Only meaningful with --tools
+        assert!(
+            crate::Cli::try_parse_from(["trickery", "generate", "--tool-choice", "required"])
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tool_choice_must_name_a_selected_tool() {
+        use clap::Parser;
+        let cli = crate::Cli::try_parse_from([
+            "trickery",
+            "--dry-run",
+            "generate",
+            "Hi",
+            "--tools",
+            "calculate",
+            "--tool-choice",
+            "read_file",
+        ])
+        .unwrap();
+        let Some(crate::Commands::Generate(ref args)) = cli.command else {
+            panic!("Expected Generate command");
+        };
+        let err = args.exec(&cli).await.err().unwrap();
+        assert!(
+            err.to_string()
+                .contains("--tool-choice 'read_file' is not among the selected tools"),
+            "{}",
+            err
+        );
+    }
+
     #[test]
     fn test_parse_reasoning_level() {
         assert_eq!(parse_reasoning_level("low").unwrap(), ReasoningLevel::Low);
@@ -2,34 +2,54 @@ use clap::{Args, ValueHint};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::fs::read_to_string;
+use tracing::debug;
 
 use super::{CommandExec, CommandResult};
-use crate::provider::{ImageAction, ImageBackground, ImageFormat, ImageQuality, ImageSize};
-use crate::trickery::image::{generate_image, ImageConfig};
+use crate::error::print_warning;
+use crate::provider::{ImageAction, ImageBackground, ImageFormat, ImageQuality, ImageSize, Usage};
+use crate::trickery::front_matter::parse_front_matter;
+use crate::trickery::generate::{substitute_variables, SubstitutionMode};
+use crate::trickery::image::{describe_image_request, generate_image, ImageConfig};
+use crate::trickery::include::expand_includes;
+use crate::trickery::remote::{fetch_template, is_template_url};
+use crate::trickery::vars::{collect_variables, parse_key_val};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ImageResult {
-    pub output_path: String,
+    /// File the image was written to; absent with --stdout-base64 and no --save, and in dry run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_path: Option<String>,
+    /// Prompt sent to the image model, after substitution and --enhance-tools; in dry run,
+    /// the prompt that would be sent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub final_prompt: Option<String>,
     pub revised_prompt: Option<String>,
+    /// Base64 image data, set with --stdout-base64
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    /// Tokens spent, when the provider reports them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    /// Provider was not called and no file was written
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
 }
 
-impl CommandResult<ImageResult> for ImageResult {
-    fn get_result(&self) -> &ImageResult {
-        self
-    }
+/// Command output: a single image object, or an array when `--count` is above 1
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+pub enum ImageOutput {
+    Single(ImageResult),
+    Batch(Vec<ImageResult>),
 }
 
-fn parse_key_val(s: &str) -> Result<(String, Value), String> {
-    let pos = s
-        .find('=')
-        .ok_or_else(|| format!("invalid KEY=VALUE: no `=` found in `{}`", s))?;
-    Ok((
-        s[..pos].to_string(),
-        Value::String(s[pos + 1..].to_string()),
-    ))
+impl CommandResult<ImageOutput> for ImageOutput {
+    fn get_result(&self) -> &ImageOutput {
+        self
+    }
 }
 
 fn parse_image_size(s: &str) -> Result<ImageSize, String> {
@@ -52,16 +72,27 @@ fn parse_image_action(s: &str) -> Result<ImageAction, String> {
     s.parse()
 }
 
-/// Resolve input to template content.
-/// If input exists as a file, read from file; otherwise treat as direct text.
-async fn resolve_input(input: &str) -> Result<String, Box<dyn std::error::Error>> {
+/// Resolve input to template content with includes expanded.
+/// An http(s) URL is fetched; if input exists as a file, read from file; otherwise treat as
+/// direct text. `timeout` is the global --timeout for the fetch.
+async fn resolve_input(
+    input: &str,
+    timeout: Option<Duration>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if is_template_url(input) {
+        return fetch_template(input, timeout).await;
+    }
     let path = Path::new(input);
     if path.exists() {
-        read_to_string(path)
+        let content = read_to_string(path)
             .await
-            .map_err(|e| format!("Failed to read input file '{}': {}", path.display(), e).into())
+            .map_err(|e| format!("Failed to read input file '{}': {}", path.display(), e))?;
+        debug!(path = %path.display(), bytes = content.len(), "loaded template file");
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        Ok(expand_includes(&content, base_dir)?)
     } else {
-        Ok(input.to_string())
+        debug!(bytes = input.len(), "using input as template text");
+        Ok(expand_includes(input, Path::new(""))?)
     }
 }
 
@@ -82,13 +113,36 @@ fn generate_output_filename(input: Option<&str>, format: Option<&ImageFormat>) -
         .map(char::from)
         .collect();
 
-    let ext = match format {
+    PathBuf::from(format!(
+        "{}-{}.{}",
+        stem,
+        suffix.to_lowercase(),
+        format_extension(format)
+    ))
+}
+
+/// File extension for an output format (png when none is requested)
+fn format_extension(format: Option<&ImageFormat>) -> &'static str {
+    match format {
         Some(ImageFormat::Jpeg) => "jpg",
         Some(ImageFormat::Webp) => "webp",
         _ => "png",
-    };
+    }
+}
 
-    PathBuf::from(format!("{}-{}.{}", stem, suffix.to_lowercase(), ext))
+/// `path` with the extension of `format`: a mismatching extension is replaced, a missing one
+/// appended. Extensions naming the same format (`.jpeg` for jpeg) are kept.
+fn with_format_extension(path: &Path, format: &ImageFormat) -> PathBuf {
+    let matches = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(|ext| ext.parse::<ImageFormat>().ok())
+        .is_some_and(|ext_format| &ext_format == format);
+    if matches {
+        path.to_path_buf()
+    } else {
+        path.with_extension(format_extension(Some(format)))
+    }
 }
 
 #[derive(Args)]
@@ -110,6 +164,14 @@ pub struct ImageArgs {
     #[arg(short, long="var", value_parser = parse_key_val, number_of_values = 1)]
     pub vars: Vec<(String, Value)>,
 
+    /// JSON or YAML file with variables (overridden by --var)
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub vars_file: Option<PathBuf>,
+
+    /// Fail when a template variable has no value and no default
+    #[arg(long)]
+    strict_vars: bool,
+
     /// Model to use (e.g., gpt-4.1, gpt-5, gpt-5.2)
     #[arg(short, long)]
     model: Option<String>,
@@ -141,6 +203,58 @@ pub struct ImageArgs {
     /// Compression level (0-100) for jpeg/webp formats
     #[arg(long)]
     compression: Option<u8>,
+
+    /// Number of images to generate (1-10); files are numbered NAME-1.png, NAME-2.png, ...
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=10))]
+    count: Option<u32>,
+
+    /// Return base64 image data (in `data` for -o json/yaml, printed otherwise);
+    /// no file is written unless --save is given
+    #[arg(long)]
+    stdout_base64: bool,
+
+    /// Rewrite the prompt with this built-in tool before generating (can be repeated); the
+    /// model calls the tools in an agent loop and its answer becomes the image prompt
+    #[arg(long = "enhance-tools", value_name = "NAME", value_delimiter = ',')]
+    enhance_tools: Vec<String>,
+
+    /// Maximum agent loop iterations for --enhance-tools (default: 10)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..), requires = "enhance_tools")]
+    enhance_iterations: Option<u32>,
+}
+
+/// Lines printed for one image as (stdout, stderr). With --stdout-base64 stdout carries only
+/// the data so it can be piped; details go to stderr. Quiet mode keeps only the result: the
+/// data, or else the saved path.
+fn image_messages(result: &ImageResult, quiet: bool) -> (Vec<String>, Vec<String>) {
+    let mut stdout = Vec::new();
+    let mut details = Vec::new();
+    if let Some(ref data) = result.data {
+        stdout.push(data.clone());
+    } else if quiet {
+        stdout.extend(result.output_path.clone());
+    }
+    if !quiet {
+        if let Some(ref path) = result.output_path {
+            details.push(format!("Image saved to: {}", path));
+        }
+        if let Some(ref prompt) = result.final_prompt {
+            details.push(format!("Prompt: {}", prompt));
+        }
+        if let Some(ref revised) = result.revised_prompt {
+            details.push(format!("Revised prompt: {}", revised));
+        }
+    }
+    let mut stderr = Vec::new();
+    if result.data.is_some() {
+        stderr.append(&mut details);
+    } else {
+        stdout.append(&mut details);
+    }
+    if let Some(usage) = result.usage.as_ref().filter(|_| !quiet) {
+        stderr.push(usage.summary());
+    }
+    (stdout, stderr)
 }
 
 impl ImageArgs {
@@ -150,27 +264,11 @@ impl ImageArgs {
             .as_ref()
             .or(self.input_option.as_ref())
     }
-}
 
-impl CommandExec<ImageResult> for ImageArgs {
-    async fn exec(
-        &self,
-        context: &impl super::CommandExecutionContext,
-    ) -> Result<Box<dyn CommandResult<ImageResult>>, Box<dyn std::error::Error>> {
-        let input = self
-            .get_input()
-            .ok_or("Input required: use positional arg or -i (file path or text)")?;
-
-        let template = resolve_input(input).await?;
-
-        let input_variables: HashMap<String, Value> = self
-            .vars
-            .iter()
-            .map(|(k, v)| (k.clone(), v.clone()))
-            .collect();
-
-        let config = ImageConfig {
-            model: self.model.clone(),
+    /// Image settings from the flags; `model` (front matter) applies without --model
+    fn config(&self, model: Option<String>) -> ImageConfig {
+        ImageConfig {
+            model: self.model.clone().or(model),
             input_images: if self.image.is_empty() {
                 None
             } else {
@@ -182,24 +280,115 @@ impl CommandExec<ImageResult> for ImageArgs {
             background: self.background.clone(),
             action: self.action.clone(),
             compression: self.compression,
-        };
+            count: self.count,
+            include_data: self.stdout_base64,
+            substitution_mode: if self.strict_vars {
+                SubstitutionMode::Strict
+            } else {
+                SubstitutionMode::Lenient
+            },
+            tool_names: (!self.enhance_tools.is_empty()).then(|| self.enhance_tools.clone()),
+            max_iterations: self.enhance_iterations,
+            ..Default::default()
+        }
+    }
 
-        // Use provided save path or auto-generate from input filename
-        let output_path = match &self.save {
-            Some(path) => path.clone(),
-            None => generate_output_filename(Some(input), self.format.as_ref()),
-        };
+    /// File to write: --save (with the extension of --format, which decides the format), else
+    /// an auto-generated name, else none with --stdout-base64
+    fn output_path(&self, input: &str) -> Option<PathBuf> {
+        match self.save {
+            Some(ref path) => Some(match self.format {
+                Some(ref format) => with_format_extension(path, format),
+                None => path.clone(),
+            }),
+            None if self.stdout_base64 => None,
+            None => Some(generate_output_filename(Some(input), self.format.as_ref())),
+        }
+    }
+}
+
+impl CommandExec<ImageOutput> for ImageArgs {
+    async fn exec(
+        &self,
+        context: &impl super::CommandExecutionContext,
+    ) -> Result<Box<dyn CommandResult<ImageOutput>>, Box<dyn std::error::Error>> {
+        let input = self
+            .get_input()
+            .ok_or("Input required: use positional arg or -i (file path or text)")?;
+
+        let timeout = context.get_cli().timeout.map(Duration::from_secs);
+        let raw = resolve_input(input, timeout).await?;
+
+        // Front matter provides defaults (model); CLI flags take precedence
+        let (front_matter, template) = parse_front_matter(&raw)?;
 
-        let result = generate_image(&template, &input_variables, config, &output_path).await?;
+        let input_variables = collect_variables(self.vars_file.as_deref(), &self.vars)?;
+
+        let mut config = self.config(front_matter.model);
+        if context.get_cli().tools_disabled() && config.tool_names.take().is_some() {
+            print_warning("--enhance-tools ignored: tools are disabled by --no-tools");
+        }
+
+        let output_path = self.output_path(input);
+        if let (Some(save), Some(path)) = (&self.save, &output_path) {
+            if save != path {
+                print_warning(&format!(
+                    "Saving to '{}' to match --format (instead of '{}')",
+                    path.display(),
+                    save.display()
+                ));
+            }
+        }
+
+        if context.get_cli().dry_run {
+            let prompt =
+                substitute_variables(&template, &input_variables, config.substitution_mode)?;
+            if context.get_cli().is_interactive() {
+                println!(
+                    "{}",
+                    describe_image_request(&prompt, &config, output_path.as_deref())
+                );
+            }
+            return Ok(Box::from(ImageOutput::Single(ImageResult {
+                output_path: None,
+                final_prompt: Some(prompt),
+                revised_prompt: None,
+                data: None,
+                usage: None,
+                dry_run: true,
+            })));
+        }
+
+        let provider = context.get_cli().build_provider()?;
+        let spinner = context.get_cli().start_spinner();
+        let mut results = generate_image(
+            provider.as_ref(),
+            &template,
+            &input_variables,
+            config,
+            output_path.as_deref(),
+        )
+        .await?;
+        drop(spinner);
 
         if context.get_cli().is_interactive() {
-            println!("Image saved to: {}", output_path.display());
-            if let Some(ref revised) = result.revised_prompt {
-                println!("Revised prompt: {}", revised);
+            for result in &results {
+                let (stdout, stderr) = image_messages(result, context.get_cli().is_quiet());
+                for line in stdout {
+                    println!("{}", line);
+                }
+                for line in stderr {
+                    eprintln!("{}", line);
+                }
             }
         }
 
-        Ok(Box::from(result))
+        let output = if results.len() == 1 {
+            ImageOutput::Single(results.remove(0))
+        } else {
+            ImageOutput::Batch(results)
+        };
+        Ok(Box::from(output))
     }
 }
 
@@ -214,6 +403,168 @@ mod tests {
         assert_eq!(val, Value::String("John".to_string()));
     }
 
+    #[test]
+    fn test_image_messages_quiet() {
+        let saved = ImageResult {
+            output_path: Some("cat.png".to_string()),
+            final_prompt: Some("A cat".to_string()),
+            revised_prompt: Some("A tabby cat".to_string()),
+            data: None,
+            usage: Some(Usage {
+                prompt_tokens: 10,
+                completion_tokens: 20,
+                total_tokens: 30,
+            }),
+            dry_run: false,
+        };
+        let (stdout, stderr) = image_messages(&saved, false);
+        assert_eq!(
+            stdout,
+            vec![
+                "Image saved to: cat.png",
+                "Prompt: A cat",
+                "Revised prompt: A tabby cat"
+            ]
+        );
+        assert_eq!(stderr.len(), 1);
+        // Quiet: only the saved path, nothing on stderr
+        assert_eq!(
+            image_messages(&saved, true),
+            (vec!["cat.png".to_string()], vec![])
+        );
+
+        let piped = ImageResult {
+            data: Some("aGVsbG8=".to_string()),
+            ..saved
+        };
+        assert_eq!(image_messages(&piped, false).1.len(), 4);
+        assert_eq!(
+            image_messages(&piped, true),
+            (vec!["aGVsbG8=".to_string()], vec![])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_skips_provider() {
+        use clap::Parser;
+        // An unknown provider would fail if the command tried to build it
+        let cli = crate::Cli::try_parse_from([
+            "trickery",
+            "--dry-run",
+            "--provider",
+            "nonexistent",
+            "-o",
+            "json",
+            "image",
+            "A {{ style }} banner",
+            "--var",
+            "style=modern",
+            "--save",
+            "/nonexistent/dir/banner.png",
+        ])
+        .unwrap();
+        let args = match cli.command {
+            Some(crate::Commands::Image(ref args)) => args,
+            _ => panic!("Expected Image command"),
+        };
+
+        let output = args.exec(&cli).await.unwrap();
+        let result = match output.get_result() {
+            ImageOutput::Single(result) => result,
+            other => panic!("Expected a single result, got {:?}", other),
+        };
+        assert!(result.dry_run);
+        assert_eq!(result.final_prompt.as_deref(), Some("A modern banner"));
+        let json = serde_json::to_value(result).unwrap();
+        assert_eq!(json["final_prompt"], "A modern banner");
+        assert!(json.get("prompt").is_none());
+        // Nothing was written, so there is no file to report
+        assert_eq!(result.output_path, None);
+    }
+
+    #[test]
+    fn test_image_output_json_shape() {
+        let result = || ImageResult {
+            output_path: Some("cat.png".to_string()),
+            final_prompt: None,
+            revised_prompt: None,
+            data: None,
+            usage: None,
+            dry_run: false,
+        };
+        // A single image keeps the object shape used before --count existed
+        let single = serde_json::to_value(ImageOutput::Single(result())).unwrap();
+        assert_eq!(single["output_path"], "cat.png");
+        let batch = serde_json::to_value(ImageOutput::Batch(vec![result(), result()])).unwrap();
+        assert_eq!(batch.as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_format_decides_save_extension() {
+        use clap::Parser;
+        let args = |extra: &[&str]| {
+            let mut argv = vec!["trickery", "image", "A cat"];
+            argv.extend_from_slice(extra);
+            match crate::Cli::try_parse_from(argv).unwrap().command {
+                Some(crate::Commands::Image(args)) => args,
+                _ => panic!("Expected Image command"),
+            }
+        };
+
+        let webp = args(&["--save", "out.png", "--format", "webp"]);
+        assert_eq!(webp.output_path("A cat"), Some(PathBuf::from("out.webp")));
+        assert_eq!(webp.config(None).output_format, Some(ImageFormat::Webp));
+        // Without --format the path is kept as given
+        assert_eq!(
+            args(&["--save", "out.bin"]).output_path("A cat"),
+            Some(PathBuf::from("out.bin"))
+        );
+
+        assert_eq!(
+            with_format_extension(Path::new("out.JPEG"), &ImageFormat::Jpeg),
+            PathBuf::from("out.JPEG")
+        );
+        assert_eq!(
+            with_format_extension(Path::new("dir/out.bin"), &ImageFormat::Png),
+            PathBuf::from("dir/out.png")
+        );
+        assert_eq!(
+            with_format_extension(Path::new("out"), &ImageFormat::Jpeg),
+            PathBuf::from("out.jpg")
+        );
+    }
+
+    #[test]
+    fn test_stdout_base64_skips_file_unless_saved() {
+        use clap::Parser;
+        let args = |extra: &[&str]| {
+            let mut argv = vec!["trickery", "-o", "json", "image", "A cat"];
+            argv.extend_from_slice(extra);
+            match crate::Cli::try_parse_from(argv).unwrap().command {
+                Some(crate::Commands::Image(args)) => args,
+                _ => panic!("Expected Image command"),
+            }
+        };
+
+        assert_eq!(args(&["--stdout-base64"]).output_path("A cat"), None);
+        assert_eq!(
+            args(&["--stdout-base64", "--save", "cat.png"]).output_path("A cat"),
+            Some(PathBuf::from("cat.png"))
+        );
+        assert!(args(&[]).output_path("A cat").is_some());
+    }
+
+    #[test]
+    fn test_count_range() {
+        use clap::Parser;
+        let parse = |count: &str| {
+            crate::Cli::try_parse_from(["trickery", "image", "A cat", "--count", count])
+        };
+        assert!(parse("3").is_ok());
+        assert!(parse("0").is_err());
+        assert!(parse("11").is_err());
+    }
+
     #[test]
     fn test_parse_image_size() {
         assert_eq!(parse_image_size("auto").unwrap(), ImageSize::Auto);
@@ -0,0 +1,268 @@
+// lint: checks prompt templates without calling a provider.
+// For each file: includes are expanded (missing files and cycles are errors), front matter is
+// parsed (invalid values are errors, unknown keys warnings) and `{{ var }}` references are
+// collected. Variables without a value (--var/--vars-file) and without a default are errors.
+// Interactive mode prints one line per finding and fails when any file has errors; with
+// -o json/yaml the reports are the result and `ok` tells whether the file passed.
+
+use clap::{Args, ValueHint};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::super::trickery::front_matter::{parse_front_matter, unknown_front_matter_keys};
+use super::super::trickery::generate::{template_variables, TemplateVariable};
+use super::super::trickery::include::expand_includes;
+use super::super::trickery::vars::{collect_variables, parse_key_val};
+use super::{CommandExec, CommandResult};
+
+/// Findings for one template file
+#[derive(Serialize, Debug, PartialEq)]
+pub struct LintReport {
+    pub path: String,
+    /// No errors (warnings are allowed)
+    pub ok: bool,
+    /// Variables the template references, in order of first use
+    pub variables: Vec<TemplateVariable>,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Reports for every linted file
+#[derive(Serialize, Debug)]
+#[serde(transparent)]
+pub struct LintResult {
+    pub reports: Vec<LintReport>,
+}
+
+impl CommandResult<LintResult> for LintResult {
+    fn get_result(&self) -> &LintResult {
+        self
+    }
+}
+
+#[derive(Args)]
+pub struct LintArgs {
+    /// Template files to check
+    #[arg(required = true, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    inputs: Vec<PathBuf>,
+
+    /// Variables that will be provided (these need no default)
+    #[arg(short, long = "var", value_parser = parse_key_val, number_of_values = 1)]
+    vars: Vec<(String, Value)>,
+
+    /// JSON or YAML file with variables that will be provided
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    vars_file: Option<PathBuf>,
+}
+
+/// Check the template at `path`; `variables` are the values that will be provided
+fn lint_file(path: &Path, variables: &HashMap<String, Value>) -> LintReport {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    let mut template_vars = Vec::new();
+
+    match std::fs::read_to_string(path) {
+        Err(e) => errors.push(format!("Failed to read template: {}", e)),
+        Ok(raw) => {
+            let base_dir = path.parent().unwrap_or(Path::new(""));
+            // Keep checking the unexpanded text so one bad include doesn't hide other findings
+            let expanded = expand_includes(&raw, base_dir).unwrap_or_else(|e| {
+                errors.push(e.to_string());
+                raw.clone()
+            });
+            for key in unknown_front_matter_keys(&expanded) {
+                warnings.push(format!("Unknown front matter key '{}' (ignored)", key));
+            }
+            let body = match parse_front_matter(&expanded) {
+                Ok((_, body)) => body,
+                Err(e) => {
+                    errors.push(e);
+                    expanded
+                }
+            };
+            template_vars = template_variables(&body);
+            for variable in &template_vars {
+                if variable.default.is_none() && !variables.contains_key(&variable.name) {
+                    errors.push(format!(
+                        "Variable '{}' has no value and no default (add {{{{ {}|default }}}} or pass --var {}=...)",
+                        variable.name, variable.name, variable.name
+                    ));
+                }
+            }
+        }
+    }
+
+    LintReport {
+        path: path.display().to_string(),
+        ok: errors.is_empty(),
+        variables: template_vars,
+        errors,
+        warnings,
+    }
+}
+
+/// Compiler-style listing: `path: error: message`, or `path: ok` for a clean file
+fn format_report(report: &LintReport) -> String {
+    let findings: Vec<String> = report
+        .errors
+        .iter()
+        .map(|e| format!("{}: error: {}", report.path, e))
+        .chain(
+            report
+                .warnings
+                .iter()
+                .map(|w| format!("{}: warning: {}", report.path, w)),
+        )
+        .collect();
+    if findings.is_empty() {
+        format!("{}: ok", report.path)
+    } else {
+        findings.join("\n")
+    }
+}
+
+impl CommandExec<LintResult> for LintArgs {
+    async fn exec(
+        &self,
+        context: &impl super::CommandExecutionContext,
+    ) -> Result<Box<dyn CommandResult<LintResult>>, Box<dyn std::error::Error>> {
+        let variables = collect_variables(self.vars_file.as_deref(), &self.vars)?;
+        let reports: Vec<LintReport> = self
+            .inputs
+            .iter()
+            .map(|path| lint_file(path, &variables))
+            .collect();
+
+        if context.get_cli().is_interactive() {
+            for report in &reports {
+                println!("{}", format_report(report));
+            }
+            let failed = reports.iter().filter(|r| !r.ok).count();
+            if failed > 0 {
+                return Err(
+                    format!("{} of {} templates have errors", failed, reports.len()).into(),
+                );
+            }
+        }
+
+        Ok(Box::from(LintResult { reports }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn template(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_clean_template() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = template(
+            dir.path(),
+            "ok.md",
+            "---\nmodel: gpt-5.2\n---\nHello {{ name }}, {{ tone|friendly }}",
+        );
+        let variables = HashMap::from([("name".to_string(), Value::from("Ada"))]);
+        let report = lint_file(&path, &variables);
+        assert!(report.ok, "{:?}", report);
+        assert!(report.warnings.is_empty());
+        let names: Vec<&str> = report.variables.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["name", "tone"]);
+        assert_eq!(format_report(&report), format!("{}: ok", path.display()));
+    }
+
+    #[test]
+    fn test_missing_variables() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = template(dir.path(), "vars.md", "{{ a }} {{ b|x }} {{ c }} {{ a }}");
+        let report = lint_file(&path, &HashMap::new());
+        assert!(!report.ok);
+        assert_eq!(report.errors.len(), 2, "{:?}", report.errors);
+        assert!(report.errors[0].starts_with("Variable 'a' has no value"));
+        assert!(report.errors[1].starts_with("Variable 'c' has no value"));
+    }
+
+    #[test]
+    fn test_bad_front_matter() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = template(
+            dir.path(),
+            "fm.md",
+            "---\nreasoning: extreme\nmodle: gpt-5.2\n---\nHi {{ who|you }}",
+        );
+        let report = lint_file(&path, &HashMap::new());
+        assert!(!report.ok);
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.errors[0].contains("Invalid front matter"));
+        assert_eq!(
+            report.warnings,
+            vec!["Unknown front matter key 'modle' (ignored)"]
+        );
+        // Variables are still checked after a front matter error
+        assert_eq!(report.variables.len(), 1);
+        let listing = format_report(&report);
+        assert!(
+            listing.contains(": error: Invalid front matter"),
+            "{}",
+            listing
+        );
+        assert!(
+            listing.contains(": warning: Unknown front matter key"),
+            "{}",
+            listing
+        );
+    }
+
+    #[test]
+    fn test_include_cycle_and_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        template(dir.path(), "b.md", r#"{{include "a.md"}}"#);
+        let a = template(dir.path(), "a.md", r#"{{include "b.md"}}"#);
+        let report = lint_file(&a, &HashMap::new());
+        assert!(!report.ok);
+        assert!(
+            report.errors[0].starts_with("Include cycle"),
+            "{:?}",
+            report
+        );
+
+        let report = lint_file(&dir.path().join("missing.md"), &HashMap::new());
+        assert!(report.errors[0].starts_with("Failed to read template"));
+    }
+
+    #[tokio::test]
+    async fn test_lint_json_reports_every_file() {
+        use clap::Parser;
+        let dir = tempfile::tempdir().unwrap();
+        let good = template(dir.path(), "good.md", "Hi {{ name }}");
+        let bad = template(dir.path(), "bad.md", "Hi {{ other }}");
+        let cli = crate::Cli::try_parse_from([
+            "trickery".to_string(),
+            "-o".to_string(),
+            "json".to_string(),
+            "lint".to_string(),
+            good.display().to_string(),
+            bad.display().to_string(),
+            "--var".to_string(),
+            "name=Ada".to_string(),
+        ])
+        .unwrap();
+        let Some(crate::Commands::Lint(ref args)) = cli.command else {
+            panic!("Expected Lint command");
+        };
+
+        let result = args.exec(&cli).await.unwrap();
+        let json = serde_json::to_value(result.get_result()).unwrap();
+        assert_eq!(json[0]["ok"], true);
+        assert_eq!(json[1]["ok"], false);
+        assert_eq!(json[1]["variables"][0]["name"], "other");
+    }
+}
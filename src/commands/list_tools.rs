@@ -0,0 +1,93 @@
+use clap::Args;
+use serde::Serialize;
+
+use super::{CommandExec, CommandResult};
+use crate::provider::Tool;
+use crate::tools::ToolRegistry;
+
+/// Tool definitions available to `generate --tools`
+#[derive(Serialize, Debug)]
+#[serde(transparent)]
+pub struct ListToolsResult {
+    pub tools: Vec<Tool>,
+}
+
+impl CommandResult<ListToolsResult> for ListToolsResult {
+    fn get_result(&self) -> &ListToolsResult {
+        self
+    }
+}
+
+#[derive(Args)]
+pub struct ListToolsArgs {}
+
+/// One line per tool: name padded to a common width, then its description
+fn format_tool_listing(tools: &[Tool]) -> String {
+    let width = tools
+        .iter()
+        .map(|t| t.function.name.len())
+        .max()
+        .unwrap_or(0);
+    tools
+        .iter()
+        .map(|t| {
+            format!(
+                "{:width$}  {}",
+                t.function.name,
+                t.function.description,
+                width = width
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl CommandExec<ListToolsResult> for ListToolsArgs {
+    async fn exec(
+        &self,
+        context: &impl super::CommandExecutionContext,
+    ) -> Result<Box<dyn CommandResult<ListToolsResult>>, Box<dyn std::error::Error>> {
+        let tools = ToolRegistry::with_builtins().definitions();
+
+        if context.get_cli().is_interactive() {
+            println!("{}", format_tool_listing(&tools));
+        }
+
+        Ok(Box::from(ListToolsResult { tools }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_tool_listing_includes_builtins() {
+        let tools = ToolRegistry::with_builtins().definitions();
+        let listing = format_tool_listing(&tools);
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(lines.len(), tools.len());
+        assert!(lines.iter().any(|l| l.starts_with("calculate  ")));
+        assert!(lines.iter().any(|l| l.starts_with("read_file  ")));
+    }
+
+    #[tokio::test]
+    async fn test_list_tools_json_is_definitions_array() {
+        use clap::Parser;
+        let cli = crate::Cli::try_parse_from(["trickery", "-o", "json", "list-tools"]).unwrap();
+        let args = match cli.command {
+            Some(crate::Commands::ListTools(ref args)) => args,
+            _ => panic!("Expected ListTools command"),
+        };
+
+        let result = args.exec(&cli).await.unwrap();
+        let json = serde_json::to_value(result.get_result()).unwrap();
+        let tools = json.as_array().unwrap();
+        assert!(tools
+            .iter()
+            .any(|t| t["function"]["name"] == "calculate" && t["type"] == "function"));
+        assert!(tools
+            .iter()
+            .all(|t| t["function"]["parameters"].is_object()));
+    }
+}
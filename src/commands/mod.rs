@@ -1,8 +1,14 @@
 use crate::Cli;
 use serde::ser;
 
+pub mod chat;
+pub mod doctor;
 pub mod generate;
 pub mod image;
+pub mod lint;
+pub mod list_tools;
+pub mod models;
+pub mod summarize;
 
 pub trait CommandExecutionContext {
     fn get_cli(&self) -> &Cli;
@@ -23,4 +29,9 @@ where
     T: ser::Serialize,
 {
     fn get_result(&self) -> &T;
+
+    /// Partial failure: the result is still written, then the process exits with code 1
+    fn failed(&self) -> bool {
+        false
+    }
 }
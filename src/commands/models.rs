@@ -0,0 +1,75 @@
+use clap::Args;
+use serde::Serialize;
+
+use super::{CommandExec, CommandResult};
+
+/// Model IDs offered by the selected provider
+#[derive(Serialize, Debug)]
+#[serde(transparent)]
+pub struct ModelsResult {
+    pub models: Vec<String>,
+}
+
+impl CommandResult<ModelsResult> for ModelsResult {
+    fn get_result(&self) -> &ModelsResult {
+        self
+    }
+}
+
+#[derive(Args)]
+pub struct ModelsArgs {}
+
+impl CommandExec<ModelsResult> for ModelsArgs {
+    async fn exec(
+        &self,
+        context: &impl super::CommandExecutionContext,
+    ) -> Result<Box<dyn CommandResult<ModelsResult>>, Box<dyn std::error::Error>> {
+        let cli = context.get_cli();
+        // The listing changes as models are released; never serve it from the cache
+        let provider = cli.build_provider_with_cache(None)?;
+        let models = provider.list_models().await?;
+
+        if cli.is_interactive() {
+            for model in &models {
+                println!("{}", model);
+            }
+        }
+
+        Ok(Box::from(ModelsResult { models }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[tokio::test]
+    async fn test_models_json_is_id_array() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/models")
+            .with_body(r#"{"data": [{"id": "qwen3"}, {"id": "llama3.2"}]}"#)
+            .create_async()
+            .await;
+
+        let url = server.url();
+        let cli = crate::Cli::try_parse_from([
+            "trickery",
+            "--provider",
+            "ollama",
+            "--base-url",
+            &url,
+            "-o",
+            "json",
+            "models",
+        ])
+        .unwrap();
+        let Some(crate::Commands::Models(ref args)) = cli.command else {
+            panic!("Expected Models command");
+        };
+        let result = args.exec(&cli).await.unwrap();
+        let json = serde_json::to_value(result.get_result()).unwrap();
+        assert_eq!(json, serde_json::json!(["llama3.2", "qwen3"]));
+    }
+}
@@ -0,0 +1,181 @@
+// summarize: summarizes a document of any length with map-reduce (trickery/summarize.rs).
+// Input is a file or piped stdin. --chunk-size and --chunk-overlap are in characters, so
+// they don't depend on the model's tokenizer. With --dry-run only the chunking is shown.
+
+use clap::{Args, ValueHint};
+use serde::Serialize;
+use std::io::{IsTerminal, Read};
+use std::path::PathBuf;
+
+use super::super::trickery::summarize::{
+    chunk_text, summarize, SummarizeConfig, DEFAULT_CHUNK_OVERLAP, DEFAULT_CHUNK_SIZE,
+};
+use super::{CommandExec, CommandResult};
+use crate::provider::Usage;
+
+/// Final summary and how many chunks the input was split into
+#[derive(Serialize, Debug)]
+pub struct SummarizeResult {
+    pub summary: String,
+    pub chunks: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+impl CommandResult<SummarizeResult> for SummarizeResult {
+    fn get_result(&self) -> &SummarizeResult {
+        self
+    }
+}
+
+#[derive(Args)]
+pub struct SummarizeArgs {
+    /// File to summarize; reads piped stdin when omitted or "-"
+    #[arg(value_name = "FILE", value_hint = ValueHint::FilePath)]
+    input: Option<PathBuf>,
+
+    /// Model (default: config file model, then the provider default)
+    #[arg(short, long)]
+    model: Option<String>,
+
+    /// Maximum characters per chunk
+    #[arg(long, value_name = "CHARS", default_value_t = DEFAULT_CHUNK_SIZE)]
+    chunk_size: usize,
+
+    /// Characters shared by consecutive chunks (less than --chunk-size)
+    #[arg(long, value_name = "CHARS", default_value_t = DEFAULT_CHUNK_OVERLAP)]
+    chunk_overlap: usize,
+
+    /// Extra instructions for every summarization request (e.g. "Focus on decisions")
+    #[arg(long, value_name = "TEXT")]
+    instructions: Option<String>,
+}
+
+impl SummarizeArgs {
+    fn read_input(&self) -> Result<String, Box<dyn std::error::Error>> {
+        match self.input {
+            Some(ref path) if path.as_os_str() != "-" => std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read '{}': {}", path.display(), e).into()),
+            _ if std::io::stdin().is_terminal() => {
+                Err("Input required: pass a file or pipe text via stdin".into())
+            }
+            _ => {
+                let mut buffer = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut buffer)
+                    .map_err(|e| format!("Failed to read stdin: {}", e))?;
+                Ok(buffer)
+            }
+        }
+    }
+}
+
+impl CommandExec<SummarizeResult> for SummarizeArgs {
+    async fn exec(
+        &self,
+        context: &impl super::CommandExecutionContext,
+    ) -> Result<Box<dyn CommandResult<SummarizeResult>>, Box<dyn std::error::Error>> {
+        let cli = context.get_cli();
+        let text = self.read_input()?;
+        let config = SummarizeConfig {
+            model: self.model.clone().or(cli.config.model.clone()),
+            chunk_size: self.chunk_size,
+            overlap: self.chunk_overlap,
+            instructions: self.instructions.clone(),
+        };
+
+        if cli.dry_run {
+            let chunks = chunk_text(&text, config.chunk_size, config.overlap)?;
+            if cli.is_interactive() {
+                for (index, chunk) in chunks.iter().enumerate() {
+                    println!("Chunk {}: {} chars", index + 1, chunk.chars().count());
+                }
+            }
+            return Ok(Box::from(SummarizeResult {
+                summary: String::new(),
+                chunks: chunks.len(),
+                usage: None,
+            }));
+        }
+
+        let provider = cli.build_provider()?;
+        let spinner = cli.start_spinner();
+        let output = summarize(provider.as_ref(), &text, &config).await?;
+        drop(spinner);
+        // Providers that don't report usage leave it zeroed
+        let usage = Some(output.usage).filter(|u| u.total_tokens > 0);
+
+        if cli.is_interactive() {
+            println!("{}", output.summary);
+            if let Some(usage) = usage.as_ref().filter(|_| !cli.is_quiet()) {
+                eprintln!("{}", usage.summary());
+            }
+        }
+        Ok(Box::from(SummarizeResult {
+            summary: output.summary,
+            chunks: output.chunks,
+            usage,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[tokio::test]
+    async fn test_summarize_file_in_chunks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("doc.txt");
+        std::fs::write(&path, "first part of the text\n\nsecond part of the text").unwrap();
+
+        let mut server = mockito::Server::new_async().await;
+        let body = |content: &str| {
+            serde_json::json!({"choices": [{"message": {"role": "assistant", "content": content}, "finish_reason": "stop"}]})
+                .to_string()
+        };
+        let parts = server
+            .mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::Regex("part [12] of 2".to_string()))
+            .with_body(body("part summary"))
+            .expect(2)
+            .create_async()
+            .await;
+        let combine = server
+            .mock("POST", "/chat/completions")
+            .match_body(mockito::Matcher::Regex("Combine them".to_string()))
+            .with_body(body("whole summary"))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let url = server.url();
+        let cli = crate::Cli::try_parse_from([
+            "trickery",
+            "--provider",
+            "ollama",
+            "--base-url",
+            &url,
+            "-o",
+            "json",
+            "summarize",
+            path.to_str().unwrap(),
+            "--chunk-size",
+            "30",
+            "--chunk-overlap",
+            "0",
+        ])
+        .unwrap();
+        let Some(crate::Commands::Summarize(ref args)) = cli.command else {
+            panic!("Expected Summarize command");
+        };
+        let result = args.exec(&cli).await.unwrap();
+        let result = result.get_result();
+
+        assert_eq!(result.summary, "whole summary");
+        assert_eq!(result.chunks, 2);
+        parts.assert_async().await;
+        combine.assert_async().await;
+    }
+}
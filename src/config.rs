@@ -0,0 +1,217 @@
+// User configuration file with defaults applied to every invocation.
+// Location: $TRICKERY_CONFIG, else $XDG_CONFIG_HOME/trickery/config.toml,
+// else ~/.config/trickery/config.toml.
+// Precedence: CLI flags (and their env vars) > template front matter > config file > built-in defaults.
+// A missing file means built-in defaults; an unreadable or invalid file is reported and ignored.
+// Environment: --env-file (or ./.env when present) is loaded into the process environment
+// before anything reads it; variables that are already set take precedence over the file.
+
+use crate::provider::ReasoningLevel;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Defaults read from config.toml
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Provider used when --provider/TRICKERY_PROVIDER is not set
+    pub provider: Option<String>,
+    /// Model for generate
+    pub model: Option<String>,
+    /// Reasoning level for generate
+    pub reasoning: Option<ReasoningLevel>,
+    /// Token limit for generate
+    pub max_tokens: Option<u32>,
+    /// API base URL, used when the provider's own *_BASE_URL variable is unset
+    pub base_url: Option<String>,
+    /// Cache completions on disk, as with --cache (--no-cache bypasses it)
+    pub cache: Option<bool>,
+    /// Extra headers for every provider request (`[headers]` table); --header adds to them
+    pub headers: Option<BTreeMap<String, String>>,
+}
+
+/// Config file path: `explicit` (from TRICKERY_CONFIG) or the default location
+fn resolve_config_path(explicit: Option<PathBuf>) -> Option<PathBuf> {
+    if let Some(path) = explicit.filter(|p| !p.as_os_str().is_empty()) {
+        return Some(path);
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("trickery").join("config.toml"))
+}
+
+/// Read config from `path`; a missing file yields the defaults
+pub fn load_config_from(path: &Path) -> Result<Config, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => {
+            return Err(format!(
+                "Failed to read config file '{}': {}",
+                path.display(),
+                e
+            ))
+        }
+    };
+    toml::from_str(&content).map_err(|e| format!("Invalid config file '{}': {}", path.display(), e))
+}
+
+/// Env file loaded when --env-file is not given
+pub const DEFAULT_ENV_FILE: &str = ".env";
+
+/// Load variables from `explicit` or ./.env into the environment, keeping variables already set.
+/// Returns the loaded path; a missing default file is skipped, a missing explicit one is an error.
+pub fn load_env_file(explicit: Option<&Path>) -> Result<Option<PathBuf>, String> {
+    let path = explicit.unwrap_or(Path::new(DEFAULT_ENV_FILE));
+    if explicit.is_none() && !path.is_file() {
+        return Ok(None);
+    }
+    dotenvy::from_path(path)
+        .map(|()| Some(path.to_path_buf()))
+        .map_err(|e| format!("Failed to load env file '{}': {}", path.display(), e))
+}
+
+/// Load config from TRICKERY_CONFIG or the default location, warning on errors
+pub fn load_config() -> Config {
+    let Some(path) = resolve_config_path(std::env::var_os("TRICKERY_CONFIG").map(PathBuf::from))
+    else {
+        return Config::default();
+    };
+    load_config_from(&path).unwrap_or_else(|e| {
+        crate::error::print_warning(&format!("{} (using defaults)", e));
+        Config::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn config_file(content: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::with_suffix(".toml").unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_load_config_missing_file() {
+        let config = load_config_from(Path::new("/nonexistent/trickery/config.toml")).unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn test_load_config_full() {
+        let file = config_file(
+            r#"
+provider = "ollama"
+model = "llama3"
+reasoning = "high"
+max_tokens = 2000
+base_url = "http://gpu-box:11434/v1"
+cache = true
+
+[headers]
+X-Gateway-Token = "abc123"
+"#,
+        );
+        let config = load_config_from(file.path()).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                provider: Some("ollama".to_string()),
+                model: Some("llama3".to_string()),
+                reasoning: Some(ReasoningLevel::High),
+                max_tokens: Some(2000),
+                base_url: Some("http://gpu-box:11434/v1".to_string()),
+                cache: Some(true),
+                headers: Some(BTreeMap::from([(
+                    "X-Gateway-Token".to_string(),
+                    "abc123".to_string()
+                )])),
+            }
+        );
+    }
+
+    #[test]
+    fn test_load_config_partial() {
+        let file = config_file("model = \"gpt-5.2\"\n");
+        let config = load_config_from(file.path()).unwrap();
+        assert_eq!(config.model.as_deref(), Some("gpt-5.2"));
+        assert!(config.provider.is_none());
+        assert!(config.reasoning.is_none());
+        assert!(config.max_tokens.is_none());
+    }
+
+    #[test]
+    fn test_load_config_invalid() {
+        let err = load_config_from(config_file("modle = \"gpt-5\"\n").path()).unwrap_err();
+        assert!(err.contains("Invalid config file"), "{}", err);
+        assert!(err.contains("modle"), "{}", err);
+        assert!(load_config_from(config_file("reasoning = \"extreme\"\n").path()).is_err());
+    }
+
+    #[test]
+    fn test_env_file_provides_api_key() {
+        // A name no other test touches: tests run in parallel and share the environment
+        let file = config_file("# local keys\nTRICKERY_TEST_ENV_FILE_KEY=sk-from-env-file\n");
+        let loaded = load_env_file(Some(file.path())).unwrap();
+        assert_eq!(loaded.as_deref(), Some(file.path()));
+        assert_eq!(
+            std::env::var("TRICKERY_TEST_ENV_FILE_KEY").as_deref(),
+            Ok("sk-from-env-file")
+        );
+    }
+
+    #[test]
+    fn test_env_file_does_not_override_environment() {
+        std::env::set_var("TRICKERY_TEST_ENV_FILE_VAR", "from-shell");
+        let file = config_file("TRICKERY_TEST_ENV_FILE_VAR=from-file\n");
+        load_env_file(Some(file.path())).unwrap();
+        assert_eq!(
+            std::env::var("TRICKERY_TEST_ENV_FILE_VAR").as_deref(),
+            Ok("from-shell")
+        );
+    }
+
+    #[test]
+    fn test_env_file_errors() {
+        let err = load_env_file(Some(Path::new("/nonexistent/.env"))).unwrap_err();
+        assert!(err.contains("/nonexistent/.env"), "{}", err);
+        let err = load_env_file(Some(config_file("NOT VALID\n").path())).unwrap_err();
+        assert!(err.contains("Failed to load env file"), "{}", err);
+    }
+
+    #[test]
+    fn test_resolve_config_path_prefers_explicit() {
+        let explicit = PathBuf::from("/etc/trickery.toml");
+        assert_eq!(resolve_config_path(Some(explicit.clone())), Some(explicit));
+        // Empty TRICKERY_CONFIG falls back to the default location
+        if let Some(path) = resolve_config_path(Some(PathBuf::new())) {
+            assert!(path.ends_with("trickery/config.toml"));
+        }
+    }
+
+    #[test]
+    fn test_cli_overrides_config() {
+        use clap::Parser;
+        let config = Config {
+            provider: Some("ollama".to_string()),
+            ..Default::default()
+        };
+
+        let mut cli = crate::Cli::try_parse_from(["trickery", "generate", "Hi"]).unwrap();
+        cli.config = config.clone();
+        assert_eq!(cli.provider_name(), "ollama");
+
+        let mut cli =
+            crate::Cli::try_parse_from(["trickery", "generate", "Hi", "--provider", "gemini"])
+                .unwrap();
+        cli.config = config;
+        assert_eq!(cli.provider_name(), "gemini");
+    }
+}
@@ -1,15 +1,24 @@
 use clap::{CommandFactory, Parser, Subcommand};
 use clap_complete::aot::{generate, Shell};
 use serde::ser;
-use std::io;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use tokio_util::sync::CancellationToken;
 
-use commands::{generate::GenerateArgs, image::ImageArgs, CommandExec, CommandExecutionContext};
-use output::write_command_stdout_as_json;
+use commands::{
+    chat::ChatArgs, doctor::DoctorArgs, generate::GenerateArgs, image::ImageArgs, lint::LintArgs,
+    list_tools::ListToolsArgs, models::ModelsArgs, summarize::SummarizeArgs, CommandExec,
+    CommandExecutionContext,
+};
+use output::{write_command_file, write_command_stdout};
 
 mod commands;
+mod config;
 mod error;
 mod output;
 mod provider;
+mod spinner;
+mod tools;
 mod trickery;
 
 const LONG_ABOUT: &str = "\
@@ -20,7 +29,9 @@ It supports Jinja2-like template variables in prompts, model selection, and is
 designed for CI/CD integration.
 
 ENVIRONMENT VARIABLES:
-  OPENAI_API_KEY    Required. Your OpenAI API key for authentication.
+  OPENAI_API_KEY      Required. Your OpenAI API key for authentication.
+  GEMINI_API_KEY      Required for the gemini provider.
+  TRICKERY_PROVIDER   Optional. LLM provider to use: openai (default), ollama, gemini, azure.
 
 For comprehensive help with all options and examples, use: trickery help --full";
 
@@ -34,23 +45,170 @@ pub struct Cli {
     /// Type of the output format
     #[arg(short, long, global = true)]
     output: Option<Output>,
+
+    /// Write the result to this file instead of stdout (JSON unless --output is set)
+    #[arg(long, global = true, value_hint = clap::ValueHint::FilePath)]
+    output_file: Option<PathBuf>,
+
+    /// Indent JSON on stdout (the default when stdout is a terminal; piped JSON is compact)
+    #[arg(long, global = true)]
+    pretty: bool,
+
+    /// Load environment variables (API keys, ...) from this file; default: ./.env if present
+    #[arg(long, global = true, value_name = "PATH", value_hint = clap::ValueHint::FilePath)]
+    env_file: Option<PathBuf>,
+
+    /// LLM provider: openai, ollama, gemini, azure (default: openai)
+    #[arg(long, global = true, env = "TRICKERY_PROVIDER")]
+    provider: Option<String>,
+
+    /// API base URL for the selected provider, overriding *_BASE_URL variables and the config file
+    #[arg(long, global = true, value_name = "URL")]
+    base_url: Option<String>,
+
+    /// OpenAI organization ID sent as OpenAI-Organization, overriding OPENAI_ORG_ID
+    #[arg(long, global = true, value_name = "ID")]
+    org: Option<String>,
+
+    /// OpenAI project ID sent as OpenAI-Project, overriding OPENAI_PROJECT_ID
+    #[arg(long, global = true, value_name = "ID")]
+    project: Option<String>,
+
+    /// Network timeout in seconds for provider requests, network tools and template URLs, overriding *_TIMEOUT_SECS
+    #[arg(long, global = true, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: Option<u64>,
+
+    /// Maximum simultaneous chat completions, overriding OPENAI_MAX_CONCURRENCY
+    #[arg(long, global = true, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_concurrency: Option<u64>,
+
+    /// Send the token limit as max_tokens instead of max_completion_tokens (older
+    /// OpenAI-compatible servers and proxies)
+    #[arg(long, global = true)]
+    legacy_max_tokens: bool,
+
+    /// Model to retry with when the model is rate limited or overloaded; repeat for a chain
+    #[arg(long, global = true, value_name = "MODEL")]
+    model_fallback: Vec<String>,
+
+    /// Print each request body (pretty JSON) to stderr before sending it
+    #[arg(long, global = true, env = "TRICKERY_DEBUG", value_parser = clap::builder::BoolishValueParser::new())]
+    debug_request: bool,
+
+    /// Write each completion request body (pretty JSON, API key redacted) to this file
+    #[arg(long, global = true, value_name = "PATH", value_hint = clap::ValueHint::FilePath)]
+    save_request: Option<PathBuf>,
+
+    /// Write each raw completion response body to this file
+    #[arg(long, global = true, value_name = "PATH", value_hint = clap::ValueHint::FilePath)]
+    save_response: Option<PathBuf>,
+
+    /// Render the prompt and request settings without calling the provider
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Reuse responses to identical completion requests from the on-disk cache
+    #[arg(long, global = true, env = "TRICKERY_CACHE", value_parser = clap::builder::BoolishValueParser::new())]
+    cache: bool,
+
+    /// Bypass the cache, even when enabled by --cache or the config file
+    #[arg(long, global = true, conflicts_with = "cache")]
+    no_cache: bool,
+
+    /// Extra header for every provider request, as `Name: value` (can be repeated)
+    #[arg(long = "header", global = true, value_name = "NAME: VALUE", value_parser = provider::parse_header)]
+    headers: Vec<(String, String)>,
+
+    /// Let --header (or the config file) replace the Authorization/api-key header
+    #[arg(long, global = true)]
+    allow_auth_header: bool,
+
+    /// Never send tools or run tool calls, even when --tools or --enhance-tools ask for them
+    #[arg(long, global = true)]
+    no_tools: bool,
+
+    /// Seconds a cached response stays valid (default: 86400)
+    #[arg(long, global = true, value_name = "SECS")]
+    cache_ttl: Option<u64>,
+
+    /// Plain ASCII labels instead of emoji in errors (also with NO_COLOR or when stderr is not a TTY)
+    #[arg(long, visible_alias = "plain", global = true)]
+    no_color: bool,
+
+    /// Print only the result: no spinner, usage summaries or status lines (errors and warnings
+    /// still go to stderr)
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Log to stderr: --verbose for info, --verbose --verbose for debug (default: warnings)
+    #[arg(long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Defaults from the config file, below CLI flags
+    #[arg(skip)]
+    config: config::Config,
+
+    /// Cancelled on Ctrl-C
+    #[arg(skip)]
+    cancellation: CancellationToken,
 }
 
 #[derive(clap::ValueEnum, Clone)]
 enum Output {
     Json,
+    Yaml,
+}
+
+/// Output of the completion subcommand: a shell completion script or a roff man page
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum CompletionTarget {
+    Bash,
+    Elvish,
+    Fish,
+    Powershell,
+    Zsh,
+    Nushell,
+    Manpage,
+}
+
+/// Write the completion script or man page for the CLI
+fn write_completion(target: CompletionTarget, out: &mut dyn Write) -> io::Result<()> {
+    let mut cmd = Cli::command();
+    let name = cmd.get_name().to_string();
+    match target {
+        CompletionTarget::Bash => generate(Shell::Bash, &mut cmd, name, out),
+        CompletionTarget::Elvish => generate(Shell::Elvish, &mut cmd, name, out),
+        CompletionTarget::Fish => generate(Shell::Fish, &mut cmd, name, out),
+        CompletionTarget::Powershell => generate(Shell::PowerShell, &mut cmd, name, out),
+        CompletionTarget::Zsh => generate(Shell::Zsh, &mut cmd, name, out),
+        CompletionTarget::Nushell => generate(clap_complete_nushell::Nushell, &mut cmd, name, out),
+        CompletionTarget::Manpage => return clap_mangen::Man::new(cmd).render(out),
+    }
+    Ok(())
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     /// Generate content
-    Generate(GenerateArgs),
+    Generate(Box<GenerateArgs>),
     /// Generate or edit images
     Image(ImageArgs),
-    /// Outputs the completion file for given shell
+    /// Chat interactively, keeping the conversation across turns
+    Chat(ChatArgs),
+    /// Check prompt templates (includes, front matter, variables) without calling a provider
+    Lint(LintArgs),
+    /// Check that the provider is configured and reachable
+    Doctor(DoctorArgs),
+    /// List tools available to generate --tools
+    ListTools(ListToolsArgs),
+    /// List models offered by the provider
+    Models(ModelsArgs),
+    /// Summarize a document of any length (chunked map-reduce)
+    Summarize(SummarizeArgs),
+    /// Outputs the completion file for given shell, or a man page
     Completion {
         #[arg(index = 1, value_enum)]
-        shell: Shell,
+        shell: CompletionTarget,
     },
     /// Print help information
     Help {
@@ -65,21 +223,144 @@ impl Cli {
     where
         T: ser::Serialize,
     {
-        match executor.exec(self).await {
+        // Dropping the command future on Ctrl-C aborts any in-flight request
+        let result = tokio::select! {
+            result = executor.exec(self) => result,
+            _ = self.cancellation.cancelled() => Err(provider::ProviderError::Cancelled.into()),
+        };
+        match result {
             Ok(result) => {
-                if let Some(Output::Json) = self.output {
-                    write_command_stdout_as_json(&*result)
+                if let Some(ref path) = self.output_file {
+                    let format = self.output.clone().unwrap_or(Output::Json);
+                    if let Err(err) = write_command_file(&*result, &format, path) {
+                        error::print_error(&err);
+                        std::process::exit(1);
+                    }
+                } else if let Some(ref format) = self.output {
+                    write_command_stdout(&*result, format, self.pretty_json())
+                }
+                if result.failed() {
+                    std::process::exit(1);
                 }
             }
             Err(err) => {
                 error::print_error(err.as_ref());
-                std::process::exit(1);
+                // 130 = terminated by Ctrl-C (128 + SIGINT), as shells report it
+                let cancelled = matches!(
+                    err.downcast_ref::<provider::ProviderError>(),
+                    Some(provider::ProviderError::Cancelled)
+                );
+                std::process::exit(if cancelled { 130 } else { 1 });
             }
         }
     }
 
+    /// Whether JSON on stdout is indented: with --pretty or when a person is reading it
+    fn pretty_json(&self) -> bool {
+        self.pretty || io::stdout().is_terminal()
+    }
+
     pub fn is_interactive(&self) -> bool {
-        self.output.is_none()
+        self.output.is_none() && self.output_file.is_none()
+    }
+
+    /// Whether tool use is switched off for every command (--no-tools)
+    pub fn tools_disabled(&self) -> bool {
+        self.no_tools
+    }
+
+    /// Whether informational messages around the result are suppressed (--quiet)
+    pub fn is_quiet(&self) -> bool {
+        self.quiet
+    }
+
+    /// "Thinking..." on stderr until the guard is dropped; None when output is structured,
+    /// piped, or stderr carries logs
+    pub fn start_spinner(&self) -> Option<spinner::Spinner> {
+        self.spinner_allowed(io::stdout().is_terminal(), io::stderr().is_terminal())
+            .then(|| spinner::Spinner::start("Thinking..."))
+    }
+
+    fn spinner_allowed(&self, stdout_is_terminal: bool, stderr_is_terminal: bool) -> bool {
+        spinner::spinner_enabled(
+            self.is_interactive() && !self.quiet,
+            stdout_is_terminal,
+            stderr_is_terminal,
+            self.verbose > 0 || self.debug_request,
+        )
+    }
+
+    /// Provider name from --provider/TRICKERY_PROVIDER, the config file, or the default
+    pub fn provider_name(&self) -> &str {
+        self.provider
+            .as_deref()
+            .or(self.config.provider.as_deref())
+            .unwrap_or(provider::DEFAULT_PROVIDER)
+    }
+
+    /// Build the selected provider, applying --base-url or the config file's base_url
+    pub fn build_provider(&self) -> Result<Box<dyn provider::Provider>, provider::ProviderError> {
+        self.build_provider_with_cache(self.cache_options())
+    }
+
+    /// Build the selected provider with the given cache settings (None: always call the backend)
+    pub fn build_provider_with_cache(
+        &self,
+        cache: Option<provider::cache::CacheOptions>,
+    ) -> Result<Box<dyn provider::Provider>, provider::ProviderError> {
+        let headers = self.request_headers();
+        provider::build_provider(
+            self.provider_name(),
+            &provider::ProviderOptions {
+                base_url: self.base_url.as_deref(),
+                fallback_base_url: self.config.base_url.as_deref(),
+                organization: self.org.as_deref(),
+                project: self.project.as_deref(),
+                timeout: self.timeout.map(std::time::Duration::from_secs),
+                max_concurrency: self.max_concurrency.map(|n| n as usize),
+                legacy_max_tokens: self.legacy_max_tokens,
+                debug_requests: self.debug_request,
+                capture: provider::PayloadCapture {
+                    request: self.save_request.clone(),
+                    response: self.save_response.clone(),
+                },
+                cache,
+                model_fallbacks: &self.model_fallback,
+                headers: &headers,
+                allow_auth_header: self.allow_auth_header,
+            },
+        )
+    }
+
+    /// Headers from the config file, then --header (so a flag replaces a file entry)
+    fn request_headers(&self) -> Vec<(String, String)> {
+        let mut headers: Vec<(String, String)> = self
+            .config
+            .headers
+            .iter()
+            .flatten()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        headers.extend(self.headers.iter().cloned());
+        headers
+    }
+
+    /// Cache settings when caching is on (--cache or `cache = true`) and not bypassed
+    fn cache_options(&self) -> Option<provider::cache::CacheOptions> {
+        if self.no_cache || !(self.cache || self.config.cache.unwrap_or(false)) {
+            return None;
+        }
+        let Some(dir) = provider::cache::default_cache_dir() else {
+            error::print_warning("No cache directory (set TRICKERY_CACHE_DIR); caching disabled");
+            return None;
+        };
+        Some(provider::cache::CacheOptions {
+            dir,
+            ttl: self
+                .cache_ttl
+                .map(std::time::Duration::from_secs)
+                .unwrap_or(provider::cache::DEFAULT_CACHE_TTL),
+        })
     }
 }
 
@@ -91,20 +372,69 @@ impl CommandExecutionContext for Cli {
 
 #[tokio::main]
 async fn main() {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    match config::load_env_file(cli.env_file.as_deref()) {
+        // Parse again so env fallbacks of flags (TRICKERY_PROVIDER, ...) see the file too
+        Ok(Some(_)) => cli = Cli::parse(),
+        Ok(None) => {}
+        Err(e) if cli.env_file.is_some() => {
+            error::print_error(Box::<dyn std::error::Error>::from(e).as_ref());
+            std::process::exit(1);
+        }
+        Err(e) => error::print_warning(&format!("{} (ignored)", e)),
+    }
+    init_logging(cli.verbose);
+    error::set_icon_style(error::IconStyle::detect(
+        cli.no_color,
+        std::env::var("NO_COLOR").ok().as_deref(),
+        io::stderr().is_terminal(),
+    ));
+    cli.config = config::load_config();
+
+    let cancellation = cli.cancellation.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancellation.cancel();
+        }
+    });
 
     match &cli.command {
         Some(Commands::Generate(args)) => {
-            cli.exec_command(args).await;
+            cli.exec_command(args.as_ref()).await;
         }
         Some(Commands::Image(args)) => {
             cli.exec_command(args).await;
         }
+        Some(Commands::Chat(args)) => {
+            cli.exec_command(args).await;
+        }
+        Some(Commands::Lint(args)) => {
+            cli.exec_command(args).await;
+        }
+        Some(Commands::Doctor(args)) => {
+            cli.exec_command(args).await;
+        }
+        Some(Commands::ListTools(args)) => {
+            cli.exec_command(args).await;
+        }
+        Some(Commands::Models(args)) => {
+            cli.exec_command(args).await;
+        }
+        Some(Commands::Summarize(args)) => {
+            cli.exec_command(args).await;
+        }
         Some(Commands::Completion { shell }) => {
-            let mut cmd = Cli::command();
-            let name = cmd.get_name().to_string();
-            eprintln!("Generating completion file for {shell}...");
-            generate(*shell, &mut cmd, name, &mut io::stdout());
+            if !cli.is_quiet() {
+                if *shell == CompletionTarget::Manpage {
+                    eprintln!("Generating man page...");
+                } else {
+                    eprintln!("Generating completion file for {shell:?}...");
+                }
+            }
+            if let Err(err) = write_completion(*shell, &mut io::stdout()) {
+                error::print_error(&err);
+                std::process::exit(1);
+            }
         }
         Some(Commands::Help { full }) => {
             if *full {
@@ -117,6 +447,25 @@ async fn main() {
     }
 }
 
+/// Log level for the number of --verbose flags
+fn log_level(verbose: u8) -> tracing::Level {
+    match verbose {
+        0 => tracing::Level::WARN,
+        1 => tracing::Level::INFO,
+        _ => tracing::Level::DEBUG,
+    }
+}
+
+/// Send this crate's tracing events to stderr (dependencies stay quiet)
+fn init_logging(verbose: u8) {
+    use tracing_subscriber::{filter::Targets, layer::SubscriberExt, util::SubscriberInitExt};
+
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(io::stderr))
+        .with(Targets::new().with_target(env!("CARGO_CRATE_NAME"), log_level(verbose)))
+        .init();
+}
+
 fn print_full_help() {
     print!(
         r#"# trickery - CLI tool for generating textual artifacts using LLM
@@ -136,10 +485,87 @@ cargo install trickery
 ## Environment Variables
 
 - `OPENAI_API_KEY` (required): Your OpenAI API key for authentication
+- `OPENAI_BASE_URL` (optional): API base URL (default: https://api.openai.com/v1)
+- `OPENAI_TIMEOUT_SECS` (optional): Request timeout in seconds (default: 120)
+- `OPENAI_DEFAULT_MODEL` (optional): Model used without `--model` (default: gpt-5-mini)
+- `OPENAI_REASONING_MODELS` (optional): Comma-separated model prefixes treated as reasoning models
+- `OPENAI_DEVELOPER_ROLE` (optional): Send system prompts with the `developer` role: `never`
+  (default), `auto` (reasoning models) or `always`
+- `OPENAI_ORG_ID`, `OPENAI_PROJECT_ID` (optional): Sent as `OpenAI-Organization` and
+  `OpenAI-Project` headers on every request
+- `OPENAI_MAX_CONCURRENCY` (optional): Most chat completions in flight at once (default: unlimited)
+- `OPENAI_LEGACY_MAX_TOKENS` (optional): `1`/`true` sends the token limit as `max_tokens`
+  (see `--legacy-max-tokens`)
+- `TRICKERY_PROVIDER` (optional): LLM provider to use: openai, ollama, gemini, azure (default: openai)
+- `OLLAMA_BASE_URL` (optional): Ollama API base URL (default: http://localhost:11434/v1)
+- `GEMINI_API_KEY` (required for gemini): Your Gemini API key
+- `GEMINI_BASE_URL` (optional): Gemini API base URL (default: https://generativelanguage.googleapis.com/v1beta)
+- `GEMINI_TIMEOUT_SECS` (optional): Gemini request timeout in seconds (default: 120)
+- `GEMINI_DEFAULT_MODEL` (optional): Gemini model used without `--model` (default: gemini-2.5-flash)
+- `AZURE_OPENAI_ENDPOINT`, `AZURE_OPENAI_API_KEY`, `AZURE_OPENAI_DEPLOYMENT` (required for azure):
+  Resource endpoint, key and chat deployment name
+- `AZURE_OPENAI_API_VERSION` (optional): Azure API version (default: 2024-10-21)
+- `TRICKERY_CONFIG` (optional): Config file path (default: ~/.config/trickery/config.toml)
+- `TRICKERY_CACHE_DIR` (optional): Directory for `--cache` (default: $XDG_CACHE_HOME/trickery or ~/.cache/trickery)
+- `TRICKERY_USER` (optional): Default for `--user`
+- `SEARCH_API_URL` (required for web_search), `SEARCH_API_KEY` (optional): Search API for
+  `--allow-tool web_search`, sent as a Bearer token when set
+- `WEATHER_API_KEY` (required for get_weather), `WEATHER_API_URL` (optional): OpenWeatherMap key
+  and endpoint for `--allow-tool get_weather`
+
+Any of these can be kept in a `.env` file (`KEY=value` lines): `./.env` is loaded when present,
+or pass `--env-file <PATH>`. Variables already set in the environment take precedence.
 
 ## Global Options
 
-- `-o, --output <FORMAT>`: Output format (json). When set, outputs structured JSON
+- `-o, --output <FORMAT>`: Output format (json, yaml). When set, outputs structured data
+  including token `usage` when the provider reports it. Without it, a "Thinking..." spinner is
+  shown on stderr while waiting for the provider (only on a terminal, not with `--verbose`,
+  `--debug-request` or `--trace`)
+- `--output-file <PATH>`: Write the result to PATH instead of stdout (JSON unless `-o` is set)
+- `--pretty`: Indent JSON written to stdout. This is the default when stdout is a terminal;
+  piped JSON is compact (one line). `--output-file` JSON is always indented
+- `--env-file <PATH>`: Load environment variables from PATH (default: `./.env` if present);
+  existing variables win. A missing or malformed explicit file is an error
+- `--provider <NAME>`: LLM provider: openai, ollama, gemini, azure (default: openai, env: TRICKERY_PROVIDER)
+- `--base-url <URL>`: API base URL for the selected provider (e.g. a proxy or gateway);
+  overrides `OPENAI_BASE_URL`/`OLLAMA_BASE_URL`/`GEMINI_BASE_URL`/`AZURE_OPENAI_ENDPOINT` and the config file. Keys still come from the environment
+- `--timeout <SECS>`: Timeout for every provider request, overriding `OPENAI_TIMEOUT_SECS`/
+  `GEMINI_TIMEOUT_SECS` (default: 120), for network tools from `--allow-tool` and for fetching
+  a template from a URL (default: 30)
+- `--max-concurrency <N>`: At most N chat completions in flight at once; further calls wait
+  (OpenAI-compatible providers, overrides `OPENAI_MAX_CONCURRENCY`)
+- `--legacy-max-tokens`: Send the token limit as `max_tokens` instead of `max_completion_tokens`,
+  for OpenAI-compatible servers and proxies (e.g. LiteLLM) that only accept the older name
+- `--header <NAME: VALUE>`: Extra header for every provider request, e.g. for a gateway
+  (`--header "X-Gateway-Token: abc"`; repeatable, also `[headers]` in the config file).
+  OpenAI-compatible providers only; gemini fails when headers are set. Replacing `Authorization`/`api-key` is rejected unless
+  `--allow-auth-header` is given
+- `--no-tools`: Disable tool use everywhere: no tools are sent and tool calls in responses are
+  ignored, even with `--tools` or `--enhance-tools` (a warning says so)
+- `--model-fallback <MODEL>`: When a completion fails with a rate limit or overload (429, 5xx,
+  timeout), retry it with this model. Repeat for an ordered chain, e.g.
+  `--model-fallback gpt-4.1-mini --model-fallback gpt-4.1-nano`. Other errors fail immediately
+- `--org <ID>`, `--project <ID>`: OpenAI organization and project headers, overriding
+  `OPENAI_ORG_ID`/`OPENAI_PROJECT_ID` (openai provider only)
+- `--verbose`: Log to stderr; repeat for more detail (`--verbose`: info such as token usage and
+  tool calls, `--verbose --verbose`: debug such as requests and template loading). API keys are never logged
+- `-q, --quiet`: Print only the result: no spinner, token usage summary, "Image saved to:" or
+  other status lines (a saved image prints just its path). Errors and warnings still go to stderr
+- `--no-color`, `--plain`: Use ASCII labels (`[error]`, `[info]`, ...) instead of emoji in errors
+  and warnings. Also applied when `NO_COLOR` is set or stderr is not a terminal
+- `--debug-request`: Print each request (URL and pretty JSON body) to stderr before sending it
+  (env: TRICKERY_DEBUG=1). API keys are redacted
+- `--save-request <PATH>`, `--save-response <PATH>`: Write the completion request body (pretty
+  JSON, API key redacted) and the raw response body, error responses included, to files. Each
+  call overwrites them, so after an agent loop they hold the last exchange. Cached answers
+  don't touch them
+- `--dry-run`: Print the rendered prompt and request settings without calling the provider
+  (no API key needed; results are marked with `"dry_run": true`)
+- `--cache`: Answer repeated identical completion requests (same provider, base URL, model, messages
+  and parameters) from an on-disk cache instead of calling the API (env: TRICKERY_CACHE=1)
+- `--no-cache`: Bypass the cache, even when `cache = true` is set in the config file
+- `--cache-ttl <SECS>`: How long cached responses stay valid (default: 86400)
 - `-h, --help`: Print help (use `--help` for detailed info)
 - `-V, --version`: Print version
 
@@ -147,8 +573,10 @@ cargo install trickery
 
 ### generate - Generate content from prompts
 
-Generate text content from a prompt. Input is auto-detected: if a file exists at
-the given path, it reads from the file; otherwise treats input as direct text.
+Generate text content from a prompt. Input is auto-detected: an `http(s)://` URL is
+fetched (up to 1 MiB, no includes); if a file exists at the given path, it reads from the
+file; otherwise treats input as direct text.
+When no input is given and stdin is piped, the prompt is read from stdin.
 
 **Usage:**
 ```bash
@@ -158,11 +586,85 @@ trickery generate [INPUT] [OPTIONS]
 **Options:**
 - `[INPUT]`: Prompt input - file path or direct text (auto-detected)
 - `-i, --input <INPUT>`: Alternative to positional (for backwards compatibility)
+- `-t, --text <TEXT>`: Prompt as direct text, never treated as a file path
+- `--input-dir <DIR>`: Run every `*.md`/`*.txt` prompt file in DIR with the same variables and flags.
+  Printed as `==> name <==` sections; JSON/YAML results map each file path (relative to DIR) to its output
+- `--recursive`: With `--input-dir`, also run prompt files in subdirectories
+- `--concurrency <N>`: With `--input-dir`, number of files generated at the same time (default: 4)
+- `--continue-on-error`: With `--input-dir`, keep going when a file fails. Failures are printed
+  after the outputs; JSON/YAML results become `{{"outputs": {{...}}, "failures": {{path: error}}}}`.
+  The exit code is 1 if any file failed
+- `--jsonl <FILE>`: Generate once per line of a JSON Lines file; each line is an object of
+  variables that override `--var`/`--vars-file`. Prints `{{"line": N, "output": "..."}}` per line as
+  soon as it is done (`{{"line": N, "error": "..."}}` for malformed lines or failed generations,
+  and the run goes on). Blank lines are skipped; `-o`/`--output-file` are not supported. The
+  exit code is 1 if any line failed
 - `-v, --var <KEY=VALUE>`: Variables to be used in prompt (can be repeated)
+- `--vars-file <FILE>`: JSON or YAML file with variables (overridden by `--var`)
+- `--strict-vars`: Fail if a template variable has no value and no default
+- `--system <TEXT>`: System prompt sent before the prompt (supports template variables)
+- `--system-file <FILE>`: Read the system prompt from FILE (conflicts with `--system`)
+- `--conversation <FILE>`: JSON array of prior messages (with `role` and `content`) sent before the prompt;
+  the prompt is optional when given. Template variables apply to every message
+- `--save-transcript <FILE>`: After the run, write the messages sent plus the reply to FILE (JSON, same format)
+- `--continue <FILE>`: Send a saved transcript verbatim before the prompt (conflicts with `--conversation`,
+  `--system`); combine with `--save-transcript FILE` to keep a running session
 - `-m, --model <MODEL>`: Model to use (e.g., gpt-5.2, gpt-5-mini, o1, o3-mini)
-- `-r, --reasoning <LEVEL>`: Reasoning level for o1/o3 models: low, medium, high
-- `--max-tokens <N>`: Maximum tokens in response
-- `--image <PATH|URL>`: Image files or URLs for multimodal prompts (can be repeated)
+- `-r, --reasoning <LEVEL>`: Reasoning level for reasoning models (o-series, gpt-5): low, medium, high
+- `--max-tokens <N>`: Maximum tokens in response (must be at least 1)
+- `--temperature <T>`: Sampling temperature 0.0-2.0 (ignored for reasoning models)
+- `--seed <N>`: Seed for reproducible sampling (best effort). Results include `system_fingerprint`
+  when the provider reports it; a changed fingerprint means the backend changed
+- `--user <ID>`: End-user identifier sent as OpenAI's `user` field for abuse monitoring
+  (env: TRICKERY_USER). Not sent to Gemini
+- `--logit-bias <TOKEN_ID=BIAS>`: Bias a token ID from -100 (ban) to 100 (force), can be repeated
+  (OpenAI-compatible backends; ignored for reasoning models)
+- `--frequency-penalty <N>`, `--presence-penalty <N>`: Discourage repetition, -2.0 to 2.0
+  (frequency scales with how often a token appeared, presence applies once it appeared; ignored for reasoning models)
+- `--strip-fences`: Remove a code fence (and its language tag) that wraps the whole output, then
+  trim it. Output with text around the fence or several blocks is only trimmed
+- `--render <FORMAT>`: `markdown` (default) or `raw` print the output as returned; `plain`
+  flattens markdown to plain text (heading/quote markers, emphasis and code backticks removed,
+  bullets as `- `, links as `text (url)`, code block contents kept)
+- `--logprobs`: Include each output token's log probability in JSON/YAML results (`logprobs`;
+  OpenAI-compatible providers, ignored for reasoning models). Conflicts with `--tools`
+- `--top-logprobs <N>`: With `--logprobs`, also list the N most likely alternatives per token (0-20)
+- `--stop <SEQ>`: Stop generating at SEQ (repeatable, up to 4; ignored for reasoning models)
+- `-n, --count <N>`: Generate N candidates (1-10, best-of-n) in one request (the API's `n`; backends
+  that return fewer choices get extra requests, each with its own seed: `--seed` plus the candidate
+  index, or a random one). Printed separated by `---`;
+  JSON/YAML results list them in `outputs` (`output` is the first). Conflicts with `--tools`
+- `--tools [NAME]`: Let the model call tools in an agent loop (repeatable; no name = all built-in tools; see `trickery list-tools`)
+- `--allow-tool <NAME>`: With `--tools`, also offer an opt-in tool (repeatable): run_shell runs
+  shell commands without confirmation, killing them after 120s; fetch_url GETs any http(s) URL;
+  web_search queries `SEARCH_API_URL`; get_weather uses OpenWeatherMap (`WEATHER_API_KEY`);
+  validate_json checks JSON against a JSON Schema
+- `--allow-command <PROGRAM>`: With `--allow-tool run_shell`, only let it run these programs (repeatable);
+  shell operators such as `;` and `|` are then rejected
+- `--tool-choice <CHOICE>`: With `--tools`: auto, none (no tool calls), required (at least one
+  call) or a tool name to force that call. A forced choice applies to the first round only
+- `--max-iterations <N>`: Maximum agent loop iterations with `--tools` (default: 10).
+  When exhausted, the last partial answer is returned with a warning on stderr
+- `--fail-on-max-iterations`: Exit with an error instead when `--max-iterations` runs out
+- `--token-budget <N>`: With `--tools`, stop once N total tokens are used and return the partial answer
+- `--context-limit <CHARS>`: With `--tools`, drop the oldest messages before each request to keep the history
+  under CHARS characters (system prompt kept; tool calls dropped with their results)
+- `--timezone <TZ>`: With `--tools`, default time zone for `current_time`: UTC, local or an offset like +02:00
+- `--time-format <FORMAT>`: With `--tools`, default strftime format for `current_time` (default: RFC 3339)
+- `--trace`: With `--tools`, write each executed tool call to stderr as a JSON line
+  (`iteration`, `id`, `name`, `arguments`, `output`, `is_error`)
+- `--stream`: With `--tools`, print the answer as it arrives and `Calling <tool>...` on stderr as soon as
+  the model makes each call (terminal output only; not with `--strip-fences` or `--render`)
+- `--json-object`: Require the response to be a JSON object
+- `--json-schema <FILE>`: Require a JSON response matching the JSON Schema in FILE
+- `--expect json`: Fail unless the output parses as JSON (checked after `--strip-fences`); works with any
+  provider or prompt, not only with `--json-object`/`--json-schema`
+- `--expect-retry`: With `--expect`, send the parse error back once and ask for corrected JSON before failing
+- `--context <PATH>`: Append a text file to the prompt as a `<file name="PATH">` block (can be
+  repeated). File contents are not treated as a template; all files together are capped at
+  200000 bytes, with a warning naming the files cut short
+- `--image <PATH|URL>`: Image files or URLs for multimodal prompts (can be repeated; alias `--attach`).
+  Local files are sent base64-encoded with the prompt text in one user message
 - `--image-detail <LEVEL>`: Image detail level: auto, low, high (default: auto)
 
 **Examples:**
@@ -174,6 +676,9 @@ trickery generate prompts/greeting.md
 # Direct text input
 trickery generate "Write a haiku about programming"
 
+# Prompt from stdin
+echo "Write a haiku about pipes" | trickery generate
+
 # Long text with shell quoting
 trickery generate "You are a helpful assistant.
 
@@ -187,20 +692,48 @@ trickery generate "Hello {{ name }}!" --var name=Alice
 # Using a specific model
 trickery generate "Explain quantum computing" -m gpt-5.2
 
-# With reasoning (for o1/o3 models)
+# Every prompt file in a directory, as a JSON map of file -> output
+trickery generate --input-dir prompts/ --recursive --var lang=en -o json
+
+# Three candidates to pick from
+trickery generate "Suggest a name for a coffee shop" -n 3 -m gpt-4.1 --temperature 1.2
+
+# Multi-turn session kept in a transcript file
+trickery generate "Suggest a project name" --save-transcript chat.json
+trickery generate "Shorter, please" --continue chat.json --save-transcript chat.json
+
+# With a system prompt
+trickery generate "Review this function" --system "You are a strict senior reviewer"
+
+# Letting the model use tools
+trickery generate "What is 17.5% of 2340?" --tools calculate
+trickery generate "Summarize Cargo.toml" --tools --max-iterations 5
+trickery generate "How many Rust files are under src?" --tools list_dir --allow-tool run_shell
+
+# With reasoning (for reasoning models)
 trickery generate prompts/analysis.md -m o3-mini -r high
 
 # JSON output for CI/CD
 trickery generate "Generate a JSON object" -o json
 
+# YAML output
+trickery generate "Say hello" -o yaml
+
+# Write JSON result to a file
+trickery generate "Say hello" --output-file results/hello.json
+
+# Structured output constrained by a JSON Schema
+trickery generate "Describe a fictional person" --json-schema person.schema.json
+
 # Multimodal with image input
 trickery generate "What is in this image?" --image photo.jpg
 ```
 
 ### image - Generate or edit images
 
-Generate new images or edit existing ones. Input is auto-detected: if a file exists
-at the given path, it reads from the file; otherwise treats input as direct text.
+Generate new images or edit existing ones. Input is auto-detected: an `http(s)://` URL
+is fetched; if a file exists at the given path, it reads from the file; otherwise treats
+input as direct text.
 
 **Usage:**
 ```bash
@@ -212,14 +745,24 @@ trickery image [INPUT] [OPTIONS]
 - `-i, --input <INPUT>`: Alternative to positional (for backwards compatibility)
 - `-s, --save <FILE>`: Output file path (auto-generated if not provided)
 - `-v, --var <KEY=VALUE>`: Variables to be used in prompt (can be repeated)
+- `--vars-file <FILE>`: JSON or YAML file with variables (overridden by `--var`)
+- `--strict-vars`: Fail if a template variable has no value and no default
 - `-m, --model <MODEL>`: Model to use (e.g., gpt-4.1, gpt-5, gpt-5.2)
 - `--image <PATH|URL>`: Input image files or URLs for editing (can be repeated)
 - `--size <SIZE>`: Image size: auto, 1024x1024, 1024x1536 (portrait), 1536x1024 (landscape)
 - `--quality <QUALITY>`: Image quality: auto, low, medium, high
-- `--format <FORMAT>`: Output format: png, jpeg, webp
+- `--format <FORMAT>`: Output format: png, jpeg, webp. Decides the file type: a `--save` path with another
+  extension is renamed to match (`--save out.png --format webp` writes `out.webp`, with a warning)
 - `--background <BG>`: Background: auto, transparent, opaque
 - `--action <ACTION>`: Action: auto, generate, edit
 - `--compression <0-100>`: Compression level for jpeg/webp formats
+- `--count <N>`: Number of images to generate (1-10). With N > 1, files are numbered
+  (`cat-x1y2z-1.png`, `cat-x1y2z-2.png`, ...) and JSON output is an array of results
+- `--stdout-base64`: Return base64 image data instead of writing a file (unless `--save` is
+  given): in a `data` field with `-o json/yaml`, otherwise printed to stdout
+- `--enhance-tools <NAME>`: Rewrite the prompt with this built-in tool first (can be repeated); the
+  model calls the tools in an agent loop and its answer becomes the image prompt
+- `--enhance-iterations <N>`: Agent loop iterations for `--enhance-tools` (default: 10)
 
 **Examples:**
 
@@ -240,6 +783,12 @@ trickery image "A simple house icon" -s icons/home.png
 # With template variables
 trickery image "A {{ style }} banner" --var style=modern
 
+# Three variations of an icon (icons/home-1.png ... icons/home-3.png)
+trickery image "A simple house icon" -s icons/home.png --count 3
+
+# Base64 image data in JSON, no file written
+trickery image "A simple house icon" --stdout-base64 -o json
+
 # High quality landscape image
 trickery image "Beautiful mountain sunset" --size 1536x1024 --quality high
 
@@ -253,16 +802,132 @@ trickery image "Simple app icon" --background transparent --format png
 trickery image prompts/asset.md -o json
 ```
 
+### chat - Chat interactively
+
+Read messages from stdin one line at a time and print each reply; the conversation is kept
+across turns. Ends at `/exit` (or `/quit`), end of input (Ctrl-D) or Ctrl-C.
+With `-o json`/`-o yaml` or `--output-file`, replies go to stderr and the result is the
+full transcript (same format as `generate --save-transcript`).
+
+**Usage:**
+```bash
+trickery chat [OPTIONS]
+```
+
+**Commands (typed at the prompt):**
+- `/reset`: Forget the conversation (the system prompt is kept)
+- `/system <TEXT>`: Replace the system prompt; `/system` alone removes it
+- `/exit`, `/quit`: End the session
+
+**Options:**
+- `--system <TEXT>`: System prompt for the session
+- `-m, --model <MODEL>`, `-r, --reasoning <LEVEL>`, `--max-tokens <N>`, `--temperature <T>`, `--user <ID>`: As for generate
+- `--tools [NAME]`: Let the model call tools on every turn (repeatable; no name = all built-in tools).
+  Only your messages and the final answers are kept in the history
+- `--allow-tool <NAME>`: With `--tools`, also offer an opt-in tool (repeatable), as for generate
+- `--max-iterations <N>`: Agent loop iterations per turn (default: 10, requires `--tools`)
+
+**Examples:**
+```bash
+trickery chat --system "You are a concise assistant"
+trickery chat --tools calculate -m gpt-5.2
+```
+
+### lint - Check prompt templates
+
+Check template files without calling a provider: includes are expanded (missing files and
+cycles are errors), front matter is parsed (invalid values are errors, unknown keys are
+warnings) and every `{{{{ var }}}}` needs a value from `--var`/`--vars-file` or a default.
+Prints `FILE: ok` or one `FILE: error: ...`/`FILE: warning: ...` line per finding and exits 1
+when any file has errors. With `-o json`/`-o yaml` the result is one report per file
+(`path`, `ok`, `variables`, `errors`, `warnings`) and the exit code is 0.
+
+**Usage:**
+```bash
+trickery lint <FILE>... [--var NAME=VALUE] [--vars-file FILE]
+```
+
+**Examples:**
+```bash
+trickery lint prompts/*.md
+trickery lint prompts/welcome.md --var name=Ada
+trickery -o json lint prompts/*.md
+```
+
+### doctor - Check provider setup
+
+Check that the selected provider is configured (API key and required variables set) and
+reachable (a minimal completion succeeds; the cache is bypassed). Prints the resolved
+provider, model and base URL, then `[ok]` or `[failed]` per check with the error and its
+hints. With `-o json`/`-o yaml` the result is `status` (`ok` or `error`), `provider`, `model`,
+`base_url` and `checks` (`name`, `ok`, `detail`). Exits 1 when a check fails.
+
+**Usage:**
+```bash
+trickery doctor [-m MODEL]
+```
+
+**Examples:**
+```bash
+trickery doctor
+trickery --provider gemini doctor -m gemini-2.5-flash
+trickery -o json doctor
+```
+
+### list-tools - List tools for generate --tools
+
+Print the name and description of each built-in tool. With `-o json` or `-o yaml`,
+emit the full tool definitions (name, description, JSON Schema parameters).
+
+**Usage:**
+```bash
+trickery list-tools
+trickery list-tools -o json
+```
+
+### models - List available models
+
+Print the model IDs the provider offers (`GET {{base_url}}/models`), one per line and sorted;
+use them with `--model`. With `-o json` or `-o yaml`, emit an array of IDs. Supported by the
+openai and ollama providers.
+
+**Usage:**
+```bash
+trickery models
+trickery --provider ollama -o json models
+```
+
+### summarize - Summarize long documents
+
+Summarize a file (or piped stdin) of any length. Text longer than `--chunk-size` characters is
+split into overlapping chunks, at paragraph, line or word breaks when possible; each chunk is
+summarized, then the summaries are combined in one final request. With `-o json` or `-o yaml`,
+emit `summary`, `chunks` and `usage`. `--dry-run` prints the chunk sizes without calling the
+provider.
+
+**Options:**
+- `-m, --model <MODEL>`: Model for every request (default: config file model, then provider default)
+- `--chunk-size <CHARS>`: Maximum characters per chunk (default: 12000)
+- `--chunk-overlap <CHARS>`: Characters repeated between consecutive chunks (default: 500)
+- `--instructions <TEXT>`: Extra instructions added to every request (e.g. "Focus on decisions")
+
+**Usage:**
+```bash
+trickery summarize report.md
+cat meeting.txt | trickery summarize --instructions "List action items"
+```
+
 ### completion - Generate shell completions
 
-Generate shell completion scripts for bash, zsh, fish, elvish, or powershell.
+Generate shell completion scripts for bash, zsh, fish, elvish, powershell, or nushell,
+or a man page (roff) with `manpage`.
 
 **Usage:**
 ```bash
 trickery completion <SHELL>
 ```
 
-**Supported shells:** bash, zsh, fish, elvish, powershell
+**Supported shells:** bash, zsh, fish, elvish, powershell, nushell (plus `manpage`)
 
 **Examples:**
 
@@ -275,6 +940,12 @@ trickery completion zsh > ~/.zfunc/_trickery
 
 # Generate fish completions
 trickery completion fish > ~/.config/fish/completions/trickery.fish
+
+# Generate nushell completions
+trickery completion nushell > ~/.config/nushell/completions/trickery.nu
+
+# Generate a man page
+trickery completion manpage > /usr/local/share/man/man1/trickery.1
 ```
 
 ## Template Variables
@@ -292,10 +963,66 @@ Keep it concise and friendly.
 trickery generate prompts/email.md --var name="Alice" --var topic="quarterly review"
 ```
 
+Whitespace inside braces is optional: `{{{{name}}}}` works the same as `{{{{ name }}}}`.
+Provide a default after `|`, used when the variable is not set: `{{{{ tone|friendly }}}}`.
+Write `\{{\{{` to emit literal `{{{{` braces.
+`--var` values that are valid JSON keep their type (`count=5`, `enabled=true`).
+Unset variables without a default are left as-is; pass `--strict-vars` to fail instead.
+
+Many variables can be loaded from a JSON or YAML object with `--vars-file`;
+`--var` flags take precedence over file values:
+```bash
+trickery generate prompts/email.md --vars-file vars.yaml --var name="Bob"
+```
+
+Shared fragments (style guides, personas) can be spliced in with `{{{{include "path"}}}}`.
+Paths are relative to the including file (the current directory for direct text);
+includes may nest up to 10 levels and cycles are reported as errors.
+Includes are expanded before front matter and variables, so fragments can use variables.
+
+## Front Matter
+
+Prompt files may start with a YAML front matter block declaring defaults.
+CLI flags (e.g. `-m`, `-r`, `--max-tokens`) override front matter values.
+
+```
+---
+model: o3-mini
+reasoning: high
+temperature: 0.2
+max_tokens: 1000
+---
+Summarize {{{{ topic }}}} in three bullet points.
+```
+
+The `image` command uses `model` from front matter; other keys apply to `generate`.
+Other keys are ignored (`trickery lint` warns about them).
+
+## Config File
+
+Defaults for every invocation can be set in `~/.config/trickery/config.toml`
+(`$XDG_CONFIG_HOME/trickery/config.toml` when set, or the path in `TRICKERY_CONFIG`).
+A missing file is fine; an invalid one is reported and ignored.
+
+```toml
+provider = "ollama"
+model = "llama3"                      # generate only
+reasoning = "medium"                  # generate only
+max_tokens = 2000                     # generate only
+base_url = "http://gpu-box:11434/v1"  # unless OPENAI_/OLLAMA_/GEMINI_BASE_URL is set
+cache = true                          # as --cache; --no-cache bypasses it
+
+[headers]                             # sent with every request, as --header
+X-Gateway-Token = "abc123"
+```
+
+Precedence: CLI flags (and their environment variables) > front matter > config file > built-in defaults.
+
 ## Exit Codes
 
 - `0`: Success
 - `1`: Error (missing file, API error, invalid arguments, etc.)
+- `130`: Cancelled with Ctrl-C (in-flight requests are aborted)
 
 ## See Also
 
@@ -314,6 +1041,81 @@ mod tests {
         Cli::command().debug_assert();
     }
 
+    /// Parse `generate Hi` with flag `id` taken from `value` in the environment. The flag reads
+    /// `var` instead of its own variable: tests run in parallel and share the environment, so
+    /// each caller passes a name no other test sets.
+    fn parse_with_env(id: &str, var: &'static str, value: &str) -> Cli {
+        use clap::FromArgMatches;
+        std::env::set_var(var, value);
+        let matches = Cli::command()
+            .mut_arg(id, |arg| arg.env(var))
+            .try_get_matches_from(["trickery", "generate", "Hi"]);
+        std::env::remove_var(var);
+        Cli::from_arg_matches(&matches.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_cache_from_environment() {
+        let cmd = Cli::command();
+        let arg = cmd.get_arguments().find(|a| a.get_id() == "cache").unwrap();
+        assert_eq!(arg.get_env(), Some(std::ffi::OsStr::new("TRICKERY_CACHE")));
+        for (value, expected) in [("1", true), ("yes", true), ("0", false)] {
+            let cli = parse_with_env("cache", "TRICKERY_TEST_CACHE", value);
+            assert_eq!(cli.cache, expected, "TRICKERY_CACHE={}", value);
+        }
+    }
+
+    #[test]
+    fn test_debug_request_from_environment() {
+        let cmd = Cli::command();
+        let arg = cmd
+            .get_arguments()
+            .find(|a| a.get_id() == "debug_request")
+            .unwrap();
+        assert_eq!(arg.get_env(), Some(std::ffi::OsStr::new("TRICKERY_DEBUG")));
+        for (value, expected) in [("1", true), ("true", true), ("0", false)] {
+            let cli = parse_with_env("debug_request", "TRICKERY_TEST_DEBUG", value);
+            assert_eq!(cli.debug_request, expected, "TRICKERY_DEBUG={}", value);
+        }
+    }
+
+    #[test]
+    fn test_verbose_levels() {
+        let verbose = |args: &[&str]| {
+            let mut argv = vec!["trickery"];
+            argv.extend_from_slice(args);
+            log_level(Cli::try_parse_from(argv).unwrap().verbose)
+        };
+        assert_eq!(verbose(&["generate", "Hi"]), tracing::Level::WARN);
+        assert_eq!(
+            verbose(&["--verbose", "generate", "Hi"]),
+            tracing::Level::INFO
+        );
+        assert_eq!(
+            verbose(&["generate", "Hi", "--verbose", "--verbose", "--verbose"]),
+            tracing::Level::DEBUG
+        );
+        // -v stays --var on generate and image
+        assert!(Cli::try_parse_from(["trickery", "generate", "Hi", "-v", "a=b"]).is_ok());
+    }
+
+    #[test]
+    fn test_generate_manpage() {
+        let mut out = Vec::new();
+        write_completion(CompletionTarget::Manpage, &mut out).unwrap();
+        let roff = String::from_utf8(out).unwrap();
+        assert!(roff.contains(".TH trickery"), "{}", roff);
+        assert!(roff.contains("generate"));
+    }
+
+    #[test]
+    fn test_generate_nushell_completion() {
+        let mut out = Vec::new();
+        write_completion(CompletionTarget::Nushell, &mut out).unwrap();
+        let script = String::from_utf8(out).unwrap();
+        assert!(script.contains("export extern trickery"), "{}", script);
+    }
+
     #[test]
     fn test_parse_help_command() {
         let cli = Cli::try_parse_from(["trickery", "help"]).unwrap();
@@ -345,8 +1147,15 @@ mod tests {
             "## Commands",
             "### generate",
             "### image",
+            "### chat",
+            "### lint",
+            "### doctor",
+            "### list-tools",
+            "### models",
+            "### summarize",
             "### completion",
             "## Template Variables",
+            "## Front Matter",
             "## Exit Codes",
         ];
 
@@ -387,6 +1196,75 @@ mod tests {
         assert!(full_help.contains("[INPUT]"));
     }
 
+    #[test]
+    fn test_parse_output_yaml() {
+        let cli = Cli::try_parse_from(["trickery", "generate", "Hi", "-o", "yaml"]).unwrap();
+        assert!(matches!(cli.output, Some(Output::Yaml)));
+    }
+
+    #[test]
+    fn test_parse_pretty_flag() {
+        let cli =
+            Cli::try_parse_from(["trickery", "generate", "Hi", "-o", "json", "--pretty"]).unwrap();
+        assert!(cli.pretty);
+        assert!(cli.pretty_json());
+    }
+
+    #[test]
+    fn test_max_concurrency_is_separate_from_generate_concurrency() {
+        let cli = Cli::try_parse_from([
+            "trickery",
+            "--max-concurrency",
+            "2",
+            "generate",
+            "--input-dir",
+            "prompts",
+            "--concurrency",
+            "8",
+        ])
+        .unwrap();
+        assert_eq!(cli.max_concurrency, Some(2));
+    }
+
+    #[test]
+    fn test_output_file_disables_interactive() {
+        let cli = Cli::try_parse_from(["trickery", "generate", "Hi"]).unwrap();
+        assert!(cli.is_interactive());
+
+        let cli = Cli::try_parse_from(["trickery", "generate", "Hi", "--output-file", "out.json"])
+            .unwrap();
+        assert!(!cli.is_interactive());
+    }
+
+    #[test]
+    fn test_spinner_not_started_for_json_output() {
+        let cli = Cli::try_parse_from(["trickery", "generate", "Hi"]).unwrap();
+        assert!(cli.spinner_allowed(true, true));
+
+        let cli = Cli::try_parse_from(["trickery", "-o", "json", "generate", "Hi"]).unwrap();
+        assert!(!cli.spinner_allowed(true, true));
+        assert!(cli.start_spinner().is_none());
+
+        let cli = Cli::try_parse_from(["trickery", "--verbose", "generate", "Hi"]).unwrap();
+        assert!(!cli.spinner_allowed(true, true));
+
+        let cli = Cli::try_parse_from(["trickery", "generate", "Hi", "-q"]).unwrap();
+        assert!(!cli.spinner_allowed(true, true));
+    }
+
+    #[test]
+    fn test_provider_defaults_to_openai() {
+        let cli = Cli::try_parse_from(["trickery", "generate", "Hi"]).unwrap();
+        assert_eq!(cli.provider_name(), "openai");
+    }
+
+    #[test]
+    fn test_parse_provider_flag() {
+        let cli =
+            Cli::try_parse_from(["trickery", "generate", "Hi", "--provider", "ollama"]).unwrap();
+        assert_eq!(cli.provider_name(), "ollama");
+    }
+
     #[test]
     fn test_parse_generate_with_input_flag() {
         let cli = Cli::try_parse_from(["trickery", "generate", "-i", "prompts/test.md"]).unwrap();
@@ -397,6 +1275,13 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_generate_text_conflicts_with_input() {
+        let result =
+            Cli::try_parse_from(["trickery", "generate", "prompts/test.md", "--text", "Hello"]);
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_parse_generate_with_positional() {
         let cli = Cli::try_parse_from(["trickery", "generate", "prompts/test.md"]).unwrap();
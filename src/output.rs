@@ -1,14 +1,144 @@
 use serde::ser;
 use std::io::{stdout, Write};
+use std::path::Path;
 
 use crate::commands::CommandResult;
+use crate::Output;
 
-pub(super) fn write_command_stdout_as_json<T>(result: &dyn CommandResult<T>)
+/// Serialize a result in the requested structured format; `pretty` indents JSON (YAML is
+/// always block style)
+pub(super) fn format_result<T>(data: &T, format: &Output, pretty: bool) -> Result<String, String>
 where
     T: ser::Serialize,
 {
-    let data = result.get_result();
-    let data_json = serde_json::to_string_pretty(&data).unwrap();
+    let result = match format {
+        Output::Json if pretty => serde_json::to_string_pretty(data),
+        Output::Json => serde_json::to_string(data),
+        Output::Yaml => return serde_yaml::to_string(data).map_err(|e| e.to_string()),
+    };
+    result.map_err(|e| e.to_string())
+}
+
+/// Write the serialized result to stdout, JSON compact unless `pretty`
+pub(super) fn write_command_stdout<T>(result: &dyn CommandResult<T>, format: &Output, pretty: bool)
+where
+    T: ser::Serialize,
+{
+    let data = format_result(result.get_result(), format, pretty).unwrap();
+
+    stdout().write_all(data.as_bytes()).unwrap();
+}
+
+/// Write serialized result to a file, creating parent directories as needed.
+/// Files always get pretty JSON, since they're usually read or diffed by people
+pub(super) fn write_command_file<T>(
+    result: &dyn CommandResult<T>,
+    format: &Output,
+    path: &Path,
+) -> std::io::Result<()>
+where
+    T: ser::Serialize,
+{
+    let data = format_result(result.get_result(), format, true)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::generate::GenerateResult;
+    use crate::commands::image::{ImageOutput, ImageResult};
+
+    fn generate_result() -> GenerateResult {
+        GenerateResult {
+            output: "Hello: world\n- not a list".to_string(),
+            outputs: Vec::new(),
+            usage: None,
+            system_fingerprint: None,
+            logprobs: None,
+            dry_run: false,
+        }
+    }
+
+    fn image_result() -> ImageResult {
+        ImageResult {
+            output_path: Some("image-abc12.png".to_string()),
+            final_prompt: None,
+            revised_prompt: Some("A cat".to_string()),
+            data: None,
+            usage: None,
+            dry_run: false,
+        }
+    }
+
+    #[test]
+    fn test_format_generate_result_json() {
+        let json = format_result(&generate_result(), &Output::Json, true).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["output"], "Hello: world\n- not a list");
+    }
+
+    #[test]
+    fn test_format_json_pretty_and_compact() {
+        let pretty = format_result(&generate_result(), &Output::Json, true).unwrap();
+        assert!(pretty.contains("\n  \"output\": "), "{}", pretty);
+
+        let compact = format_result(&generate_result(), &Output::Json, false).unwrap();
+        assert!(!compact.contains('\n'), "{}", compact);
+        assert!(compact.starts_with("{\"output\":"), "{}", compact);
+        let value: serde_json::Value = serde_json::from_str(&compact).unwrap();
+        assert_eq!(value["output"], "Hello: world\n- not a list");
+    }
+
+    #[test]
+    fn test_format_generate_result_yaml() {
+        let yaml = format_result(&generate_result(), &Output::Yaml, false).unwrap();
+        let parsed: GenerateResult = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(parsed.output, "Hello: world\n- not a list");
+    }
+
+    #[test]
+    fn test_format_image_result_json() {
+        let json = format_result(&image_result(), &Output::Json, true).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["output_path"], "image-abc12.png");
+        assert_eq!(value["revised_prompt"], "A cat");
+    }
+
+    #[test]
+    fn test_write_command_file_json() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("nested/out/result.json");
+
+        write_command_file(&generate_result(), &Output::Json, &path).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(value["output"], "Hello: world\n- not a list");
+    }
+
+    #[test]
+    fn test_write_command_file_yaml() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("result.yaml");
+
+        write_command_file(&ImageOutput::Single(image_result()), &Output::Yaml, &path).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let parsed: ImageResult = serde_yaml::from_str(&content).unwrap();
+        assert_eq!(parsed.output_path.as_deref(), Some("image-abc12.png"));
+    }
 
-    stdout().write_all(data_json.as_bytes()).unwrap();
+    #[test]
+    fn test_format_image_result_yaml() {
+        let yaml = format_result(&image_result(), &Output::Yaml, false).unwrap();
+        let parsed: ImageResult = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(parsed.output_path.as_deref(), Some("image-abc12.png"));
+        assert_eq!(parsed.revised_prompt, Some("A cat".to_string()));
+    }
 }
@@ -0,0 +1,360 @@
+// On-disk completion cache (--cache), a Provider decorator.
+// Key: the serialized request (model, messages and every parameter) plus a namespace for the
+// backend: the provider name and the wrapped provider's effective base URL and default model
+// (wherever they came from: flags, config or environment). Entries are JSON files named by
+// the key's FNV-1a hash; the full key is stored too and compared on lookup, so a hash
+// collision is just a miss. Entries older than the TTL are misses and get overwritten.
+// A streamed miss streams from the wrapped provider and stores the final response; a
+// streamed hit is replayed as events. Cache read/write failures never fail a request: they
+// are logged and the provider is called as usual.
+
+use super::{
+    replay_events, CompletionRequest, CompletionResponse, Provider, ProviderError,
+    ResponsesRequest, ResponsesResponse, StreamEvent,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+/// How long cached completions stay valid unless --cache-ttl is given
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Where and for how long completions are cached
+#[derive(Debug, Clone)]
+pub struct CacheOptions {
+    pub dir: PathBuf,
+    pub ttl: Duration,
+}
+
+/// Cache directory: $TRICKERY_CACHE_DIR, else $XDG_CACHE_HOME/trickery, else ~/.cache/trickery
+pub fn default_cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("TRICKERY_CACHE_DIR").filter(|v| !v.is_empty()) {
+        return Some(PathBuf::from(dir));
+    }
+    let cache_home = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(cache_home.join("trickery"))
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    key: String,
+    /// Unix time (seconds) when the response was stored
+    created: u64,
+    response: CompletionResponse,
+}
+
+/// Provider that serves repeated completion requests from disk
+pub struct CachingProvider {
+    inner: Box<dyn Provider>,
+    namespace: String,
+    options: CacheOptions,
+}
+
+impl CachingProvider {
+    /// `namespace` names the backend; the inner provider's base URL and default model are
+    /// added to it, so a request without a model never hits another model's entry
+    pub fn new(
+        inner: Box<dyn Provider>,
+        namespace: impl Into<String>,
+        options: CacheOptions,
+    ) -> Self {
+        let namespace = format!(
+            "{}\n{}\n{}",
+            namespace.into(),
+            inner.base_url().unwrap_or_default(),
+            inner.default_model().unwrap_or_default()
+        );
+        Self {
+            inner,
+            namespace,
+            options,
+        }
+    }
+
+    fn key(&self, request: &CompletionRequest) -> Result<String, serde_json::Error> {
+        Ok(format!(
+            "{}\n{}",
+            self.namespace,
+            serde_json::to_string(request)?
+        ))
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.options.dir.join(format!("{:016x}.json", fnv1a(key)))
+    }
+
+    async fn lookup(&self, key: &str, path: &Path) -> Option<CompletionResponse> {
+        let content = tokio::fs::read_to_string(path).await.ok()?;
+        let entry: CacheEntry = serde_json::from_str(&content).ok()?;
+        let age = now_secs().saturating_sub(entry.created);
+        if entry.key != key || age >= self.options.ttl.as_secs() {
+            debug!(path = %path.display(), age, "cache entry stale or for another request");
+            return None;
+        }
+        Some(entry.response)
+    }
+
+    async fn store(&self, key: String, path: &Path, response: &CompletionResponse) {
+        let entry = CacheEntry {
+            key,
+            created: now_secs(),
+            response: response.clone(),
+        };
+        let result = match serde_json::to_string(&entry) {
+            Ok(json) => match tokio::fs::create_dir_all(&self.options.dir).await {
+                Ok(()) => tokio::fs::write(path, json).await,
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            debug!(path = %path.display(), error = %e, "failed to write cache entry");
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for CachingProvider {
+    async fn complete(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, ProviderError> {
+        let key = match self.key(&request) {
+            Ok(key) => key,
+            Err(_) => return self.inner.complete(request).await,
+        };
+        let path = self.entry_path(&key);
+        if let Some(response) = self.lookup(&key, &path).await {
+            debug!(path = %path.display(), "completion cache hit");
+            return Ok(response);
+        }
+
+        let response = self.inner.complete(request).await?;
+        self.store(key, &path, &response).await;
+        Ok(response)
+    }
+
+    async fn complete_streaming(
+        &self,
+        request: CompletionRequest,
+        on_event: &(dyn Fn(StreamEvent) + Send + Sync),
+    ) -> Result<CompletionResponse, ProviderError> {
+        let key = match self.key(&request) {
+            Ok(key) => key,
+            Err(_) => return self.inner.complete_streaming(request, on_event).await,
+        };
+        let path = self.entry_path(&key);
+        if let Some(response) = self.lookup(&key, &path).await {
+            debug!(path = %path.display(), "completion cache hit");
+            replay_events(&response, on_event);
+            return Ok(response);
+        }
+
+        let response = self.inner.complete_streaming(request, on_event).await?;
+        self.store(key, &path, &response).await;
+        Ok(response)
+    }
+
+    async fn create_response(
+        &self,
+        request: ResponsesRequest,
+    ) -> Result<ResponsesResponse, ProviderError> {
+        self.inner.create_response(request).await
+    }
+
+    fn base_url(&self) -> Option<&str> {
+        self.inner.base_url()
+    }
+
+    fn default_model(&self) -> Option<&str> {
+        self.inner.default_model()
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, ProviderError> {
+        self.inner.list_models().await
+    }
+}
+
+/// 64-bit FNV-1a: stable across builds and platforms, unlike `DefaultHasher`
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::openai::OpenAIProvider;
+    use crate::provider::Message;
+    use mockito::{Matcher, Server};
+
+    const REPLY: &str = r#"{"choices": [{"message": {"role": "assistant", "content": "Hi!"}, "finish_reason": "stop"}]}"#;
+
+    fn cached(server: &Server, dir: &Path, ttl: Duration) -> CachingProvider {
+        let inner = OpenAIProvider::new("test-key".to_string(), Some(server.url()));
+        CachingProvider::new(
+            Box::new(inner),
+            "openai",
+            CacheOptions {
+                dir: dir.to_path_buf(),
+                ttl,
+            },
+        )
+    }
+
+    fn request() -> CompletionRequest {
+        CompletionRequest::new(vec![Message::user("Hello")]).with_model("gpt-4.1")
+    }
+
+    #[test]
+    fn test_fnv1a_is_stable() {
+        assert_eq!(fnv1a(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a("a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[tokio::test]
+    async fn test_identical_requests_hit_cache() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .with_body(REPLY)
+            .expect(1)
+            .create_async()
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let provider = cached(&server, dir.path(), DEFAULT_CACHE_TTL);
+
+        let first = provider.complete(request()).await.unwrap();
+        let second = provider.complete(request()).await.unwrap();
+        assert_eq!(first.content.as_deref(), Some("Hi!"));
+        assert_eq!(second.content.as_deref(), Some("Hi!"));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_changed_params_bust_cache() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .with_body(REPLY)
+            .expect(3)
+            .create_async()
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let provider = cached(&server, dir.path(), DEFAULT_CACHE_TTL);
+
+        provider.complete(request()).await.unwrap();
+        provider
+            .complete(request().with_temperature(0.5))
+            .await
+            .unwrap();
+        provider
+            .complete(request().with_model("gpt-4.1-mini"))
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_are_refreshed() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .with_body(REPLY)
+            .expect(2)
+            .create_async()
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let provider = cached(&server, dir.path(), Duration::ZERO);
+
+        provider.complete(request()).await.unwrap();
+        provider.complete(request()).await.unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_namespace_separates_backends() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .with_body(REPLY)
+            .expect(2)
+            .create_async()
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let options = CacheOptions {
+            dir: dir.path().to_path_buf(),
+            ttl: DEFAULT_CACHE_TTL,
+        };
+        for namespace in ["openai", "ollama"] {
+            let inner = OpenAIProvider::new("test-key".to_string(), Some(server.url()));
+            CachingProvider::new(Box::new(inner), namespace, options.clone())
+                .complete(request())
+                .await
+                .unwrap();
+        }
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_streaming_miss_streams_and_hit_replays() {
+        use std::sync::Mutex;
+
+        let mut server = Server::new_async().await;
+        let body: String = [
+            r#"{"choices":[{"index":0,"delta":{"role":"assistant","content":"Hi"}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"content":" there"},"finish_reason":"stop"}]}"#,
+            "[DONE]",
+        ]
+        .iter()
+        .map(|e| format!("data: {}\n\n", e))
+        .collect();
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(serde_json::json!({"stream": true})))
+            .with_header("content-type", "text/event-stream")
+            .with_body(body)
+            .expect(1)
+            .create_async()
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let provider = cached(&server, dir.path(), DEFAULT_CACHE_TTL);
+
+        async fn stream(provider: &CachingProvider) -> (Vec<String>, CompletionResponse) {
+            let seen = Mutex::new(Vec::new());
+            let response = provider
+                .complete_streaming(request(), &|event| {
+                    if let StreamEvent::TextDelta(text) = event {
+                        seen.lock().unwrap().push(text);
+                    }
+                })
+                .await
+                .unwrap();
+            (seen.into_inner().unwrap(), response)
+        }
+
+        // Miss: the deltas arrive as the backend sends them
+        let (seen, response) = stream(&provider).await;
+        assert_eq!(seen, ["Hi", " there"]);
+        assert_eq!(response.content.as_deref(), Some("Hi there"));
+
+        // Hit: the stored answer is replayed without another request
+        let (seen, response) = stream(&provider).await;
+        assert_eq!(seen, ["Hi there"]);
+        assert_eq!(response.content.as_deref(), Some("Hi there"));
+        mock.assert_async().await;
+    }
+}
@@ -0,0 +1,184 @@
+// Model fallback chain (--model-fallback), a Provider decorator.
+// A completion that fails with a transient error (rate limited or overloaded: 429, 5xx,
+// timeouts) is sent again with the next model of the chain; any other error, or the last
+// model failing, is returned as is. `CompletionResponse.model` tells which model answered.
+// A streamed completion only falls back while nothing has been streamed yet.
+
+use super::{
+    CompletionRequest, CompletionResponse, Provider, ProviderError, ResponsesRequest,
+    ResponsesResponse, StreamEvent,
+};
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::warn;
+
+/// Provider that retries transient failures with fallback models, in order
+pub struct FallbackProvider {
+    inner: Box<dyn Provider>,
+    models: Vec<String>,
+}
+
+impl FallbackProvider {
+    pub fn new(inner: Box<dyn Provider>, models: Vec<String>) -> Self {
+        Self { inner, models }
+    }
+
+    /// Model the failed request asked for, for the log line
+    fn requested_model<'a>(&'a self, request: &'a CompletionRequest) -> &'a str {
+        request
+            .model
+            .as_deref()
+            .or(self.inner.default_model())
+            .unwrap_or("default model")
+    }
+}
+
+#[async_trait]
+impl Provider for FallbackProvider {
+    async fn complete(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, ProviderError> {
+        let mut result = self.inner.complete(request.clone()).await;
+        let mut failed = self.requested_model(&request).to_string();
+        for model in &self.models {
+            match result {
+                Err(ref e) if e.is_transient() => {
+                    warn!(failed = %failed, fallback = %model, error = %e, "falling back to next model");
+                    result = self.inner.complete(request.clone().with_model(model)).await;
+                    failed.clone_from(model);
+                }
+                _ => break,
+            }
+        }
+        result
+    }
+
+    async fn complete_streaming(
+        &self,
+        request: CompletionRequest,
+        on_event: &(dyn Fn(StreamEvent) + Send + Sync),
+    ) -> Result<CompletionResponse, ProviderError> {
+        // Output already shown can't be taken back, so only a silent failure falls back
+        let streamed = AtomicBool::new(false);
+        let forward = |event: StreamEvent| {
+            streamed.store(true, Ordering::Relaxed);
+            on_event(event);
+        };
+        let mut result = self
+            .inner
+            .complete_streaming(request.clone(), &forward)
+            .await;
+        let mut failed = self.requested_model(&request).to_string();
+        for model in &self.models {
+            match result {
+                Err(ref e) if e.is_transient() && !streamed.load(Ordering::Relaxed) => {
+                    warn!(failed = %failed, fallback = %model, error = %e, "falling back to next model");
+                    result = self
+                        .inner
+                        .complete_streaming(request.clone().with_model(model), &forward)
+                        .await;
+                    failed.clone_from(model);
+                }
+                _ => break,
+            }
+        }
+        result
+    }
+
+    async fn create_response(
+        &self,
+        request: ResponsesRequest,
+    ) -> Result<ResponsesResponse, ProviderError> {
+        self.inner.create_response(request).await
+    }
+
+    fn base_url(&self) -> Option<&str> {
+        self.inner.base_url()
+    }
+
+    fn default_model(&self) -> Option<&str> {
+        self.inner.default_model()
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, ProviderError> {
+        self.inner.list_models().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::openai::OpenAIProvider;
+    use crate::provider::Message;
+    use mockito::{Matcher, Server};
+
+    fn fallback(server: &Server, models: &[&str]) -> FallbackProvider {
+        let inner = OpenAIProvider::new("test-key".to_string(), Some(server.url()));
+        FallbackProvider::new(
+            Box::new(inner),
+            models.iter().map(|m| m.to_string()).collect(),
+        )
+    }
+
+    fn request() -> CompletionRequest {
+        CompletionRequest::new(vec![Message::user("Hello")]).with_model("gpt-4.1")
+    }
+
+    async fn mock_model(
+        server: &mut Server,
+        model: &str,
+        status: usize,
+        hits: usize,
+    ) -> mockito::Mock {
+        server
+            .mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(serde_json::json!({ "model": model })))
+            .with_status(status)
+            .with_body(if status == 200 {
+                format!(
+                    r#"{{"model": "{}", "choices": [{{"message": {{"role": "assistant", "content": "Hi!"}}, "finish_reason": "stop"}}]}}"#,
+                    model
+                )
+            } else {
+                r#"{"error": {"message": "The server is overloaded", "type": "server_error"}}"#
+                    .to_string()
+            })
+            .expect(hits)
+            .create_async()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_overloaded_model_falls_back() {
+        let mut server = Server::new_async().await;
+        let primary = mock_model(&mut server, "gpt-4.1", 503, 1).await;
+        let secondary = mock_model(&mut server, "gpt-4.1-mini", 503, 1).await;
+        let last = mock_model(&mut server, "gpt-4.1-nano", 200, 1).await;
+
+        let response = fallback(&server, &["gpt-4.1-mini", "gpt-4.1-nano"])
+            .complete(request())
+            .await
+            .unwrap();
+        assert_eq!(response.content.as_deref(), Some("Hi!"));
+        assert_eq!(response.model.as_deref(), Some("gpt-4.1-nano"));
+        primary.assert_async().await;
+        secondary.assert_async().await;
+        last.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_permanent_error_does_not_fall_back() {
+        let mut server = Server::new_async().await;
+        let primary = mock_model(&mut server, "gpt-4.1", 400, 1).await;
+        let secondary = mock_model(&mut server, "gpt-4.1-mini", 200, 0).await;
+
+        let err = fallback(&server, &["gpt-4.1-mini"])
+            .complete(request())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProviderError::Api { status: 400, .. }));
+        primary.assert_async().await;
+        secondary.assert_async().await;
+    }
+}
@@ -0,0 +1,854 @@
+// Gemini provider implementation (generateContent API).
+// Env vars: GEMINI_API_KEY (required), GEMINI_BASE_URL (optional,
+// default: https://generativelanguage.googleapis.com/v1beta), GEMINI_TIMEOUT_SECS (optional, default: 120),
+// GEMINI_DEFAULT_MODEL (optional, default: gemini-2.5-flash)
+// Mapping: assistant -> `model` role, system messages -> `systemInstruction`, tool results ->
+// `functionResponse` parts in a user turn, tools -> `functionDeclarations`.
+// Image generation (Responses API) is not supported.
+
+use super::{
+    format_request_dump, CompletionRequest, CompletionResponse, ContentPart, FunctionCall, Message,
+    PayloadCapture, Provider, ProviderError, RequestLog, ResponseFormat, ResponsesRequest,
+    ResponsesResponse, Role, ToolCall, ToolChoice, Usage,
+};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
+use std::time::Duration;
+use tracing::{debug, info};
+
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+const DEFAULT_MODEL: &str = "gemini-2.5-flash";
+const DEFAULT_TIMEOUT_SECS: u64 = 120;
+
+/// Gemini API client
+pub struct GeminiProvider {
+    client: Client,
+    api_key: String,
+    base_url: String,
+    default_model: String,
+    timeout: Duration,
+    /// Receives each request body before it is sent (--debug-request)
+    request_log: Option<RequestLog>,
+    /// Files for completion payloads (--save-request, --save-response)
+    capture: PayloadCapture,
+}
+
+impl GeminiProvider {
+    /// Create new provider from environment variables.
+    /// GEMINI_API_KEY - required
+    /// GEMINI_BASE_URL - optional (default: https://generativelanguage.googleapis.com/v1beta)
+    /// GEMINI_TIMEOUT_SECS - optional (default: 120)
+    /// GEMINI_DEFAULT_MODEL - optional (default: gemini-2.5-flash)
+    pub fn from_env() -> Result<Self, ProviderError> {
+        let api_key = env::var("GEMINI_API_KEY")
+            .map_err(|_| ProviderError::MissingApiKey("GEMINI_API_KEY".to_string()))?;
+        let base_url = env::var("GEMINI_BASE_URL").unwrap_or_else(|_| DEFAULT_BASE_URL.to_string());
+        let timeout_secs = env::var("GEMINI_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+
+        let mut provider =
+            Self::new(api_key, Some(base_url)).with_timeout(Duration::from_secs(timeout_secs));
+        if let Some(model) = env::var("GEMINI_DEFAULT_MODEL")
+            .ok()
+            .filter(|m| !m.trim().is_empty())
+        {
+            provider = provider.with_default_model(model.trim());
+        }
+        Ok(provider)
+    }
+
+    /// Create provider with explicit configuration (useful for testing)
+    pub fn new(api_key: String, base_url: Option<String>) -> Self {
+        let timeout = Duration::from_secs(DEFAULT_TIMEOUT_SECS);
+        Self {
+            client: build_client(timeout),
+            api_key,
+            base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            default_model: DEFAULT_MODEL.to_string(),
+            timeout,
+            request_log: None,
+            capture: PayloadCapture::default(),
+        }
+    }
+
+    /// Set request timeout (rebuilds the HTTP client)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = build_client(timeout);
+        self.timeout = timeout;
+        self
+    }
+
+    /// Model used when a request names none
+    pub fn with_default_model(mut self, model: impl Into<String>) -> Self {
+        self.default_model = model.into();
+        self
+    }
+
+    /// Override the API base URL
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Dump each request body to `log` before sending it
+    pub fn with_request_log(mut self, log: Option<RequestLog>) -> Self {
+        self.request_log = log;
+        self
+    }
+
+    /// Save completion request and response bodies to the capture's files
+    pub fn with_capture(mut self, capture: PayloadCapture) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    /// Map a CompletionRequest to the generateContent wire format
+    fn build_request(&self, request: &CompletionRequest) -> GeminiRequest {
+        let mut system_parts = Vec::new();
+        let mut contents: Vec<GeminiContent> = Vec::new();
+        // Gemini function responses are matched by name, our tool results by call id
+        let mut call_names: HashMap<&str, &str> = HashMap::new();
+
+        for message in &request.messages {
+            let (role, parts) = match message.role {
+                Role::System | Role::Developer => {
+                    system_parts.extend(text_parts(message));
+                    continue;
+                }
+                Role::User => ("user", content_parts(message)),
+                Role::Assistant => {
+                    let mut parts = content_parts(message);
+                    for call in message.tool_calls.iter().flatten() {
+                        call_names.insert(&call.id, &call.function.name);
+                        parts.push(GeminiPart::FunctionCall {
+                            function_call: GeminiFunctionCall {
+                                name: call.function.name.clone(),
+                                args: serde_json::from_str(&call.function.arguments)
+                                    .unwrap_or_else(|_| serde_json::json!({})),
+                            },
+                        });
+                    }
+                    ("model", parts)
+                }
+                Role::Tool => {
+                    let id = message.tool_call_id.as_deref().unwrap_or_default();
+                    let name = call_names.get(id).copied().unwrap_or(id);
+                    let part = GeminiPart::FunctionResponse {
+                        function_response: GeminiFunctionResponse {
+                            name: name.to_string(),
+                            response: serde_json::json!({
+                                "content": message.text_content().unwrap_or_default()
+                            }),
+                        },
+                    };
+                    ("user", vec![part])
+                }
+            };
+
+            // Consecutive turns of the same role (e.g. parallel tool results) share one content
+            match contents.last_mut() {
+                Some(last) if last.role == role => last.parts.extend(parts),
+                _ => contents.push(GeminiContent {
+                    role: role.to_string(),
+                    parts,
+                }),
+            }
+        }
+
+        let (response_mime_type, response_json_schema) = match request.response_format {
+            Some(ResponseFormat::JsonObject) => (Some("application/json".to_string()), None),
+            Some(ResponseFormat::JsonSchema { ref schema }) => {
+                (Some("application/json".to_string()), Some(schema.clone()))
+            }
+            Some(ResponseFormat::Text) | None => (None, None),
+        };
+
+        GeminiRequest {
+            contents,
+            system_instruction: if system_parts.is_empty() {
+                None
+            } else {
+                Some(GeminiSystemInstruction {
+                    parts: system_parts,
+                })
+            },
+            tools: request.tools.as_ref().map(|tools| {
+                vec![GeminiTool {
+                    function_declarations: tools
+                        .iter()
+                        .map(|tool| GeminiFunctionDeclaration {
+                            name: tool.function.name.clone(),
+                            description: tool.function.description.clone(),
+                            parameters: tool.function.parameters.clone(),
+                        })
+                        .collect(),
+                }]
+            }),
+            tool_config: request
+                .tool_choice
+                .as_ref()
+                .filter(|_| request.tools.is_some())
+                .map(|choice| {
+                    let (mode, allowed) = match choice {
+                        ToolChoice::Auto => ("AUTO", None),
+                        ToolChoice::None => ("NONE", None),
+                        ToolChoice::Required => ("ANY", None),
+                        ToolChoice::Function(name) => ("ANY", Some(vec![name.clone()])),
+                    };
+                    GeminiToolConfig {
+                        function_calling_config: GeminiFunctionCallingConfig {
+                            mode: mode.to_string(),
+                            allowed_function_names: allowed,
+                        },
+                    }
+                }),
+            generation_config: GeminiGenerationConfig {
+                max_output_tokens: request.max_tokens,
+                temperature: request.temperature,
+                stop_sequences: request.stop.clone(),
+                seed: request.seed,
+                frequency_penalty: request.frequency_penalty,
+                presence_penalty: request.presence_penalty,
+                response_mime_type,
+                response_json_schema,
+            },
+        }
+    }
+}
+
+/// Build HTTP client with request timeout
+fn build_client(timeout: Duration) -> Client {
+    Client::builder()
+        .timeout(timeout)
+        .build()
+        .expect("Failed to build HTTP client")
+}
+
+fn text_parts(message: &Message) -> Vec<GeminiPart> {
+    message
+        .text_content()
+        .filter(|text| !text.is_empty())
+        .map(|text| vec![GeminiPart::Text { text }])
+        .unwrap_or_default()
+}
+
+fn content_parts(message: &Message) -> Vec<GeminiPart> {
+    message
+        .content
+        .iter()
+        .flatten()
+        .map(|part| match part {
+            ContentPart::Text { text } => GeminiPart::Text { text: text.clone() },
+            ContentPart::ImageUrl { image_url } => image_part(&image_url.url),
+        })
+        .collect()
+}
+
+/// Data URLs are sent inline, anything else as a file reference
+fn image_part(url: &str) -> GeminiPart {
+    if let Some((mime_type, data)) = url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(";base64,"))
+    {
+        return GeminiPart::InlineData {
+            inline_data: GeminiBlob {
+                mime_type: mime_type.to_string(),
+                data: data.to_string(),
+            },
+        };
+    }
+    GeminiPart::FileData {
+        file_data: GeminiFileData {
+            file_uri: url.to_string(),
+        },
+    }
+}
+
+/// Map Gemini finish reasons to the OpenAI-style values used across providers
+fn finish_reason(reason: Option<&str>, has_tool_calls: bool) -> String {
+    if has_tool_calls {
+        return "tool_calls".to_string();
+    }
+    match reason {
+        Some("STOP") => "stop".to_string(),
+        Some("MAX_TOKENS") => "length".to_string(),
+        Some("SAFETY") | Some("RECITATION") | Some("PROHIBITED_CONTENT") => {
+            "content_filter".to_string()
+        }
+        Some(other) => other.to_lowercase(),
+        None => String::new(),
+    }
+}
+
+#[async_trait]
+impl Provider for GeminiProvider {
+    /// Complete a chat request
+    async fn complete(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, ProviderError> {
+        let api_request = self.build_request(&request);
+        let model = request.model.as_deref().unwrap_or(&self.default_model);
+        let expects_json = request
+            .response_format
+            .as_ref()
+            .is_some_and(ResponseFormat::expects_json);
+
+        let url = format!("{}/models/{}:generateContent", self.base_url, model);
+        debug!(
+            model = %model,
+            url = %url,
+            contents = api_request.contents.len(),
+            "sending generateContent request"
+        );
+        if let Some(ref log) = self.request_log {
+            log(&format_request_dump(&url, &api_request, &[&self.api_key]));
+        }
+        self.capture.save_request(&api_request, &[&self.api_key]);
+        let response = self
+            .client
+            .post(&url)
+            .header("x-goog-api-key", &self.api_key)
+            .header("Content-Type", "application/json")
+            .json(&api_request)
+            .send()
+            .await?;
+
+        let status = response.status();
+        debug!(status = status.as_u16(), "generateContent response");
+        let body = response.text().await?;
+        self.capture.save_response(&body);
+        if !status.is_success() {
+            return Err(ProviderError::from_api_response(
+                status.as_u16(),
+                &body,
+                &[&self.api_key],
+            ));
+        }
+
+        let api_response: GeminiResponse = serde_json::from_str(&body)
+            .map_err(|e| ProviderError::InvalidResponse(e.to_string()))?;
+        if let Some(ref usage) = api_response.usage_metadata {
+            info!(
+                model = %model,
+                prompt_tokens = usage.prompt_token_count,
+                completion_tokens = usage.candidates_token_count,
+                total_tokens = usage.total_token_count,
+                "generateContent completion"
+            );
+        }
+        let candidate = api_response.candidates.into_iter().next().ok_or_else(|| {
+            ProviderError::InvalidResponse("No candidates in response".to_string())
+        })?;
+        if let Some(reason) = candidate.finish_reason.as_deref() {
+            if finish_reason(Some(reason), false) == "content_filter" {
+                return Err(ProviderError::ContentFiltered(format!(
+                    "finishReason {}",
+                    reason
+                )));
+            }
+        }
+
+        let mut text = String::new();
+        let mut tool_calls = Vec::new();
+        for part in candidate.content.map(|c| c.parts).unwrap_or_default() {
+            if let Some(part_text) = part.text {
+                text.push_str(&part_text);
+            }
+            if let Some(call) = part.function_call {
+                tool_calls.push(ToolCall {
+                    // Gemini does not always assign call ids; synthesize stable ones
+                    id: call
+                        .id
+                        .unwrap_or_else(|| format!("call_{}", tool_calls.len())),
+                    call_type: "function".to_string(),
+                    function: FunctionCall {
+                        name: call.name,
+                        arguments: call
+                            .args
+                            .unwrap_or_else(|| serde_json::json!({}))
+                            .to_string(),
+                    },
+                });
+            }
+        }
+
+        let content = if text.is_empty() { None } else { Some(text) };
+        if expects_json {
+            if let Some(ref content) = content {
+                serde_json::from_str::<Value>(content).map_err(|e| {
+                    ProviderError::InvalidResponse(format!("Expected JSON content: {}", e))
+                })?;
+            }
+        }
+
+        Ok(CompletionResponse {
+            content,
+            finish_reason: finish_reason(
+                candidate.finish_reason.as_deref(),
+                !tool_calls.is_empty(),
+            ),
+            tool_calls: if tool_calls.is_empty() {
+                None
+            } else {
+                Some(tool_calls)
+            },
+            usage: api_response
+                .usage_metadata
+                .map(|u| Usage {
+                    prompt_tokens: u.prompt_token_count,
+                    completion_tokens: u.candidates_token_count,
+                    total_tokens: u.total_token_count,
+                })
+                .unwrap_or_default(),
+            system_fingerprint: None,
+            logprobs: None,
+            choices: Vec::new(),
+            refusal: None,
+            model: Some(
+                api_response
+                    .model_version
+                    .unwrap_or_else(|| model.to_string()),
+            ),
+        })
+    }
+
+    fn base_url(&self) -> Option<&str> {
+        Some(&self.base_url)
+    }
+
+    fn default_model(&self) -> Option<&str> {
+        Some(&self.default_model)
+    }
+
+    async fn create_response(
+        &self,
+        _request: ResponsesRequest,
+    ) -> Result<ResponsesResponse, ProviderError> {
+        Err(ProviderError::Unsupported(
+            "image generation is not supported by the gemini provider".to_string(),
+        ))
+    }
+}
+
+// Gemini API request/response types
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiRequest {
+    contents: Vec<GeminiContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<GeminiSystemInstruction>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<GeminiTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_config: Option<GeminiToolConfig>,
+    generation_config: GeminiGenerationConfig,
+}
+
+#[derive(Debug, Serialize)]
+struct GeminiContent {
+    role: String,
+    parts: Vec<GeminiPart>,
+}
+
+#[derive(Debug, Serialize)]
+struct GeminiSystemInstruction {
+    parts: Vec<GeminiPart>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum GeminiPart {
+    Text {
+        text: String,
+    },
+    InlineData {
+        #[serde(rename = "inlineData")]
+        inline_data: GeminiBlob,
+    },
+    FileData {
+        #[serde(rename = "fileData")]
+        file_data: GeminiFileData,
+    },
+    FunctionCall {
+        #[serde(rename = "functionCall")]
+        function_call: GeminiFunctionCall,
+    },
+    FunctionResponse {
+        #[serde(rename = "functionResponse")]
+        function_response: GeminiFunctionResponse,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiBlob {
+    mime_type: String,
+    data: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiFileData {
+    file_uri: String,
+}
+
+#[derive(Debug, Serialize)]
+struct GeminiFunctionCall {
+    name: String,
+    args: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct GeminiFunctionResponse {
+    name: String,
+    response: Value,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiTool {
+    function_declarations: Vec<GeminiFunctionDeclaration>,
+}
+
+/// `toolConfig`: ANY forces a call, optionally restricted to `allowedFunctionNames`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiToolConfig {
+    function_calling_config: GeminiFunctionCallingConfig,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiFunctionCallingConfig {
+    mode: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    allowed_function_names: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+struct GeminiFunctionDeclaration {
+    name: String,
+    description: String,
+    parameters: Value,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiGenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_json_schema: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiResponse {
+    #[serde(default)]
+    candidates: Vec<GeminiCandidate>,
+    usage_metadata: Option<GeminiUsage>,
+    model_version: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiCandidate {
+    content: Option<GeminiResponseContent>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiResponseContent {
+    #[serde(default)]
+    parts: Vec<GeminiResponsePart>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiResponsePart {
+    text: Option<String>,
+    function_call: Option<GeminiResponseFunctionCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiResponseFunctionCall {
+    id: Option<String>,
+    name: String,
+    args: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiUsage {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    candidates_token_count: u32,
+    #[serde(default)]
+    total_token_count: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::Tool;
+    use mockito::{Matcher, Server};
+
+    #[test]
+    fn test_provider_new() {
+        let provider = GeminiProvider::new("test-key".to_string(), None);
+        assert_eq!(provider.api_key, "test-key");
+        assert_eq!(provider.base_url, DEFAULT_BASE_URL);
+        assert_eq!(provider.default_model, DEFAULT_MODEL);
+        assert_eq!(provider.timeout, Duration::from_secs(DEFAULT_TIMEOUT_SECS));
+    }
+
+    #[test]
+    fn test_build_request_maps_roles() {
+        let provider = GeminiProvider::new("test-key".to_string(), None);
+        let request = CompletionRequest::new(vec![
+            Message::system("Be brief."),
+            Message::user("Weather in Paris?"),
+            Message::assistant_tool_calls(
+                None,
+                vec![ToolCall {
+                    id: "call_0".to_string(),
+                    call_type: "function".to_string(),
+                    function: FunctionCall {
+                        name: "get_weather".to_string(),
+                        arguments: r#"{"location":"Paris"}"#.to_string(),
+                    },
+                }],
+            ),
+            Message::tool_result("call_0", "Sunny"),
+        ])
+        .with_tools(vec![Tool::function(
+            "get_weather",
+            "Get weather",
+            serde_json::json!({"type": "object"}),
+        )])
+        .with_max_tokens(100)
+        .with_response_format(ResponseFormat::JsonObject);
+
+        let json = serde_json::to_value(provider.build_request(&request)).unwrap();
+        assert_eq!(
+            json["systemInstruction"],
+            serde_json::json!({"parts": [{"text": "Be brief."}]})
+        );
+        assert_eq!(
+            json["contents"],
+            serde_json::json!([
+                {"role": "user", "parts": [{"text": "Weather in Paris?"}]},
+                {"role": "model", "parts": [
+                    {"functionCall": {"name": "get_weather", "args": {"location": "Paris"}}}
+                ]},
+                {"role": "user", "parts": [
+                    {"functionResponse": {"name": "get_weather", "response": {"content": "Sunny"}}}
+                ]}
+            ])
+        );
+        assert_eq!(
+            json["tools"][0]["functionDeclarations"][0]["name"],
+            "get_weather"
+        );
+        assert_eq!(json["generationConfig"]["maxOutputTokens"], 100);
+        assert_eq!(
+            json["generationConfig"]["responseMimeType"],
+            "application/json"
+        );
+        assert!(json.get("toolConfig").is_none());
+    }
+
+    #[test]
+    fn test_build_request_tool_choice() {
+        let provider = GeminiProvider::new("test-key".to_string(), None);
+        let tool = Tool::function("get_weather", "Get weather", serde_json::json!({}));
+        for (choice, expected) in [
+            (ToolChoice::Auto, serde_json::json!({"mode": "AUTO"})),
+            (ToolChoice::None, serde_json::json!({"mode": "NONE"})),
+            (ToolChoice::Required, serde_json::json!({"mode": "ANY"})),
+            (
+                ToolChoice::Function("get_weather".to_string()),
+                serde_json::json!({"mode": "ANY", "allowedFunctionNames": ["get_weather"]}),
+            ),
+        ] {
+            let request = CompletionRequest::new(vec![Message::user("Hi")])
+                .with_tools(vec![tool.clone()])
+                .with_tool_choice(choice);
+            let json = serde_json::to_value(provider.build_request(&request)).unwrap();
+            assert_eq!(json["toolConfig"]["functionCallingConfig"], expected);
+        }
+    }
+
+    #[test]
+    fn test_image_part() {
+        let inline = serde_json::to_value(image_part("data:image/png;base64,AAAA")).unwrap();
+        assert_eq!(
+            inline,
+            serde_json::json!({"inlineData": {"mimeType": "image/png", "data": "AAAA"}})
+        );
+        let remote = serde_json::to_value(image_part("https://example.com/cat.png")).unwrap();
+        assert_eq!(
+            remote,
+            serde_json::json!({"fileData": {"fileUri": "https://example.com/cat.png"}})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_complete_mock() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/models/gemini-2.5-pro:generateContent")
+            .match_header("x-goog-api-key", "test-key")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "contents": [{"role": "user", "parts": [{"text": "Hi"}]}]
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                    "candidates": [{
+                        "content": {"role": "model", "parts": [{"text": "Hello! "}, {"text": "How can I help?"}]},
+                        "finishReason": "STOP"
+                    }],
+                    "usageMetadata": {
+                        "promptTokenCount": 4,
+                        "candidatesTokenCount": 6,
+                        "totalTokenCount": 10
+                    }
+                }"#,
+            )
+            .create_async()
+            .await;
+
+        let provider = GeminiProvider::new("test-key".to_string(), Some(server.url()));
+        let request =
+            CompletionRequest::new(vec![Message::user("Hi")]).with_model("gemini-2.5-pro");
+        let response = provider.complete(request).await.unwrap();
+
+        assert_eq!(response.content.as_deref(), Some("Hello! How can I help?"));
+        assert_eq!(response.finish_reason, "stop");
+        assert!(response.tool_calls.is_none());
+        assert_eq!(response.usage.prompt_tokens, 4);
+        assert_eq!(response.usage.total_tokens, 10);
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_complete_function_call_mock() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/models/gemini-2.5-flash:generateContent")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "tools": [{"functionDeclarations": [{"name": "get_weather"}]}]
+            })))
+            .with_status(200)
+            .with_body(
+                r#"{
+                    "candidates": [{
+                        "content": {"role": "model", "parts": [
+                            {"functionCall": {"name": "get_weather", "args": {"location": "Paris"}}},
+                            {"functionCall": {"id": "fc_1", "name": "get_weather", "args": {"location": "Rome"}}}
+                        ]},
+                        "finishReason": "STOP"
+                    }]
+                }"#,
+            )
+            .create_async()
+            .await;
+
+        let provider = GeminiProvider::new("test-key".to_string(), Some(server.url()));
+        let tool = Tool::function(
+            "get_weather",
+            "Get weather",
+            serde_json::json!({"type": "object"}),
+        );
+        let request =
+            CompletionRequest::new(vec![Message::user("Weather?")]).with_tools(vec![tool]);
+        let response = provider.complete(request).await.unwrap();
+
+        assert!(response.content.is_none());
+        assert_eq!(response.finish_reason, "tool_calls");
+        let tool_calls = response.tool_calls.unwrap();
+        assert_eq!(tool_calls.len(), 2);
+        assert_eq!(tool_calls[0].id, "call_0");
+        assert_eq!(tool_calls[0].function.name, "get_weather");
+        assert_eq!(
+            serde_json::from_str::<Value>(&tool_calls[0].function.arguments).unwrap(),
+            serde_json::json!({"location": "Paris"})
+        );
+        assert_eq!(tool_calls[1].id, "fc_1");
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_api_error_handling() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("POST", Matcher::Any)
+            .with_status(400)
+            .with_body(r#"{"error": {"message": "API key not valid"}}"#)
+            .create_async()
+            .await;
+
+        let provider = GeminiProvider::new("bad-key".to_string(), Some(server.url()));
+        let result = provider
+            .complete(CompletionRequest::new(vec![Message::user("Hi")]))
+            .await;
+        match result.unwrap_err() {
+            ProviderError::Api { status, .. } => assert_eq!(status, 400),
+            other => panic!("Expected Api error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_safety_block_is_content_filtered() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("POST", Matcher::Any)
+            .with_status(200)
+            .with_body(r#"{"candidates": [{"finishReason": "SAFETY"}]}"#)
+            .create_async()
+            .await;
+
+        let provider = GeminiProvider::new("test-key".to_string(), Some(server.url()));
+        let err = provider
+            .complete(CompletionRequest::new(vec![Message::user("Hi")]))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, ProviderError::ContentFiltered(ref d) if d == "finishReason SAFETY"),
+            "{:?}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_response_unsupported() {
+        let provider = GeminiProvider::new("test-key".to_string(), None);
+        let result = provider
+            .create_response(ResponsesRequest::new("Draw a cat"))
+            .await;
+        assert!(matches!(result, Err(ProviderError::Unsupported(_))));
+    }
+}
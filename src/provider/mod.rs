@@ -2,10 +2,32 @@
 // Design: Each provider implements the Provider trait with its own client.
 // Note: Provider only handles API contract, no template processing.
 
+pub mod cache;
+pub mod fallback;
+pub mod gemini;
 pub mod openai;
+pub mod retry;
+pub mod stream;
 
+use async_trait::async_trait;
+use cache::{CacheOptions, CachingProvider};
+use fallback::FallbackProvider;
+use gemini::GeminiProvider;
+use openai::OpenAIProvider;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+/// Provider used when neither --provider nor TRICKERY_PROVIDER is set
+pub const DEFAULT_PROVIDER: &str = "openai";
+
+/// Provider names accepted by `build_provider`
+pub const AVAILABLE_PROVIDERS: &[&str] = &["openai", "ollama", "gemini", "azure"];
 
 #[derive(Error, Debug)]
 pub enum ProviderError {
@@ -14,9 +36,439 @@ pub enum ProviderError {
     #[error("HTTP error: {0}")]
     Http(#[from] reqwest::Error),
     #[error("API error: {status} - {message}")]
-    Api { status: u16, message: String },
+    Api {
+        status: u16,
+        message: String,
+        /// Parsed fields of a structured error body
+        details: Option<ApiErrorDetails>,
+    },
     #[error("Invalid response: {0}")]
     InvalidResponse(String),
+    #[error("Unknown provider: {0}")]
+    UnknownProvider(String),
+    #[error("Not supported: {0}")]
+    Unsupported(String),
+    #[error("Missing configuration: {0}")]
+    MissingConfig(String),
+    #[error("Cancelled")]
+    Cancelled,
+    /// The provider's content filter stopped the completion (`finish_reason: content_filter`)
+    #[error("Content filtered: {0}")]
+    ContentFiltered(String),
+    /// The model declined to answer and returned only a refusal message
+    #[error("Model refused: {0}")]
+    Refusal(String),
+    /// A custom request header is malformed or would replace the API key
+    #[error("Invalid header: {0}")]
+    InvalidHeader(String),
+}
+
+/// LLM backend contract. Commands talk to providers only through this trait.
+#[async_trait]
+pub trait Provider: Send + Sync {
+    /// Complete a chat request
+    async fn complete(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, ProviderError>;
+
+    /// Generate images using the Responses API with image_generation tool
+    async fn create_response(
+        &self,
+        request: ResponsesRequest,
+    ) -> Result<ResponsesResponse, ProviderError>;
+
+    /// API base URL requests are sent to (for diagnostics)
+    fn base_url(&self) -> Option<&str> {
+        None
+    }
+
+    /// Model used when a request names none
+    fn default_model(&self) -> Option<&str> {
+        None
+    }
+
+    /// IDs of the models the API offers, sorted
+    async fn list_models(&self) -> Result<Vec<String>, ProviderError> {
+        Err(ProviderError::Unsupported(
+            "listing models with this provider".to_string(),
+        ))
+    }
+
+    /// Complete a chat request, aborting the in-flight request once `cancel` fires
+    async fn complete_cancellable(
+        &self,
+        request: CompletionRequest,
+        cancel: &CancellationToken,
+    ) -> Result<CompletionResponse, ProviderError> {
+        tokio::select! {
+            response = self.complete(request) => response,
+            _ = cancel.cancelled() => Err(ProviderError::Cancelled),
+        }
+    }
+
+    /// Complete a chat request, passing output to `on_event` as it arrives. Returns the
+    /// same response as `complete`; providers without streaming emit it all at the end.
+    async fn complete_streaming(
+        &self,
+        request: CompletionRequest,
+        on_event: &(dyn Fn(StreamEvent) + Send + Sync),
+    ) -> Result<CompletionResponse, ProviderError> {
+        let response = self.complete(request).await?;
+        replay_events(&response, on_event);
+        Ok(response)
+    }
+}
+
+/// Emit a finished response as stream events: all of its text at once, then its tool calls
+pub(crate) fn replay_events(
+    response: &CompletionResponse,
+    on_event: &(dyn Fn(StreamEvent) + Send + Sync),
+) {
+    if let Some(ref content) = response.content {
+        on_event(StreamEvent::TextDelta(content.clone()));
+    }
+    for call in response.tool_calls.iter().flatten() {
+        on_event(StreamEvent::ToolCall(call.clone()));
+    }
+}
+
+/// Output of a streamed completion, in arrival order
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    /// Next piece of assistant text
+    TextDelta(String),
+    /// A tool call whose name and arguments are complete
+    ToolCall(ToolCall),
+}
+
+/// Fields of a structured API error body: `{"error": {"message", "type", "code", "param"}}`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApiErrorDetails {
+    /// Error category, e.g. `invalid_request_error` (Gemini: `status`, e.g. `INVALID_ARGUMENT`)
+    pub error_type: Option<String>,
+    /// Machine-readable code, e.g. `insufficient_quota`
+    pub code: Option<String>,
+    /// Request parameter the error refers to
+    pub param: Option<String>,
+}
+
+impl ProviderError {
+    /// API error from a non-success response body, with secrets redacted.
+    /// Structured error bodies are parsed; anything else is kept as raw text.
+    pub fn from_api_response(status: u16, body: &str, secrets: &[&str]) -> Self {
+        let body = redact_secrets(body, secrets);
+        match parse_api_error(&body) {
+            Some((message, details)) => ProviderError::Api {
+                status,
+                message,
+                details: Some(details),
+            },
+            None => ProviderError::Api {
+                status,
+                message: body,
+                details: None,
+            },
+        }
+    }
+}
+
+/// Message and details of a `{"error": {...}}` body; None when the body has another shape
+fn parse_api_error(body: &str) -> Option<(String, ApiErrorDetails)> {
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    let error = value.get("error")?;
+    let message = error.get("message")?.as_str()?.to_string();
+    // Codes are strings for OpenAI and numbers for Gemini
+    let field = |name: &str| match error.get(name)? {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    };
+    let details = ApiErrorDetails {
+        error_type: field("type").or_else(|| field("status")),
+        code: field("code"),
+        param: field("param"),
+    };
+    Some((message, details))
+}
+
+/// Settings applied on top of a provider's environment configuration
+#[derive(Default)]
+pub struct ProviderOptions<'a> {
+    /// API base URL that always wins (--base-url)
+    pub base_url: Option<&'a str>,
+    /// API base URL used when the provider's *_BASE_URL variable is unset (config file)
+    pub fallback_base_url: Option<&'a str>,
+    /// OpenAI organization and project, overriding OPENAI_ORG_ID/OPENAI_PROJECT_ID (--org,
+    /// --project); OpenAI provider only
+    pub organization: Option<&'a str>,
+    pub project: Option<&'a str>,
+    /// Request timeout overriding the provider's *_TIMEOUT_SECS variable (--timeout)
+    pub timeout: Option<Duration>,
+    /// Cap on simultaneous chat completions, overriding OPENAI_MAX_CONCURRENCY
+    /// (--max-concurrency); OpenAI-compatible providers only
+    pub max_concurrency: Option<usize>,
+    /// Send the token limit as `max_tokens` (--legacy-max-tokens); OpenAI-compatible
+    /// providers only. When false, OPENAI_LEGACY_MAX_TOKENS still applies
+    pub legacy_max_tokens: bool,
+    /// Print each request body to stderr before sending (--debug-request)
+    pub debug_requests: bool,
+    /// Write completion payloads to files (--save-request, --save-response)
+    pub capture: PayloadCapture,
+    /// Serve repeated completions from an on-disk cache (--cache)
+    pub cache: Option<CacheOptions>,
+    /// Models tried in order when a completion fails with a transient error (--model-fallback)
+    pub model_fallbacks: &'a [String],
+    /// Extra headers sent with every request, later ones replacing earlier ones of the same
+    /// name (config `headers`, --header); OpenAI-compatible providers only
+    pub headers: &'a [(String, String)],
+    /// Let `headers` replace the headers carrying the API key (--allow-auth-header)
+    pub allow_auth_header: bool,
+}
+
+/// Headers that carry the API key (`api-key` for Azure)
+const CREDENTIAL_HEADERS: &[&str] = &["authorization", "api-key"];
+
+/// Parse a `Name: value` request header (--header)
+pub fn parse_header(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("invalid header: expected `Name: value`, got `{}`", s))?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("invalid header: no name in `{}`", s));
+    }
+    Ok((name.to_string(), value.trim().to_string()))
+}
+
+/// Custom headers may not replace the API key unless explicitly allowed
+fn check_credential_headers(options: &ProviderOptions) -> Result<(), ProviderError> {
+    if options.allow_auth_header {
+        return Ok(());
+    }
+    match options.headers.iter().find(|(name, _)| {
+        CREDENTIAL_HEADERS
+            .iter()
+            .any(|credential| name.eq_ignore_ascii_case(credential))
+    }) {
+        Some((name, _)) => Err(ProviderError::InvalidHeader(format!(
+            "'{}' would replace the API key (pass --allow-auth-header to allow it)",
+            name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Receives request dumps (URL and pretty JSON body) for --debug-request
+pub type RequestLog = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Pretty request dump for --debug-request, with secrets redacted
+pub(crate) fn format_request_dump(url: &str, body: &impl Serialize, secrets: &[&str]) -> String {
+    let json = serde_json::to_string_pretty(body).unwrap_or_default();
+    redact_secrets(&format!("POST {}\n{}", url, json), secrets)
+}
+
+/// Files receiving the exact completion payloads, for bug reports (--save-request,
+/// --save-response). Each completion call overwrites them, so they hold the last exchange.
+#[derive(Debug, Clone, Default)]
+pub struct PayloadCapture {
+    /// Request body as pretty JSON, secrets redacted
+    pub request: Option<PathBuf>,
+    /// Response body as received, error responses included
+    pub response: Option<PathBuf>,
+}
+
+impl PayloadCapture {
+    pub(crate) fn save_request(&self, body: &impl Serialize, secrets: &[&str]) {
+        if let Some(ref path) = self.request {
+            let json = serde_json::to_string_pretty(body).unwrap_or_default();
+            write_capture(path, &redact_secrets(&json, secrets));
+        }
+    }
+
+    pub(crate) fn save_response(&self, body: &str) {
+        if let Some(ref path) = self.response {
+            write_capture(path, body);
+        }
+    }
+}
+
+/// A capture that can't be written is reported, not fatal: the request itself is fine
+fn write_capture(path: &Path, content: &str) {
+    if let Err(e) = std::fs::write(path, content) {
+        warn!(path = %path.display(), error = %e, "failed to save payload");
+    }
+}
+
+/// Construct provider by name (openai, ollama, gemini, azure).
+/// API keys always come from the environment; see `ProviderOptions` for base URL precedence.
+pub fn build_provider(
+    name: &str,
+    options: &ProviderOptions,
+) -> Result<Box<dyn Provider>, ProviderError> {
+    let provider = build_uncached_provider(name, options)?;
+    // The same request sent to another backend must not share an entry; the cache adds the
+    // provider's effective base URL and default model to the name
+    let provider: Box<dyn Provider> = match options.cache {
+        Some(ref cache) => Box::new(CachingProvider::new(
+            provider,
+            name.to_lowercase(),
+            cache.clone(),
+        )),
+        None => provider,
+    };
+    // Outside the cache, so each model's answers are cached under its own request
+    Ok(if options.model_fallbacks.is_empty() {
+        provider
+    } else {
+        Box::new(FallbackProvider::new(
+            provider,
+            options.model_fallbacks.to_vec(),
+        ))
+    })
+}
+
+fn build_uncached_provider(
+    name: &str,
+    options: &ProviderOptions,
+) -> Result<Box<dyn Provider>, ProviderError> {
+    let base_url = |env_var: &str| {
+        options.base_url.or(options
+            .fallback_base_url
+            .filter(|_| std::env::var_os(env_var).is_none()))
+    };
+    check_credential_headers(options)?;
+    let request_log = options
+        .debug_requests
+        .then(|| -> RequestLog { Arc::new(|dump: &str| eprintln!("{}", dump)) });
+    match name.to_lowercase().as_str() {
+        "openai" => {
+            let mut provider = OpenAIProvider::from_env()?
+                .with_request_log(request_log)
+                .with_capture(options.capture.clone())
+                .with_headers(options.headers)?;
+            if let Some(url) = base_url("OPENAI_BASE_URL") {
+                provider = provider.with_base_url(url);
+            }
+            if let Some(timeout) = options.timeout {
+                provider = provider.with_timeout(timeout);
+            }
+            if let Some(max) = options.max_concurrency {
+                provider = provider.with_max_concurrency(Some(max));
+            }
+            if options.legacy_max_tokens {
+                provider = provider.with_legacy_max_tokens(true);
+            }
+            if let Some(organization) = options.organization {
+                provider = provider.with_organization(Some(organization.to_string()));
+            }
+            if let Some(project) = options.project {
+                provider = provider.with_project(Some(project.to_string()));
+            }
+            Ok(Box::new(provider))
+        }
+        "ollama" => {
+            let mut provider = OpenAIProvider::ollama_from_env()
+                .with_request_log(request_log)
+                .with_capture(options.capture.clone())
+                .with_headers(options.headers)?;
+            if let Some(url) = base_url("OLLAMA_BASE_URL") {
+                provider = provider.with_base_url(url);
+            }
+            if let Some(timeout) = options.timeout {
+                provider = provider.with_timeout(timeout);
+            }
+            if let Some(max) = options.max_concurrency {
+                provider = provider.with_max_concurrency(Some(max));
+            }
+            if options.legacy_max_tokens {
+                provider = provider.with_legacy_max_tokens(true);
+            }
+            Ok(Box::new(provider))
+        }
+        "gemini" => {
+            if !options.headers.is_empty() {
+                return Err(ProviderError::Unsupported(
+                    "Custom headers (--header or [headers] in the config file) are not supported \
+                     by the gemini provider"
+                        .to_string(),
+                ));
+            }
+            let mut provider = GeminiProvider::from_env()?
+                .with_request_log(request_log)
+                .with_capture(options.capture.clone());
+            if let Some(url) = base_url("GEMINI_BASE_URL") {
+                provider = provider.with_base_url(url);
+            }
+            if let Some(timeout) = options.timeout {
+                provider = provider.with_timeout(timeout);
+            }
+            Ok(Box::new(provider))
+        }
+        "azure" => {
+            let mut provider = OpenAIProvider::azure_from_env()?
+                .with_request_log(request_log)
+                .with_capture(options.capture.clone())
+                .with_headers(options.headers)?;
+            if let Some(url) = base_url("AZURE_OPENAI_ENDPOINT") {
+                provider = provider.with_base_url(url);
+            }
+            if let Some(timeout) = options.timeout {
+                provider = provider.with_timeout(timeout);
+            }
+            if let Some(max) = options.max_concurrency {
+                provider = provider.with_max_concurrency(Some(max));
+            }
+            if options.legacy_max_tokens {
+                provider = provider.with_legacy_max_tokens(true);
+            }
+            Ok(Box::new(provider))
+        }
+        _ => Err(ProviderError::UnknownProvider(name.to_string())),
+    }
+}
+
+/// Replacement for redacted secrets
+const REDACTED: &str = "***";
+
+/// Secrets shorter than this are not redacted by value (e.g. Ollama's placeholder key),
+/// since they would also match ordinary words
+const MIN_SECRET_LEN: usize = 8;
+
+/// Replace secrets in error text: the given keys, `Bearer <token>` values and `sk-...` keys
+pub fn redact_secrets(text: &str, secrets: &[&str]) -> String {
+    let mut result = text.to_string();
+    for secret in secrets.iter().filter(|s| s.len() >= MIN_SECRET_LEN) {
+        result = result.replace(secret, REDACTED);
+    }
+    let result = redact_tokens(&result, "Bearer ", 1);
+    redact_tokens(&result, "sk-", MIN_SECRET_LEN)
+}
+
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~' | '+' | '/' | '=')
+}
+
+/// Redact the token following each `marker` that starts a word, when at least `min_len` long
+fn redact_tokens(text: &str, marker: &str, min_len: usize) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find(marker) {
+        let starts_word = !rest[..pos].chars().next_back().is_some_and(is_token_char);
+        let after = &rest[pos + marker.len()..];
+        let token_len = after.find(|c| !is_token_char(c)).unwrap_or(after.len());
+        // A trailing dot ends the sentence, not the token
+        let token_len = after[..token_len].trim_end_matches('.').len();
+        result.push_str(&rest[..pos + marker.len()]);
+        if starts_word && token_len >= min_len && &after[..token_len] != REDACTED {
+            result.push_str(REDACTED);
+        } else {
+            result.push_str(&after[..token_len]);
+        }
+        rest = &after[token_len..];
+    }
+    result.push_str(rest);
+    result
 }
 
 /// Reasoning effort level for models that support it (o1, o3, etc.)
@@ -48,11 +500,20 @@ impl std::str::FromStr for ReasoningLevel {
 #[serde(rename_all = "lowercase")]
 pub enum Role {
     System,
+    /// Instructions for newer OpenAI models; they take the place of `system`
+    Developer,
     User,
     Assistant,
     Tool,
 }
 
+impl Role {
+    /// System or developer instructions, as opposed to conversation turns
+    pub fn is_instruction(&self) -> bool {
+        matches!(self, Role::System | Role::Developer)
+    }
+}
+
 /// Content part in a message (OpenAI format)
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -89,7 +550,11 @@ impl ContentPart {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub role: Role,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_content"
+    )]
     pub content: Option<Vec<ContentPart>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
@@ -97,8 +562,27 @@ pub struct Message {
     pub tool_call_id: Option<String>,
 }
 
+/// Accept message `content` as a plain string or an array of content parts
+fn deserialize_content<'de, D>(deserializer: D) -> Result<Option<Vec<ContentPart>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Content {
+        Text(String),
+        Parts(Vec<ContentPart>),
+    }
+
+    Ok(
+        Option::<Content>::deserialize(deserializer)?.map(|content| match content {
+            Content::Text(text) => vec![ContentPart::text(text)],
+            Content::Parts(parts) => parts,
+        }),
+    )
+}
+
 impl Message {
-    #[allow(dead_code)] // Part of public API for future providers
     pub fn system(content: impl Into<String>) -> Self {
         Self {
             role: Role::System,
@@ -108,6 +592,15 @@ impl Message {
         }
     }
 
+    pub fn developer(content: impl Into<String>) -> Self {
+        Self {
+            role: Role::Developer,
+            content: Some(vec![ContentPart::text(content)]),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
     pub fn user(content: impl Into<String>) -> Self {
         Self {
             role: Role::User,
@@ -127,7 +620,25 @@ impl Message {
         }
     }
 
-    #[allow(dead_code)] // Part of public API for future providers
+    /// Assistant turn that requested tool calls (echoed back in the next request)
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: Role::Assistant,
+            content: Some(vec![ContentPart::text(content)]),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    pub fn assistant_tool_calls(content: Option<String>, tool_calls: Vec<ToolCall>) -> Self {
+        Self {
+            role: Role::Assistant,
+            content: content.map(|text| vec![ContentPart::text(text)]),
+            tool_calls: Some(tool_calls),
+            tool_call_id: None,
+        }
+    }
+
     pub fn tool_result(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
         Self {
             role: Role::Tool,
@@ -138,7 +649,6 @@ impl Message {
     }
 
     /// Get text content as string (concatenates all text parts)
-    #[allow(dead_code)]
     pub fn text_content(&self) -> Option<String> {
         self.content.as_ref().map(|parts| {
             parts
@@ -203,17 +713,90 @@ pub struct FunctionDef {
     pub parameters: serde_json::Value,
 }
 
+/// Response format constraint for completion output
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum ResponseFormat {
+    #[allow(dead_code)] // Part of public API, plain text is the API default
+    Text,
+    /// Any valid JSON object
+    JsonObject,
+    /// JSON conforming to the given JSON Schema
+    JsonSchema { schema: serde_json::Value },
+}
+
+impl ResponseFormat {
+    /// Whether returned content must parse as JSON
+    pub fn expects_json(&self) -> bool {
+        !matches!(self, Self::Text)
+    }
+}
+
+/// Whether and which tools the model may call
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum ToolChoice {
+    /// The model decides (the API default)
+    Auto,
+    /// No tool calls
+    None,
+    /// At least one tool call
+    Required,
+    /// A call to the named tool
+    Function(String),
+}
+
+impl std::fmt::Display for ToolChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Auto => write!(f, "auto"),
+            Self::None => write!(f, "none"),
+            Self::Required => write!(f, "required"),
+            Self::Function(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl ToolChoice {
+    /// Whether the model must call a tool
+    pub fn forces_tool_call(&self) -> bool {
+        matches!(self, Self::Required | Self::Function(_))
+    }
+}
+
 /// Request configuration for completion
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct CompletionRequest {
     pub messages: Vec<Message>,
     pub model: Option<String>,
     pub reasoning_level: Option<ReasoningLevel>,
     pub tools: Option<Vec<Tool>>,
+    /// How the model may use `tools` (ignored without tools)
+    pub tool_choice: Option<ToolChoice>,
     pub max_tokens: Option<u32>,
     pub temperature: Option<f32>,
+    /// Sequences where generation stops (OpenAI accepts at most `MAX_STOP_SEQUENCES`)
+    pub stop: Option<Vec<String>>,
+    /// Seed for best-effort deterministic sampling
+    pub seed: Option<i64>,
+    /// Number of choices to generate in one request (OpenAI-compatible backends)
+    pub n: Option<u32>,
+    /// Penalize tokens by how often they already appeared (-2.0 to 2.0)
+    pub frequency_penalty: Option<f32>,
+    /// Penalize tokens that already appeared at all (-2.0 to 2.0)
+    pub presence_penalty: Option<f32>,
+    /// Bias added to the logits of token IDs (-100 to 100); sorted so cache keys are stable
+    pub logit_bias: Option<BTreeMap<u32, f32>>,
+    pub response_format: Option<ResponseFormat>,
+    /// Return the log probability of each output token
+    pub logprobs: Option<bool>,
+    /// With logprobs, also return this many most likely alternatives per token (0-20)
+    pub top_logprobs: Option<u8>,
+    /// End-user identifier for the provider's abuse monitoring (OpenAI `user`)
+    pub user: Option<String>,
 }
 
+/// Most stop sequences a completion request may carry
+pub const MAX_STOP_SEQUENCES: usize = 4;
+
 impl CompletionRequest {
     pub fn new(messages: Vec<Message>) -> Self {
         Self {
@@ -237,37 +820,154 @@ impl CompletionRequest {
         self
     }
 
+    pub fn with_tool_choice(mut self, choice: ToolChoice) -> Self {
+        self.tool_choice = Some(choice);
+        self
+    }
+
     pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
         self.max_tokens = Some(max_tokens);
         self
     }
 
-    #[allow(dead_code)] // Part of public API for future providers
     pub fn with_temperature(mut self, temperature: f32) -> Self {
         self.temperature = Some(temperature);
         self
     }
+
+    pub fn with_stop(mut self, stop: Vec<String>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: i64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn with_n(mut self, n: u32) -> Self {
+        self.n = Some(n);
+        self
+    }
+
+    pub fn with_frequency_penalty(mut self, penalty: f32) -> Self {
+        self.frequency_penalty = Some(penalty);
+        self
+    }
+
+    pub fn with_presence_penalty(mut self, penalty: f32) -> Self {
+        self.presence_penalty = Some(penalty);
+        self
+    }
+
+    pub fn with_logit_bias(mut self, bias: BTreeMap<u32, f32>) -> Self {
+        self.logit_bias = Some(bias);
+        self
+    }
+
+    pub fn with_response_format(mut self, format: ResponseFormat) -> Self {
+        self.response_format = Some(format);
+        self
+    }
+
+    /// Request token log probabilities, with `top` alternatives per token
+    pub fn with_logprobs(mut self, top: Option<u8>) -> Self {
+        self.logprobs = Some(true);
+        self.top_logprobs = top;
+        self
+    }
+
+    pub fn with_user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
 }
 
 /// Response from completion
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)] // Fields are part of public API
 pub struct CompletionResponse {
     pub content: Option<String>,
     pub tool_calls: Option<Vec<ToolCall>>,
     pub finish_reason: String,
     pub usage: Usage,
+    /// Backend configuration that served the request (changes may affect seeded output)
+    pub system_fingerprint: Option<String>,
+    /// Log probability of each output token, when requested and supported
+    pub logprobs: Option<Vec<TokenLogprob>>,
+    /// Content of every choice when the backend returned several (request `n`);
+    /// the first is also `content`. Empty for a single choice
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<String>,
+    /// Refusal message when the model declined to answer (OpenAI `message.refusal`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refusal: Option<String>,
+    /// Model that produced the response, as reported by the API (else the one requested);
+    /// differs from the requested model after a --model-fallback
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+impl CompletionResponse {
+    /// Error for a response that carries only a refusal (no content, no tool calls)
+    pub fn check_refusal(&self) -> Result<(), ProviderError> {
+        let has_output = self.content.as_deref().is_some_and(|c| !c.is_empty())
+            || self
+                .tool_calls
+                .as_ref()
+                .is_some_and(|calls| !calls.is_empty());
+        match &self.refusal {
+            Some(refusal) if !has_output => Err(ProviderError::Refusal(refusal.clone())),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Log probability of one output token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenLogprob {
+    pub token: String,
+    pub logprob: f64,
+    /// Most likely alternatives at this position (with top_logprobs)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub top_logprobs: Vec<TopLogprob>,
+}
+
+/// An alternative token and its log probability
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopLogprob {
+    pub token: String,
+    pub logprob: f64,
 }
 
 /// Token usage info
-#[derive(Debug, Clone, Default)]
-#[allow(dead_code)] // Fields are part of public API
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Usage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
 }
 
+impl Usage {
+    /// One-line summary for interactive output
+    pub fn summary(&self) -> String {
+        format!(
+            "Tokens: {} prompt + {} completion = {} total",
+            self.prompt_tokens, self.completion_tokens, self.total_tokens
+        )
+    }
+}
+
+impl std::ops::AddAssign<&Usage> for Usage {
+    fn add_assign(&mut self, other: &Usage) {
+        self.prompt_tokens = self.prompt_tokens.saturating_add(other.prompt_tokens);
+        self.completion_tokens = self
+            .completion_tokens
+            .saturating_add(other.completion_tokens);
+        self.total_tokens = self.total_tokens.saturating_add(other.total_tokens);
+    }
+}
+
 // ============================================================================
 // Responses API types (for image generation tool)
 // ============================================================================
@@ -484,12 +1184,315 @@ pub struct ImageGenerationResult {
 pub struct ResponsesResponse {
     pub id: String,
     pub images: Vec<ImageGenerationResult>,
+    pub usage: Option<Usage>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_redact_secrets() {
+        let text = "invalid key abc123secret456, header: Authorization: Bearer tok_live.XYZ=";
+        assert_eq!(
+            redact_secrets(text, &["abc123secret456"]),
+            "invalid key ***, header: Authorization: Bearer ***"
+        );
+        assert_eq!(
+            redact_secrets("Incorrect API key provided: sk-proj-AbC123xyz789.", &[]),
+            "Incorrect API key provided: sk-***."
+        );
+        // Short placeholder keys and words containing `sk-` are left alone
+        assert_eq!(
+            redact_secrets("model ollama not found for task-runner", &["ollama"]),
+            "model ollama not found for task-runner"
+        );
+    }
+
+    #[test]
+    fn test_api_error_structured_body() {
+        let err = ProviderError::from_api_response(
+            429,
+            r#"{"error": {"message": "You exceeded your current quota.", "type": "insufficient_quota", "param": null, "code": "insufficient_quota"}}"#,
+            &[],
+        );
+        match err {
+            ProviderError::Api {
+                status,
+                message,
+                details,
+            } => {
+                assert_eq!(status, 429);
+                assert_eq!(message, "You exceeded your current quota.");
+                assert_eq!(
+                    details,
+                    Some(ApiErrorDetails {
+                        error_type: Some("insufficient_quota".to_string()),
+                        code: Some("insufficient_quota".to_string()),
+                        param: None,
+                    })
+                );
+            }
+            other => panic!("Expected Api error, got {:?}", other),
+        }
+
+        // Gemini: numeric code, `status` as the type
+        let err = ProviderError::from_api_response(
+            400,
+            r#"{"error": {"code": 400, "message": "Invalid JSON payload", "status": "INVALID_ARGUMENT"}}"#,
+            &[],
+        );
+        match err {
+            ProviderError::Api { details, .. } => {
+                let details = details.unwrap();
+                assert_eq!(details.error_type.as_deref(), Some("INVALID_ARGUMENT"));
+                assert_eq!(details.code.as_deref(), Some("400"));
+            }
+            other => panic!("Expected Api error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_api_error_unstructured_body() {
+        for body in ["upstream connect error", r#"{"detail": "Not Found"}"#, ""] {
+            match ProviderError::from_api_response(502, body, &[]) {
+                ProviderError::Api {
+                    message, details, ..
+                } => {
+                    assert_eq!(message, body);
+                    assert!(details.is_none());
+                }
+                other => panic!("Expected Api error, got {:?}", other),
+            }
+        }
+        // Redaction applies before parsing
+        match ProviderError::from_api_response(
+            401,
+            r#"{"error": {"message": "Incorrect API key provided: my-secret-key-123"}}"#,
+            &["my-secret-key-123"],
+        ) {
+            ProviderError::Api { message, .. } => {
+                assert_eq!(message, "Incorrect API key provided: ***")
+            }
+            other => panic!("Expected Api error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_build_provider_ollama() {
+        assert!(build_provider("ollama", &ProviderOptions::default()).is_ok());
+        assert!(build_provider(
+            "Ollama",
+            &ProviderOptions {
+                fallback_base_url: Some("http://gpu-box:11434/v1"),
+                ..Default::default()
+            }
+        )
+        .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_build_provider_base_url_override() {
+        use mockito::Server;
+
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"choices": [{"message": {"role": "assistant", "content": "ok"}, "finish_reason": "stop"}]}"#,
+            )
+            .create_async()
+            .await;
+
+        // The override wins over the config fallback (and any OLLAMA_BASE_URL)
+        let url = server.url();
+        let options = ProviderOptions {
+            base_url: Some(&url),
+            fallback_base_url: Some("http://127.0.0.1:9/v1"),
+            ..Default::default()
+        };
+        let provider = build_provider("ollama", &options).unwrap();
+        let response = provider
+            .complete(CompletionRequest::new(vec![Message::user("Hi")]))
+            .await
+            .unwrap();
+
+        assert_eq!(response.content.as_deref(), Some("ok"));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_cache_separates_base_urls_from_environment() {
+        use mockito::Server;
+
+        let mut servers = Vec::new();
+        let mut mocks = Vec::new();
+        for reply in ["Hi 1", "OTHER 1"] {
+            let mut server = Server::new_async().await;
+            mocks.push(
+                server
+                    .mock("POST", "/chat/completions")
+                    .with_body(format!(
+                        r#"{{"choices": [{{"message": {{"role": "assistant", "content": "{}"}}, "finish_reason": "stop"}}]}}"#,
+                        reply
+                    ))
+                    .expect(1)
+                    .create_async()
+                    .await,
+            );
+            servers.push(server);
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let options = ProviderOptions {
+            cache: Some(CacheOptions {
+                dir: dir.path().to_path_buf(),
+                ttl: cache::DEFAULT_CACHE_TTL,
+            }),
+            ..Default::default()
+        };
+
+        // Only this test sets the variable; same request, same cache, another endpoint
+        let mut replies = Vec::new();
+        for server in &servers {
+            std::env::set_var("OLLAMA_BASE_URL", server.url());
+            let provider = build_provider("ollama", &options);
+            std::env::remove_var("OLLAMA_BASE_URL");
+            let response = provider
+                .unwrap()
+                .complete(CompletionRequest::new(vec![Message::user("hello")]))
+                .await
+                .unwrap();
+            replies.push(response.content.unwrap());
+        }
+
+        assert_eq!(replies, ["Hi 1", "OTHER 1"]);
+        for mock in mocks {
+            mock.assert_async().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_headers_sent_with_requests() {
+        use mockito::Server;
+
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_header("x-gateway-token", "abc123")
+            .match_header("authorization", "Bearer ollama")
+            .with_body(
+                r#"{"choices": [{"message": {"role": "assistant", "content": "ok"}, "finish_reason": "stop"}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let url = server.url();
+        let headers = vec![parse_header("X-Gateway-Token: abc123").unwrap()];
+        let options = ProviderOptions {
+            base_url: Some(&url),
+            headers: &headers,
+            ..Default::default()
+        };
+        let provider = build_provider("ollama", &options).unwrap();
+        provider
+            .complete(CompletionRequest::new(vec![Message::user("Hi")]))
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[test]
+    fn test_auth_header_override_needs_opt_in() {
+        let headers = vec![("authorization".to_string(), "Bearer other".to_string())];
+        let options = ProviderOptions {
+            headers: &headers,
+            ..Default::default()
+        };
+        let err = build_provider("ollama", &options).err().unwrap();
+        assert!(matches!(err, ProviderError::InvalidHeader(ref m) if m.contains("authorization")));
+
+        let options = ProviderOptions {
+            headers: &headers,
+            allow_auth_header: true,
+            ..Default::default()
+        };
+        assert!(build_provider("ollama", &options).is_ok());
+
+        let bad = vec![("X Token".to_string(), "v".to_string())];
+        let options = ProviderOptions {
+            headers: &bad,
+            ..Default::default()
+        };
+        assert!(matches!(
+            build_provider("ollama", &options).err().unwrap(),
+            ProviderError::InvalidHeader(_)
+        ));
+    }
+
+    #[test]
+    fn test_gemini_rejects_custom_headers() {
+        let headers = vec![("X-Gateway-Token".to_string(), "abc".to_string())];
+        let options = ProviderOptions {
+            headers: &headers,
+            ..Default::default()
+        };
+        let err = build_provider("gemini", &options).err().unwrap();
+        assert!(matches!(err, ProviderError::Unsupported(ref m) if m.contains("gemini")));
+    }
+
+    #[test]
+    fn test_parse_header() {
+        assert_eq!(
+            parse_header("X-Gateway-Token:  abc: 1 ").unwrap(),
+            ("X-Gateway-Token".to_string(), "abc: 1".to_string())
+        );
+        assert!(parse_header("no-colon").is_err());
+        assert!(parse_header(": value").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_build_provider_timeout_override() {
+        use mockito::Server;
+
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/chat/completions")
+            .with_body_from_request(|_| {
+                std::thread::sleep(Duration::from_millis(500));
+                b"{}".to_vec()
+            })
+            .create_async()
+            .await;
+
+        let url = server.url();
+        let options = ProviderOptions {
+            base_url: Some(&url),
+            timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let provider = build_provider("ollama", &options).unwrap();
+        let err = provider
+            .complete(CompletionRequest::new(vec![Message::user("Hi")]))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, ProviderError::Http(ref e) if e.is_timeout()),
+            "{:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_build_provider_unknown() {
+        let err = build_provider("nope", &ProviderOptions::default())
+            .err()
+            .unwrap();
+        assert!(matches!(err, ProviderError::UnknownProvider(ref name) if name == "nope"));
+        assert_eq!(err.to_string(), "Unknown provider: nope");
+    }
+
     #[test]
     fn test_reasoning_level_from_str() {
         assert_eq!(
@@ -533,6 +1536,17 @@ mod tests {
         assert_eq!(tool.tool_call_id, Some("call_123".to_string()));
     }
 
+    #[test]
+    fn test_developer_message_serialization() {
+        let message = Message::developer("Answer in French");
+        assert!(message.role.is_instruction());
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["role"], "developer");
+        let parsed: Message = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.role, Role::Developer);
+        assert!(!Role::User.is_instruction());
+    }
+
     #[test]
     fn test_message_content_parts() {
         let msg = Message::user_parts(vec![
@@ -1,17 +1,60 @@
 // OpenAI provider implementation.
-// Env vars: OPENAI_API_KEY (required), OPENAI_BASE_URL (optional, default: https://api.openai.com/v1)
-
+// Env vars: OPENAI_API_KEY (required), OPENAI_BASE_URL (optional, default: https://api.openai.com/v1),
+// OPENAI_TIMEOUT_SECS (optional, default: 120), OPENAI_DEFAULT_MODEL (optional, default: gpt-5-mini),
+// OPENAI_REASONING_MODELS (optional, comma-separated model prefixes treated as reasoning models),
+// OPENAI_DEVELOPER_ROLE (optional: never (default), auto for reasoning models, always; sends
+// `system` messages with the `developer` role), OPENAI_ORG_ID and OPENAI_PROJECT_ID (optional,
+// sent as OpenAI-Organization and OpenAI-Project headers on every request),
+// OPENAI_MAX_CONCURRENCY (optional, cap on simultaneous chat completions of one client)
+// Ollama reuses this client via its OpenAI-compatible API: OLLAMA_BASE_URL (optional,
+// default: http://localhost:11434/v1), no API key required.
+// Azure OpenAI reuses it too: same bodies, but requests go to
+// {endpoint}/openai/deployments/{deployment}/chat/completions?api-version=... with an
+// `api-key` header. Env: AZURE_OPENAI_ENDPOINT, AZURE_OPENAI_API_KEY, AZURE_OPENAI_DEPLOYMENT
+// (required), AZURE_OPENAI_API_VERSION (optional). Image generation is not routed to Azure.
+// Streaming (complete_streaming) sends `stream: true` and reads server-sent events, text
+// deltas and reassembled tool calls are passed on as they arrive; only the first choice is kept.
+
+use super::stream::{SseDecoder, ToolCallAssembler};
 use super::{
-    CompletionRequest, CompletionResponse, ContentPart, FunctionCall, ImageGenerationResult,
-    ProviderError, ReasoningLevel, ResponsesRequest, ResponsesResponse, Tool, ToolCall, Usage,
+    format_request_dump, CompletionRequest, CompletionResponse, ContentPart, FunctionCall,
+    ImageGenerationResult, PayloadCapture, Provider, ProviderError, ReasoningLevel, RequestLog,
+    ResponseFormat, ResponsesRequest, ResponsesResponse, Role, StreamEvent, TokenLogprob, Tool,
+    ToolCall, ToolChoice, Usage,
 };
-use reqwest::Client;
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, RequestBuilder};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
+use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::{debug, info};
 
 const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
 const DEFAULT_MODEL: &str = "gpt-5-mini";
 const DEFAULT_IMAGE_MODEL: &str = "gpt-4.1";
+const DEFAULT_TIMEOUT_SECS: u64 = 120;
+const DEFAULT_OLLAMA_BASE_URL: &str = "http://localhost:11434/v1";
+const DEFAULT_AZURE_API_VERSION: &str = "2024-10-21";
+
+/// Whether a model is a reasoning model (accepts reasoning_effort, rejects temperature).
+/// Covers the o-series (o1, o3, o4-mini, ...) and the gpt-5 family except chat variants.
+pub fn is_reasoning_model(model: &str) -> bool {
+    let mut chars = model.chars();
+    let o_series = chars.next() == Some('o') && chars.next().is_some_and(|c| c.is_ascii_digit());
+    let gpt5 = model.starts_with("gpt-5") && !model.starts_with("gpt-5-chat");
+    o_series || gpt5
+}
+
+/// Build HTTP client with request timeout
+fn build_client(timeout: Duration) -> Client {
+    Client::builder()
+        .timeout(timeout)
+        .build()
+        .expect("Failed to build HTTP client")
+}
 
 /// OpenAI API client
 pub struct OpenAIProvider {
@@ -19,33 +62,296 @@ pub struct OpenAIProvider {
     api_key: String,
     base_url: String,
     default_model: String,
+    timeout: Duration,
+    /// Overrides built-in reasoning model detection when set
+    reasoning_models: Option<Vec<String>>,
+    /// Azure OpenAI deployment; changes URLs and the auth header
+    azure: Option<AzureDeployment>,
+    /// When `system` messages are sent with the `developer` role
+    developer_role: DeveloperRole,
+    /// OpenAI-Organization header
+    organization: Option<String>,
+    /// OpenAI-Project header
+    project: Option<String>,
+    /// Receives each request body before it is sent (--debug-request)
+    request_log: Option<RequestLog>,
+    /// Files for completion payloads (--save-request, --save-response)
+    capture: PayloadCapture,
+    /// Slots for simultaneous chat completions; unlimited when None
+    limiter: Option<Semaphore>,
+    /// Send the token limit as `max_tokens` instead of `max_completion_tokens`
+    legacy_max_tokens: bool,
+    /// Extra headers for every request (--header); applied last, so they win
+    headers: HeaderMap,
+}
+
+/// When `system` messages are sent as `developer` messages (OPENAI_DEVELOPER_ROLE)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeveloperRole {
+    /// Keep `system` (older models and most OpenAI-compatible servers need it)
+    #[default]
+    Never,
+    /// Translate for reasoning models
+    Auto,
+    /// Translate for every model
+    Always,
+}
+
+impl DeveloperRole {
+    /// Parse `never`, `auto` or `always`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "never" => Some(Self::Never),
+            "auto" => Some(Self::Auto),
+            "always" => Some(Self::Always),
+            _ => None,
+        }
+    }
+}
+
+/// Azure OpenAI deployment addressed by the provider
+#[derive(Debug, Clone, PartialEq)]
+struct AzureDeployment {
+    deployment: String,
+    api_version: String,
+}
+
+/// Required environment variable, reported as missing configuration when unset or empty
+fn required_env(name: &str) -> Result<String, ProviderError> {
+    env::var(name)
+        .ok()
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| ProviderError::MissingConfig(name.to_string()))
 }
 
 impl OpenAIProvider {
     /// Create new provider from environment variables.
     /// OPENAI_API_KEY - required
     /// OPENAI_BASE_URL - optional (default: https://api.openai.com/v1)
+    /// OPENAI_TIMEOUT_SECS - optional (default: 120)
+    /// OPENAI_DEFAULT_MODEL, OPENAI_DEVELOPER_ROLE, OPENAI_REASONING_MODELS - optional
+    /// OPENAI_ORG_ID, OPENAI_PROJECT_ID, OPENAI_MAX_CONCURRENCY - optional
+    /// OPENAI_LEGACY_MAX_TOKENS - optional (1/true: send `max_tokens`)
     pub fn from_env() -> Result<Self, ProviderError> {
         let api_key = env::var("OPENAI_API_KEY")
             .map_err(|_| ProviderError::MissingApiKey("OPENAI_API_KEY".to_string()))?;
         let base_url = env::var("OPENAI_BASE_URL").unwrap_or_else(|_| DEFAULT_BASE_URL.to_string());
+        let timeout_secs = env::var("OPENAI_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+
+        let optional_env = |name: &str| env::var(name).ok().filter(|v| !v.trim().is_empty());
+
+        Ok(Self::new(api_key, Some(base_url))
+            .with_timeout(Duration::from_secs(timeout_secs))
+            .with_model_settings_from_env()
+            .with_organization(optional_env("OPENAI_ORG_ID"))
+            .with_project(optional_env("OPENAI_PROJECT_ID"))
+            .with_max_concurrency(
+                optional_env("OPENAI_MAX_CONCURRENCY").and_then(|v| v.trim().parse().ok()),
+            )
+            .with_legacy_max_tokens(
+                optional_env("OPENAI_LEGACY_MAX_TOKENS").is_some_and(|v| {
+                    matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes")
+                }),
+            ))
+    }
 
-        Ok(Self {
-            client: Client::new(),
-            api_key,
-            base_url,
-            default_model: DEFAULT_MODEL.to_string(),
-        })
+    /// Apply OPENAI_DEFAULT_MODEL, OPENAI_DEVELOPER_ROLE and OPENAI_REASONING_MODELS, when set
+    fn with_model_settings_from_env(mut self) -> Self {
+        if let Some(model) = env::var("OPENAI_DEFAULT_MODEL")
+            .ok()
+            .filter(|m| !m.trim().is_empty())
+        {
+            self = self.with_default_model(model.trim());
+        }
+        if let Some(mode) = env::var("OPENAI_DEVELOPER_ROLE")
+            .ok()
+            .and_then(|v| DeveloperRole::parse(&v))
+        {
+            self = self.with_developer_role(mode);
+        }
+        if let Ok(prefixes) = env::var("OPENAI_REASONING_MODELS") {
+            self = self.with_reasoning_models(
+                prefixes
+                    .split(',')
+                    .map(|p| p.trim().to_string())
+                    .filter(|p| !p.is_empty())
+                    .collect(),
+            );
+        }
+        self
+    }
+
+    /// Create provider for a local Ollama server (OpenAI-compatible API).
+    /// OLLAMA_BASE_URL - optional (default: http://localhost:11434/v1)
+    pub fn ollama_from_env() -> Self {
+        let base_url =
+            env::var("OLLAMA_BASE_URL").unwrap_or_else(|_| DEFAULT_OLLAMA_BASE_URL.to_string());
+        // Ollama ignores the key, but the Authorization header is still sent
+        Self::new("ollama".to_string(), Some(base_url))
+    }
+
+    /// Create provider for an Azure OpenAI deployment.
+    /// AZURE_OPENAI_ENDPOINT, AZURE_OPENAI_API_KEY, AZURE_OPENAI_DEPLOYMENT - required
+    /// AZURE_OPENAI_API_VERSION - optional (default: 2024-10-21)
+    pub fn azure_from_env() -> Result<Self, ProviderError> {
+        let api_key = env::var("AZURE_OPENAI_API_KEY")
+            .map_err(|_| ProviderError::MissingApiKey("AZURE_OPENAI_API_KEY".to_string()))?;
+        let endpoint = required_env("AZURE_OPENAI_ENDPOINT")?;
+        let deployment = required_env("AZURE_OPENAI_DEPLOYMENT")?;
+        let api_version = env::var("AZURE_OPENAI_API_VERSION")
+            .unwrap_or_else(|_| DEFAULT_AZURE_API_VERSION.to_string());
+        Ok(Self::azure(api_key, endpoint, deployment, api_version))
+    }
+
+    /// Create provider for an Azure OpenAI deployment with explicit configuration
+    pub fn azure(
+        api_key: String,
+        endpoint: impl Into<String>,
+        deployment: impl Into<String>,
+        api_version: impl Into<String>,
+    ) -> Self {
+        let deployment = deployment.into();
+        let mut provider = Self::new(api_key, Some(endpoint.into()));
+        // The deployment decides the model; it's also sent as `model` and used for
+        // reasoning detection when the request names no model
+        provider.default_model = deployment.clone();
+        provider.azure = Some(AzureDeployment {
+            deployment,
+            api_version: api_version.into(),
+        });
+        provider
     }
 
     /// Create provider with explicit configuration (useful for testing)
-    #[allow(dead_code)] // Used in tests and for manual configuration
     pub fn new(api_key: String, base_url: Option<String>) -> Self {
+        let timeout = Duration::from_secs(DEFAULT_TIMEOUT_SECS);
         Self {
-            client: Client::new(),
+            client: build_client(timeout),
             api_key,
             base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
             default_model: DEFAULT_MODEL.to_string(),
+            timeout,
+            reasoning_models: None,
+            azure: None,
+            developer_role: DeveloperRole::default(),
+            organization: None,
+            project: None,
+            request_log: None,
+            capture: PayloadCapture::default(),
+            limiter: None,
+            legacy_max_tokens: false,
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// Set request timeout (rebuilds the HTTP client)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = build_client(timeout);
+        self.timeout = timeout;
+        self
+    }
+
+    /// Override the API base URL
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Dump each request body to `log` before sending it
+    pub fn with_request_log(mut self, log: Option<RequestLog>) -> Self {
+        self.request_log = log;
+        self
+    }
+
+    /// Save completion request and response bodies to the capture's files
+    pub fn with_capture(mut self, capture: PayloadCapture) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    /// Pass the request body to the request log, if any
+    fn log_request(&self, url: &str, body: &impl Serialize) {
+        if let Some(ref log) = self.request_log {
+            log(&format_request_dump(url, body, &[&self.api_key]));
+        }
+    }
+
+    /// Model used when a request names none (default: gpt-5-mini)
+    pub fn with_default_model(mut self, model: impl Into<String>) -> Self {
+        self.default_model = model.into();
+        self
+    }
+
+    /// Send an OpenAI-Organization header with every request
+    pub fn with_organization(mut self, organization: Option<String>) -> Self {
+        self.organization = organization;
+        self
+    }
+
+    /// Send an OpenAI-Project header with every request
+    pub fn with_project(mut self, project: Option<String>) -> Self {
+        self.project = project;
+        self
+    }
+
+    /// Allow at most `max` chat completions in flight at once through this client; further
+    /// calls wait for a free slot. None or 0 means unlimited
+    pub fn with_max_concurrency(mut self, max: Option<usize>) -> Self {
+        self.limiter = max.filter(|&max| max > 0).map(Semaphore::new);
+        self
+    }
+
+    /// Send these headers with every request, after the built-in ones (so they replace them);
+    /// later headers replace earlier ones of the same name
+    pub fn with_headers(mut self, headers: &[(String, String)]) -> Result<Self, ProviderError> {
+        for (name, value) in headers {
+            let header_name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| ProviderError::InvalidHeader(format!("bad name '{}'", name)))?;
+            let mut header_value = HeaderValue::from_str(value)
+                .map_err(|_| ProviderError::InvalidHeader(format!("bad value for '{}'", name)))?;
+            // Often a token: keep it out of debug output
+            header_value.set_sensitive(true);
+            self.headers.insert(header_name, header_value);
+        }
+        Ok(self)
+    }
+
+    /// Send the completion token limit as the older `max_tokens` field, for OpenAI-compatible
+    /// servers and proxies that don't accept `max_completion_tokens`
+    pub fn with_legacy_max_tokens(mut self, legacy: bool) -> Self {
+        self.legacy_max_tokens = legacy;
+        self
+    }
+
+    /// Wait for a free request slot when concurrency is capped; the permit holds the slot
+    async fn acquire_slot(&self) -> Option<SemaphorePermit<'_>> {
+        match self.limiter {
+            // The semaphore is never closed, so acquiring only fails in theory
+            Some(ref limiter) => limiter.acquire().await.ok(),
+            None => None,
+        }
+    }
+
+    /// Send `system` messages with the `developer` role as `mode` says
+    pub fn with_developer_role(mut self, mode: DeveloperRole) -> Self {
+        self.developer_role = mode;
+        self
+    }
+
+    /// Treat models starting with any of these prefixes as reasoning models,
+    /// replacing built-in detection
+    pub fn with_reasoning_models(mut self, prefixes: Vec<String>) -> Self {
+        self.reasoning_models = Some(prefixes);
+        self
+    }
+
+    fn is_reasoning(&self, model: &str) -> bool {
+        match self.reasoning_models {
+            Some(ref prefixes) => prefixes.iter().any(|p| model.starts_with(p.as_str())),
+            None => is_reasoning_model(model),
         }
     }
 
@@ -58,38 +364,129 @@ impl OpenAIProvider {
             api_key,
             base_url,
             default_model: DEFAULT_MODEL.to_string(),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            reasoning_models: None,
+            azure: None,
+            developer_role: DeveloperRole::default(),
+            organization: None,
+            project: None,
+            request_log: None,
+            capture: PayloadCapture::default(),
+            limiter: None,
+            legacy_max_tokens: false,
+            headers: HeaderMap::new(),
         }
     }
 
-    /// Complete a chat request
-    pub async fn complete(
-        &self,
-        request: CompletionRequest,
-    ) -> Result<CompletionResponse, ProviderError> {
+    /// URL of an API operation (e.g. `chat/completions`)
+    fn endpoint_url(&self, operation: &str) -> String {
+        match self.azure {
+            // The Azure portal shows endpoints with a trailing slash
+            Some(ref azure) => format!(
+                "{}/openai/deployments/{}/{}?api-version={}",
+                self.base_url.trim_end_matches('/'),
+                azure.deployment,
+                operation,
+                azure.api_version
+            ),
+            None => format!("{}/{}", self.base_url, operation),
+        }
+    }
+
+    /// Add the API key (`api-key` header for Azure, bearer token otherwise) and the
+    /// organization and project headers, when set
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        let mut request = match self.azure {
+            Some(_) => request.header("api-key", &self.api_key),
+            None => request.header("Authorization", format!("Bearer {}", self.api_key)),
+        };
+        if let Some(ref organization) = self.organization {
+            request = request.header("OpenAI-Organization", organization);
+        }
+        if let Some(ref project) = self.project {
+            request = request.header("OpenAI-Project", project);
+        }
+        request.headers(self.headers.clone())
+    }
+
+    /// Map a CompletionRequest to the chat completions wire format
+    fn build_chat_request(&self, request: &CompletionRequest) -> OpenAIRequest {
         let model = request.model.as_deref().unwrap_or(&self.default_model);
-        let is_reasoning_model = model.starts_with("o1") || model.starts_with("o3");
+        let is_reasoning_model = self.is_reasoning(model);
+        let translate_system = match self.developer_role {
+            DeveloperRole::Never => false,
+            DeveloperRole::Auto => is_reasoning_model,
+            DeveloperRole::Always => true,
+        };
 
         let mut api_request = OpenAIRequest {
             model: model.to_string(),
             messages: request
                 .messages
                 .iter()
-                .map(OpenAIMessage::from_message)
+                .map(|message| {
+                    let mut api_message = OpenAIMessage::from_message(message);
+                    if message.role == Role::System && translate_system {
+                        api_message.role = "developer".to_string();
+                    }
+                    api_message
+                })
                 .collect(),
             tools: request
                 .tools
                 .as_ref()
                 .map(|tools| tools.iter().map(OpenAITool::from_tool).collect()),
-            max_completion_tokens: request.max_tokens,
+            // The API rejects tool_choice without tools
+            tool_choice: request
+                .tool_choice
+                .as_ref()
+                .filter(|_| request.tools.is_some())
+                .map(|choice| match choice {
+                    ToolChoice::Auto => serde_json::json!("auto"),
+                    ToolChoice::None => serde_json::json!("none"),
+                    ToolChoice::Required => serde_json::json!("required"),
+                    ToolChoice::Function(name) => serde_json::json!({
+                        "type": "function",
+                        "function": {"name": name}
+                    }),
+                }),
+            max_tokens: request.max_tokens.filter(|_| self.legacy_max_tokens),
+            max_completion_tokens: request.max_tokens.filter(|_| !self.legacy_max_tokens),
             temperature: if is_reasoning_model {
                 None
             } else {
                 request.temperature
             },
+            // Reasoning models reject stop sequences too
+            stop: if is_reasoning_model {
+                None
+            } else {
+                request.stop.clone()
+            },
+            seed: request.seed,
+            n: request.n.filter(|n| *n > 1),
+            // Like temperature, the penalties and logit bias are rejected by reasoning models
+            frequency_penalty: request.frequency_penalty.filter(|_| !is_reasoning_model),
+            presence_penalty: request.presence_penalty.filter(|_| !is_reasoning_model),
+            logit_bias: request.logit_bias.clone().filter(|_| !is_reasoning_model),
+            // Reasoning models don't return logprobs and reject the parameters
+            logprobs: request.logprobs.filter(|_| !is_reasoning_model),
+            top_logprobs: request.top_logprobs.filter(|_| !is_reasoning_model),
             reasoning_effort: None,
+            response_format: request.response_format.as_ref().map(|format| match format {
+                ResponseFormat::Text => serde_json::json!({"type": "text"}),
+                ResponseFormat::JsonObject => serde_json::json!({"type": "json_object"}),
+                ResponseFormat::JsonSchema { schema } => serde_json::json!({
+                    "type": "json_schema",
+                    "json_schema": {"name": "response", "schema": schema}
+                }),
+            }),
+            user: request.user.clone(),
+            stream: None,
+            stream_options: None,
         };
 
-        // Add reasoning effort for o1/o3 models
+        // Add reasoning effort for reasoning models
         if is_reasoning_model {
             if let Some(level) = request.reasoning_level {
                 api_request.reasoning_effort = Some(match level {
@@ -100,26 +497,95 @@ impl OpenAIProvider {
             }
         }
 
-        let url = format!("{}/chat/completions", self.base_url);
+        api_request
+    }
+}
+
+#[async_trait]
+impl Provider for OpenAIProvider {
+    /// Complete a chat request
+    async fn complete(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, ProviderError> {
+        let api_request = self.build_chat_request(&request);
+        let expects_json = request
+            .response_format
+            .as_ref()
+            .is_some_and(ResponseFormat::expects_json);
+
+        let url = self.endpoint_url("chat/completions");
+        debug!(
+            model = %api_request.model,
+            url = %url,
+            messages = api_request.messages.len(),
+            tools = api_request.tools.as_ref().map_or(0, Vec::len),
+            "sending chat completion request"
+        );
+        self.log_request(&url, &api_request);
+        self.capture.save_request(&api_request, &[&self.api_key]);
+        let _slot = self.acquire_slot().await;
         let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
+            .authorize(self.client.post(&url))
             .header("Content-Type", "application/json")
             .json(&api_request)
             .send()
             .await?;
 
         let status = response.status();
+        debug!(status = status.as_u16(), "chat completion response");
+        let body = response.text().await?;
+        self.capture.save_response(&body);
         if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(ProviderError::Api {
-                status: status.as_u16(),
-                message: error_text,
-            });
+            return Err(ProviderError::from_api_response(
+                status.as_u16(),
+                &body,
+                &[&self.api_key],
+            ));
         }
 
-        let api_response: OpenAIResponse = response.json().await?;
+        let api_response: OpenAIResponse = serde_json::from_str(&body)
+            .map_err(|e| ProviderError::InvalidResponse(e.to_string()))?;
+        if let Some(ref usage) = api_response.usage {
+            info!(
+                model = %api_request.model,
+                prompt_tokens = usage.prompt_tokens,
+                completion_tokens = usage.completion_tokens,
+                total_tokens = usage.total_tokens,
+                "chat completion"
+            );
+        }
+        let system_fingerprint = api_response.system_fingerprint;
+        // Checked before JSON validation: filtered content is usually cut off
+        if api_response
+            .choices
+            .first()
+            .is_some_and(|c| c.finish_reason.as_deref() == Some("content_filter"))
+        {
+            return Err(ProviderError::ContentFiltered(
+                "finish_reason content_filter".to_string(),
+            ));
+        }
+        if expects_json {
+            for content in api_response
+                .choices
+                .iter()
+                .filter_map(|c| c.message.content.as_ref())
+            {
+                serde_json::from_str::<serde_json::Value>(content).map_err(|e| {
+                    ProviderError::InvalidResponse(format!("Expected JSON content: {}", e))
+                })?;
+            }
+        }
+        let choices = if api_response.choices.len() > 1 {
+            api_response
+                .choices
+                .iter()
+                .map(|c| c.message.content.clone().unwrap_or_default())
+                .collect()
+        } else {
+            Vec::new()
+        };
         let choice =
             api_response.choices.into_iter().next().ok_or_else(|| {
                 ProviderError::InvalidResponse("No choices in response".to_string())
@@ -141,22 +607,154 @@ impl OpenAIProvider {
                     .collect()
             }),
             finish_reason: choice.finish_reason.unwrap_or_default(),
-            usage: api_response
-                .usage
-                .map(|u| Usage {
-                    prompt_tokens: u.prompt_tokens,
-                    completion_tokens: u.completion_tokens,
-                    total_tokens: u.total_tokens,
-                })
-                .unwrap_or_default(),
+            logprobs: choice.logprobs.and_then(|logprobs| logprobs.content),
+            usage: api_response.usage.map(Usage::from).unwrap_or_default(),
+            system_fingerprint,
+            choices,
+            refusal: choice.message.refusal,
+            model: Some(api_response.model.unwrap_or(api_request.model)),
         })
     }
 
+    /// Stream a chat completion over server-sent events
+    async fn complete_streaming(
+        &self,
+        request: CompletionRequest,
+        on_event: &(dyn Fn(StreamEvent) + Send + Sync),
+    ) -> Result<CompletionResponse, ProviderError> {
+        let mut api_request = self.build_chat_request(&request);
+        api_request.stream = Some(true);
+        api_request.stream_options = Some(serde_json::json!({"include_usage": true}));
+        let expects_json = request
+            .response_format
+            .as_ref()
+            .is_some_and(ResponseFormat::expects_json);
+
+        let url = self.endpoint_url("chat/completions");
+        debug!(
+            model = %api_request.model,
+            url = %url,
+            messages = api_request.messages.len(),
+            tools = api_request.tools.as_ref().map_or(0, Vec::len),
+            "sending streaming chat completion request"
+        );
+        self.log_request(&url, &api_request);
+        self.capture.save_request(&api_request, &[&self.api_key]);
+        // Held until the stream is fully read
+        let _slot = self.acquire_slot().await;
+        let mut response = self
+            .authorize(self.client.post(&url))
+            .header("Content-Type", "application/json")
+            .json(&api_request)
+            .send()
+            .await?;
+
+        let status = response.status();
+        debug!(
+            status = status.as_u16(),
+            "streaming chat completion response"
+        );
+        if !status.is_success() {
+            let body = response.text().await?;
+            self.capture.save_response(&body);
+            return Err(ProviderError::from_api_response(
+                status.as_u16(),
+                &body,
+                &[&self.api_key],
+            ));
+        }
+
+        let mut decoder = SseDecoder::default();
+        // Chunks name the serving model; the requested one stands in if they don't
+        let mut stream = ChatStream {
+            model: Some(api_request.model.clone()),
+            ..Default::default()
+        };
+        // Raw event stream, kept for --capture
+        let mut raw = Vec::new();
+        'read: while let Some(bytes) = response.chunk().await? {
+            raw.extend_from_slice(&bytes);
+            for payload in decoder.push(&bytes) {
+                if payload == "[DONE]" {
+                    break 'read;
+                }
+                let chunk: OpenAIStreamChunk = serde_json::from_str(&payload)
+                    .map_err(|e| ProviderError::InvalidResponse(e.to_string()))?;
+                stream.apply(chunk, on_event);
+            }
+        }
+        self.capture.save_response(&String::from_utf8_lossy(&raw));
+
+        if let Some(ref usage) = stream.usage {
+            info!(
+                model = %api_request.model,
+                prompt_tokens = usage.prompt_tokens,
+                completion_tokens = usage.completion_tokens,
+                total_tokens = usage.total_tokens,
+                "streaming chat completion"
+            );
+        }
+        if stream.finish_reason.as_deref() == Some("content_filter") {
+            return Err(ProviderError::ContentFiltered(
+                "finish_reason content_filter".to_string(),
+            ));
+        }
+        let response = stream.finish(on_event)?;
+        if expects_json {
+            if let Some(ref content) = response.content {
+                serde_json::from_str::<serde_json::Value>(content).map_err(|e| {
+                    ProviderError::InvalidResponse(format!("Expected JSON content: {}", e))
+                })?;
+            }
+        }
+        Ok(response)
+    }
+
+    fn base_url(&self) -> Option<&str> {
+        Some(&self.base_url)
+    }
+
+    fn default_model(&self) -> Option<&str> {
+        Some(&self.default_model)
+    }
+
+    /// IDs from `GET /models`
+    async fn list_models(&self) -> Result<Vec<String>, ProviderError> {
+        if self.azure.is_some() {
+            return Err(ProviderError::Unsupported(
+                "listing models with the azure provider (models are deployments)".to_string(),
+            ));
+        }
+        let url = self.endpoint_url("models");
+        debug!(url = %url, "sending list models request");
+        let response = self.authorize(self.client.get(&url)).send().await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(ProviderError::from_api_response(
+                status.as_u16(),
+                &error_text,
+                &[&self.api_key],
+            ));
+        }
+
+        let models: ModelsResponse = response.json().await?;
+        let mut ids: Vec<String> = models.data.into_iter().map(|m| m.id).collect();
+        ids.sort();
+        Ok(ids)
+    }
+
     /// Generate images using the Responses API with image_generation tool
-    pub async fn create_response(
+    async fn create_response(
         &self,
         request: ResponsesRequest,
     ) -> Result<ResponsesResponse, ProviderError> {
+        if self.azure.is_some() {
+            return Err(ProviderError::Unsupported(
+                "image generation with the azure provider".to_string(),
+            ));
+        }
         let model = request
             .model
             .as_deref()
@@ -216,11 +814,11 @@ impl OpenAIProvider {
             "tools": [tool]
         });
 
-        let url = format!("{}/responses", self.base_url);
+        let url = self.endpoint_url("responses");
+        debug!(model = %model, url = %url, "sending responses request");
+        self.log_request(&url, &api_request);
         let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
+            .authorize(self.client.post(&url))
             .header("Content-Type", "application/json")
             .json(&api_request)
             .send()
@@ -229,12 +827,14 @@ impl OpenAIProvider {
         let status = response.status();
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            return Err(ProviderError::Api {
-                status: status.as_u16(),
-                message: error_text,
-            });
+            return Err(ProviderError::from_api_response(
+                status.as_u16(),
+                &error_text,
+                &[&self.api_key],
+            ));
         }
 
+        debug!(status = status.as_u16(), "responses response");
         let api_response: ResponsesApiResponse = response.json().await?;
 
         // Extract image generation results from output
@@ -257,6 +857,11 @@ impl OpenAIProvider {
         Ok(ResponsesResponse {
             id: api_response.id,
             images,
+            usage: api_response.usage.map(|u| Usage {
+                prompt_tokens: u.input_tokens,
+                completion_tokens: u.output_tokens,
+                total_tokens: u.total_tokens,
+            }),
         })
     }
 }
@@ -270,11 +875,40 @@ struct OpenAIRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<OpenAITool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
+    /// Legacy name of `max_completion_tokens`; only one of the two is sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     max_completion_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logit_bias: Option<BTreeMap<u32, f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logprobs: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_logprobs: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     reasoning_effort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<serde_json::Value>,
 }
 
 /// OpenAI message with content as array of parts
@@ -309,6 +943,7 @@ impl OpenAIMessage {
         Self {
             role: match msg.role {
                 super::Role::System => "system".to_string(),
+                super::Role::Developer => "developer".to_string(),
                 super::Role::User => "user".to_string(),
                 super::Role::Assistant => "assistant".to_string(),
                 super::Role::Tool => "tool".to_string(),
@@ -354,6 +989,8 @@ struct OpenAIResponseMessage {
     role: String,
     content: Option<String>,
     tool_calls: Option<Vec<OpenAIToolCall>>,
+    /// Set instead of `content` when the model declines to answer
+    refusal: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -401,12 +1038,21 @@ struct OpenAIFunctionDef {
 struct OpenAIResponse {
     choices: Vec<OpenAIChoice>,
     usage: Option<OpenAIUsage>,
+    system_fingerprint: Option<String>,
+    model: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct OpenAIChoice {
     message: OpenAIResponseMessage,
     finish_reason: Option<String>,
+    logprobs: Option<OpenAILogprobs>,
+}
+
+/// `choices[].logprobs`: per-token entries (extra fields such as `bytes` are ignored)
+#[derive(Debug, Deserialize)]
+struct OpenAILogprobs {
+    content: Option<Vec<TokenLogprob>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -416,12 +1062,163 @@ struct OpenAIUsage {
     total_tokens: u32,
 }
 
+impl From<OpenAIUsage> for Usage {
+    fn from(u: OpenAIUsage) -> Self {
+        Usage {
+            prompt_tokens: u.prompt_tokens,
+            completion_tokens: u.completion_tokens,
+            total_tokens: u.total_tokens,
+        }
+    }
+}
+
+// Streaming types: one `chat.completion.chunk` per server-sent event
+
+#[derive(Debug, Deserialize)]
+struct OpenAIStreamChunk {
+    #[serde(default)]
+    choices: Vec<OpenAIStreamChoice>,
+    /// Only on the final chunk, with stream_options.include_usage
+    usage: Option<OpenAIUsage>,
+    system_fingerprint: Option<String>,
+    model: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAIStreamChoice {
+    #[serde(default)]
+    index: u32,
+    delta: OpenAIStreamDelta,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OpenAIStreamDelta {
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<OpenAIToolCallDelta>,
+    refusal: Option<String>,
+}
+
+/// Fragment of a tool call; `index` says which call it belongs to
+#[derive(Debug, Deserialize)]
+struct OpenAIToolCallDelta {
+    index: usize,
+    id: Option<String>,
+    function: Option<OpenAIFunctionDelta>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OpenAIFunctionDelta {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+/// First choice of a streamed completion, accumulated chunk by chunk
+#[derive(Default)]
+struct ChatStream {
+    has_choice: bool,
+    content: String,
+    refusal: Option<String>,
+    tool_calls: ToolCallAssembler,
+    finish_reason: Option<String>,
+    usage: Option<OpenAIUsage>,
+    system_fingerprint: Option<String>,
+    model: Option<String>,
+}
+
+impl ChatStream {
+    fn apply(&mut self, chunk: OpenAIStreamChunk, on_event: &(dyn Fn(StreamEvent) + Send + Sync)) {
+        if chunk.usage.is_some() {
+            self.usage = chunk.usage;
+        }
+        if chunk.model.is_some() {
+            self.model = chunk.model;
+        }
+        if chunk.system_fingerprint.is_some() {
+            self.system_fingerprint = chunk.system_fingerprint;
+        }
+        let Some(choice) = chunk.choices.into_iter().find(|c| c.index == 0) else {
+            return;
+        };
+        self.has_choice = true;
+        if let Some(text) = choice.delta.content.filter(|t| !t.is_empty()) {
+            self.content.push_str(&text);
+            on_event(StreamEvent::TextDelta(text));
+        }
+        if let Some(refusal) = choice.delta.refusal {
+            self.refusal
+                .get_or_insert_with(String::new)
+                .push_str(&refusal);
+        }
+        for fragment in choice.delta.tool_calls {
+            let function = fragment.function.unwrap_or_default();
+            if let Some(call) = self.tool_calls.push(
+                fragment.index,
+                fragment.id.as_deref(),
+                function.name.as_deref(),
+                function.arguments.as_deref(),
+            ) {
+                on_event(StreamEvent::ToolCall(call));
+            }
+        }
+        if choice.finish_reason.is_some() {
+            self.finish_reason = choice.finish_reason;
+        }
+    }
+
+    /// Response once the stream ended; emits the last tool call, which is now complete
+    fn finish(
+        self,
+        on_event: &(dyn Fn(StreamEvent) + Send + Sync),
+    ) -> Result<CompletionResponse, ProviderError> {
+        if !self.has_choice {
+            return Err(ProviderError::InvalidResponse(
+                "No choices in response".to_string(),
+            ));
+        }
+        if let Some(call) = self.tool_calls.last() {
+            on_event(StreamEvent::ToolCall(call.clone()));
+        }
+        let tool_calls = self.tool_calls.finish();
+        Ok(CompletionResponse {
+            content: (!self.content.is_empty()).then_some(self.content),
+            tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+            finish_reason: self.finish_reason.unwrap_or_default(),
+            usage: self.usage.map(Usage::from).unwrap_or_default(),
+            system_fingerprint: self.system_fingerprint,
+            logprobs: None,
+            choices: Vec::new(),
+            refusal: self.refusal,
+            model: self.model,
+        })
+    }
+}
+
 // Responses API types
 
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
 #[derive(Debug, Deserialize)]
 struct ResponsesApiResponse {
     id: String,
     output: Vec<ResponsesOutputItem>,
+    usage: Option<ResponsesUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponsesUsage {
+    input_tokens: u32,
+    output_tokens: u32,
+    total_tokens: u32,
 }
 
 #[derive(Debug, Deserialize)]
@@ -443,6 +1240,14 @@ mod tests {
         assert_eq!(provider.api_key, "test-key");
         assert_eq!(provider.base_url, DEFAULT_BASE_URL);
         assert_eq!(provider.default_model, DEFAULT_MODEL);
+        assert_eq!(provider.timeout, Duration::from_secs(DEFAULT_TIMEOUT_SECS));
+    }
+
+    #[test]
+    fn test_provider_with_timeout() {
+        let provider =
+            OpenAIProvider::new("test-key".to_string(), None).with_timeout(Duration::from_secs(5));
+        assert_eq!(provider.timeout, Duration::from_secs(5));
     }
 
     #[test]
@@ -467,51 +1272,732 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_openai_message_serialization() {
-        let msg = super::super::Message::user("Hello world");
-        let openai_msg = OpenAIMessage::from_message(&msg);
-        let json = serde_json::to_string(&openai_msg).unwrap();
-        assert!(json.contains(r#""type":"text""#));
-        assert!(json.contains(r#""text":"Hello world""#));
-    }
-
-    #[test]
-    fn test_openai_tool_conversion() {
-        let tool = Tool::function(
-            "get_weather",
-            "Get weather for a location",
-            serde_json::json!({
-                "type": "object",
-                "properties": {
-                    "location": {"type": "string"}
-                }
-            }),
-        );
-        let openai_tool = OpenAITool::from_tool(&tool);
-        assert_eq!(openai_tool.type_field, "function");
-        assert_eq!(openai_tool.function.name, "get_weather");
-    }
-
     #[tokio::test]
-    async fn test_complete_mock() {
-        use mockito::Server;
+    async fn test_default_model_from_env() {
+        use mockito::{Matcher, Server};
 
         let mut server = Server::new_async().await;
         let mock = server
             .mock("POST", "/chat/completions")
-            .with_status(200)
-            .with_header("content-type", "application/json")
+            .match_body(Matcher::PartialJson(
+                serde_json::json!({"model": "gpt-4.1-nano"}),
+            ))
             .with_body(
-                r#"{
-                    "choices": [{
-                        "message": {
-                            "role": "assistant",
-                            "content": "Hello! How can I help you?"
-                        },
-                        "finish_reason": "stop"
-                    }],
-                    "usage": {
+                r#"{"choices": [{"message": {"role": "assistant", "content": "ok"}, "finish_reason": "stop"}]}"#,
+            )
+            .create_async()
+            .await;
+
+        // Only this test sets the variable
+        env::set_var("OPENAI_DEFAULT_MODEL", "gpt-4.1-nano");
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(server.url()))
+            .with_model_settings_from_env();
+        env::remove_var("OPENAI_DEFAULT_MODEL");
+        assert_eq!(provider.default_model(), Some("gpt-4.1-nano"));
+
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")]);
+        provider.complete(request).await.unwrap();
+        mock.assert_async().await;
+
+        let provider = OpenAIProvider::new("test-key".to_string(), None);
+        assert_eq!(provider.default_model(), Some(DEFAULT_MODEL));
+        assert_eq!(
+            provider.with_default_model("o4-mini").default_model(),
+            Some("o4-mini")
+        );
+    }
+
+    #[test]
+    fn test_developer_role_translation() {
+        let request = CompletionRequest::new(vec![
+            super::super::Message::system("Be brief"),
+            super::super::Message::developer("Answer in French"),
+            super::super::Message::user("Hi"),
+        ]);
+        let roles = |provider: &OpenAIProvider, model: &str| -> Vec<String> {
+            let request = request.clone().with_model(model);
+            provider
+                .build_chat_request(&request)
+                .messages
+                .into_iter()
+                .map(|m| m.role)
+                .collect()
+        };
+
+        let provider = OpenAIProvider::new("test-key".to_string(), None);
+        assert_eq!(roles(&provider, "o3"), ["system", "developer", "user"]);
+
+        let provider = provider.with_developer_role(DeveloperRole::Auto);
+        assert_eq!(roles(&provider, "o3"), ["developer", "developer", "user"]);
+        assert_eq!(roles(&provider, "gpt-4.1"), ["system", "developer", "user"]);
+
+        let provider = provider.with_developer_role(DeveloperRole::Always);
+        assert_eq!(
+            roles(&provider, "gpt-4.1"),
+            ["developer", "developer", "user"]
+        );
+
+        assert_eq!(DeveloperRole::parse(" Auto"), Some(DeveloperRole::Auto));
+        assert_eq!(DeveloperRole::parse("sometimes"), None);
+    }
+
+    #[tokio::test]
+    async fn test_complete_sends_developer_role() {
+        use mockito::{Matcher, Server};
+
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "messages": [
+                    {"role": "developer", "content": [{"type": "text", "text": "Answer in French"}]},
+                    {"role": "user", "content": [{"type": "text", "text": "Hi"}]}
+                ]
+            })))
+            .with_body(
+                r#"{"choices": [{"message": {"role": "assistant", "content": "Salut"}, "finish_reason": "stop"}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(server.url()));
+        let request = CompletionRequest::new(vec![
+            super::super::Message::developer("Answer in French"),
+            super::super::Message::user("Hi"),
+        ]);
+        let response = provider.complete(request).await.unwrap();
+        assert_eq!(response.content.as_deref(), Some("Salut"));
+        mock.assert_async().await;
+    }
+
+    #[test]
+    fn test_openai_message_serialization() {
+        let msg = super::super::Message::user("Hello world");
+        let openai_msg = OpenAIMessage::from_message(&msg);
+        let json = serde_json::to_string(&openai_msg).unwrap();
+        assert!(json.contains(r#""type":"text""#));
+        assert!(json.contains(r#""text":"Hello world""#));
+    }
+
+    #[test]
+    fn test_openai_tool_conversion() {
+        let tool = Tool::function(
+            "get_weather",
+            "Get weather for a location",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "location": {"type": "string"}
+                }
+            }),
+        );
+        let openai_tool = OpenAITool::from_tool(&tool);
+        assert_eq!(openai_tool.type_field, "function");
+        assert_eq!(openai_tool.function.name, "get_weather");
+    }
+
+    #[test]
+    fn test_is_reasoning_model() {
+        for model in [
+            "o1",
+            "o1-mini",
+            "o3",
+            "o3-mini",
+            "o4-mini",
+            "gpt-5",
+            "gpt-5-mini",
+            "gpt-5.2",
+        ] {
+            assert!(
+                is_reasoning_model(model),
+                "{} should be a reasoning model",
+                model
+            );
+        }
+        for model in [
+            "gpt-4.1",
+            "gpt-4o",
+            "gpt-4o-mini",
+            "gpt-5-chat-latest",
+            "omni",
+            "llama3",
+        ] {
+            assert!(
+                !is_reasoning_model(model),
+                "{} should not be a reasoning model",
+                model
+            );
+        }
+    }
+
+    #[test]
+    fn test_custom_reasoning_models() {
+        let provider = OpenAIProvider::new("test-key".to_string(), None)
+            .with_reasoning_models(vec!["deepseek-r1".to_string()]);
+        assert!(provider.is_reasoning("deepseek-r1:8b"));
+        assert!(!provider.is_reasoning("o3-mini"));
+    }
+
+    #[test]
+    fn test_reasoning_effort_only_for_reasoning_models() {
+        let provider = OpenAIProvider::new("test-key".to_string(), None);
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")])
+            .with_reasoning_level(ReasoningLevel::High);
+
+        let reasoning = request.clone().with_model("o3-mini");
+        let json = serde_json::to_value(provider.build_chat_request(&reasoning)).unwrap();
+        assert_eq!(json["reasoning_effort"], "high");
+
+        let standard = request.with_model("gpt-4.1");
+        let json = serde_json::to_value(provider.build_chat_request(&standard)).unwrap();
+        assert!(json.get("reasoning_effort").is_none());
+    }
+
+    #[test]
+    fn test_token_limit_field_name() {
+        let request =
+            CompletionRequest::new(vec![super::super::Message::user("Hi")]).with_max_tokens(256);
+
+        let provider = OpenAIProvider::new("test-key".to_string(), None);
+        let json = serde_json::to_value(provider.build_chat_request(&request)).unwrap();
+        assert_eq!(json["max_completion_tokens"], 256);
+        assert!(json.get("max_tokens").is_none());
+
+        let provider = provider.with_legacy_max_tokens(true);
+        let json = serde_json::to_value(provider.build_chat_request(&request)).unwrap();
+        assert_eq!(json["max_tokens"], 256);
+        assert!(json.get("max_completion_tokens").is_none());
+    }
+
+    #[test]
+    fn test_temperature_omitted_for_reasoning_models() {
+        let provider = OpenAIProvider::new("test-key".to_string(), None);
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")])
+            .with_temperature(0.5)
+            .with_model("o3");
+        let json = serde_json::to_value(provider.build_chat_request(&request)).unwrap();
+        assert!(json.get("temperature").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_complete_sends_stop_sequences() {
+        use mockito::{Matcher, Server};
+
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "model": "gpt-4.1",
+                "stop": ["END", "\n\n"]
+            })))
+            .with_status(200)
+            .with_body(
+                r#"{"choices": [{"message": {"role": "assistant", "content": "ok"}, "finish_reason": "stop"}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(server.url()));
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")])
+            .with_model("gpt-4.1")
+            .with_stop(vec!["END".to_string(), "\n\n".to_string()]);
+        provider.complete(request).await.unwrap();
+
+        mock.assert_async().await;
+
+        // Reasoning models reject stop sequences
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")])
+            .with_model("o3-mini")
+            .with_stop(vec!["END".to_string()]);
+        let json = serde_json::to_value(provider.build_chat_request(&request)).unwrap();
+        assert!(json.get("stop").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_complete_sends_user() {
+        use mockito::{Matcher, Server};
+
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(
+                serde_json::json!({"user": "user-1234"}),
+            ))
+            .with_body(
+                r#"{"choices": [{"message": {"role": "assistant", "content": "ok"}, "finish_reason": "stop"}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(server.url()));
+        let request =
+            CompletionRequest::new(vec![super::super::Message::user("Hi")]).with_user("user-1234");
+        provider.complete(request).await.unwrap();
+        mock.assert_async().await;
+
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")]);
+        let json = serde_json::to_value(provider.build_chat_request(&request)).unwrap();
+        assert!(json.get("user").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_organization_and_project_headers() {
+        use mockito::{Matcher, Server};
+
+        let mut server = Server::new_async().await;
+        let body = r#"{"choices": [{"message": {"role": "assistant", "content": "ok"}, "finish_reason": "stop"}]}"#;
+        let scoped = server
+            .mock("POST", "/chat/completions")
+            .match_header("OpenAI-Organization", "org-123")
+            .match_header("OpenAI-Project", "proj_456")
+            .with_body(body)
+            .expect(1)
+            .create_async()
+            .await;
+        let unscoped = server
+            .mock("POST", "/chat/completions")
+            .match_header("OpenAI-Organization", Matcher::Missing)
+            .match_header("OpenAI-Project", Matcher::Missing)
+            .with_body(body)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let request = || CompletionRequest::new(vec![super::super::Message::user("Hi")]);
+        OpenAIProvider::new("test-key".to_string(), Some(server.url()))
+            .with_organization(Some("org-123".to_string()))
+            .with_project(Some("proj_456".to_string()))
+            .complete(request())
+            .await
+            .unwrap();
+        OpenAIProvider::new("test-key".to_string(), Some(server.url()))
+            .complete(request())
+            .await
+            .unwrap();
+
+        scoped.assert_async().await;
+        unscoped.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_complete_streaming_reassembles_tool_calls() {
+        use mockito::{Matcher, Server};
+        use std::sync::Mutex;
+
+        let mut server = Server::new_async().await;
+        let events = [
+            r#"{"choices":[{"index":0,"delta":{"role":"assistant","content":"Let me "}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"content":"check."}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"calculate","arguments":""}}]}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"expression\""}}]}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":": \"6*7\"}"}}]}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{},"finish_reason":"tool_calls"}]}"#,
+            r#"{"choices":[],"usage":{"prompt_tokens":9,"completion_tokens":5,"total_tokens":14}}"#,
+        ];
+        let body: String = events
+            .iter()
+            .map(|e| format!("data: {}\n\n", e))
+            .chain(std::iter::once("data: [DONE]\n\n".to_string()))
+            .collect();
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "stream": true,
+                "stream_options": {"include_usage": true}
+            })))
+            .with_header("content-type", "text/event-stream")
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(server.url()));
+        let seen = Mutex::new(Vec::new());
+        let response = provider
+            .complete_streaming(
+                CompletionRequest::new(vec![super::super::Message::user("6*7?")]),
+                &|event| {
+                    seen.lock().unwrap().push(match event {
+                        StreamEvent::TextDelta(text) => text,
+                        StreamEvent::ToolCall(call) => format!("<{}>", call.function.name),
+                    })
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(*seen.lock().unwrap(), ["Let me ", "check.", "<calculate>"]);
+        assert_eq!(response.content.as_deref(), Some("Let me check."));
+        let calls = response.tool_calls.unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].id, "call_1");
+        assert_eq!(calls[0].function.name, "calculate");
+        assert_eq!(calls[0].function.arguments, r#"{"expression": "6*7"}"#);
+        assert_eq!(response.finish_reason, "tool_calls");
+        assert_eq!(response.usage.total_tokens, 14);
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_complete_sends_seed_and_parses_fingerprint() {
+        use mockito::{Matcher, Server};
+
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(serde_json::json!({"seed": 42})))
+            .with_status(200)
+            .with_body(
+                r#"{
+                    "choices": [{"message": {"role": "assistant", "content": "ok"}, "finish_reason": "stop"}],
+                    "system_fingerprint": "fp_44709d6fcb"
+                }"#,
+            )
+            .create_async()
+            .await;
+
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(server.url()));
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")]).with_seed(42);
+        let response = provider.complete(request).await.unwrap();
+
+        assert_eq!(
+            response.system_fingerprint.as_deref(),
+            Some("fp_44709d6fcb")
+        );
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_complete_logprobs_round_trip() {
+        use mockito::{Matcher, Server};
+
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(
+                serde_json::json!({"logprobs": true, "top_logprobs": 2}),
+            ))
+            .with_status(200)
+            .with_body(
+                r#"{
+                    "choices": [{
+                        "message": {"role": "assistant", "content": "Yes"},
+                        "finish_reason": "stop",
+                        "logprobs": {
+                            "content": [{
+                                "token": "Yes",
+                                "logprob": -0.0125,
+                                "bytes": [89, 101, 115],
+                                "top_logprobs": [
+                                    {"token": "Yes", "logprob": -0.0125, "bytes": [89, 101, 115]},
+                                    {"token": "No", "logprob": -4.5, "bytes": [78, 111]}
+                                ]
+                            }],
+                            "refusal": null
+                        }
+                    }]
+                }"#,
+            )
+            .create_async()
+            .await;
+
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(server.url()));
+        let request = CompletionRequest::new(vec![super::super::Message::user("Yes or no?")])
+            .with_model("gpt-4.1")
+            .with_logprobs(Some(2));
+        let response = provider.complete(request).await.unwrap();
+        mock.assert_async().await;
+
+        let expected = serde_json::json!([{
+            "token": "Yes",
+            "logprob": -0.0125,
+            "top_logprobs": [
+                {"token": "Yes", "logprob": -0.0125},
+                {"token": "No", "logprob": -4.5}
+            ]
+        }]);
+        assert_eq!(serde_json::to_value(&response.logprobs).unwrap(), expected);
+        let parsed: Vec<super::super::TokenLogprob> = serde_json::from_value(expected).unwrap();
+        assert_eq!(response.logprobs, Some(parsed));
+    }
+
+    #[tokio::test]
+    async fn test_complete_sends_penalties() {
+        use mockito::{Matcher, Server};
+
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(
+                serde_json::json!({"frequency_penalty": 0.5, "presence_penalty": -1.0}),
+            ))
+            .with_body(r#"{"choices": [{"message": {"role": "assistant", "content": "Hi"}, "finish_reason": "stop"}]}"#)
+            .create_async()
+            .await;
+
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(server.url()));
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")])
+            .with_model("gpt-4.1")
+            .with_frequency_penalty(0.5)
+            .with_presence_penalty(-1.0);
+        provider.complete(request).await.unwrap();
+        mock.assert_async().await;
+    }
+
+    /// Highest number of chat completions a mock server saw in flight, for `calls` parallel
+    /// completions through one provider. mockito answers one request at a time, so this
+    /// server handles each connection on its own thread.
+    async fn max_in_flight(max_concurrency: Option<usize>, calls: usize) -> usize {
+        use std::io::{BufRead, BufReader, Read, Write};
+        use std::net::TcpListener;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (active_in, peak_in) = (active.clone(), peak.clone());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(calls) {
+                let (active, peak) = (active_in.clone(), peak_in.clone());
+                std::thread::spawn(move || {
+                    let mut stream = stream.unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut content_length = 0;
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = line.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                content_length = value.trim().parse().unwrap();
+                            }
+                        }
+                    }
+                    reader.read_exact(&mut vec![0; content_length]).unwrap();
+
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(50));
+                    active.fetch_sub(1, Ordering::SeqCst);
+
+                    let body = r#"{"choices": [{"message": {"role": "assistant", "content": "ok"}, "finish_reason": "stop"}]}"#;
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                    .unwrap();
+                });
+            }
+        });
+
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(url))
+            .with_max_concurrency(max_concurrency);
+        let requests = (0..calls).map(|_| {
+            provider.complete(CompletionRequest::new(vec![super::super::Message::user(
+                "Hi",
+            )]))
+        });
+        for response in futures::future::join_all(requests).await {
+            response.unwrap();
+        }
+        peak.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrency_serializes_requests() {
+        assert_eq!(max_in_flight(Some(1), 4).await, 1);
+        assert!(max_in_flight(Some(2), 4).await <= 2);
+        // Sanity check: without a cap the server does see overlapping requests
+        assert!(max_in_flight(None, 4).await > 1);
+    }
+
+    #[tokio::test]
+    async fn test_complete_sends_logit_bias() {
+        use mockito::{Matcher, Server};
+
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(
+                serde_json::json!({"logit_bias": {"50256": -100.0, "1734": 5.5}}),
+            ))
+            .with_body(r#"{"choices": [{"message": {"role": "assistant", "content": "Hi"}, "finish_reason": "stop"}]}"#)
+            .create_async()
+            .await;
+
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(server.url()));
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")])
+            .with_model("gpt-4.1")
+            .with_logit_bias(BTreeMap::from([(50256, -100.0), (1734, 5.5)]));
+        provider.complete(request).await.unwrap();
+        mock.assert_async().await;
+    }
+
+    #[test]
+    fn test_penalties_omitted_for_reasoning_models() {
+        let provider = OpenAIProvider::new("test-key".to_string(), None);
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")])
+            .with_model("o3-mini")
+            .with_frequency_penalty(0.5)
+            .with_presence_penalty(0.5)
+            .with_logit_bias(BTreeMap::from([(1, 10.0)]));
+        let json = serde_json::to_value(provider.build_chat_request(&request)).unwrap();
+        assert!(json.get("frequency_penalty").is_none());
+        assert!(json.get("presence_penalty").is_none());
+        assert!(json.get("logit_bias").is_none());
+    }
+
+    #[test]
+    fn test_logprobs_omitted_for_reasoning_models() {
+        let provider = OpenAIProvider::new("test-key".to_string(), None);
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")])
+            .with_model("o3-mini")
+            .with_logprobs(Some(3));
+        let json = serde_json::to_value(provider.build_chat_request(&request)).unwrap();
+        assert!(json.get("logprobs").is_none());
+        assert!(json.get("top_logprobs").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_complete_sends_temperature() {
+        use mockito::{Matcher, Server};
+
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "model": "gpt-4.1",
+                "temperature": 0.5
+            })))
+            .with_status(200)
+            .with_body(
+                r#"{"choices": [{"message": {"role": "assistant", "content": "ok"}, "finish_reason": "stop"}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(server.url()));
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")])
+            .with_model("gpt-4.1")
+            .with_temperature(0.5);
+        provider.complete(request).await.unwrap();
+
+        mock.assert_async().await;
+    }
+
+    #[test]
+    fn test_response_format_serialization() {
+        let provider = OpenAIProvider::new("test-key".to_string(), None);
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")]);
+
+        let json = serde_json::to_value(provider.build_chat_request(&request)).unwrap();
+        assert!(json.get("response_format").is_none());
+
+        let text = request.clone().with_response_format(ResponseFormat::Text);
+        let json = serde_json::to_value(provider.build_chat_request(&text)).unwrap();
+        assert_eq!(json["response_format"], serde_json::json!({"type": "text"}));
+    }
+
+    async fn mock_json_completion(
+        format: ResponseFormat,
+        expected_format: serde_json::Value,
+        content: &str,
+    ) -> Result<CompletionResponse, ProviderError> {
+        use mockito::{Matcher, Server};
+
+        let mut server = Server::new_async().await;
+        let body = serde_json::json!({
+            "choices": [{
+                "message": {"role": "assistant", "content": content},
+                "finish_reason": "stop"
+            }]
+        });
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_body(Matcher::PartialJson(
+                serde_json::json!({ "response_format": expected_format }),
+            ))
+            .with_status(200)
+            .with_body(body.to_string())
+            .create_async()
+            .await;
+
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(server.url()));
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")])
+            .with_response_format(format);
+        let result = provider.complete(request).await;
+        mock.assert_async().await;
+        result
+    }
+
+    #[tokio::test]
+    async fn test_complete_json_object_format() {
+        let response = mock_json_completion(
+            ResponseFormat::JsonObject,
+            serde_json::json!({"type": "json_object"}),
+            r#"{"ok": true}"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.content, Some(r#"{"ok": true}"#.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_complete_json_schema_format() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {"name": {"type": "string"}}
+        });
+        let response = mock_json_completion(
+            ResponseFormat::JsonSchema {
+                schema: schema.clone(),
+            },
+            serde_json::json!({
+                "type": "json_schema",
+                "json_schema": {"name": "response", "schema": schema}
+            }),
+            r#"{"name": "Alice"}"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.content, Some(r#"{"name": "Alice"}"#.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_complete_json_format_rejects_invalid_content() {
+        let result = mock_json_completion(
+            ResponseFormat::JsonObject,
+            serde_json::json!({"type": "json_object"}),
+            "not json",
+        )
+        .await;
+        assert!(matches!(result, Err(ProviderError::InvalidResponse(_))));
+    }
+
+    #[tokio::test]
+    async fn test_complete_mock() {
+        use mockito::Server;
+
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                    "choices": [{
+                        "message": {
+                            "role": "assistant",
+                            "content": "Hello! How can I help you?"
+                        },
+                        "finish_reason": "stop"
+                    }],
+                    "usage": {
                         "prompt_tokens": 10,
                         "completion_tokens": 8,
                         "total_tokens": 18
@@ -595,6 +2081,116 @@ mod tests {
         mock.assert_async().await;
     }
 
+    /// Collects formatted tracing output in memory
+    #[derive(Clone, Default)]
+    struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_complete_emits_debug_events() {
+        use mockito::Server;
+
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/chat/completions")
+            .with_status(200)
+            .with_body(
+                r#"{
+                    "choices": [{"message": {"role": "assistant", "content": "Hi"}, "finish_reason": "stop"}],
+                    "usage": {"prompt_tokens": 3, "completion_tokens": 1, "total_tokens": 4}
+                }"#,
+            )
+            .create_async()
+            .await;
+
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let provider = OpenAIProvider::new("sk-secret-key".to_string(), Some(server.url()));
+        let request =
+            CompletionRequest::new(vec![super::super::Message::user("Hi")]).with_model("gpt-4.1");
+        provider.complete(request).await.unwrap();
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(
+            output.contains("sending chat completion request"),
+            "{}",
+            output
+        );
+        assert!(output.contains("model=gpt-4.1"), "{}", output);
+        assert!(output.contains("status=200"), "{}", output);
+        assert!(output.contains("total_tokens=4"), "{}", output);
+        assert!(
+            !output.contains("sk-secret-key"),
+            "API key leaked: {}",
+            output
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_log_dumps_chat_and_responses_bodies() {
+        use mockito::Server;
+        use std::sync::{Arc, Mutex};
+
+        let mut server = Server::new_async().await;
+        let _chat = server
+            .mock("POST", "/chat/completions")
+            .with_status(200)
+            .with_body(
+                r#"{"choices": [{"message": {"role": "assistant", "content": "Hi"}, "finish_reason": "stop"}]}"#,
+            )
+            .create_async()
+            .await;
+        let _responses = server
+            .mock("POST", "/responses")
+            .with_status(200)
+            .with_body(
+                r#"{"id": "resp_1", "output": [{"type": "image_generation_call", "id": "ig_1", "result": "aGk="}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let dumps = Arc::new(Mutex::new(Vec::<String>::new()));
+        let sink = dumps.clone();
+        let log: RequestLog =
+            Arc::new(move |dump: &str| sink.lock().unwrap().push(dump.to_string()));
+        let provider = OpenAIProvider::new("sk-secret-key-123".to_string(), Some(server.url()))
+            .with_request_log(Some(log));
+
+        let request =
+            CompletionRequest::new(vec![super::super::Message::user("Hi")]).with_model("gpt-4.1");
+        provider.complete(request).await.unwrap();
+        provider
+            .create_response(ResponsesRequest::new("A cat".to_string()).with_model("gpt-5"))
+            .await
+            .unwrap();
+
+        let dumps = dumps.lock().unwrap();
+        assert_eq!(dumps.len(), 2);
+        assert!(dumps[0].starts_with("POST http://"), "{}", dumps[0]);
+        assert!(dumps[0].contains("/chat/completions\n{"), "{}", dumps[0]);
+        assert!(dumps[0].contains("\"model\": \"gpt-4.1\""), "{}", dumps[0]);
+        assert!(dumps[1].contains("/responses"), "{}", dumps[1]);
+        assert!(dumps[1].contains("\"model\": \"gpt-5\""), "{}", dumps[1]);
+        assert!(dumps.iter().all(|d| !d.contains("sk-secret-key-123")));
+    }
+
     #[tokio::test]
     async fn test_api_error_handling() {
         use mockito::Server;
@@ -620,4 +2216,306 @@ mod tests {
 
         mock.assert_async().await;
     }
+
+    #[tokio::test]
+    async fn test_capture_saves_request_and_response() {
+        use mockito::Server;
+
+        let body = r#"{"choices": [{"message": {"role": "assistant", "content": "Hi"}, "finish_reason": "stop"}]}"#;
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/chat/completions")
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let capture = PayloadCapture {
+            request: Some(dir.path().join("request.json")),
+            response: Some(dir.path().join("response.json")),
+        };
+        let provider = OpenAIProvider::new("sk-secret-key-123".to_string(), Some(server.url()))
+            .with_capture(capture.clone());
+
+        let request = CompletionRequest::new(vec![super::super::Message::user(
+            "Why does sk-secret-key-123 fail?",
+        )])
+        .with_model("gpt-4.1");
+        provider.complete(request).await.unwrap();
+
+        let saved: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(capture.request.unwrap()).unwrap())
+                .unwrap();
+        assert_eq!(saved["model"], "gpt-4.1");
+        assert_eq!(
+            saved["messages"][0]["content"][0]["text"],
+            "Why does *** fail?"
+        );
+        assert_eq!(
+            std::fs::read_to_string(capture.response.unwrap()).unwrap(),
+            body
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_models_mock() {
+        use mockito::Server;
+
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/models")
+            .match_header("authorization", "Bearer test-key")
+            .with_body(
+                r#"{"object": "list", "data": [
+                    {"id": "gpt-5.2", "object": "model", "owned_by": "openai"},
+                    {"id": "gpt-4o-mini", "object": "model", "owned_by": "openai"}
+                ]}"#,
+            )
+            .create_async()
+            .await;
+
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(server.url()));
+        let models = provider.list_models().await.unwrap();
+        assert_eq!(models, vec!["gpt-4o-mini", "gpt-5.2"]);
+        mock.assert_async().await;
+
+        let azure = OpenAIProvider::azure("k".to_string(), server.url(), "deploy", "2024-10-21");
+        assert!(matches!(
+            azure.list_models().await,
+            Err(ProviderError::Unsupported(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_api_error_redacts_api_key() {
+        use mockito::Server;
+
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/chat/completions")
+            .with_status(401)
+            .with_body(
+                r#"{"error": {"message": "Incorrect API key provided: my-fake-key-0123456789"}}"#,
+            )
+            .create_async()
+            .await;
+
+        let provider =
+            OpenAIProvider::new("my-fake-key-0123456789".to_string(), Some(server.url()));
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")]);
+        match provider.complete(request).await.unwrap_err() {
+            ProviderError::Api { message, .. } => {
+                assert!(!message.contains("my-fake-key-0123456789"), "{}", message);
+                assert!(
+                    message.contains("Incorrect API key provided: ***"),
+                    "{}",
+                    message
+                );
+            }
+            other => panic!("Expected Api error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        use mockito::Server;
+
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/chat/completions")
+            .with_status(200)
+            .with_body_from_request(|_| {
+                std::thread::sleep(Duration::from_millis(500));
+                b"{}".to_vec()
+            })
+            .create_async()
+            .await;
+
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(server.url()))
+            .with_timeout(Duration::from_millis(100));
+
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")]);
+        let result = provider.complete(request).await;
+
+        match result.unwrap_err() {
+            ProviderError::Http(err) => assert!(err.is_timeout()),
+            other => panic!("Expected Http timeout error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_azure_endpoint_url() {
+        let provider = OpenAIProvider::azure(
+            "azure-key".to_string(),
+            "https://contoso.openai.azure.com/",
+            "gpt4o-prod",
+            "2024-10-21",
+        );
+        assert_eq!(
+            provider.endpoint_url("chat/completions"),
+            "https://contoso.openai.azure.com/openai/deployments/gpt4o-prod/chat/completions?api-version=2024-10-21"
+        );
+        assert_eq!(provider.default_model, "gpt4o-prod");
+
+        let provider =
+            OpenAIProvider::new("k".to_string(), Some("http://localhost:4000/v1".into()));
+        assert_eq!(
+            provider.endpoint_url("chat/completions"),
+            "http://localhost:4000/v1/chat/completions"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_azure_complete_uses_deployment_url_and_api_key_header() {
+        use mockito::{Matcher, Server};
+
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("POST", "/openai/deployments/gpt4o-prod/chat/completions")
+            .match_query(Matcher::UrlEncoded(
+                "api-version".into(),
+                "2024-10-21".into(),
+            ))
+            .match_header("api-key", "azure-key-123")
+            .match_header("authorization", Matcher::Missing)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"choices": [{"message": {"role": "assistant", "content": "Hello from Azure"}, "finish_reason": "stop"}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let provider = OpenAIProvider::azure(
+            "azure-key-123".to_string(),
+            server.url(),
+            "gpt4o-prod",
+            "2024-10-21",
+        );
+        let response = provider
+            .complete(CompletionRequest::new(vec![super::super::Message::user(
+                "Hi",
+            )]))
+            .await
+            .unwrap();
+
+        assert_eq!(response.content.as_deref(), Some("Hello from Azure"));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_complete_sends_tool_choice() {
+        use mockito::{Matcher, Server};
+
+        let tool = Tool::function(
+            "get_weather",
+            "Get weather",
+            serde_json::json!({"type": "object"}),
+        );
+        let mut server = Server::new_async().await;
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(server.url()));
+        for (choice, expected) in [
+            (ToolChoice::Auto, serde_json::json!("auto")),
+            (ToolChoice::None, serde_json::json!("none")),
+            (ToolChoice::Required, serde_json::json!("required")),
+            (
+                ToolChoice::Function("get_weather".to_string()),
+                serde_json::json!({"type": "function", "function": {"name": "get_weather"}}),
+            ),
+        ] {
+            let mock = server
+                .mock("POST", "/chat/completions")
+                .match_body(Matcher::PartialJson(
+                    serde_json::json!({"tool_choice": expected}),
+                ))
+                .with_status(200)
+                .with_body(
+                    r#"{"choices": [{"message": {"role": "assistant", "content": "ok"}, "finish_reason": "stop"}]}"#,
+                )
+                .create_async()
+                .await;
+            let request = CompletionRequest::new(vec![super::super::Message::user("Hi")])
+                .with_tools(vec![tool.clone()])
+                .with_tool_choice(choice);
+            provider.complete(request).await.unwrap();
+            mock.assert_async().await;
+            mock.remove_async().await;
+        }
+
+        // Without tools the choice is dropped (the API would reject it)
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")])
+            .with_tool_choice(ToolChoice::Required);
+        let json = serde_json::to_value(provider.build_chat_request(&request)).unwrap();
+        assert!(json.get("tool_choice").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_complete_parses_refusal() {
+        use mockito::Server;
+
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/chat/completions")
+            .with_status(200)
+            .with_body(
+                r#"{"choices": [{"message": {"role": "assistant", "content": null, "refusal": "I can't help with that."}, "finish_reason": "stop"}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(server.url()));
+        let response = provider
+            .complete(CompletionRequest::new(vec![super::super::Message::user(
+                "Hi",
+            )]))
+            .await
+            .unwrap();
+
+        assert_eq!(response.content, None);
+        assert_eq!(response.refusal.as_deref(), Some("I can't help with that."));
+        assert!(matches!(
+            response.check_refusal(),
+            Err(ProviderError::Refusal(ref m)) if m == "I can't help with that."
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_complete_content_filter_is_an_error() {
+        use mockito::Server;
+
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/chat/completions")
+            .with_status(200)
+            .with_body(
+                r#"{"choices": [{"message": {"role": "assistant", "content": "{\"partial"}, "finish_reason": "content_filter"}]}"#,
+            )
+            .create_async()
+            .await;
+
+        let provider = OpenAIProvider::new("test-key".to_string(), Some(server.url()));
+        let request = CompletionRequest::new(vec![super::super::Message::user("Hi")])
+            .with_response_format(ResponseFormat::JsonObject);
+        let err = provider.complete(request).await.unwrap_err();
+        assert!(
+            matches!(err, ProviderError::ContentFiltered(_)),
+            "{:?}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_azure_image_generation_unsupported() {
+        let provider = OpenAIProvider::azure(
+            "azure-key".to_string(),
+            "http://127.0.0.1:9",
+            "gpt4o-prod",
+            "2024-10-21",
+        );
+        let err = provider
+            .create_response(ResponsesRequest::new("A cat".to_string()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ProviderError::Unsupported(_)));
+    }
 }
@@ -0,0 +1,137 @@
+// Retries for transient provider failures: rate limits (429, except exhausted quota), server
+// errors (5xx) and timeouts or dropped connections. Delays double after every failed attempt;
+// the last error is returned unchanged so the error formatter still explains it.
+
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+use super::ProviderError;
+
+/// How often and how patiently to retry
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first
+    pub max_attempts: u32,
+    /// Wait before the first retry; doubled for each following one
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl ProviderError {
+    /// Whether the same request may succeed when sent again
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ProviderError::Api {
+                status: 429,
+                details,
+                ..
+            } => !details
+                .as_ref()
+                .is_some_and(|d| d.code.as_deref() == Some("insufficient_quota")),
+            ProviderError::Api { status, .. } => *status >= 500,
+            ProviderError::Http(e) => e.is_timeout() || e.is_connect(),
+            _ => false,
+        }
+    }
+}
+
+/// Run `operation` until it succeeds, fails with a non-transient error or runs out of attempts
+pub async fn with_retries<T, F, Fut>(
+    policy: &RetryPolicy,
+    mut operation: F,
+) -> Result<T, ProviderError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ProviderError>>,
+{
+    let mut delay = policy.base_delay;
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Err(e) if e.is_transient() && attempt < policy.max_attempts => {
+                warn!(attempt, delay_ms = delay.as_millis() as u64, error = %e, "retrying");
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::ApiErrorDetails;
+
+    fn api_error(status: u16, code: Option<&str>) -> ProviderError {
+        ProviderError::Api {
+            status,
+            message: "error".to_string(),
+            details: Some(ApiErrorDetails {
+                code: code.map(str::to_string),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(api_error(429, Some("rate_limit_exceeded")).is_transient());
+        assert!(api_error(503, None).is_transient());
+        assert!(!api_error(429, Some("insufficient_quota")).is_transient());
+        assert!(!api_error(400, None).is_transient());
+        assert!(!ProviderError::InvalidResponse("x".to_string()).is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_with_retries_stops_on_success_or_permanent_error() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::ZERO,
+        };
+
+        let mut calls = 0;
+        let result = with_retries(&policy, || {
+            calls += 1;
+            let outcome = if calls < 3 {
+                Err(api_error(500, None))
+            } else {
+                Ok(calls)
+            };
+            async move { outcome }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let result: Result<(), _> = with_retries(&policy, || {
+            calls += 1;
+            async { Err(api_error(401, None)) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+
+        let mut calls = 0;
+        let result: Result<(), _> = with_retries(&policy, || {
+            calls += 1;
+            async { Err(api_error(429, None)) }
+        })
+        .await;
+        assert!(matches!(
+            result,
+            Err(ProviderError::Api { status: 429, .. })
+        ));
+        assert_eq!(calls, 3);
+    }
+}
@@ -0,0 +1,136 @@
+// Building blocks for streamed (server-sent events) chat completions.
+// OpenAI streams a tool call in fragments tagged with the call's index: the first carries
+// the id and function name, later ones append pieces of the arguments. A call is complete
+// once a fragment for a later index arrives or the stream ends.
+
+use super::{FunctionCall, ToolCall};
+use std::collections::BTreeMap;
+
+/// Splits a server-sent events byte stream into `data:` payloads.
+/// Bytes are buffered until a full line arrives, so chunks may split lines or characters.
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+}
+
+impl SseDecoder {
+    /// Feed the next chunk of the body; returns the payloads of the lines it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut payloads = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(data) = line.trim_end().strip_prefix("data:") {
+                payloads.push(data.trim_start().to_string());
+            }
+        }
+        payloads
+    }
+}
+
+/// Reassembles streamed tool-call fragments into complete `ToolCall`s
+#[derive(Debug, Default)]
+pub struct ToolCallAssembler {
+    calls: BTreeMap<usize, ToolCall>,
+    /// Highest index seen so far; lower indices are complete
+    current: Option<usize>,
+}
+
+impl ToolCallAssembler {
+    /// Add a fragment of the call at `index`. Returns the previous call once a fragment
+    /// for a later index starts, since no more fragments will arrive for it.
+    pub fn push(
+        &mut self,
+        index: usize,
+        id: Option<&str>,
+        name: Option<&str>,
+        arguments: Option<&str>,
+    ) -> Option<ToolCall> {
+        let call = self.calls.entry(index).or_insert_with(|| ToolCall {
+            id: String::new(),
+            call_type: "function".to_string(),
+            function: FunctionCall {
+                name: String::new(),
+                arguments: String::new(),
+            },
+        });
+        if let Some(id) = id {
+            call.id.push_str(id);
+        }
+        if let Some(name) = name {
+            call.function.name.push_str(name);
+        }
+        if let Some(arguments) = arguments {
+            call.function.arguments.push_str(arguments);
+        }
+
+        let completed = match self.current {
+            Some(current) if index > current => self.calls.get(&current).cloned(),
+            _ => None,
+        };
+        self.current = Some(self.current.map_or(index, |current| current.max(index)));
+        completed
+    }
+
+    /// The call still being assembled, if any (complete once the stream ends)
+    pub fn last(&self) -> Option<&ToolCall> {
+        self.current.and_then(|index| self.calls.get(&index))
+    }
+
+    /// All calls in index order
+    pub fn finish(self) -> Vec<ToolCall> {
+        self.calls.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fragmented_tool_call_reassembles() {
+        let mut assembler = ToolCallAssembler::default();
+        assert!(assembler
+            .push(0, Some("call_1"), Some("calculate"), Some(""))
+            .is_none());
+        assert!(assembler.push(0, None, None, Some("{\"expr")).is_none());
+        assert!(assembler
+            .push(0, None, None, Some("ession\": \"2+"))
+            .is_none());
+        assert!(assembler.push(0, None, None, Some("2\"}")).is_none());
+        assert_eq!(assembler.last().unwrap().function.name, "calculate");
+
+        let calls = assembler.finish();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].id, "call_1");
+        assert_eq!(calls[0].call_type, "function");
+        assert_eq!(calls[0].function.name, "calculate");
+        assert_eq!(calls[0].function.arguments, r#"{"expression": "2+2"}"#);
+    }
+
+    #[test]
+    fn test_next_index_completes_previous_call() {
+        let mut assembler = ToolCallAssembler::default();
+        assembler.push(0, Some("a"), Some("current_time"), Some("{}"));
+        let completed = assembler
+            .push(1, Some("b"), Some("calculate"), Some("{\"expression\":"))
+            .unwrap();
+        assert_eq!(completed.id, "a");
+        assert_eq!(completed.function.name, "current_time");
+        assembler.push(1, None, None, Some("\"1\"}"));
+
+        let calls = assembler.finish();
+        let names: Vec<&str> = calls.iter().map(|c| c.function.name.as_str()).collect();
+        assert_eq!(names, ["current_time", "calculate"]);
+        assert_eq!(calls[1].function.arguments, r#"{"expression":"1"}"#);
+    }
+
+    #[test]
+    fn test_sse_decoder_buffers_partial_lines() {
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.push(b"data: {\"a\"").is_empty());
+        assert_eq!(decoder.push(b":1}\n\n: keep-alive\n"), [r#"{"a":1}"#]);
+        assert_eq!(decoder.push(b"data: [DONE]\r\n"), ["[DONE]".to_string()]);
+    }
+}
@@ -0,0 +1,81 @@
+// "Thinking..." indicator on stderr while a provider call is in flight.
+// Design: a background thread redraws one line until the guard is dropped; dropping joins the
+// thread and erases the line, so it is gone before the result (or error) is printed.
+// Only shown to a person: interactive output, stdout and stderr both terminals, no --verbose,
+// --debug-request or other stderr traces that the redraws would garble.
+
+use std::io::{stderr, Write};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+const FRAMES: &[&str] = &["|", "/", "-", "\\"];
+const FRAME_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Running spinner; stops and clears its line when dropped
+pub struct Spinner {
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Spinner {
+    /// Start drawing `message` with a spinning frame on stderr
+    pub fn start(message: impl Into<String>) -> Self {
+        let message = message.into();
+        let (stop, stopped) = mpsc::channel::<()>();
+        let handle = std::thread::spawn(move || {
+            let mut stderr = stderr();
+            for frame in FRAMES.iter().cycle() {
+                let _ = write!(stderr, "\r{} {}", frame, message);
+                let _ = stderr.flush();
+                match stopped.recv_timeout(FRAME_INTERVAL) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    // Stop requested or the guard is gone
+                    _ => break,
+                }
+            }
+            let _ = write!(stderr, "\r\x1b[2K");
+            let _ = stderr.flush();
+        });
+        Self {
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Whether a spinner may be drawn: interactive output to a terminal and a quiet stderr
+pub fn spinner_enabled(
+    interactive: bool,
+    stdout_is_terminal: bool,
+    stderr_is_terminal: bool,
+    stderr_busy: bool,
+) -> bool {
+    interactive && stdout_is_terminal && stderr_is_terminal && !stderr_busy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spinner_enabled() {
+        assert!(spinner_enabled(true, true, true, false));
+        // -o json / --output-file
+        assert!(!spinner_enabled(false, true, true, false));
+        // Piped stdout or stderr
+        assert!(!spinner_enabled(true, false, true, false));
+        assert!(!spinner_enabled(true, true, false, false));
+        // Logs or traces on stderr
+        assert!(!spinner_enabled(true, true, true, true));
+    }
+}
@@ -0,0 +1,276 @@
+// calculate tool: evaluates arithmetic expressions, since models are unreliable at math.
+// Hand-written recursive descent parser over f64; only numbers, + - * /, ^ (or **)
+// and parentheses are accepted. No identifiers or functions, so nothing can be executed.
+
+use super::{parse_arguments, ToolError, ToolExecutor};
+use crate::provider::Tool;
+use async_trait::async_trait;
+use serde::Deserialize;
+
+/// Nesting limit for parentheses and unary operators, guards against stack overflow
+const MAX_DEPTH: usize = 64;
+
+pub struct CalculatorTool;
+
+#[derive(Deserialize)]
+struct CalculatorArgs {
+    expression: String,
+}
+
+#[async_trait]
+impl ToolExecutor for CalculatorTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "calculate",
+            "Evaluate an arithmetic expression with +, -, *, /, ^ and parentheses",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "expression": {
+                        "type": "string",
+                        "description": "Arithmetic expression, e.g. 2 * (3 + 4) ^ 2"
+                    }
+                },
+                "required": ["expression"]
+            }),
+        )
+    }
+
+    async fn execute(&self, arguments: &str) -> Result<String, ToolError> {
+        let args: CalculatorArgs = parse_arguments(arguments)?;
+        let value = evaluate(&args.expression)?;
+        Ok(format_number(value))
+    }
+}
+
+/// Evaluate an arithmetic expression
+fn evaluate(expression: &str) -> Result<f64, ToolError> {
+    let mut parser = Parser {
+        chars: expression.chars().collect(),
+        pos: 0,
+        depth: 0,
+    };
+    let value = parser.expr()?;
+    parser.skip_whitespace();
+    if let Some(c) = parser.peek() {
+        return Err(parser.unexpected(c));
+    }
+    if !value.is_finite() {
+        return Err(ToolError::ExecutionFailed(format!(
+            "Result of '{}' is not a finite number",
+            expression
+        )));
+    }
+    Ok(value)
+}
+
+/// Whole numbers without a fractional part, everything else as-is
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        value.to_string()
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consume `token` (after whitespace) if it is next
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let matches = token
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c));
+        if matches {
+            self.pos += token.chars().count();
+        }
+        matches
+    }
+
+    fn unexpected(&self, c: char) -> ToolError {
+        if c.is_alphabetic() || c == '_' {
+            ToolError::InvalidArguments(format!(
+                "Identifiers and functions are not supported (at position {})",
+                self.pos
+            ))
+        } else {
+            ToolError::InvalidArguments(format!("Unexpected '{}' at position {}", c, self.pos))
+        }
+    }
+
+    fn enter(&mut self) -> Result<(), ToolError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(ToolError::InvalidArguments(
+                "Expression is nested too deeply".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<f64, ToolError> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat("+") {
+                value += self.term()?;
+            } else if self.eat("-") {
+                value -= self.term()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// term := unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<f64, ToolError> {
+        let mut value = self.unary()?;
+        loop {
+            // `**` is exponent, handled in power()
+            if self.chars.get(self.pos..self.pos + 2) != Some(&['*', '*']) && self.eat("*") {
+                value *= self.unary()?;
+            } else if self.eat("/") {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return Err(ToolError::ExecutionFailed("Division by zero".to_string()));
+                }
+                value /= divisor;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// unary := ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<f64, ToolError> {
+        self.enter()?;
+        let value = if self.eat("-") {
+            -self.unary()?
+        } else if self.eat("+") {
+            self.unary()?
+        } else {
+            self.power()?
+        };
+        self.depth -= 1;
+        Ok(value)
+    }
+
+    /// power := primary (('^' | '**') unary)?, right associative
+    fn power(&mut self) -> Result<f64, ToolError> {
+        let base = self.primary()?;
+        self.skip_whitespace();
+        if self.eat("^") || self.eat("**") {
+            let exponent = self.unary()?;
+            return Ok(base.powf(exponent));
+        }
+        Ok(base)
+    }
+
+    /// primary := number | '(' expr ')'
+    fn primary(&mut self) -> Result<f64, ToolError> {
+        if self.eat("(") {
+            let value = self.expr()?;
+            if !self.eat(")") {
+                return Err(ToolError::InvalidArguments(format!(
+                    "Expected ')' at position {}",
+                    self.pos
+                )));
+            }
+            return Ok(value);
+        }
+
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(match self.peek() {
+                Some(c) => self.unexpected(c),
+                None => ToolError::InvalidArguments("Unexpected end of expression".to_string()),
+            });
+        }
+        let literal: String = self.chars[start..self.pos].iter().collect();
+        literal
+            .parse()
+            .map_err(|_| ToolError::InvalidArguments(format!("Invalid number '{}'", literal)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calculate(expression: &str) -> Result<String, ToolError> {
+        futures::executor::block_on(
+            CalculatorTool.execute(&serde_json::json!({ "expression": expression }).to_string()),
+        )
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(calculate("2 * (3 + 4)").unwrap(), "14");
+        assert_eq!(calculate("2 + 3 * 4").unwrap(), "14");
+        assert_eq!(calculate("10 - 4 - 3").unwrap(), "3");
+        assert_eq!(calculate("2 ^ 3 ^ 2").unwrap(), "512");
+        assert_eq!(calculate("2 ** 10").unwrap(), "1024");
+        assert_eq!(calculate("-2 ^ 2").unwrap(), "-4");
+        assert_eq!(calculate("7 / 2").unwrap(), "3.5");
+        assert_eq!(calculate("1.5*-2").unwrap(), "-3");
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        let err = calculate("1 / (2 - 2)").unwrap_err();
+        assert!(matches!(err, ToolError::ExecutionFailed(_)));
+    }
+
+    #[test]
+    fn test_malformed_input() {
+        for expression in ["", "2 +", "(1 + 2", "1 2", "1..2", "3 % 2", "1 + )"] {
+            let err = calculate(expression).unwrap_err();
+            assert!(
+                matches!(err, ToolError::InvalidArguments(_)),
+                "expected InvalidArguments for {:?}",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_identifiers_and_functions() {
+        for expression in ["x + 1", "sqrt(4)", "__import__('os')"] {
+            let err = calculate(expression).unwrap_err();
+            assert!(
+                matches!(err, ToolError::InvalidArguments(ref msg) if msg.contains("Identifiers")),
+                "expected identifier error for {:?}",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_deep_nesting() {
+        let expression = format!("{}1{}", "(".repeat(200), ")".repeat(200));
+        assert!(matches!(
+            calculate(&expression),
+            Err(ToolError::InvalidArguments(_))
+        ));
+    }
+}
@@ -0,0 +1,157 @@
+// current_time tool: the current date and time, since models don't know "now".
+// Time zones are UTC, the local zone or a fixed offset (+02:00); the format is a strftime
+// pattern. Both fall back to the defaults the tool was configured with (ToolConfig),
+// then to UTC and RFC 3339.
+
+use super::{parse_arguments, ToolError, ToolExecutor};
+use crate::provider::Tool;
+use async_trait::async_trait;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use serde::Deserialize;
+
+/// RFC 3339, e.g. 2026-10-17T09:30:00+00:00
+pub const DEFAULT_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%:z";
+
+#[derive(Default)]
+pub struct CurrentTimeTool {
+    /// Time zone used when the model doesn't pass one
+    default_timezone: Option<String>,
+    /// strftime format used when the model doesn't pass one
+    default_format: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CurrentTimeArgs {
+    timezone: Option<String>,
+    format: Option<String>,
+}
+
+impl CurrentTimeTool {
+    pub fn new(default_timezone: Option<String>, default_format: Option<String>) -> Self {
+        Self {
+            default_timezone,
+            default_format,
+        }
+    }
+}
+
+#[async_trait]
+impl ToolExecutor for CurrentTimeTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "current_time",
+            "Get the current date and time",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "timezone": {
+                        "type": "string",
+                        "description": "UTC, local, or a UTC offset such as +02:00"
+                    },
+                    "format": {
+                        "type": "string",
+                        "description": "strftime format, e.g. %Y-%m-%d %H:%M (default: RFC 3339)"
+                    }
+                }
+            }),
+        )
+    }
+
+    async fn execute(&self, arguments: &str) -> Result<String, ToolError> {
+        let args: CurrentTimeArgs = parse_arguments(arguments)?;
+        let timezone = args
+            .timezone
+            .as_deref()
+            .or(self.default_timezone.as_deref())
+            .unwrap_or("UTC");
+        let format = args
+            .format
+            .as_deref()
+            .or(self.default_format.as_deref())
+            .unwrap_or(DEFAULT_TIME_FORMAT);
+        format_time(Utc::now(), timezone, format)
+    }
+}
+
+/// Format `now` in `timezone` with a strftime `format`
+fn format_time(now: DateTime<Utc>, timezone: &str, format: &str) -> Result<String, ToolError> {
+    let local: DateTime<FixedOffset> = match timezone.to_ascii_lowercase().as_str() {
+        "utc" | "z" => now.fixed_offset(),
+        "local" => now.with_timezone(&Local).fixed_offset(),
+        _ => {
+            let offset: FixedOffset = timezone.parse().map_err(|_| {
+                ToolError::InvalidArguments(format!(
+                    "Unknown timezone '{}' (use UTC, local or an offset like +02:00)",
+                    timezone
+                ))
+            })?;
+            now.with_timezone(&offset)
+        }
+    };
+    format_checked(local, format)
+}
+
+/// Format `time` with a strftime `format`, rejecting invalid formats instead of panicking
+pub(super) fn format_checked(
+    time: DateTime<FixedOffset>,
+    format: &str,
+) -> Result<String, ToolError> {
+    let items: Vec<Item> = StrftimeItems::new(format).collect();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        return Err(ToolError::InvalidArguments(format!(
+            "Invalid time format '{}'",
+            format
+        )));
+    }
+    Ok(time.format_with_items(items.into_iter()).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn fixed_now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 17, 9, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(
+            format_time(fixed_now(), "UTC", DEFAULT_TIME_FORMAT).unwrap(),
+            "2026-10-17T09:30:00+00:00"
+        );
+        assert_eq!(
+            format_time(fixed_now(), "+02:00", "%Y-%m-%d %H:%M").unwrap(),
+            "2026-10-17 11:30"
+        );
+        assert_eq!(
+            format_time(fixed_now(), "-0330", "%H:%M %:z").unwrap(),
+            "06:00 -03:30"
+        );
+    }
+
+    #[test]
+    fn test_invalid_timezone_and_format() {
+        let err = format_time(fixed_now(), "Mars/Olympus", "%Y").unwrap_err();
+        assert!(matches!(err, ToolError::InvalidArguments(ref m) if m.contains("Mars/Olympus")));
+        let err = format_time(fixed_now(), "UTC", "%Q").unwrap_err();
+        assert!(matches!(err, ToolError::InvalidArguments(_)));
+    }
+
+    #[tokio::test]
+    async fn test_defaults_apply_when_args_omitted() {
+        let tool = CurrentTimeTool::new(Some("+05:00".to_string()), Some("%:z".to_string()));
+        assert_eq!(tool.execute("{}").await.unwrap(), "+05:00");
+        // Arguments from the model take precedence over the configured defaults
+        assert_eq!(
+            tool.execute(r#"{"timezone": "UTC"}"#).await.unwrap(),
+            "+00:00"
+        );
+        assert_eq!(tool.execute(r#"{"format": "%z"}"#).await.unwrap(), "+0500");
+
+        let output = CurrentTimeTool::default().execute("{}").await.unwrap();
+        assert!(output.ends_with("+00:00"), "{}", output);
+    }
+}
@@ -0,0 +1,175 @@
+// date_math tool: adds or subtracts days, hours and minutes to a date or timestamp, so
+// models don't do calendar arithmetic (month lengths, leap years) in their heads.
+// `base` is a date (2024-01-15, midnight UTC), an RFC 3339 timestamp (its offset is kept) or
+// "now". A plain date with only whole days added is returned as a date; anything else as
+// RFC 3339, unless a strftime `format` is given.
+
+use super::current_time::{format_checked, DEFAULT_TIME_FORMAT};
+use super::{parse_arguments, ToolError, ToolExecutor};
+use crate::provider::Tool;
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeDelta, Utc};
+use serde::Deserialize;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+pub struct DateMathTool;
+
+#[derive(Deserialize)]
+struct DateMathArgs {
+    base: String,
+    #[serde(default)]
+    add_days: i64,
+    #[serde(default)]
+    add_hours: i64,
+    #[serde(default)]
+    add_minutes: i64,
+    /// Subtract the amounts instead of adding them
+    #[serde(default)]
+    subtract: bool,
+    format: Option<String>,
+}
+
+#[async_trait]
+impl ToolExecutor for DateMathTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "date_math",
+            "Add or subtract days, hours and minutes to a date or timestamp",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "base": {
+                        "type": "string",
+                        "description": "Date (2024-01-15), RFC 3339 timestamp (2024-01-15T09:00:00+02:00) or now"
+                    },
+                    "add_days": {"type": "integer", "description": "Days to add (negative to go back)"},
+                    "add_hours": {"type": "integer", "description": "Hours to add"},
+                    "add_minutes": {"type": "integer", "description": "Minutes to add"},
+                    "subtract": {
+                        "type": "boolean",
+                        "description": "Subtract the amounts instead of adding them"
+                    },
+                    "format": {
+                        "type": "string",
+                        "description": "strftime format of the result (default: date for date input, otherwise RFC 3339)"
+                    }
+                },
+                "required": ["base"]
+            }),
+        )
+    }
+
+    async fn execute(&self, arguments: &str) -> Result<String, ToolError> {
+        let args: DateMathArgs = parse_arguments(arguments)?;
+        let (base, date_only) = parse_base(&args.base, Utc::now())?;
+
+        let out_of_range = || ToolError::InvalidArguments("Result is out of range".to_string());
+        let delta = [
+            TimeDelta::try_days(args.add_days),
+            TimeDelta::try_hours(args.add_hours),
+            TimeDelta::try_minutes(args.add_minutes),
+        ]
+        .into_iter()
+        .try_fold(TimeDelta::zero(), |sum, part| sum.checked_add(&part?))
+        .ok_or_else(out_of_range)?;
+        let delta = if args.subtract { -delta } else { delta };
+        let result = base.checked_add_signed(delta).ok_or_else(out_of_range)?;
+
+        let format = match args.format {
+            Some(ref format) => format.as_str(),
+            None if date_only && args.add_hours == 0 && args.add_minutes == 0 => DATE_FORMAT,
+            None => DEFAULT_TIME_FORMAT,
+        };
+        format_checked(result, format)
+    }
+}
+
+/// Timestamp for `base`, and whether it was a plain date
+fn parse_base(base: &str, now: DateTime<Utc>) -> Result<(DateTime<FixedOffset>, bool), ToolError> {
+    let base = base.trim();
+    if base.eq_ignore_ascii_case("now") {
+        return Ok((now.fixed_offset(), false));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(base, DATE_FORMAT) {
+        let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+        return Ok((midnight.and_utc().fixed_offset(), true));
+    }
+    DateTime::parse_from_rfc3339(base)
+        .map(|time| (time, false))
+        .map_err(|_| {
+            ToolError::InvalidArguments(format!(
+                "Invalid base '{}' (use a date like 2024-01-15, an RFC 3339 timestamp or now)",
+                base
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn date_math(arguments: serde_json::Value) -> Result<String, ToolError> {
+        DateMathTool.execute(&arguments.to_string()).await
+    }
+
+    #[tokio::test]
+    async fn test_add_and_subtract_days() {
+        let result = date_math(serde_json::json!({"base": "2024-01-15", "add_days": 30})).await;
+        assert_eq!(result.unwrap(), "2024-02-14");
+        // Leap year
+        let result =
+            date_math(serde_json::json!({"base": "2024-03-01", "add_days": 1, "subtract": true}))
+                .await;
+        assert_eq!(result.unwrap(), "2024-02-29");
+        let result = date_math(serde_json::json!({"base": "2024-01-15", "add_days": -15})).await;
+        assert_eq!(result.unwrap(), "2023-12-31");
+    }
+
+    #[tokio::test]
+    async fn test_add_and_subtract_hours() {
+        let result = date_math(serde_json::json!({"base": "2024-01-15", "add_hours": 36})).await;
+        assert_eq!(result.unwrap(), "2024-01-16T12:00:00+00:00");
+        // The base offset is kept
+        let result = date_math(serde_json::json!({
+            "base": "2024-01-15T01:30:00+02:00",
+            "add_hours": 2,
+            "add_minutes": 15,
+            "subtract": true
+        }))
+        .await;
+        assert_eq!(result.unwrap(), "2024-01-14T23:15:00+02:00");
+        let result = date_math(serde_json::json!({
+            "base": "2024-01-15T10:00:00Z",
+            "add_days": 1,
+            "format": "%A %H:%M"
+        }))
+        .await;
+        assert_eq!(result.unwrap(), "Tuesday 10:00");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_arguments() {
+        for arguments in [
+            serde_json::json!({"base": "2024-13-45", "add_days": 1}),
+            serde_json::json!({"base": "next tuesday"}),
+            serde_json::json!({"base": "2024-01-15", "format": "%Q"}),
+            serde_json::json!({"base": "2024-01-15", "add_days": i64::MAX}),
+            serde_json::json!({"add_days": 1}),
+        ] {
+            let err = date_math(arguments.clone()).await.unwrap_err();
+            assert!(
+                matches!(err, ToolError::InvalidArguments(_)),
+                "{}: {:?}",
+                arguments,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_base_now() {
+        let now = Utc::now();
+        assert_eq!(parse_base("NOW", now).unwrap(), (now.fixed_offset(), false));
+    }
+}
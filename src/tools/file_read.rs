@@ -0,0 +1,166 @@
+// read_file tool: returns UTF-8 contents of a file under the working directory.
+// Paths are resolved against `root` and canonicalized; anything escaping it is rejected
+// (resolve_in_root, shared with list_dir).
+
+use super::{parse_arguments, truncate_utf8, ToolError, ToolExecutor};
+use crate::provider::Tool;
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Default cap on returned content, to keep tool results within context limits
+const DEFAULT_MAX_BYTES: usize = 100_000;
+
+pub struct FileReadTool {
+    root: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct FileReadArgs {
+    path: String,
+    max_bytes: Option<usize>,
+}
+
+impl FileReadTool {
+    /// Create tool restricted to the given working directory
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+/// Canonical `root` and `path` resolved against it, rejecting anything outside of it
+pub(super) fn resolve_in_root(root: &Path, path: &str) -> Result<(PathBuf, PathBuf), ToolError> {
+    let root = root
+        .canonicalize()
+        .map_err(|e| ToolError::ExecutionFailed(format!("Invalid working directory: {}", e)))?;
+    let resolved = root
+        .join(Path::new(path))
+        .canonicalize()
+        .map_err(|e| ToolError::ExecutionFailed(format!("Failed to read '{}': {}", path, e)))?;
+
+    if !resolved.starts_with(&root) {
+        return Err(ToolError::InvalidArguments(format!(
+            "Path '{}' is outside of the working directory",
+            path
+        )));
+    }
+    Ok((root, resolved))
+}
+
+impl Default for FileReadTool {
+    fn default() -> Self {
+        Self::new(std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
+    }
+}
+
+#[async_trait]
+impl ToolExecutor for FileReadTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "read_file",
+            "Read a UTF-8 text file from the working directory",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "File path relative to the working directory"
+                    },
+                    "max_bytes": {
+                        "type": "integer",
+                        "description": "Maximum number of bytes to return"
+                    }
+                },
+                "required": ["path"]
+            }),
+        )
+    }
+
+    async fn execute(&self, arguments: &str) -> Result<String, ToolError> {
+        let args: FileReadArgs = parse_arguments(arguments)?;
+        let (_, path) = resolve_in_root(&self.root, &args.path)?;
+
+        let mut content = std::fs::read_to_string(&path).map_err(|e| {
+            ToolError::ExecutionFailed(format!("Failed to read '{}': {}", args.path, e))
+        })?;
+
+        let total = content.len();
+        let max_bytes = args.max_bytes.unwrap_or(DEFAULT_MAX_BYTES);
+        if truncate_utf8(&mut content, max_bytes) {
+            content.push_str(&format!(
+                "\n[truncated: showing {} of {} bytes]",
+                content.len(),
+                total
+            ));
+        }
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn setup() -> (TempDir, FileReadTool) {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "Hello from file").unwrap();
+        let tool = FileReadTool::new(dir.path());
+        (dir, tool)
+    }
+
+    #[tokio::test]
+    async fn test_read_file_success() {
+        let (_dir, tool) = setup();
+        let result = tool.execute(r#"{"path": "notes.txt"}"#).await.unwrap();
+        assert_eq!(result, "Hello from file");
+    }
+
+    #[tokio::test]
+    async fn test_read_file_missing() {
+        let (_dir, tool) = setup();
+        let err = tool
+            .execute(r#"{"path": "missing.txt"}"#)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolError::ExecutionFailed(_)));
+        assert!(err.to_string().contains("missing.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_read_file_truncation() {
+        let (_dir, tool) = setup();
+        let result = tool
+            .execute(r#"{"path": "notes.txt", "max_bytes": 5}"#)
+            .await
+            .unwrap();
+        assert!(result.starts_with("Hello\n"));
+        assert!(result.contains("[truncated: showing 5 of 15 bytes]"));
+    }
+
+    #[tokio::test]
+    async fn test_read_file_rejects_escape() {
+        let (dir, _tool) = setup();
+        let nested = dir.path().join("nested");
+        std::fs::create_dir(&nested).unwrap();
+        let tool = FileReadTool::new(&nested);
+
+        let err = tool
+            .execute(r#"{"path": "../notes.txt"}"#)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolError::InvalidArguments(_)));
+
+        let absolute = dir.path().join("notes.txt");
+        let args = serde_json::json!({ "path": absolute }).to_string();
+        let err = tool.execute(&args).await.unwrap_err();
+        assert!(matches!(err, ToolError::InvalidArguments(_)));
+    }
+
+    #[tokio::test]
+    async fn test_read_file_invalid_arguments() {
+        let (_dir, tool) = setup();
+        let err = tool.execute(r#"{"file": "notes.txt"}"#).await.unwrap_err();
+        assert!(matches!(err, ToolError::InvalidArguments(_)));
+    }
+}
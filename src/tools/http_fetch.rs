@@ -0,0 +1,256 @@
+// fetch_url tool: GET an http(s) URL and return the body as text.
+// Opt-in (network access, --allow-tool fetch_url). Only http/https schemes; body size
+// capped; 30s timeout unless the global --timeout overrides it.
+
+use super::{parse_arguments, ToolError, ToolExecutor};
+use crate::provider::Tool;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use std::time::Duration;
+
+const DEFAULT_MAX_BYTES: usize = 100_000;
+/// Upper bound for the model's max_bytes, so a body can't be made arbitrarily large
+const MAX_BYTES: usize = 1_000_000;
+const FETCH_TIMEOUT_SECS: u64 = 30;
+
+pub struct HttpFetchTool {
+    timeout: Duration,
+}
+
+#[derive(Deserialize)]
+struct HttpFetchArgs {
+    url: String,
+    max_bytes: Option<usize>,
+}
+
+impl HttpFetchTool {
+    pub fn new() -> Self {
+        Self {
+            timeout: Duration::from_secs(FETCH_TIMEOUT_SECS),
+        }
+    }
+
+    /// Override the request timeout (default: 30s)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Default for HttpFetchTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ToolExecutor for HttpFetchTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "fetch_url",
+            "Fetch an http(s) URL and return the response body as text",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "URL to fetch (http or https)"
+                    },
+                    "max_bytes": {
+                        "type": "integer",
+                        "description": "Maximum number of bytes to return (default 100000, at most 1000000)"
+                    }
+                },
+                "required": ["url"]
+            }),
+        )
+    }
+
+    async fn execute(&self, arguments: &str) -> Result<String, ToolError> {
+        let args: HttpFetchArgs = parse_arguments(arguments)?;
+        let url = reqwest::Url::parse(&args.url).map_err(|e| {
+            ToolError::InvalidArguments(format!("Invalid URL '{}': {}", args.url, e))
+        })?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(ToolError::InvalidArguments(format!(
+                "Unsupported URL scheme '{}'. Use http or https",
+                url.scheme()
+            )));
+        }
+
+        let client = Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        let mut response = client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(format!("Request failed: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ToolError::ExecutionFailed(format!(
+                "HTTP {} fetching {}",
+                status.as_u16(),
+                args.url
+            )));
+        }
+
+        // Stop reading at the cap so a huge body is never held in memory
+        let max_bytes = args.max_bytes.unwrap_or(DEFAULT_MAX_BYTES).min(MAX_BYTES);
+        let total = response.content_length();
+        let mut bytes = Vec::new();
+        let mut truncated = false;
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(format!("Failed to read body: {}", e)))?
+        {
+            let room = max_bytes - bytes.len();
+            if chunk.len() > room {
+                bytes.extend_from_slice(&chunk[..room]);
+                truncated = true;
+                break;
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+
+        if truncated {
+            // Drop a character cut in half at the cap
+            if let Err(e) = std::str::from_utf8(&bytes) {
+                if e.error_len().is_none() {
+                    bytes.truncate(e.valid_up_to());
+                }
+            }
+        }
+        let mut body = String::from_utf8_lossy(&bytes).into_owned();
+        if truncated {
+            match total {
+                Some(total) => body.push_str(&format!(
+                    "\n[truncated: showing {} of {} bytes]",
+                    bytes.len(),
+                    total
+                )),
+                None => body.push_str(&format!(
+                    "\n[truncated: showing the first {} bytes]",
+                    bytes.len()
+                )),
+            }
+        }
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Server;
+
+    #[tokio::test]
+    async fn test_fetch_url_success() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/page")
+            .with_status(200)
+            .with_body("Hello from server")
+            .create_async()
+            .await;
+
+        let tool = HttpFetchTool::new();
+        let args = serde_json::json!({ "url": format!("{}/page", server.url()) }).to_string();
+        let result = tool.execute(&args).await.unwrap();
+        assert_eq!(result, "Hello from server");
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_fetch_url_truncation() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/big")
+            .with_status(200)
+            .with_body("0123456789")
+            .create_async()
+            .await;
+
+        let tool = HttpFetchTool::new();
+        let args = serde_json::json!({ "url": format!("{}/big", server.url()), "max_bytes": 4 })
+            .to_string();
+        let result = tool.execute(&args).await.unwrap();
+        assert!(result.starts_with("0123\n"));
+        assert!(result.contains("[truncated: showing 4 of 10 bytes]"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_url_stops_reading_at_cap() {
+        let mut server = Server::new_async().await;
+        // Chunked, so there is no Content-Length to go by; far more than the cap
+        let _mock = server
+            .mock("GET", "/huge")
+            .with_status(200)
+            .with_chunked_body(|w| {
+                for _ in 0..1024 {
+                    w.write_all("é".repeat(4096).as_bytes())?;
+                }
+                Ok(())
+            })
+            .create_async()
+            .await;
+
+        let tool = HttpFetchTool::new();
+        let args = serde_json::json!({ "url": format!("{}/huge", server.url()), "max_bytes": 5 })
+            .to_string();
+        let result = tool.execute(&args).await.unwrap();
+        assert_eq!(result, "éé\n[truncated: showing the first 4 bytes]");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_url_clamps_max_bytes() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/large")
+            .with_status(200)
+            .with_body("x".repeat(MAX_BYTES + 10))
+            .create_async()
+            .await;
+
+        let tool = HttpFetchTool::new();
+        let args =
+            serde_json::json!({ "url": format!("{}/large", server.url()), "max_bytes": u64::MAX })
+                .to_string();
+        let result = tool.execute(&args).await.unwrap();
+        assert!(result.ends_with(&format!(
+            "\n[truncated: showing {} of {} bytes]",
+            MAX_BYTES,
+            MAX_BYTES + 10
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_url_error_status() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/missing")
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let tool = HttpFetchTool::new();
+        let args = serde_json::json!({ "url": format!("{}/missing", server.url()) }).to_string();
+        let err = tool.execute(&args).await.unwrap_err();
+        assert!(matches!(err, ToolError::ExecutionFailed(_)));
+        assert!(err.to_string().contains("404"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_url_rejects_file_scheme() {
+        let tool = HttpFetchTool::new();
+        let err = tool
+            .execute(r#"{"url": "file:///etc/passwd"}"#)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolError::InvalidArguments(_)));
+    }
+}
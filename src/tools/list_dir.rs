@@ -0,0 +1,235 @@
+// list_dir tool: lists a directory under the working directory, optionally recursively.
+// Result: {"entries": [{"path", "type": "file"|"dir"|"symlink", "size"}], "truncated"}, paths
+// relative to the working directory and sorted. Symlinks are listed but not followed, so a
+// recursive walk stays under the root; at most `max_entries` entries are returned.
+
+use super::file_read::resolve_in_root;
+use super::{parse_arguments, ToolError, ToolExecutor};
+use crate::provider::Tool;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Default cap on returned entries, to keep tool results within context limits
+const DEFAULT_MAX_ENTRIES: usize = 500;
+
+pub struct ListDirTool {
+    root: PathBuf,
+    max_entries: usize,
+}
+
+#[derive(Deserialize)]
+struct ListDirArgs {
+    #[serde(default = "current_dir")]
+    path: String,
+    #[serde(default)]
+    recursive: bool,
+}
+
+fn current_dir() -> String {
+    ".".to_string()
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct Entry {
+    path: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// Bytes, for files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct Listing {
+    entries: Vec<Entry>,
+    /// More entries exist than `max_entries`
+    truncated: bool,
+}
+
+impl ListDirTool {
+    /// Create tool restricted to the given working directory
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            max_entries: DEFAULT_MAX_ENTRIES,
+        }
+    }
+
+    /// Append the entries of `dir` (and below, with `recursive`) until the cap is exceeded
+    fn walk(
+        &self,
+        root: &Path,
+        dir: &Path,
+        recursive: bool,
+        entries: &mut Vec<Entry>,
+    ) -> Result<(), ToolError> {
+        let failed = |e: std::io::Error| {
+            ToolError::ExecutionFailed(format!("Failed to list '{}': {}", dir.display(), e))
+        };
+        let mut children: Vec<_> = std::fs::read_dir(dir)
+            .map_err(failed)?
+            .collect::<Result<_, _>>()
+            .map_err(failed)?;
+        children.sort_by_key(|child| child.file_name());
+
+        for child in children {
+            if entries.len() > self.max_entries {
+                return Ok(());
+            }
+            let path = child.path();
+            let metadata = child.metadata().map_err(failed)?;
+            let file_type = metadata.file_type();
+            let kind = if file_type.is_symlink() {
+                "symlink"
+            } else if file_type.is_dir() {
+                "dir"
+            } else {
+                "file"
+            };
+            entries.push(Entry {
+                path: path
+                    .strip_prefix(root)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .replace('\\', "/"),
+                kind,
+                size: file_type.is_file().then_some(metadata.len()),
+            });
+            if recursive && file_type.is_dir() {
+                self.walk(root, &path, recursive, entries)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for ListDirTool {
+    fn default() -> Self {
+        Self::new(std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
+    }
+}
+
+#[async_trait]
+impl ToolExecutor for ListDirTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "list_dir",
+            "List files and directories in the working directory",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Directory relative to the working directory (default: .)"
+                    },
+                    "recursive": {
+                        "type": "boolean",
+                        "description": "Also list subdirectories (default: false)"
+                    }
+                }
+            }),
+        )
+    }
+
+    async fn execute(&self, arguments: &str) -> Result<String, ToolError> {
+        let args: ListDirArgs = parse_arguments(arguments)?;
+        let (root, dir) = resolve_in_root(&self.root, &args.path)?;
+        if !dir.is_dir() {
+            return Err(ToolError::InvalidArguments(format!(
+                "'{}' is not a directory",
+                args.path
+            )));
+        }
+
+        let mut entries = Vec::new();
+        self.walk(&root, &dir, args.recursive, &mut entries)?;
+        let truncated = entries.len() > self.max_entries;
+        entries.truncate(self.max_entries);
+        serde_json::to_string(&Listing { entries, truncated })
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use tempfile::TempDir;
+
+    fn setup() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("b.txt"), "hello").unwrap();
+        std::fs::create_dir_all(dir.path().join("a/nested")).unwrap();
+        std::fs::write(dir.path().join("a/one.md"), "1").unwrap();
+        std::fs::write(dir.path().join("a/nested/two.md"), "22").unwrap();
+        dir
+    }
+
+    fn list(tool: &ListDirTool, arguments: &str) -> Value {
+        serde_json::from_str(&futures::executor::block_on(tool.execute(arguments)).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_list_flat() {
+        let dir = setup();
+        let tool = ListDirTool::new(dir.path());
+        assert_eq!(
+            list(&tool, "{}"),
+            json!({"entries": [
+                {"path": "a", "type": "dir"},
+                {"path": "b.txt", "type": "file", "size": 5}
+            ], "truncated": false})
+        );
+        let listing = list(&tool, r#"{"path": "a"}"#);
+        assert_eq!(listing["entries"][0]["path"], "a/nested");
+        assert_eq!(listing["entries"][1]["path"], "a/one.md");
+    }
+
+    #[test]
+    fn test_list_recursive_and_capped() {
+        let dir = setup();
+        let tool = ListDirTool::new(dir.path());
+        let listing = list(&tool, r#"{"recursive": true}"#);
+        let paths: Vec<&str> = listing["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["path"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            paths,
+            ["a", "a/nested", "a/nested/two.md", "a/one.md", "b.txt"]
+        );
+
+        let tool = ListDirTool {
+            max_entries: 2,
+            ..ListDirTool::new(dir.path())
+        };
+        let listing = list(&tool, r#"{"recursive": true}"#);
+        assert_eq!(listing["entries"].as_array().unwrap().len(), 2);
+        assert_eq!(listing["truncated"], true);
+    }
+
+    #[tokio::test]
+    async fn test_list_rejects_escape() {
+        let dir = setup();
+        let tool = ListDirTool::new(dir.path().join("a"));
+        for arguments in [r#"{"path": ".."}"#, r#"{"path": "../a/.."}"#] {
+            assert!(matches!(
+                tool.execute(arguments).await,
+                Err(ToolError::InvalidArguments(_))
+            ));
+        }
+        let args = json!({"path": dir.path()}).to_string();
+        assert!(matches!(
+            tool.execute(&args).await,
+            Err(ToolError::InvalidArguments(_))
+        ));
+        assert!(matches!(
+            tool.execute(r#"{"path": "one.md"}"#).await,
+            Err(ToolError::InvalidArguments(_))
+        ));
+    }
+}
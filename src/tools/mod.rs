@@ -0,0 +1,528 @@
+// Tools the model can call during an agentic run.
+// Design: Each tool implements ToolExecutor (definition + execute with JSON arguments).
+// ToolRegistry maps tool names to executors and hands their definitions to the provider.
+// Note: Tools that touch the network or run commands are opt-in, never in with_builtins;
+// allow() registers them by name (--allow-tool).
+// Executors are async so network tools can use the async HTTP client on the caller's
+// runtime, and run_shell can wait on its child process with a time limit.
+// Tools doing only quick local work (small file reads) just run inline.
+// Tools with state (scratchpad) are registered per run: the registry keeps a factory and
+// for_run() creates fresh instances, so state lives for one agent loop run only.
+// The registry checks arguments against the tool's declared JSON Schema before executing,
+// so the model gets every violation at once instead of the tool's first parse error.
+
+pub mod calculator;
+pub mod current_time;
+pub mod date_math;
+pub mod file_read;
+pub mod http_fetch;
+pub mod list_dir;
+pub mod scratchpad;
+pub mod shell;
+pub mod text_stats;
+pub mod validate_json;
+pub mod weather;
+pub mod web_search;
+
+use crate::provider::Tool;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::warn;
+
+use calculator::CalculatorTool;
+use current_time::CurrentTimeTool;
+use date_math::DateMathTool;
+use file_read::FileReadTool;
+use http_fetch::HttpFetchTool;
+use list_dir::ListDirTool;
+use scratchpad::ScratchpadTool;
+use shell::ShellTool;
+use text_stats::TextStatsTool;
+use validate_json::ValidateJsonTool;
+use weather::WeatherTool;
+use web_search::WebSearchTool;
+
+#[derive(Error, Debug)]
+pub enum ToolError {
+    #[error("Unknown tool: {0}")]
+    NotFound(String),
+    #[error("Invalid arguments: {0}")]
+    InvalidArguments(String),
+    #[error("Execution failed: {0}")]
+    ExecutionFailed(String),
+    #[error("Tool not configured: {0}")]
+    NotConfigured(String),
+}
+
+/// Defaults for configurable built-in tools; the model's arguments still take precedence
+#[derive(Debug, Clone, Default)]
+pub struct ToolConfig {
+    /// Time zone for current_time: UTC, local or an offset like +02:00
+    pub timezone: Option<String>,
+    /// strftime format for current_time
+    pub time_format: Option<String>,
+    /// Programs run_shell may run; None allows any command
+    pub shell_allowlist: Option<Vec<String>>,
+    /// Request timeout for network tools; None keeps each tool's default
+    pub timeout: Option<Duration>,
+}
+
+/// A tool that can be called by the model
+#[async_trait]
+pub trait ToolExecutor: Send + Sync {
+    /// Tool definition (name, description, JSON Schema parameters) sent to the provider
+    fn definition(&self) -> Tool;
+
+    /// Execute the tool with JSON-encoded arguments produced by the model
+    async fn execute(&self, arguments: &str) -> Result<String, ToolError>;
+}
+
+/// Parse JSON-encoded tool arguments into a typed struct
+pub(crate) fn parse_arguments<T: DeserializeOwned>(arguments: &str) -> Result<T, ToolError> {
+    serde_json::from_str(arguments).map_err(|e| ToolError::InvalidArguments(e.to_string()))
+}
+
+/// Truncate to at most `max_bytes`, backing off to a char boundary.
+/// Returns true if content was truncated.
+pub(crate) fn truncate_utf8(content: &mut String, max_bytes: usize) -> bool {
+    if content.len() <= max_bytes {
+        return false;
+    }
+    let mut end = max_bytes;
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    content.truncate(end);
+    true
+}
+
+/// Tools left out of with_builtins, registered by name with `ToolRegistry::allow`
+pub const OPT_IN_TOOLS: &[&str] = &[
+    "fetch_url",
+    "get_weather",
+    "run_shell",
+    "validate_json",
+    "web_search",
+];
+
+/// Creates a fresh instance of a tool that keeps state for one run
+type ToolFactory = Arc<dyn Fn() -> Arc<dyn ToolExecutor> + Send + Sync>;
+
+enum RegisteredTool {
+    /// One instance for every call
+    Shared(Arc<dyn ToolExecutor>),
+    /// A new instance per run (see `ToolRegistry::for_run`)
+    PerRun(ToolFactory),
+}
+
+impl RegisteredTool {
+    fn instance(&self) -> Arc<dyn ToolExecutor> {
+        match self {
+            Self::Shared(tool) => tool.clone(),
+            Self::PerRun(factory) => factory(),
+        }
+    }
+}
+
+/// Registry of available tools, keyed by name (sorted for stable listings)
+#[derive(Default)]
+pub struct ToolRegistry {
+    tools: BTreeMap<String, RegisteredTool>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with all safe built-in tools
+    pub fn with_builtins() -> Self {
+        Self::with_builtins_config(&ToolConfig::default())
+    }
+
+    /// Registry with all safe built-in tools, configured with `config`
+    pub fn with_builtins_config(config: &ToolConfig) -> Self {
+        let mut registry = Self::new();
+        registry.register(CalculatorTool);
+        registry.register(CurrentTimeTool::new(
+            config.timezone.clone(),
+            config.time_format.clone(),
+        ));
+        registry.register(DateMathTool);
+        registry.register(FileReadTool::default());
+        registry.register(ListDirTool::default());
+        registry.register_per_run(ScratchpadTool::default);
+        registry.register(TextStatsTool);
+        registry
+    }
+
+    /// Register a tool, replacing any existing tool with the same name
+    pub fn register(&mut self, tool: impl ToolExecutor + 'static) {
+        let name = tool.definition().function.name;
+        self.tools
+            .insert(name, RegisteredTool::Shared(Arc::new(tool)));
+    }
+
+    /// Register an opt-in tool by name (see `OPT_IN_TOOLS`), configured with `config`
+    pub fn allow(&mut self, name: &str, config: &ToolConfig) -> Result<(), ToolError> {
+        match name {
+            "fetch_url" => {
+                let mut fetch = HttpFetchTool::new();
+                if let Some(timeout) = config.timeout {
+                    fetch = fetch.with_timeout(timeout);
+                }
+                self.register(fetch);
+            }
+            "get_weather" => {
+                let mut weather = WeatherTool::from_env().ok_or_else(|| {
+                    ToolError::NotConfigured("get_weather needs WEATHER_API_KEY".to_string())
+                })?;
+                if let Some(timeout) = config.timeout {
+                    weather = weather.with_timeout(timeout);
+                }
+                self.register(weather);
+            }
+            "run_shell" => {
+                let mut shell = ShellTool::new();
+                if let Some(programs) = &config.shell_allowlist {
+                    shell = shell.with_allowlist(programs.clone());
+                }
+                self.register(shell);
+            }
+            "validate_json" => self.register(ValidateJsonTool),
+            "web_search" => {
+                let mut search = WebSearchTool::from_env().ok_or_else(|| {
+                    ToolError::NotConfigured("web_search needs SEARCH_API_URL".to_string())
+                })?;
+                if let Some(timeout) = config.timeout {
+                    search = search.with_timeout(timeout);
+                }
+                self.register(search);
+            }
+            _ => {
+                return Err(ToolError::NotFound(format!(
+                    "{} (opt-in tools: {})",
+                    name,
+                    OPT_IN_TOOLS.join(", ")
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    /// Register a stateful tool: `factory` makes a new instance for each run.
+    /// Outside a run (no `for_run`) every call gets a fresh instance.
+    pub fn register_per_run<T: ToolExecutor + 'static>(
+        &mut self,
+        factory: impl Fn() -> T + Send + Sync + 'static,
+    ) {
+        let name = factory().definition().function.name;
+        let factory: ToolFactory = Arc::new(move || Arc::new(factory()));
+        self.tools.insert(name, RegisteredTool::PerRun(factory));
+    }
+
+    /// Registry for one run: per-run tools get their own instance, shared for all its calls
+    pub fn for_run(&self) -> Self {
+        let tools = self
+            .tools
+            .iter()
+            .map(|(name, tool)| (name.clone(), RegisteredTool::Shared(tool.instance())))
+            .collect();
+        Self { tools }
+    }
+
+    /// Names of all registered tools
+    pub fn names(&self) -> Vec<String> {
+        self.tools.keys().cloned().collect()
+    }
+
+    /// Tool definitions for the provider request
+    pub fn definitions(&self) -> Vec<Tool> {
+        self.tools
+            .values()
+            .map(|t| t.instance().definition())
+            .collect()
+    }
+
+    /// Keep only the named tools; an empty list keeps all of them.
+    /// Fails on the first unknown name, listing the available tools.
+    pub fn select(mut self, names: &[String]) -> Result<Self, ToolError> {
+        if let Some(unknown) = names.iter().find(|n| !self.tools.contains_key(*n)) {
+            return Err(ToolError::NotFound(format!(
+                "{} (available: {})",
+                unknown,
+                self.names().join(", ")
+            )));
+        }
+        if !names.is_empty() {
+            self.tools.retain(|name, _| names.contains(name));
+        }
+        Ok(self)
+    }
+
+    fn get(&self, name: &str) -> Result<Arc<dyn ToolExecutor>, ToolError> {
+        self.tools
+            .get(name)
+            .map(RegisteredTool::instance)
+            .ok_or_else(|| ToolError::NotFound(name.to_string()))
+    }
+
+    /// Execute a tool by name, once its arguments match the tool's parameters schema
+    pub async fn execute(&self, name: &str, arguments: &str) -> Result<String, ToolError> {
+        let tool = self.get(name)?;
+        check_arguments(tool.as_ref(), arguments)?;
+        tool.execute(arguments).await
+    }
+}
+
+/// Reject arguments that violate the tool's parameters schema, listing every violation.
+/// A tool with a malformed schema is still run: that's the tool's bug, not the model's.
+fn check_arguments(tool: &dyn ToolExecutor, arguments: &str) -> Result<(), ToolError> {
+    let definition = tool.definition();
+    let value: serde_json::Value = parse_arguments(arguments)?;
+    match validate_json::schema_violations(&value, &definition.function.parameters) {
+        Ok(violations) if violations.is_empty() => Ok(()),
+        Ok(violations) => Err(ToolError::InvalidArguments(violations.join("; "))),
+        Err(e) => {
+            warn!(tool = %definition.function.name, error = %e, "invalid parameters schema");
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_builtins() {
+        let registry = ToolRegistry::with_builtins();
+        assert!(registry.names().contains(&"read_file".to_string()));
+        assert!(registry.names().contains(&"calculate".to_string()));
+        assert_eq!(registry.definitions().len(), registry.names().len());
+    }
+
+    #[test]
+    fn test_select_tools() {
+        let registry = ToolRegistry::with_builtins()
+            .select(&["calculate".to_string()])
+            .unwrap();
+        assert_eq!(registry.names(), vec!["calculate".to_string()]);
+    }
+
+    #[test]
+    fn test_select_empty_keeps_all_tools() {
+        let all = ToolRegistry::with_builtins().names();
+        let registry = ToolRegistry::with_builtins().select(&[]).unwrap();
+        assert_eq!(registry.names(), all);
+    }
+
+    #[test]
+    fn test_select_unknown_tool_lists_available() {
+        let err = ToolRegistry::with_builtins()
+            .select(&["nope".to_string()])
+            .err()
+            .unwrap();
+        let message = err.to_string();
+        assert!(message.contains("Unknown tool: nope"));
+        assert!(message.contains(
+            "calculate, current_time, date_math, list_dir, read_file, scratchpad, text_stats"
+        ));
+    }
+
+    #[test]
+    fn test_allow_opt_in_tools() {
+        let mut registry = ToolRegistry::with_builtins();
+        assert!(!registry.names().contains(&"run_shell".to_string()));
+        let config = ToolConfig::default();
+        registry.allow("run_shell", &config).unwrap();
+        registry.allow("fetch_url", &config).unwrap();
+        registry.allow("validate_json", &config).unwrap();
+        assert!(registry.names().contains(&"run_shell".to_string()));
+        assert!(registry.names().contains(&"fetch_url".to_string()));
+        assert!(registry.names().contains(&"validate_json".to_string()));
+
+        let err = registry.allow("read_file", &config).unwrap_err();
+        assert!(matches!(err, ToolError::NotFound(_)));
+        assert!(err.to_string().contains(
+            "opt-in tools: fetch_url, get_weather, run_shell, validate_json, web_search"
+        ));
+
+        if std::env::var_os("SEARCH_API_URL").is_none() {
+            let err = registry.allow("web_search", &config).unwrap_err();
+            assert!(matches!(err, ToolError::NotConfigured(_)));
+        }
+        if std::env::var_os("WEATHER_API_KEY").is_none() {
+            let err = registry.allow("get_weather", &config).unwrap_err();
+            assert!(matches!(err, ToolError::NotConfigured(_)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_for_run_scopes_per_run_state() {
+        let registry = ToolRegistry::with_builtins();
+        let write = r#"{"operation": "write", "key": "k", "value": "v"}"#;
+        let read = r#"{"operation": "read", "key": "k"}"#;
+
+        let run = registry.for_run();
+        run.execute("scratchpad", write).await.unwrap();
+        assert_eq!(run.execute("scratchpad", read).await.unwrap(), "v");
+        // Another run, and calls outside a run, start empty
+        assert!(registry
+            .for_run()
+            .execute("scratchpad", read)
+            .await
+            .is_err());
+        registry.execute("scratchpad", write).await.unwrap();
+        assert!(registry.execute("scratchpad", read).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_with_builtins_config_sets_tool_defaults() {
+        let config = ToolConfig {
+            timezone: Some("+01:00".to_string()),
+            time_format: Some("%:z".to_string()),
+            ..Default::default()
+        };
+        let registry = ToolRegistry::with_builtins_config(&config);
+        assert_eq!(
+            registry.execute("current_time", "{}").await.unwrap(),
+            "+01:00"
+        );
+        assert_eq!(
+            registry.names(),
+            ToolRegistry::with_builtins().names(),
+            "config changes defaults, not the set of tools"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_unknown_tool() {
+        let registry = ToolRegistry::new();
+        let err = registry.execute("missing", "{}").await.unwrap_err();
+        assert!(matches!(err, ToolError::NotFound(ref name) if name == "missing"));
+    }
+
+    /// Tool that awaits a timer before echoing its arguments
+    struct DelayedEchoTool;
+
+    #[async_trait]
+    impl ToolExecutor for DelayedEchoTool {
+        fn definition(&self) -> Tool {
+            Tool::function("delayed_echo", "Echo after a delay", serde_json::json!({}))
+        }
+
+        async fn execute(&self, arguments: &str) -> Result<String, ToolError> {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            Ok(arguments.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_async_tool() {
+        let mut registry = ToolRegistry::with_builtins();
+        registry.register(DelayedEchoTool);
+        let output = registry
+            .execute("delayed_echo", r#"{"a": 1}"#)
+            .await
+            .unwrap();
+        assert_eq!(output, r#"{"a": 1}"#);
+        let output = registry
+            .execute("calculate", r#"{"expression": "1 + 1"}"#)
+            .await
+            .unwrap();
+        assert_eq!(output, "2");
+    }
+
+    /// Tool requiring a string `name`, counting how often it actually runs
+    #[derive(Default)]
+    struct GreetTool {
+        runs: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ToolExecutor for GreetTool {
+        fn definition(&self) -> Tool {
+            Tool::function(
+                "greet",
+                "Greet someone",
+                serde_json::json!({
+                    "type": "object",
+                    "properties": {"name": {"type": "string"}},
+                    "required": ["name"]
+                }),
+            )
+        }
+
+        async fn execute(&self, arguments: &str) -> Result<String, ToolError> {
+            self.runs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(arguments.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_arguments_validated_against_schema_before_execute() {
+        let tool = Arc::new(GreetTool::default());
+        let mut registry = ToolRegistry::new();
+        let shared = tool.clone();
+        registry.register_per_run(move || SharedTool(shared.clone()));
+
+        let err = registry.execute("greet", "{}").await.unwrap_err();
+        assert!(
+            matches!(err, ToolError::InvalidArguments(ref m) if m.contains("\"name\" is a required property")),
+            "{}",
+            err
+        );
+        let err = registry
+            .execute("greet", r#"{"name": 7}"#)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, ToolError::InvalidArguments(ref m) if m.starts_with("/name: ")),
+            "{}",
+            err
+        );
+        assert_eq!(tool.runs.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        registry
+            .execute("greet", r#"{"name": "Ada"}"#)
+            .await
+            .unwrap();
+        assert_eq!(tool.runs.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    /// Registers a tool the test keeps a handle to
+    struct SharedTool(Arc<GreetTool>);
+
+    #[async_trait]
+    impl ToolExecutor for SharedTool {
+        fn definition(&self) -> Tool {
+            self.0.definition()
+        }
+
+        async fn execute(&self, arguments: &str) -> Result<String, ToolError> {
+            self.0.execute(arguments).await
+        }
+    }
+
+    #[test]
+    fn test_truncate_utf8_char_boundary() {
+        let mut content = "héllo".to_string();
+        assert!(truncate_utf8(&mut content, 2));
+        assert_eq!(content, "h");
+
+        let mut short = "hi".to_string();
+        assert!(!truncate_utf8(&mut short, 10));
+        assert_eq!(short, "hi");
+    }
+
+    #[test]
+    fn test_parse_arguments_invalid_json() {
+        let result: Result<serde_json::Value, _> = parse_arguments("not json");
+        assert!(matches!(result, Err(ToolError::InvalidArguments(_))));
+    }
+}
@@ -0,0 +1,157 @@
+// scratchpad tool: notes the model keeps between tool calls of one agent loop run.
+// Operations: write (store `value` under `key`, replacing it), read (a key's value) and list
+// (stored keys, sorted). Registered per run (ToolRegistry::register_per_run), so every
+// AgentLoop::run starts with an empty scratchpad; nothing is persisted.
+
+use super::{parse_arguments, ToolError, ToolExecutor};
+use crate::provider::Tool;
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+pub struct ScratchpadTool {
+    notes: Arc<Mutex<HashMap<String, String>>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Operation {
+    Write,
+    Read,
+    List,
+}
+
+#[derive(Deserialize)]
+struct ScratchpadArgs {
+    operation: Operation,
+    key: Option<String>,
+    value: Option<String>,
+}
+
+fn required(field: Option<String>, name: &str) -> Result<String, ToolError> {
+    field.ok_or_else(|| ToolError::InvalidArguments(format!("'{}' is required", name)))
+}
+
+#[async_trait]
+impl ToolExecutor for ScratchpadTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "scratchpad",
+            "Keep notes during this task: write a value under a key, read it back, or list keys",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "operation": {
+                        "type": "string",
+                        "enum": ["write", "read", "list"]
+                    },
+                    "key": {
+                        "type": "string",
+                        "description": "Note name (write and read)"
+                    },
+                    "value": {
+                        "type": "string",
+                        "description": "Note content (write)"
+                    }
+                },
+                "required": ["operation"]
+            }),
+        )
+    }
+
+    async fn execute(&self, arguments: &str) -> Result<String, ToolError> {
+        let args: ScratchpadArgs = parse_arguments(arguments)?;
+        let mut notes = self
+            .notes
+            .lock()
+            .map_err(|_| ToolError::ExecutionFailed("Scratchpad is unavailable".to_string()))?;
+        match args.operation {
+            Operation::Write => {
+                let key = required(args.key, "key")?;
+                let value = required(args.value, "value")?;
+                notes.insert(key.clone(), value);
+                Ok(format!("Saved '{}'", key))
+            }
+            Operation::Read => {
+                let key = required(args.key, "key")?;
+                notes
+                    .get(&key)
+                    .cloned()
+                    .ok_or_else(|| ToolError::ExecutionFailed(format!("No note named '{}'", key)))
+            }
+            Operation::List => {
+                let mut keys: Vec<&String> = notes.keys().collect();
+                keys.sort();
+                serde_json::to_string(&keys).map_err(|e| ToolError::ExecutionFailed(e.to_string()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_write_then_read() {
+        let tool = ScratchpadTool::default();
+        assert_eq!(
+            tool.execute(r#"{"operation": "write", "key": "plan", "value": "1. outline"}"#)
+                .await
+                .unwrap(),
+            "Saved 'plan'"
+        );
+        tool.execute(r#"{"operation": "write", "key": "plan", "value": "2. draft"}"#)
+            .await
+            .unwrap();
+        assert_eq!(
+            tool.execute(r#"{"operation": "read", "key": "plan"}"#)
+                .await
+                .unwrap(),
+            "2. draft"
+        );
+        assert!(matches!(
+            tool.execute(r#"{"operation": "read", "key": "missing"}"#)
+                .await,
+            Err(ToolError::ExecutionFailed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list_keys() {
+        let tool = ScratchpadTool::default();
+        assert_eq!(
+            tool.execute(r#"{"operation": "list"}"#).await.unwrap(),
+            "[]"
+        );
+        for key in ["sources", "outline"] {
+            tool.execute(&format!(
+                r#"{{"operation": "write", "key": "{}", "value": "x"}}"#,
+                key
+            ))
+            .await
+            .unwrap();
+        }
+        assert_eq!(
+            tool.execute(r#"{"operation": "list"}"#).await.unwrap(),
+            r#"["outline","sources"]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_arguments() {
+        let tool = ScratchpadTool::default();
+        for arguments in [
+            r#"{"operation": "write", "key": "a"}"#,
+            r#"{"operation": "read"}"#,
+            r#"{"operation": "delete"}"#,
+        ] {
+            assert!(matches!(
+                tool.execute(arguments).await,
+                Err(ToolError::InvalidArguments(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,233 @@
+// run_shell tool: executes a shell command and returns exit code with stdout/stderr.
+// Never part of with_builtins; enabled with --allow-tool run_shell. With an allowlist,
+// only listed programs may run and shell metacharacters are rejected so that
+// `allowed; other` style chaining can't bypass the list. A command still running after
+// the time limit is killed and reported as a failure.
+
+use super::{parse_arguments, truncate_utf8, ToolError, ToolExecutor};
+use crate::provider::Tool;
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Cap on combined output returned to the model
+const DEFAULT_MAX_OUTPUT_BYTES: usize = 20_000;
+const DEFAULT_TIMEOUT_SECS: u64 = 120;
+
+/// Characters that let a command chain, pipe, redirect or substitute
+const SHELL_METACHARACTERS: &[char] = &[';', '|', '&', '$', '`', '>', '<', '\n', '(', ')'];
+
+#[derive(Default)]
+pub struct ShellTool {
+    allowlist: Option<Vec<String>>,
+    max_output_bytes: Option<usize>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Deserialize)]
+struct ShellArgs {
+    command: String,
+    #[serde(default)]
+    fail_on_error: bool,
+}
+
+impl ShellTool {
+    /// Shell tool without restrictions on which programs may run
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restrict execution to the given program names (first word of the command)
+    pub fn with_allowlist(mut self, programs: Vec<String>) -> Self {
+        self.allowlist = Some(programs);
+        self
+    }
+
+    fn check_allowed(&self, command: &str) -> Result<(), ToolError> {
+        let Some(ref allowlist) = self.allowlist else {
+            return Ok(());
+        };
+        if command.contains(SHELL_METACHARACTERS) {
+            return Err(ToolError::InvalidArguments(
+                "Shell operators are not permitted when an allowlist is configured".to_string(),
+            ));
+        }
+        let program = command.split_whitespace().next().unwrap_or_default();
+        if !allowlist.iter().any(|p| p == program) {
+            return Err(ToolError::InvalidArguments(format!(
+                "Command '{}' is not allowed. Allowed: {}",
+                program,
+                allowlist.join(", ")
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+#[async_trait]
+impl ToolExecutor for ShellTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "run_shell",
+            "Run a shell command and return its exit code, stdout and stderr",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "command": {
+                        "type": "string",
+                        "description": "Command line to execute"
+                    },
+                    "fail_on_error": {
+                        "type": "boolean",
+                        "description": "Report a tool error when the command exits with non-zero status"
+                    }
+                },
+                "required": ["command"]
+            }),
+        )
+    }
+
+    async fn execute(&self, arguments: &str) -> Result<String, ToolError> {
+        let args: ShellArgs = parse_arguments(arguments)?;
+        if args.command.trim().is_empty() {
+            return Err(ToolError::InvalidArguments("Command is empty".to_string()));
+        }
+        self.check_allowed(&args.command)?;
+
+        // Dropping the output future on timeout kills the child
+        let mut command = shell_command(&args.command);
+        command.kill_on_drop(true);
+        let timeout = self
+            .timeout
+            .unwrap_or(Duration::from_secs(DEFAULT_TIMEOUT_SECS));
+        let output = tokio::time::timeout(timeout, command.output())
+            .await
+            .map_err(|_| {
+                ToolError::ExecutionFailed(format!(
+                    "Command '{}' timed out after {:?}",
+                    args.command, timeout
+                ))
+            })?
+            .map_err(|e| {
+                ToolError::ExecutionFailed(format!("Failed to run '{}': {}", args.command, e))
+            })?;
+
+        let exit_code = output.status.code().unwrap_or(-1);
+        let mut result = format!(
+            "exit_code: {}\nstdout:\n{}\nstderr:\n{}",
+            exit_code,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let total = result.len();
+        let max_bytes = self.max_output_bytes.unwrap_or(DEFAULT_MAX_OUTPUT_BYTES);
+        if truncate_utf8(&mut result, max_bytes) {
+            result.push_str(&format!(
+                "\n[truncated: showing {} of {} bytes]",
+                result.len(),
+                total
+            ));
+        }
+
+        if args.fail_on_error && !output.status.success() {
+            return Err(ToolError::ExecutionFailed(format!(
+                "Command exited with status {}\n{}",
+                exit_code, result
+            )));
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_run_shell_success() {
+        let tool = ShellTool::new();
+        let result = tool.execute(r#"{"command": "echo hello"}"#).await.unwrap();
+        assert!(result.starts_with("exit_code: 0"));
+        assert!(result.contains("stdout:\nhello"));
+    }
+
+    #[tokio::test]
+    async fn test_run_shell_captures_stderr_and_exit_code() {
+        let tool = ShellTool::new();
+        let result = tool
+            .execute(r#"{"command": "echo oops >&2; exit 3"}"#)
+            .await
+            .unwrap();
+        assert!(result.starts_with("exit_code: 3"));
+        assert!(result.contains("stderr:\noops"));
+    }
+
+    #[tokio::test]
+    async fn test_run_shell_fail_on_error() {
+        let tool = ShellTool::new();
+        let err = tool
+            .execute(r#"{"command": "exit 2", "fail_on_error": true}"#)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolError::ExecutionFailed(_)));
+        assert!(err.to_string().contains("status 2"));
+    }
+
+    #[tokio::test]
+    async fn test_run_shell_allowlist() {
+        let tool = ShellTool::new().with_allowlist(vec!["echo".to_string()]);
+        assert!(tool.execute(r#"{"command": "echo ok"}"#).await.is_ok());
+
+        let err = tool.execute(r#"{"command": "ls"}"#).await.unwrap_err();
+        assert!(matches!(err, ToolError::InvalidArguments(_)));
+
+        let err = tool
+            .execute(r#"{"command": "echo ok; ls"}"#)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolError::InvalidArguments(_)));
+    }
+
+    #[tokio::test]
+    async fn test_run_shell_times_out() {
+        let tool = ShellTool {
+            timeout: Some(Duration::from_millis(200)),
+            ..ShellTool::new()
+        };
+        let started = std::time::Instant::now();
+        let err = tool
+            .execute(r#"{"command": "sleep 10"}"#)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolError::ExecutionFailed(_)));
+        assert!(err.to_string().contains("timed out after 200ms"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_run_shell_truncates_output() {
+        let tool = ShellTool {
+            max_output_bytes: Some(20),
+            ..ShellTool::new()
+        };
+        let result = tool
+            .execute(r#"{"command": "printf 'a%.0s' $(seq 1 100)"}"#)
+            .await
+            .unwrap();
+        assert!(result.contains("[truncated: showing 20 of"));
+    }
+}
@@ -0,0 +1,107 @@
+// text_stats tool: counts characters, words, lines and bytes so the model can hit length
+// targets when writing or summarizing. `chars` counts Unicode scalar values (Rust chars),
+// not grapheme clusters: "é" written as e + combining accent is 2, an emoji with a skin tone
+// modifier is 2. Words are whitespace-separated runs; lines follow `str::lines` (a trailing
+// newline does not start another line, empty text has 0 lines).
+
+use super::{parse_arguments, ToolError, ToolExecutor};
+use crate::provider::Tool;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+pub struct TextStatsTool;
+
+#[derive(Deserialize)]
+struct TextStatsArgs {
+    text: String,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct TextStats {
+    chars: usize,
+    words: usize,
+    lines: usize,
+    bytes: usize,
+}
+
+#[async_trait]
+impl ToolExecutor for TextStatsTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "text_stats",
+            "Count the characters, words, lines and UTF-8 bytes of a text",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "text": {
+                        "type": "string",
+                        "description": "Text to measure"
+                    }
+                },
+                "required": ["text"]
+            }),
+        )
+    }
+
+    async fn execute(&self, arguments: &str) -> Result<String, ToolError> {
+        let args: TextStatsArgs = parse_arguments(arguments)?;
+        serde_json::to_string(&text_stats(&args.text))
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))
+    }
+}
+
+fn text_stats(text: &str) -> TextStats {
+    TextStats {
+        chars: text.chars().count(),
+        words: text.split_whitespace().count(),
+        lines: text.lines().count(),
+        bytes: text.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(chars: usize, words: usize, lines: usize, bytes: usize) -> TextStats {
+        TextStats {
+            chars,
+            words,
+            lines,
+            bytes,
+        }
+    }
+
+    #[test]
+    fn test_empty_text() {
+        assert_eq!(text_stats(""), stats(0, 0, 0, 0));
+        assert_eq!(text_stats(" \n"), stats(2, 0, 1, 2));
+    }
+
+    #[test]
+    fn test_multi_line_text() {
+        assert_eq!(text_stats("one two\nthree\n"), stats(14, 3, 2, 14));
+        assert_eq!(text_stats("a\r\n\r\nb"), stats(6, 2, 3, 6));
+    }
+
+    #[test]
+    fn test_unicode_counts_scalar_values() {
+        assert_eq!(text_stats("héllo wörld"), stats(11, 2, 1, 13));
+        // e + combining acute accent: one grapheme, two chars
+        assert_eq!(text_stats("e\u{301}"), stats(2, 1, 1, 3));
+        assert_eq!(text_stats("👋🏽 hi"), stats(5, 2, 1, 11));
+    }
+
+    #[tokio::test]
+    async fn test_execute_returns_json() {
+        let output = TextStatsTool
+            .execute(r#"{"text": "Hello, world"}"#)
+            .await
+            .unwrap();
+        assert_eq!(output, r#"{"chars":12,"words":2,"lines":1,"bytes":12}"#);
+        assert!(matches!(
+            TextStatsTool.execute("{}").await,
+            Err(ToolError::InvalidArguments(_))
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+// validate_json tool: checks a JSON document against a JSON Schema so the model can
+// self-check structured output before answering. Validation failures are a normal result
+// (`{"valid": false, "errors": [...]}`); only a malformed schema is an argument error.
+// Errors are "<instance path>: <message>", with "/" for the document root.
+
+use super::{parse_arguments, ToolError, ToolExecutor};
+use crate::provider::Tool;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub struct ValidateJsonTool;
+
+#[derive(Deserialize)]
+struct ValidateJsonArgs {
+    data: Value,
+    schema: Value,
+}
+
+/// Validation outcome returned to the model
+#[derive(Debug, Serialize, PartialEq)]
+struct ValidationReport {
+    valid: bool,
+    errors: Vec<String>,
+}
+
+#[async_trait]
+impl ToolExecutor for ValidateJsonTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "validate_json",
+            "Validate a JSON document against a JSON Schema and list any violations",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "data": {
+                        "description": "JSON document to validate"
+                    },
+                    "schema": {
+                        "type": "object",
+                        "description": "JSON Schema the document must match"
+                    }
+                },
+                "required": ["data", "schema"]
+            }),
+        )
+    }
+
+    async fn execute(&self, arguments: &str) -> Result<String, ToolError> {
+        let args: ValidateJsonArgs = parse_arguments(arguments)?;
+        let report = validate(&args.data, &args.schema)?;
+        serde_json::to_string(&report).map_err(|e| ToolError::ExecutionFailed(e.to_string()))
+    }
+}
+
+/// Validate `data` against `schema`, collecting every violation
+fn validate(data: &Value, schema: &Value) -> Result<ValidationReport, ToolError> {
+    let errors = schema_violations(data, schema)
+        .map_err(|e| ToolError::InvalidArguments(format!("Invalid JSON Schema: {}", e)))?;
+    Ok(ValidationReport {
+        valid: errors.is_empty(),
+        errors,
+    })
+}
+
+/// Every violation of `schema` by `data`; Err with the reason when the schema is malformed
+pub(super) fn schema_violations(data: &Value, schema: &Value) -> Result<Vec<String>, String> {
+    let validator = jsonschema::validator_for(schema).map_err(|e| e.to_string())?;
+    Ok(validator
+        .iter_errors(data)
+        .map(|error| {
+            let path = error.instance_path().to_string();
+            let path = if path.is_empty() { "/" } else { &path };
+            format!("{}: {}", path, error)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn person_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "age": {"type": "integer", "minimum": 0}
+            },
+            "required": ["name", "age"]
+        })
+    }
+
+    fn run(data: Value, schema: Value) -> Result<Value, ToolError> {
+        let output = futures::executor::block_on(
+            ValidateJsonTool.execute(&json!({ "data": data, "schema": schema }).to_string()),
+        )?;
+        Ok(serde_json::from_str(&output).unwrap())
+    }
+
+    #[test]
+    fn test_valid_document() {
+        let report = run(json!({"name": "Ada", "age": 36}), person_schema()).unwrap();
+        assert_eq!(report, json!({"valid": true, "errors": []}));
+    }
+
+    #[test]
+    fn test_invalid_document_lists_errors() {
+        let report = run(json!({"age": -1}), person_schema()).unwrap();
+        assert_eq!(report["valid"], false);
+        let errors: Vec<&str> = report["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e.as_str().unwrap())
+            .collect();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors
+            .iter()
+            .any(|e| e.starts_with("/: ") && e.contains("\"name\"")));
+        assert!(errors.iter().any(|e| e.starts_with("/age: ")));
+    }
+
+    #[test]
+    fn test_malformed_schema() {
+        let err = run(json!({}), json!({"type": 12})).unwrap_err();
+        assert!(
+            matches!(err, ToolError::InvalidArguments(ref m) if m.contains("Invalid JSON Schema")),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_arguments() {
+        let err = ValidateJsonTool
+            .execute(r#"{"data": {}}"#)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolError::InvalidArguments(_)));
+    }
+}
@@ -0,0 +1,310 @@
+// get_weather tool: current weather for a location, a reference for writing network tools.
+// Opt-in (network access + credentials, --allow-tool get_weather): WEATHER_API_KEY,
+// optional WEATHER_API_URL.
+// Contract: the OpenWeatherMap current weather API, GET {url}?q=<location>&units=<units>&appid=<key>
+// returning `{"name", "sys": {"country"}, "weather": [{"description"}], "main": {"temp",
+// "feels_like", "humidity"}, "wind": {"speed"}}`. The model gets a one-line summary rather
+// than the raw payload. API errors (`{"message": ...}`) are passed on as tool errors.
+
+use super::{parse_arguments, ToolError, ToolExecutor};
+use crate::provider::Tool;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use std::time::Duration;
+
+const DEFAULT_WEATHER_API_URL: &str = "https://api.openweathermap.org/data/2.5/weather";
+const WEATHER_TIMEOUT_SECS: u64 = 30;
+
+pub struct WeatherTool {
+    endpoint: String,
+    api_key: String,
+    timeout: Duration,
+}
+
+#[derive(Deserialize)]
+struct WeatherArgs {
+    location: String,
+    units: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WeatherResponse {
+    #[serde(default)]
+    name: String,
+    sys: Option<WeatherSys>,
+    #[serde(default)]
+    weather: Vec<WeatherCondition>,
+    main: WeatherMain,
+    wind: Option<WeatherWind>,
+}
+
+#[derive(Deserialize)]
+struct WeatherSys {
+    country: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WeatherCondition {
+    description: String,
+}
+
+#[derive(Deserialize)]
+struct WeatherMain {
+    temp: f64,
+    feels_like: Option<f64>,
+    humidity: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct WeatherWind {
+    speed: f64,
+}
+
+/// Error body of the weather API
+#[derive(Deserialize)]
+struct WeatherApiError {
+    message: String,
+}
+
+impl WeatherTool {
+    pub fn new(endpoint: impl Into<String>, api_key: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            api_key: api_key.into(),
+            timeout: Duration::from_secs(WEATHER_TIMEOUT_SECS),
+        }
+    }
+
+    /// Override the request timeout (default: 30s)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Configure from WEATHER_API_KEY / WEATHER_API_URL; None when no key is set
+    pub fn from_env() -> Option<Self> {
+        let api_key = std::env::var("WEATHER_API_KEY")
+            .ok()
+            .filter(|v| !v.is_empty())?;
+        let endpoint = std::env::var("WEATHER_API_URL")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| DEFAULT_WEATHER_API_URL.to_string());
+        Some(Self::new(endpoint, api_key))
+    }
+}
+
+/// One-line summary of a weather API response body
+fn summarize(body: &str, imperial: bool) -> Result<String, ToolError> {
+    let response: WeatherResponse = serde_json::from_str(body)
+        .map_err(|e| ToolError::ExecutionFailed(format!("Invalid weather API response: {}", e)))?;
+    let (temp_unit, speed_unit) = if imperial {
+        ("°F", "mph")
+    } else {
+        ("°C", "m/s")
+    };
+
+    let mut place = response.name;
+    if let Some(country) = response.sys.and_then(|s| s.country) {
+        place = format!("{}, {}", place, country);
+    }
+    let conditions: Vec<&str> = response
+        .weather
+        .iter()
+        .map(|w| w.description.as_str())
+        .collect();
+
+    let mut summary = format!("{}: ", place);
+    if !conditions.is_empty() {
+        summary.push_str(&format!("{}, ", conditions.join(", ")));
+    }
+    summary.push_str(&format!("{:.1} {}", response.main.temp, temp_unit));
+    if let Some(feels_like) = response.main.feels_like {
+        summary.push_str(&format!(" (feels like {:.1} {})", feels_like, temp_unit));
+    }
+    if let Some(humidity) = response.main.humidity {
+        summary.push_str(&format!(", humidity {}%", humidity));
+    }
+    if let Some(wind) = response.wind {
+        summary.push_str(&format!(", wind {:.1} {}", wind.speed, speed_unit));
+    }
+    Ok(summary)
+}
+
+#[async_trait]
+impl ToolExecutor for WeatherTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "get_weather",
+            "Get the current weather for a location",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "location": {
+                        "type": "string",
+                        "description": "City name, optionally with country code, e.g. Paris,FR"
+                    },
+                    "units": {
+                        "type": "string",
+                        "enum": ["metric", "imperial"],
+                        "description": "metric (°C, m/s, default) or imperial (°F, mph)"
+                    }
+                },
+                "required": ["location"]
+            }),
+        )
+    }
+
+    async fn execute(&self, arguments: &str) -> Result<String, ToolError> {
+        let args: WeatherArgs = parse_arguments(arguments)?;
+        if args.location.trim().is_empty() {
+            return Err(ToolError::InvalidArguments(
+                "Location must not be empty".to_string(),
+            ));
+        }
+        let units = args.units.as_deref().unwrap_or("metric");
+        if !matches!(units, "metric" | "imperial") {
+            return Err(ToolError::InvalidArguments(format!(
+                "Unknown units '{}' (use metric or imperial)",
+                units
+            )));
+        }
+
+        let client = Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        let url = reqwest::Url::parse_with_params(
+            &self.endpoint,
+            &[
+                ("q", args.location.trim()),
+                ("units", units),
+                ("appid", self.api_key.as_str()),
+            ],
+        )
+        .map_err(|e| {
+            ToolError::ExecutionFailed(format!(
+                "Invalid WEATHER_API_URL '{}': {}",
+                self.endpoint, e
+            ))
+        })?;
+        // reqwest errors include the URL, which carries the key
+        let response = client.get(url).send().await.map_err(|e| {
+            ToolError::ExecutionFailed(format!("Weather request failed: {}", e.without_url()))
+        })?;
+
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(format!("Failed to read body: {}", e)))?;
+        if !status.is_success() {
+            let detail = serde_json::from_str::<WeatherApiError>(&body)
+                .map(|e| format!(": {}", e.message))
+                .unwrap_or_default();
+            return Err(ToolError::ExecutionFailed(format!(
+                "HTTP {} from weather API{}",
+                status.as_u16(),
+                detail
+            )));
+        }
+        summarize(&body, units == "imperial")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+
+    const LONDON: &str = r#"{
+        "name": "London",
+        "sys": {"country": "GB"},
+        "weather": [{"main": "Rain", "description": "light rain"}],
+        "main": {"temp": 12.34, "feels_like": 11.0, "humidity": 81},
+        "wind": {"speed": 4.1}
+    }"#;
+
+    #[test]
+    fn test_summarize() {
+        assert_eq!(
+            summarize(LONDON, false).unwrap(),
+            "London, GB: light rain, 12.3 °C (feels like 11.0 °C), humidity 81%, wind 4.1 m/s"
+        );
+        let minimal = r#"{"name": "Oslo", "main": {"temp": 50}}"#;
+        assert_eq!(summarize(minimal, true).unwrap(), "Oslo: 50.0 °F");
+        assert!(matches!(
+            summarize("<html>", false),
+            Err(ToolError::ExecutionFailed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_weather_request_shaping() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/weather")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("q".into(), "London,GB".into()),
+                Matcher::UrlEncoded("units".into(), "imperial".into()),
+                Matcher::UrlEncoded("appid".into(), "secret".into()),
+            ]))
+            .with_body(LONDON)
+            .create_async()
+            .await;
+
+        let tool = WeatherTool::new(format!("{}/weather", server.url()), "secret");
+        let output = tool
+            .execute(r#"{"location": " London,GB ", "units": "imperial"}"#)
+            .await
+            .unwrap();
+        assert!(
+            output.starts_with("London, GB: light rain, 12.3 °F"),
+            "{}",
+            output
+        );
+        assert!(output.ends_with("wind 4.1 mph"), "{}", output);
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_weather_api_error() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/weather")
+            .match_query(Matcher::Any)
+            .with_status(404)
+            .with_body(r#"{"cod": "404", "message": "city not found"}"#)
+            .create_async()
+            .await;
+
+        let tool = WeatherTool::new(format!("{}/weather", server.url()), "secret");
+        let err = tool
+            .execute(r#"{"location": "Atlantis"}"#)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ToolError::ExecutionFailed(_)));
+        assert!(
+            err.to_string()
+                .contains("HTTP 404 from weather API: city not found"),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_weather_invalid_arguments() {
+        let tool = WeatherTool::new("http://localhost:1/weather", "secret");
+        for arguments in [
+            r#"{"location": "  "}"#,
+            r#"{"location": "Paris", "units": "kelvin"}"#,
+            r#"{"units": "metric"}"#,
+        ] {
+            assert!(matches!(
+                tool.execute(arguments).await,
+                Err(ToolError::InvalidArguments(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,260 @@
+// web_search tool: query a configurable search API and return title/url/snippet results.
+// Opt-in (network access + credentials, --allow-tool web_search): SEARCH_API_URL and
+// optional SEARCH_API_KEY.
+// Contract: GET {url}?q=<query>&count=<n>, Bearer auth when a key is set, JSON response
+// `{"results": [{"title", "url", "snippet"}]}`; `link` and `description`/`content` are
+// accepted as aliases so simple adapters over common search APIs work unchanged.
+
+use super::{parse_arguments, ToolError, ToolExecutor};
+use crate::provider::Tool;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const DEFAULT_NUM_RESULTS: usize = 5;
+const MAX_NUM_RESULTS: usize = 20;
+const SEARCH_TIMEOUT_SECS: u64 = 30;
+
+pub struct WebSearchTool {
+    endpoint: String,
+    api_key: Option<String>,
+    timeout: Duration,
+}
+
+#[derive(Deserialize)]
+struct WebSearchArgs {
+    query: String,
+    num_results: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    results: Vec<SearchResponseItem>,
+}
+
+#[derive(Deserialize)]
+struct SearchResponseItem {
+    #[serde(default)]
+    title: String,
+    #[serde(alias = "link")]
+    url: String,
+    #[serde(default, alias = "description", alias = "content")]
+    snippet: String,
+}
+
+/// Single search hit returned to the model
+#[derive(Debug, Serialize, PartialEq)]
+struct SearchResult {
+    title: String,
+    url: String,
+    snippet: String,
+}
+
+impl WebSearchTool {
+    pub fn new(endpoint: impl Into<String>, api_key: Option<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            api_key,
+            timeout: Duration::from_secs(SEARCH_TIMEOUT_SECS),
+        }
+    }
+
+    /// Override the request timeout (default: 30s)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Configure from SEARCH_API_URL / SEARCH_API_KEY; None when no endpoint is set
+    pub fn from_env() -> Option<Self> {
+        let endpoint = std::env::var("SEARCH_API_URL")
+            .ok()
+            .filter(|v| !v.is_empty())?;
+        let api_key = std::env::var("SEARCH_API_KEY")
+            .ok()
+            .filter(|v| !v.is_empty());
+        Some(Self::new(endpoint, api_key))
+    }
+}
+
+/// Parse a search API response body into at most `limit` results
+fn parse_results(body: &str, limit: usize) -> Result<Vec<SearchResult>, ToolError> {
+    let response: SearchResponse = serde_json::from_str(body)
+        .map_err(|e| ToolError::ExecutionFailed(format!("Invalid search API response: {}", e)))?;
+    Ok(response
+        .results
+        .into_iter()
+        .take(limit)
+        .map(|item| SearchResult {
+            title: item.title,
+            url: item.url,
+            snippet: item.snippet,
+        })
+        .collect())
+}
+
+#[async_trait]
+impl ToolExecutor for WebSearchTool {
+    fn definition(&self) -> Tool {
+        Tool::function(
+            "web_search",
+            "Search the web and return a list of results with title, url and snippet",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "Search query"
+                    },
+                    "num_results": {
+                        "type": "integer",
+                        "description": "Number of results to return (1-20, default 5)"
+                    }
+                },
+                "required": ["query"]
+            }),
+        )
+    }
+
+    async fn execute(&self, arguments: &str) -> Result<String, ToolError> {
+        let args: WebSearchArgs = parse_arguments(arguments)?;
+        if args.query.trim().is_empty() {
+            return Err(ToolError::InvalidArguments(
+                "Query must not be empty".to_string(),
+            ));
+        }
+        let num_results = args
+            .num_results
+            .unwrap_or(DEFAULT_NUM_RESULTS)
+            .clamp(1, MAX_NUM_RESULTS);
+
+        let client = Client::builder()
+            .timeout(self.timeout)
+            .build()
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))?;
+        let url = reqwest::Url::parse_with_params(
+            &self.endpoint,
+            &[
+                ("q", args.query.as_str()),
+                ("count", &num_results.to_string()),
+            ],
+        )
+        .map_err(|e| {
+            ToolError::ExecutionFailed(format!("Invalid SEARCH_API_URL '{}': {}", self.endpoint, e))
+        })?;
+        let mut request = client.get(url);
+        if let Some(ref key) = self.api_key {
+            request = request.bearer_auth(key);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(format!("Search request failed: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ToolError::ExecutionFailed(format!(
+                "HTTP {} from search API",
+                status.as_u16()
+            )));
+        }
+
+        let body = response
+            .text()
+            .await
+            .map_err(|e| ToolError::ExecutionFailed(format!("Failed to read body: {}", e)))?;
+        let results = parse_results(&body, num_results)?;
+        serde_json::to_string_pretty(&results)
+            .map_err(|e| ToolError::ExecutionFailed(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+
+    #[test]
+    fn test_parse_results() {
+        let body = r#"{"results": [
+            {"title": "Rust", "url": "https://rust-lang.org", "snippet": "A language"},
+            {"title": "Crates", "link": "https://crates.io", "description": "Registry"}
+        ]}"#;
+        let results = parse_results(body, 5).unwrap();
+        assert_eq!(
+            results,
+            vec![
+                SearchResult {
+                    title: "Rust".to_string(),
+                    url: "https://rust-lang.org".to_string(),
+                    snippet: "A language".to_string(),
+                },
+                SearchResult {
+                    title: "Crates".to_string(),
+                    url: "https://crates.io".to_string(),
+                    snippet: "Registry".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_results_limit_and_invalid() {
+        let body = r#"{"results": [{"url": "https://a"}, {"url": "https://b"}]}"#;
+        assert_eq!(parse_results(body, 1).unwrap().len(), 1);
+        assert!(matches!(
+            parse_results("<html>", 5),
+            Err(ToolError::ExecutionFailed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_web_search_success() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/search")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("q".into(), "rust async".into()),
+                Matcher::UrlEncoded("count".into(), "2".into()),
+            ]))
+            .match_header("authorization", "Bearer secret")
+            .with_status(200)
+            .with_body(r#"{"results": [{"title": "Tokio", "url": "https://tokio.rs", "snippet": "Runtime"}]}"#)
+            .create_async().await;
+
+        let tool = WebSearchTool::new(format!("{}/search", server.url()), Some("secret".into()));
+        let output = tool
+            .execute(r#"{"query": "rust async", "num_results": 2}"#)
+            .await
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(value[0]["title"], "Tokio");
+        assert_eq!(value[0]["url"], "https://tokio.rs");
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_web_search_error_status() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/search")
+            .match_query(Matcher::Any)
+            .with_status(401)
+            .create_async()
+            .await;
+
+        let tool = WebSearchTool::new(format!("{}/search", server.url()), None);
+        let err = tool.execute(r#"{"query": "rust"}"#).await.unwrap_err();
+        assert!(matches!(err, ToolError::ExecutionFailed(_)));
+        assert!(err.to_string().contains("401"));
+    }
+
+    #[tokio::test]
+    async fn test_web_search_rejects_empty_query() {
+        let tool = WebSearchTool::new("http://localhost:1/search", None);
+        let err = tool.execute(r#"{"query": "  "}"#).await.unwrap_err();
+        assert!(matches!(err, ToolError::InvalidArguments(_)));
+    }
+}
@@ -0,0 +1,90 @@
+// Text files attached to the prompt with generate --context.
+// Each file becomes a <file name="..."> block appended to the user message after variable
+// substitution, so file contents are never treated as a template. The blocks share one size
+// cap: the file crossing it is cut at a UTF-8 boundary and later files are left out, and the
+// caller is told which files were cut so it can warn.
+
+use crate::tools::truncate_utf8;
+use std::path::PathBuf;
+
+/// Combined cap on attached file contents, to stay well inside context windows
+pub const DEFAULT_CONTEXT_MAX_BYTES: usize = 200_000;
+
+/// Attached files rendered as prompt text
+#[derive(Debug, Default, PartialEq)]
+pub struct ContextBlock {
+    pub text: String,
+    /// Files cut short or left out because of the size cap
+    pub truncated: Vec<String>,
+}
+
+/// Read `paths` in order into delimited blocks holding at most `max_bytes` of file contents
+pub fn load_context(
+    paths: &[PathBuf],
+    max_bytes: usize,
+) -> Result<ContextBlock, Box<dyn std::error::Error>> {
+    let mut block = ContextBlock::default();
+    let mut remaining = max_bytes;
+    let mut sections = Vec::new();
+    for path in paths {
+        let name = path.display().to_string();
+        let mut content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read context file '{}': {}", name, e))?;
+        if remaining == 0 {
+            block.truncated.push(name);
+            continue;
+        }
+        if truncate_utf8(&mut content, remaining) {
+            content.push_str("\n[truncated]");
+            block.truncated.push(name.clone());
+        }
+        remaining = remaining.saturating_sub(content.len());
+        sections.push(format!(
+            "<file name=\"{}\">\n{}\n</file>",
+            name,
+            content.trim_end()
+        ));
+    }
+    block.text = sections.join("\n\n");
+    Ok(block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_context_blocks_and_cap() {
+        let dir = TempDir::new().unwrap();
+        let notes = dir.path().join("notes.txt");
+        let data = dir.path().join("data.csv");
+        let extra = dir.path().join("extra.md");
+        std::fs::write(&notes, "Meeting at 10.\n").unwrap();
+        std::fs::write(&data, "a,b\n1,2\n").unwrap();
+        std::fs::write(&extra, "more").unwrap();
+
+        let block = load_context(&[notes.clone(), data.clone()], 1000).unwrap();
+        assert_eq!(
+            block.text,
+            format!(
+                "<file name=\"{}\">\nMeeting at 10.\n</file>\n\n<file name=\"{}\">\na,b\n1,2\n</file>",
+                notes.display(),
+                data.display()
+            )
+        );
+        assert!(block.truncated.is_empty());
+
+        // 15 bytes of notes fit, data is cut, extra is left out
+        let block = load_context(&[notes.clone(), data.clone(), extra.clone()], 18).unwrap();
+        assert!(block.text.contains("\na,b\n[truncated]\n</file>"));
+        assert!(!block.text.contains("more"));
+        assert_eq!(
+            block.truncated,
+            [data.display().to_string(), extra.display().to_string()]
+        );
+
+        let err = load_context(&[dir.path().join("missing.txt")], 1000).unwrap_err();
+        assert!(err.to_string().contains("missing.txt"));
+    }
+}
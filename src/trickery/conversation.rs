@@ -0,0 +1,155 @@
+// Saved conversations for generate --conversation, --continue and --save-transcript.
+// A conversation file is a JSON array of messages in the provider Message format;
+// `content` may be a plain string or an array of content parts. Roles are validated by
+// serde (system, user, assistant, tool). Template variables apply to every text part of a
+// --conversation file; a transcript written by --save-transcript is replayed verbatim.
+
+use crate::provider::{ContentPart, Message};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::generate::{substitute_variables, SubstitutionError, SubstitutionMode};
+
+/// Load messages from a JSON conversation file
+pub fn load_conversation(path: &Path) -> Result<Vec<Message>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        format!(
+            "Failed to read conversation file '{}': {}",
+            path.display(),
+            e
+        )
+    })?;
+    parse_conversation(&content)
+        .map_err(|e| format!("Invalid conversation file '{}': {}", path.display(), e))
+}
+
+/// Write messages as a pretty-printed JSON conversation file
+pub fn save_transcript(path: &Path, messages: &[Message]) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(messages).map_err(|e| e.to_string())?;
+    std::fs::write(path, content + "\n")
+        .map_err(|e| format!("Failed to write transcript '{}': {}", path.display(), e))
+}
+
+fn parse_conversation(content: &str) -> Result<Vec<Message>, String> {
+    let messages: Vec<Message> = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    if messages.is_empty() {
+        return Err("expected at least one message".to_string());
+    }
+    Ok(messages)
+}
+
+/// Substitute template variables in every text part of every message
+pub fn substitute_messages(
+    messages: &[Message],
+    variables: &HashMap<String, Value>,
+    mode: SubstitutionMode,
+) -> Result<Vec<Message>, SubstitutionError> {
+    messages
+        .iter()
+        .map(|message| {
+            let mut message = message.clone();
+            for part in message.content.iter_mut().flatten() {
+                if let ContentPart::Text { text } = part {
+                    *text = substitute_variables(text, variables, mode)?;
+                }
+            }
+            Ok(message)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::Role;
+    use std::io::Write;
+
+    #[test]
+    fn test_load_conversation() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"[
+                {{"role": "system", "content": "You are terse."}},
+                {{"role": "user", "content": "Hi"}},
+                {{"role": "assistant", "content": [{{"type": "text", "text": "Hello."}}]}}
+            ]"#
+        )
+        .unwrap();
+
+        let messages = load_conversation(file.path()).unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].role, Role::System);
+        assert_eq!(messages[1].text_content().as_deref(), Some("Hi"));
+        assert_eq!(messages[2].role, Role::Assistant);
+        assert_eq!(messages[2].text_content().as_deref(), Some("Hello."));
+    }
+
+    #[test]
+    fn test_save_transcript_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chat.json");
+        let messages = vec![
+            Message::system("You are terse."),
+            Message::user("Name a {{ color }} fruit"),
+            Message::assistant("Banana."),
+        ];
+        save_transcript(&path, &messages).unwrap();
+
+        let loaded = load_conversation(&path).unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&messages).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_conversation_rejects_unknown_role() {
+        let err = parse_conversation(r#"[{"role": "bot", "content": "Hi"}]"#).unwrap_err();
+        assert!(err.contains("unknown variant `bot`"));
+    }
+
+    #[test]
+    fn test_parse_conversation_rejects_empty_and_non_array() {
+        assert!(parse_conversation("[]").is_err());
+        assert!(parse_conversation(r#"{"role": "user", "content": "Hi"}"#).is_err());
+    }
+
+    #[test]
+    fn test_load_conversation_missing_file() {
+        let err = load_conversation(Path::new("/nonexistent/chat.json")).unwrap_err();
+        assert!(err.contains("Failed to read conversation file"));
+    }
+
+    #[test]
+    fn test_substitute_messages() {
+        let messages = parse_conversation(
+            r#"[
+                {"role": "system", "content": "Answer in {{ language }}."},
+                {"role": "user", "content": "Greet {{ name }}."},
+                {"role": "assistant", "content": "Hola {{ name }}!"}
+            ]"#,
+        )
+        .unwrap();
+        let mut vars = HashMap::new();
+        vars.insert("language".to_string(), Value::String("Spanish".to_string()));
+        vars.insert("name".to_string(), Value::String("Ana".to_string()));
+
+        let rendered = substitute_messages(&messages, &vars, SubstitutionMode::Strict).unwrap();
+        let texts: Vec<_> = rendered.iter().map(|m| m.text_content().unwrap()).collect();
+        assert_eq!(texts, vec!["Answer in Spanish.", "Greet Ana.", "Hola Ana!"]);
+    }
+
+    #[test]
+    fn test_substitute_messages_strict_missing() {
+        let messages =
+            parse_conversation(r#"[{"role": "user", "content": "Hi {{ name }}"}]"#).unwrap();
+        let err =
+            substitute_messages(&messages, &HashMap::new(), SubstitutionMode::Strict).unwrap_err();
+        assert_eq!(
+            err,
+            SubstitutionError::MissingVariables(vec!["name".to_string()])
+        );
+    }
+}
@@ -0,0 +1,133 @@
+// YAML front matter for prompt templates.
+// A template may start with a `---` delimited YAML block declaring defaults
+// (model, reasoning, temperature, max_tokens). CLI flags override these values.
+// Unknown keys are ignored so templates can carry their own metadata.
+
+use crate::provider::ReasoningLevel;
+use serde::Deserialize;
+
+/// Defaults declared in template front matter
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+pub struct FrontMatter {
+    pub model: Option<String>,
+    pub reasoning: Option<ReasoningLevel>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+}
+
+/// Keys FrontMatter understands; `trickery lint` warns about others
+pub const FRONT_MATTER_KEYS: &[&str] = &["model", "reasoning", "temperature", "max_tokens"];
+
+/// Split a `---` delimited block from the start of `raw`: (YAML, body)
+fn split_front_matter(raw: &str) -> Option<(&str, &str)> {
+    let rest = raw
+        .strip_prefix("---\n")
+        .or_else(|| raw.strip_prefix("---\r\n"))?;
+
+    // Find closing delimiter on its own line
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Split optional front matter from template body.
+/// Without a complete `---` ... `---` block the whole input is returned as body.
+pub fn parse_front_matter(raw: &str) -> Result<(FrontMatter, String), String> {
+    let Some((yaml, body)) = split_front_matter(raw) else {
+        return Ok((FrontMatter::default(), raw.to_string()));
+    };
+    let front_matter = if yaml.trim().is_empty() {
+        FrontMatter::default()
+    } else {
+        serde_yaml::from_str(yaml)
+            .map_err(|e| format!("Invalid front matter in template: {}", e))?
+    };
+    Ok((front_matter, body.to_string()))
+}
+
+/// Front matter keys of `raw` that FrontMatter ignores, in document order
+pub fn unknown_front_matter_keys(raw: &str) -> Vec<String> {
+    let Some((yaml, _)) = split_front_matter(raw) else {
+        return Vec::new();
+    };
+    let Ok(serde_yaml::Value::Mapping(mapping)) = serde_yaml::from_str(yaml) else {
+        return Vec::new();
+    };
+    mapping
+        .keys()
+        .map(|key| match key {
+            serde_yaml::Value::String(key) => key.clone(),
+            other => serde_yaml::to_string(other)
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+        })
+        .filter(|key| !FRONT_MATTER_KEYS.contains(&key.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_front_matter_present() {
+        let raw = "---\nmodel: o3-mini\nreasoning: high\ntemperature: 0.2\nmax_tokens: 500\n---\nHello {{ name }}\n";
+        let (fm, body) = parse_front_matter(raw).unwrap();
+        assert_eq!(fm.model, Some("o3-mini".to_string()));
+        assert_eq!(fm.reasoning, Some(ReasoningLevel::High));
+        assert_eq!(fm.temperature, Some(0.2));
+        assert_eq!(fm.max_tokens, Some(500));
+        assert_eq!(body, "Hello {{ name }}\n");
+    }
+
+    #[test]
+    fn test_parse_front_matter_absent() {
+        let raw = "Hello world\n---\nnot front matter\n";
+        let (fm, body) = parse_front_matter(raw).unwrap();
+        assert_eq!(fm, FrontMatter::default());
+        assert_eq!(body, raw);
+    }
+
+    #[test]
+    fn test_parse_front_matter_unclosed_is_body() {
+        let raw = "---\nmodel: gpt-5.2\nHello";
+        let (fm, body) = parse_front_matter(raw).unwrap();
+        assert_eq!(fm, FrontMatter::default());
+        assert_eq!(body, raw);
+    }
+
+    #[test]
+    fn test_parse_front_matter_malformed() {
+        let raw = "---\nmodel: [unclosed\n---\nHello";
+        let err = parse_front_matter(raw).unwrap_err();
+        assert!(err.contains("Invalid front matter"));
+    }
+
+    #[test]
+    fn test_parse_front_matter_invalid_reasoning() {
+        let raw = "---\nreasoning: extreme\n---\nHello";
+        assert!(parse_front_matter(raw).is_err());
+    }
+
+    #[test]
+    fn test_parse_front_matter_crlf_and_unknown_keys() {
+        let raw = "---\r\nmodel: gpt-5.2\r\nauthor: me\r\n---\r\nHello";
+        let (fm, body) = parse_front_matter(raw).unwrap();
+        assert_eq!(fm.model, Some("gpt-5.2".to_string()));
+        assert_eq!(body, "Hello");
+    }
+
+    #[test]
+    fn test_unknown_front_matter_keys() {
+        let raw = "---\nmodle: gpt-5.2\nreasoning: high\nauthor: me\n---\nHello";
+        assert_eq!(unknown_front_matter_keys(raw), vec!["modle", "author"]);
+        assert!(unknown_front_matter_keys("Hello").is_empty());
+        assert!(unknown_front_matter_keys("---\n---\nHello").is_empty());
+    }
+}
@@ -1,15 +1,22 @@
 use crate::commands::image::ImageResult;
-use crate::provider::openai::OpenAIProvider;
+use crate::provider::retry::{with_retries, RetryPolicy};
 use crate::provider::{
-    ImageAction, ImageBackground, ImageFormat, ImageGenerationOptions, ImageQuality, ImageSize,
-    ResponsesRequest,
+    CompletionRequest, ImageAction, ImageBackground, ImageFormat, ImageGenerationOptions,
+    ImageQuality, ImageSize, Message, Provider, ProviderError, ResponsesRequest,
 };
+use crate::tools::ToolRegistry;
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use serde_json::Value;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use tracing::debug;
 
-use super::generate::substitute_variables;
+use super::generate::{substitute_variables, SubstitutionMode};
+use super::image_input::image_to_url;
+use super::r#loop::{AgentLoop, LoopConfig, LoopError, DEFAULT_MAX_ITERATIONS};
+
+/// System prompt for rewriting an image prompt with tools before generation
+const ENHANCE_SYSTEM_PROMPT: &str = "You prepare prompts for an image generation model. Use the available tools to look up anything the prompt depends on, then reply with only the final image prompt.";
 
 /// Configuration for image generation
 #[derive(Debug, Clone, Default)]
@@ -22,47 +29,52 @@ pub struct ImageConfig {
     pub background: Option<ImageBackground>,
     pub action: Option<ImageAction>,
     pub compression: Option<u8>,
+    /// Number of images to generate (default: 1)
+    pub count: Option<u32>,
+    /// Return base64 image data in results
+    pub include_data: bool,
+    /// Treatment of template variables without value or default
+    pub substitution_mode: SubstitutionMode,
+    /// Retries of rate-limited or failed image requests
+    pub retry: RetryPolicy,
+    /// Built-in tools the prompt is enhanced with before generation (--enhance-tools)
+    pub tool_names: Option<Vec<String>>,
+    /// Agent loop iterations for prompt enhancement (default: 10)
+    pub max_iterations: Option<u32>,
 }
 
-/// Convert an image path or URL to a format suitable for the API.
-/// Local files are converted to base64 data URLs.
-/// URLs starting with http:// or https:// are passed through unchanged.
-fn image_to_url(image_path: &str) -> Result<String, Box<dyn std::error::Error>> {
-    // If it's already a URL, return as-is
-    if image_path.starts_with("http://") || image_path.starts_with("https://") {
-        return Ok(image_path.to_string());
-    }
-
-    // It's a local file path - read and encode as base64
-    let path = Path::new(image_path);
-    let data = std::fs::read(path)
-        .map_err(|e| format!("Failed to read image file '{}': {}", image_path, e))?;
-
-    // Detect MIME type from extension
-    let mime_type = match path.extension().and_then(|e| e.to_str()) {
-        Some("png") => "image/png",
-        Some("jpg") | Some("jpeg") => "image/jpeg",
-        Some("gif") => "image/gif",
-        Some("webp") => "image/webp",
-        _ => "image/png", // Default to PNG
+/// Path of the `index`-th (1-based) image in a batch: `images/cat-x1y2z.png` -> `images/cat-x1y2z-2.png`
+pub fn numbered_output_path(path: &Path, index: u32) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, index, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, index),
     };
-
-    let encoded = BASE64.encode(&data);
-    Ok(format!("data:{};base64,{}", mime_type, encoded))
+    path.with_file_name(name)
 }
 
-/// Generate image from template with variable substitution.
+/// Generate images from template with variable substitution.
+/// A single image is written to `output_path`; with `count` > 1 each image is written
+/// to a numbered path (see `numbered_output_path`). Without `output_path` nothing is
+/// written (use `include_data` to get the images back).
 pub async fn generate_image(
+    provider: &dyn Provider,
     template: &str,
     input_variables: &HashMap<String, Value>,
     config: ImageConfig,
-    output_path: &Path,
-) -> Result<ImageResult, Box<dyn std::error::Error>> {
-    // Substitute template variables
-    let prompt = substitute_variables(template, input_variables);
+    output_path: Option<&Path>,
+) -> Result<Vec<ImageResult>, Box<dyn std::error::Error>> {
+    let count = config.count.unwrap_or(1).max(1);
 
-    // Create provider
-    let provider = OpenAIProvider::from_env()?;
+    // Substitute template variables
+    let mut prompt = substitute_variables(template, input_variables, config.substitution_mode)?;
+    if let Some(tool_names) = config.tool_names.as_ref().filter(|n| !n.is_empty()) {
+        prompt =
+            process_prompt_with_tools(provider, &prompt, tool_names, config.max_iterations).await?;
+    }
 
     // Convert input images to URLs (base64 for local files)
     let input_images = if let Some(ref images) = config.input_images {
@@ -86,7 +98,7 @@ pub async fn generate_image(
     };
 
     // Build request
-    let mut request = ResponsesRequest::new(prompt).with_options(options);
+    let mut request = ResponsesRequest::new(prompt.clone()).with_options(options);
 
     if let Some(model) = config.model {
         request = request.with_model(model);
@@ -96,79 +108,456 @@ pub async fn generate_image(
         request = request.with_images(images);
     }
 
-    // Make API call
-    let response = provider.create_response(request).await?;
+    // The image_generation tool returns one image per call, so request until we have enough.
+    // Each response's usage is reported on the first image it produced.
+    let mut generated = Vec::new();
+    while generated.len() < count as usize {
+        let response =
+            with_retries(&config.retry, || provider.create_response(request.clone())).await?;
+        if response.images.is_empty() {
+            return Err(ProviderError::InvalidResponse(
+                "No image generated in response".to_string(),
+            )
+            .into());
+        }
+        let mut usage = response.usage.filter(|u| u.total_tokens > 0);
+        for image in response.images {
+            generated.push((image, usage.take()));
+        }
+    }
 
-    // Get first image result
-    let image_result = response
-        .images
-        .into_iter()
-        .next()
-        .ok_or("No image generated in response")?;
+    let mut results = Vec::new();
+    for (index, (image_result, usage)) in generated.into_iter().take(count as usize).enumerate() {
+        // Decode base64 (also validates data returned as-is) and save to file
+        let image_data = BASE64
+            .decode(&image_result.result)
+            .map_err(|e| format!("Failed to decode image data: {}", e))?;
 
-    // Decode base64 and save to file
-    let image_data = BASE64
-        .decode(&image_result.result)
-        .map_err(|e| format!("Failed to decode image data: {}", e))?;
+        let path = output_path.map(|path| {
+            if count == 1 {
+                path.to_path_buf()
+            } else {
+                numbered_output_path(path, index as u32 + 1)
+            }
+        });
+        if let Some(ref path) = path {
+            std::fs::write(path, &image_data)
+                .map_err(|e| format!("Failed to write image to '{}': {}", path.display(), e))?;
+        }
 
-    std::fs::write(output_path, &image_data).map_err(|e| {
-        format!(
-            "Failed to write image to '{}': {}",
-            output_path.display(),
-            e
-        )
-    })?;
+        results.push(ImageResult {
+            output_path: path.map(|path| path.display().to_string()),
+            final_prompt: Some(prompt.clone()),
+            revised_prompt: image_result.revised_prompt,
+            data: config.include_data.then_some(image_result.result),
+            usage,
+            dry_run: false,
+        });
+    }
+
+    Ok(results)
+}
 
-    Ok(ImageResult {
-        output_path: output_path.display().to_string(),
-        revised_prompt: image_result.revised_prompt,
-    })
+/// Rewrite `prompt` in an agent loop with the named built-in tools (chat completions with
+/// the provider's default model). The model's final answer becomes the image prompt; an
+/// empty answer keeps the original.
+pub async fn process_prompt_with_tools(
+    provider: &dyn Provider,
+    prompt: &str,
+    tool_names: &[String],
+    max_iterations: Option<u32>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let registry = ToolRegistry::with_builtins().select(tool_names)?;
+    let request = CompletionRequest::new(vec![
+        Message::system(ENHANCE_SYSTEM_PROMPT),
+        Message::user(prompt),
+    ]);
+    let config = LoopConfig {
+        max_iterations: max_iterations.unwrap_or(DEFAULT_MAX_ITERATIONS),
+        ..Default::default()
+    };
+    let result = match AgentLoop::new(provider, &registry, config)
+        .run(request)
+        .await
+    {
+        Ok(result) => result,
+        // Unwrap so provider errors get their recovery hints
+        Err(LoopError::Provider(e)) => return Err(e.into()),
+        Err(e) => return Err(e.into()),
+    };
+    let enhanced = result.content.trim();
+    if enhanced.is_empty() {
+        debug!("prompt enhancement returned no text, keeping the original prompt");
+        return Ok(prompt.to_string());
+    }
+    debug!(
+        iterations = result.iterations,
+        tool_calls = result.tool_calls_executed.len(),
+        "enhanced image prompt"
+    );
+    Ok(enhanced.to_string())
+}
+
+/// Lowercase name of a serializable option (as sent to the API)
+fn option_label<T: serde::Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(Value::String(s)) => s,
+        Ok(other) => other.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+/// Human-readable summary of an image request (settings, then the prompt), for --dry-run
+pub fn describe_image_request(
+    prompt: &str,
+    config: &ImageConfig,
+    output_path: Option<&Path>,
+) -> String {
+    let mut lines = vec![format!(
+        "model: {}",
+        config.model.as_deref().unwrap_or("(provider default)")
+    )];
+    if let Some(ref size) = config.size {
+        lines.push(format!("size: {}", size));
+    }
+    if let Some(ref quality) = config.quality {
+        lines.push(format!("quality: {}", option_label(quality)));
+    }
+    if let Some(ref format) = config.output_format {
+        lines.push(format!("format: {}", option_label(format)));
+    }
+    if let Some(ref background) = config.background {
+        lines.push(format!("background: {}", option_label(background)));
+    }
+    if let Some(ref action) = config.action {
+        lines.push(format!("action: {}", option_label(action)));
+    }
+    if let Some(compression) = config.compression {
+        lines.push(format!("compression: {}", compression));
+    }
+    if let Some(ref images) = config.input_images {
+        lines.push(format!("images: {}", images.join(", ")));
+    }
+    if let Some(count) = config.count.filter(|&count| count > 1) {
+        lines.push(format!("count: {}", count));
+    }
+    if let Some(tool_names) = config.tool_names.as_ref().filter(|n| !n.is_empty()) {
+        lines.push(format!("enhance tools: {}", tool_names.join(", ")));
+    }
+    let output = match output_path {
+        Some(path) => match config.count.filter(|&count| count > 1) {
+            Some(count) => (1..=count)
+                .map(|index| numbered_output_path(path, index).display().to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            None => path.display().to_string(),
+        },
+        None => "(none, base64 data only)".to_string(),
+    };
+    lines.push(format!("output: {}", output));
+    lines.push("---".to_string());
+    lines.push(prompt.to_string());
+    lines.join("\n")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::Write;
-    use tempfile::NamedTempFile;
 
     #[test]
-    fn test_image_to_url_http_passthrough() {
-        let url = "http://example.com/image.png";
-        let result = image_to_url(url).unwrap();
-        assert_eq!(result, url);
+    fn test_describe_image_request() {
+        let config = ImageConfig {
+            model: Some("gpt-5".to_string()),
+            size: Some(ImageSize::Landscape),
+            quality: Some(ImageQuality::High),
+            ..Default::default()
+        };
+        let text = describe_image_request("A red fox", &config, Some(Path::new("fox.png")));
+        assert_eq!(
+            text,
+            "model: gpt-5\nsize: 1536x1024\nquality: high\noutput: fox.png\n---\nA red fox"
+        );
     }
 
     #[test]
-    fn test_image_to_url_https_passthrough() {
-        let url = "https://example.com/path/to/image.jpg";
-        let result = image_to_url(url).unwrap();
-        assert_eq!(result, url);
+    fn test_numbered_output_path() {
+        assert_eq!(
+            numbered_output_path(Path::new("cat-x1y2z.png"), 1),
+            PathBuf::from("cat-x1y2z-1.png")
+        );
+        assert_eq!(
+            numbered_output_path(Path::new("out/banner.webp"), 12),
+            PathBuf::from("out/banner-12.webp")
+        );
+        assert_eq!(
+            numbered_output_path(Path::new("image"), 2),
+            PathBuf::from("image-2")
+        );
     }
 
-    #[test]
-    fn test_image_to_url_local_png() {
-        let mut file = NamedTempFile::with_suffix(".png").unwrap();
-        let test_data = vec![0x89, 0x50, 0x4E, 0x47]; // PNG magic bytes
-        file.write_all(&test_data).unwrap();
+    /// Provider returning one fixed image per create_response call; completions (prompt
+    /// enhancement) answer with a fixed prompt
+    #[derive(Default)]
+    struct ImageProvider {
+        calls: std::sync::Mutex<u32>,
+        completions: std::sync::Mutex<u32>,
+        /// Prompt of each image request
+        prompts: std::sync::Mutex<Vec<String>>,
+    }
 
-        let result = image_to_url(file.path().to_str().unwrap()).unwrap();
-        assert!(result.starts_with("data:image/png;base64,"));
+    #[async_trait::async_trait]
+    impl Provider for ImageProvider {
+        async fn complete(
+            &self,
+            _request: crate::provider::CompletionRequest,
+        ) -> Result<crate::provider::CompletionResponse, crate::provider::ProviderError> {
+            *self.completions.lock().unwrap() += 1;
+            Ok(crate::trickery::r#loop::tests::text_response(
+                " A red fox in fresh snow at dawn ",
+            ))
+        }
+
+        async fn create_response(
+            &self,
+            request: ResponsesRequest,
+        ) -> Result<crate::provider::ResponsesResponse, crate::provider::ProviderError> {
+            self.prompts.lock().unwrap().push(request.input);
+            let mut calls = self.calls.lock().unwrap();
+            *calls += 1;
+            Ok(crate::provider::ResponsesResponse {
+                id: format!("resp_{}", calls),
+                images: vec![crate::provider::ImageGenerationResult {
+                    id: format!("ig_{}", calls),
+                    result: BASE64.encode(format!("image {}", calls)),
+                    revised_prompt: Some(format!("Revised {}", calls)),
+                }],
+                usage: None,
+            })
+        }
     }
 
-    #[test]
-    fn test_image_to_url_local_jpeg() {
-        let mut file = NamedTempFile::with_suffix(".jpg").unwrap();
-        let test_data = vec![0xFF, 0xD8, 0xFF]; // JPEG magic bytes
-        file.write_all(&test_data).unwrap();
+    #[tokio::test]
+    async fn test_generate_image_single_uses_output_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("cat.png");
+        let provider = ImageProvider::default();
 
-        let result = image_to_url(file.path().to_str().unwrap()).unwrap();
-        assert!(result.starts_with("data:image/jpeg;base64,"));
+        let results = generate_image(
+            &provider,
+            "A cat",
+            &HashMap::new(),
+            ImageConfig::default(),
+            Some(&output),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].output_path, Some(output.display().to_string()));
+        assert!(results[0].data.is_none());
+        assert_eq!(std::fs::read(&output).unwrap(), b"image 1");
+        assert_eq!(*provider.calls.lock().unwrap(), 1);
     }
 
-    #[test]
-    fn test_image_to_url_nonexistent_file() {
-        let result = image_to_url("/nonexistent/path/to/image.png");
-        assert!(result.is_err());
+    #[tokio::test]
+    async fn test_final_prompt_is_substituted_prompt() {
+        let provider = ImageProvider::default();
+        let mut vars = HashMap::new();
+        vars.insert("animal".to_string(), Value::String("owl".to_string()));
+        let config = ImageConfig {
+            count: Some(2),
+            ..Default::default()
+        };
+
+        let results = generate_image(&provider, "A {{ animal }} at night", &vars, config, None)
+            .await
+            .unwrap();
+
+        for result in &results {
+            assert_eq!(result.final_prompt.as_deref(), Some("A owl at night"));
+        }
+        assert_eq!(*provider.completions.lock().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_enhance_tools_rewrite_prompt_before_generation() {
+        let provider = ImageProvider::default();
+        let config = ImageConfig {
+            tool_names: Some(vec!["current_time".to_string()]),
+            max_iterations: Some(2),
+            ..Default::default()
+        };
+        let results = generate_image(
+            &provider,
+            "A fox in {{ season }}",
+            &HashMap::new(),
+            config,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(*provider.completions.lock().unwrap(), 1);
+        assert_eq!(
+            results[0].final_prompt.as_deref(),
+            Some("A red fox in fresh snow at dawn")
+        );
+        assert_eq!(
+            *provider.prompts.lock().unwrap(),
+            ["A red fox in fresh snow at dawn"]
+        );
+
+        // Without enhance tools the substituted prompt goes straight to the image model
+        let provider = ImageProvider::default();
+        generate_image(
+            &provider,
+            "A fox",
+            &HashMap::new(),
+            ImageConfig::default(),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(*provider.completions.lock().unwrap(), 0);
+        assert_eq!(*provider.prompts.lock().unwrap(), ["A fox"]);
+    }
+
+    #[tokio::test]
+    async fn test_enhance_tools_reject_unknown_tool() {
+        let provider = ImageProvider::default();
+        let err = process_prompt_with_tools(&provider, "A fox", &["paint".to_string()], None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("paint"), "{}", err);
+        assert_eq!(*provider.completions.lock().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_generate_image_count_writes_numbered_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("cat-abcde.png");
+        let provider = ImageProvider::default();
+        let config = ImageConfig {
+            count: Some(3),
+            ..Default::default()
+        };
+
+        let results = generate_image(&provider, "A cat", &HashMap::new(), config, Some(&output))
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert!(!output.exists());
+        for (i, result) in results.iter().enumerate() {
+            let path = dir.path().join(format!("cat-abcde-{}.png", i + 1));
+            assert_eq!(result.output_path, Some(path.display().to_string()));
+            assert_eq!(
+                std::fs::read(&path).unwrap(),
+                format!("image {}", i + 1).as_bytes()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_image_data_without_file() {
+        let provider = ImageProvider::default();
+        let config = ImageConfig {
+            include_data: true,
+            ..Default::default()
+        };
+
+        let results = generate_image(&provider, "A cat", &HashMap::new(), config, None)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].output_path.is_none());
+        assert_eq!(results[0].data, Some(BASE64.encode("image 1")));
+    }
+
+    #[tokio::test]
+    async fn test_generate_image_data_with_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("cat.png");
+        let provider = ImageProvider::default();
+        let config = ImageConfig {
+            include_data: true,
+            ..Default::default()
+        };
+
+        let results = generate_image(&provider, "A cat", &HashMap::new(), config, Some(&output))
+            .await
+            .unwrap();
+
+        assert_eq!(results[0].data, Some(BASE64.encode("image 1")));
+        assert_eq!(std::fs::read(&output).unwrap(), b"image 1");
+    }
+
+    fn no_delay() -> ImageConfig {
+        ImageConfig {
+            include_data: true,
+            retry: RetryPolicy {
+                base_delay: std::time::Duration::ZERO,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_image_retries_transient_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let rate_limited = server
+            .mock("POST", "/responses")
+            .with_status(429)
+            .with_body(
+                r#"{"error": {"message": "Rate limit reached", "code": "rate_limit_exceeded"}}"#,
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let ok = server
+            .mock("POST", "/responses")
+            .with_body(
+                r#"{"id": "resp_1", "output": [{"type": "image_generation_call", "id": "ig_1", "result": "aGk="}]}"#,
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let provider = crate::provider::openai::OpenAIProvider::new(
+            "test-key".to_string(),
+            Some(server.url()),
+        );
+
+        let results = generate_image(&provider, "A cat", &HashMap::new(), no_delay(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(results[0].data.as_deref(), Some("aGk="));
+        rate_limited.assert_async().await;
+        ok.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_generate_image_empty_response_is_invalid() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/responses")
+            .with_body(r#"{"id": "resp_1", "output": [{"type": "message", "id": "msg_1"}]}"#)
+            .create_async()
+            .await;
+        let provider = crate::provider::openai::OpenAIProvider::new(
+            "test-key".to_string(),
+            Some(server.url()),
+        );
+
+        let err = generate_image(&provider, "A cat", &HashMap::new(), no_delay(), None)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<ProviderError>(),
+            Some(ProviderError::InvalidResponse(_))
+        ));
     }
 
     #[test]
@@ -0,0 +1,169 @@
+// Input images for generate --image and image --image.
+// Local files become base64 data URLs; the MIME type comes from the file content
+// (magic bytes), falling back to the extension. Files that are neither are rejected.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::path::Path;
+
+/// MIME type from the leading bytes of an image (PNG, JPEG, GIF, WEBP)
+fn sniff_mime_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+/// MIME type from a known image file extension
+fn extension_mime_type(path: &Path) -> Option<&'static str> {
+    match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .as_deref()
+    {
+        Some("png") => Some("image/png"),
+        Some("jpg") | Some("jpeg") => Some("image/jpeg"),
+        Some("gif") => Some("image/gif"),
+        Some("webp") => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// Convert an image path or URL to a format suitable for the API.
+/// Local files are converted to base64 data URLs.
+/// URLs starting with http:// or https:// are passed through unchanged.
+pub fn image_to_url(image_path: &str) -> Result<String, Box<dyn std::error::Error>> {
+    // If it's already a URL, return as-is
+    if image_path.starts_with("http://") || image_path.starts_with("https://") {
+        return Ok(image_path.to_string());
+    }
+
+    // It's a local file path - read and encode as base64
+    let path = Path::new(image_path);
+    let data = std::fs::read(path)
+        .map_err(|e| format!("Failed to read image file '{}': {}", image_path, e))?;
+
+    // Content wins over a mislabeled extension
+    let mime_type = sniff_mime_type(&data)
+        .or_else(|| extension_mime_type(path))
+        .ok_or_else(|| {
+            format!(
+                "'{}' is not a recognizable image (expected PNG, JPEG, GIF or WEBP)",
+                image_path
+            )
+        })?;
+
+    let encoded = BASE64.encode(&data);
+    Ok(format!("data:{};base64,{}", mime_type, encoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+    const JPEG: &[u8] = &[0xFF, 0xD8, 0xFF, 0xE0];
+    const GIF: &[u8] = b"GIF89a";
+    const WEBP: &[u8] = b"RIFF\x24\0\0\0WEBPVP8 ";
+
+    fn temp_image(suffix: &str, data: &[u8]) -> NamedTempFile {
+        let mut file = NamedTempFile::with_suffix(suffix).unwrap();
+        file.write_all(data).unwrap();
+        file
+    }
+
+    fn url_for(file: &NamedTempFile) -> Result<String, Box<dyn std::error::Error>> {
+        image_to_url(file.path().to_str().unwrap())
+    }
+
+    #[test]
+    fn test_image_to_url_http_passthrough() {
+        let url = "http://example.com/image.png";
+        let result = image_to_url(url).unwrap();
+        assert_eq!(result, url);
+    }
+
+    #[test]
+    fn test_image_to_url_https_passthrough() {
+        let url = "https://example.com/path/to/image.jpg";
+        let result = image_to_url(url).unwrap();
+        assert_eq!(result, url);
+    }
+
+    #[test]
+    fn test_image_to_url_local_png() {
+        let file = temp_image(".png", PNG);
+        let result = url_for(&file).unwrap();
+        assert!(result.starts_with("data:image/png;base64,"));
+        // Verify the base64 content decodes correctly
+        let base64_part = result.strip_prefix("data:image/png;base64,").unwrap();
+        let decoded = BASE64.decode(base64_part).unwrap();
+        assert_eq!(decoded, PNG);
+    }
+
+    #[test]
+    fn test_image_to_url_sniffs_correctly_named_files() {
+        for (suffix, data, mime) in [
+            (".jpg", JPEG, "image/jpeg"),
+            (".jpeg", JPEG, "image/jpeg"),
+            (".gif", GIF, "image/gif"),
+            (".webp", WEBP, "image/webp"),
+        ] {
+            let result = url_for(&temp_image(suffix, data)).unwrap();
+            assert!(
+                result.starts_with(&format!("data:{};base64,", mime)),
+                "{} should be {}",
+                suffix,
+                mime
+            );
+        }
+    }
+
+    #[test]
+    fn test_image_to_url_content_overrides_extension() {
+        // A JPEG saved as .png, and images without an extension
+        let result = url_for(&temp_image(".png", JPEG)).unwrap();
+        assert!(result.starts_with("data:image/jpeg;base64,"));
+        let result = url_for(&temp_image(".webp", GIF)).unwrap();
+        assert!(result.starts_with("data:image/gif;base64,"));
+        let result = url_for(&temp_image("", WEBP)).unwrap();
+        assert!(result.starts_with("data:image/webp;base64,"));
+    }
+
+    #[test]
+    fn test_image_to_url_falls_back_to_extension() {
+        // Truncated header, not sniffable, but named like an image
+        let result = url_for(&temp_image(".PNG", &[0x89, 0x50, 0x4E, 0x47])).unwrap();
+        assert!(result.starts_with("data:image/png;base64,"));
+    }
+
+    #[test]
+    fn test_image_to_url_rejects_non_image() {
+        let err = url_for(&temp_image(".unknown", &[0x00, 0x01, 0x02]))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("not a recognizable image"), "{}", err);
+        assert!(url_for(&temp_image(".txt", b"hello")).is_err());
+    }
+
+    #[test]
+    fn test_image_to_url_nonexistent_file() {
+        let result = image_to_url("/nonexistent/path/to/image.png");
+        assert!(result.is_err());
+        let err = result.unwrap_err().to_string();
+        assert!(
+            err.contains("/nonexistent/path/to/image.png"),
+            "Error should contain file path: {}",
+            err
+        );
+    }
+}
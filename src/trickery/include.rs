@@ -0,0 +1,189 @@
+// Template includes: `{{include "path"}}` splices another file into a template.
+// Expanded right after loading, before front matter parsing and variable substitution,
+// so fragments can use template variables. Paths are relative to the including file's
+// directory (the current directory for direct text and stdin). One trailing newline of
+// a fragment is dropped so a directive on its own line doesn't add a blank line.
+
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Maximum nesting of included files
+pub const MAX_INCLUDE_DEPTH: usize = 10;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum IncludeError {
+    #[error("Failed to read included file '{path}': {message}")]
+    Read { path: String, message: String },
+    #[error("Include cycle: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+    #[error("Includes nested deeper than {MAX_INCLUDE_DEPTH} levels at '{0}'")]
+    TooDeep(String),
+}
+
+/// Replace every `{{include "path"}}` in `raw` with the (recursively expanded) file content.
+/// Relative paths resolve against `base_dir`.
+pub fn expand_includes(raw: &str, base_dir: &Path) -> Result<String, IncludeError> {
+    expand(raw, base_dir, &mut Vec::new())
+}
+
+/// Whether `raw` contains an include directive
+pub fn has_includes(raw: &str) -> bool {
+    raw.match_indices("{{")
+        .any(|(start, _)| parse_directive(&raw[start..]).is_some())
+}
+
+fn expand(raw: &str, base_dir: &Path, stack: &mut Vec<PathBuf>) -> Result<String, IncludeError> {
+    let mut output = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        let Some((include, len)) = parse_directive(tail) else {
+            output.push_str("{{");
+            rest = &tail[2..];
+            continue;
+        };
+
+        let path = base_dir.join(include);
+        let path = path.canonicalize().map_err(|e| IncludeError::Read {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })?;
+        if let Some(pos) = stack.iter().position(|p| p == &path) {
+            let mut chain: Vec<String> = stack[pos..]
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect();
+            chain.push(path.display().to_string());
+            return Err(IncludeError::Cycle(chain));
+        }
+        if stack.len() >= MAX_INCLUDE_DEPTH {
+            return Err(IncludeError::TooDeep(path.display().to_string()));
+        }
+
+        let content = std::fs::read_to_string(&path).map_err(|e| IncludeError::Read {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })?;
+        let content = content.strip_suffix('\n').unwrap_or(&content);
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        stack.push(path);
+        output.push_str(&expand(content, &dir, stack)?);
+        stack.pop();
+
+        rest = &tail[len..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Parse an include directive at the start of `text`: (path, directive length in bytes)
+fn parse_directive(text: &str) -> Option<(&str, usize)> {
+    let end = text.find("}}")?;
+    let argument = text[2..end].trim().strip_prefix("include")?;
+    if !argument.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let path = argument.trim().strip_prefix('"')?.strip_suffix('"')?;
+    if path.is_empty() || path.contains('"') {
+        return None;
+    }
+    Some((path, end + 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_parse_directive() {
+        assert_eq!(
+            parse_directive(r#"{{include "style.md"}} rest"#),
+            Some(("style.md", 22))
+        );
+        assert_eq!(
+            parse_directive(r#"{{ include "a b.md" }}"#),
+            Some(("a b.md", 22))
+        );
+        assert_eq!(parse_directive("{{ name }}"), None);
+        assert_eq!(parse_directive("{{ included }}"), None);
+        assert_eq!(parse_directive(r#"{{include ""}}"#), None);
+        assert_eq!(parse_directive(r#"{{include "x.md""#), None);
+    }
+
+    #[test]
+    fn test_has_includes() {
+        assert!(has_includes(r#"Intro {{ name }} {{include "style.md"}}"#));
+        assert!(!has_includes("{{ name }} and {{ included }}"));
+    }
+
+    #[test]
+    fn test_expand_without_includes_is_unchanged() {
+        let raw = "Hello {{ name }}! {{ greeting|Hi }}";
+        assert_eq!(expand_includes(raw, Path::new(".")).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_expand_nested_relative_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("fragments")).unwrap();
+        fs::write(
+            dir.path().join("fragments/persona.md"),
+            "You are {{ persona }}.\n{{include \"style.md\"}}\n",
+        )
+        .unwrap();
+        // Resolved relative to fragments/, not the top-level directory
+        fs::write(dir.path().join("fragments/style.md"), "Be concise.\n").unwrap();
+
+        let raw = "{{include \"fragments/persona.md\"}}\n\nTask: {{ task }}";
+        let expanded = expand_includes(raw, dir.path()).unwrap();
+        assert_eq!(
+            expanded,
+            "You are {{ persona }}.\nBe concise.\n\nTask: {{ task }}"
+        );
+    }
+
+    #[test]
+    fn test_expand_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = expand_includes("{{include \"missing.md\"}}", dir.path()).unwrap_err();
+        assert!(
+            matches!(err, IncludeError::Read { ref path, .. } if path.ends_with("missing.md")),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_expand_detects_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.md"), "A {{include \"b.md\"}}").unwrap();
+        fs::write(dir.path().join("b.md"), "B {{include \"./a.md\"}}").unwrap();
+
+        let err = expand_includes("{{include \"a.md\"}}", dir.path()).unwrap_err();
+        match err {
+            IncludeError::Cycle(chain) => {
+                assert_eq!(chain.len(), 3);
+                assert!(chain[0].ends_with("a.md"));
+                assert!(chain[1].ends_with("b.md"));
+                assert!(chain[2].ends_with("a.md"));
+            }
+            other => panic!("Expected cycle, got {}", other),
+        }
+    }
+
+    #[test]
+    fn test_expand_max_depth() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..=MAX_INCLUDE_DEPTH {
+            fs::write(
+                dir.path().join(format!("{}.md", i)),
+                format!("{{{{include \"{}.md\"}}}}", i + 1),
+            )
+            .unwrap();
+        }
+        let err = expand_includes("{{include \"0.md\"}}", dir.path()).unwrap_err();
+        assert!(matches!(err, IncludeError::TooDeep(_)), "{}", err);
+    }
+}
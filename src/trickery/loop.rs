@@ -0,0 +1,883 @@
+// Native agent loop: completion -> tool calls -> tool results -> completion, until the
+// model answers without tool calls or the iteration budget runs out.
+// Tool failures are reported back to the model as tool results (it can retry or recover);
+// only provider errors abort the loop. Running out of iterations returns the partial
+// result (stopped_early) unless error_on_max_iterations asks for a hard error.
+// Token usage is summed over all completions; once it reaches token_budget no further
+// completion is requested and the current content is returned (budget_exhausted).
+// Each executed tool call is also passed to an optional callback as it happens (--trace).
+// Tool calls from one response run concurrently (joined futures); results are
+// appended in the order the model requested them so each tool_call_id lines up.
+// Cancelling the config's token aborts the in-flight completion and stops the loop before
+// the next iteration with ProviderError::Cancelled.
+// With context_limit set, the oldest non-system messages are dropped before each completion
+// until the history fits (a character count stands in for tokens); an assistant tool-call
+// message goes together with its tool results so every tool_call_id stays paired.
+// A tool_choice that forces a call (required or a named function) is sent for the first
+// round only; later requests use auto so the model can give its final answer.
+// With a stream callback, completions are streamed: text deltas and each tool call (as soon
+// as its fragments are complete) reach the callback before the response is finished.
+// With tools_disabled (--no-tools) no tools or tool_choice are sent and tool calls in a
+// response are ignored: the first response is the answer, whatever the registry holds.
+
+use crate::provider::{
+    CompletionRequest, CompletionResponse, ContentPart, Message, Provider, ProviderError, Role,
+    StreamEvent, ToolChoice, Usage,
+};
+use crate::tools::ToolRegistry;
+use futures::future::join_all;
+use serde::Serialize;
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+/// Iteration budget used when --max-iterations is not given
+pub const DEFAULT_MAX_ITERATIONS: u32 = 10;
+
+#[derive(Error, Debug)]
+pub enum LoopError {
+    #[error(transparent)]
+    Provider(#[from] ProviderError),
+    #[error(
+        "Agent loop stopped after {0} iterations without a final answer (raise --max-iterations)"
+    )]
+    MaxIterationsExceeded(u32),
+}
+
+/// Agent loop settings
+#[derive(Debug, Clone)]
+pub struct LoopConfig {
+    /// Maximum number of completion requests in one run
+    pub max_iterations: u32,
+    /// Fail with MaxIterationsExceeded instead of returning a partial result
+    pub error_on_max_iterations: bool,
+    /// Stop once total tokens across completions reach this many
+    pub token_budget: Option<u32>,
+    /// Cancelled on Ctrl-C; the loop stops before its next completion
+    pub cancellation: CancellationToken,
+    /// Keep the message history under this many characters by dropping the oldest messages
+    pub context_limit: Option<usize>,
+    /// Send no tools and never execute tool calls (--no-tools)
+    pub tools_disabled: bool,
+}
+
+impl Default for LoopConfig {
+    fn default() -> Self {
+        Self {
+            max_iterations: DEFAULT_MAX_ITERATIONS,
+            error_on_max_iterations: false,
+            token_budget: None,
+            cancellation: CancellationToken::new(),
+            context_limit: None,
+            tools_disabled: false,
+        }
+    }
+}
+
+/// A tool call requested by the model and its outcome
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ExecutedToolCall {
+    /// Loop iteration (1-based) in which the model requested the call
+    pub iteration: u32,
+    pub id: String,
+    pub name: String,
+    pub arguments: String,
+    pub output: String,
+    pub is_error: bool,
+}
+
+/// Outcome of a completed loop run
+#[derive(Debug, Clone)]
+#[allow(dead_code)] // Fields are part of public API
+pub struct LoopResult {
+    /// Final answer from the model
+    pub content: String,
+    /// Tool calls in execution order
+    pub tool_calls_executed: Vec<ExecutedToolCall>,
+    /// Number of completion requests made
+    pub iterations: u32,
+    /// Iteration budget ran out before the model gave a final answer;
+    /// `content` is the last text the model produced, if any
+    pub stopped_early: bool,
+    /// Token budget was used up before the model gave a final answer
+    pub budget_exhausted: bool,
+    /// Token usage summed over all completions
+    pub usage: Usage,
+}
+
+/// Approximate size of a message: characters of its text and tool calls
+fn message_chars(message: &Message) -> usize {
+    let text: usize = message
+        .content
+        .iter()
+        .flatten()
+        .map(|part| match part {
+            ContentPart::Text { text } => text.chars().count(),
+            ContentPart::ImageUrl { .. } => 0,
+        })
+        .sum();
+    let calls: usize = message
+        .tool_calls
+        .iter()
+        .flatten()
+        .map(|call| call.function.name.len() + call.function.arguments.chars().count())
+        .sum();
+    text + calls
+}
+
+/// Drop the oldest non-system messages until the history fits in `limit` characters.
+/// Tool results go with the message before them; the newest group is always kept.
+/// Returns the number of messages removed.
+fn trim_history(messages: &mut Vec<Message>, limit: usize) -> usize {
+    let mut removed = 0;
+    while messages.iter().map(message_chars).sum::<usize>() > limit {
+        let Some(start) = messages.iter().position(|m| !m.role.is_instruction()) else {
+            break;
+        };
+        let mut end = start + 1;
+        while end < messages.len() && messages[end].role == Role::Tool {
+            end += 1;
+        }
+        if end >= messages.len() {
+            break;
+        }
+        messages.drain(start..end);
+        removed += end - start;
+    }
+    removed
+}
+
+/// Callback invoked after each tool call
+type ToolCallCallback<'a> = Box<dyn Fn(&ExecutedToolCall) + Send + Sync + 'a>;
+
+/// Callback invoked with streamed completion output
+type StreamCallback<'a> = Box<dyn Fn(StreamEvent) + Send + Sync + 'a>;
+
+/// Runs a conversation with tool calling against a provider
+pub struct AgentLoop<'a> {
+    provider: &'a dyn Provider,
+    registry: &'a ToolRegistry,
+    config: LoopConfig,
+    on_tool_call: Option<ToolCallCallback<'a>>,
+    on_stream: Option<StreamCallback<'a>>,
+}
+
+impl<'a> AgentLoop<'a> {
+    pub fn new(provider: &'a dyn Provider, registry: &'a ToolRegistry, config: LoopConfig) -> Self {
+        Self {
+            provider,
+            registry,
+            config,
+            on_tool_call: None,
+            on_stream: None,
+        }
+    }
+
+    /// Observe each tool call right after it executes
+    pub fn on_tool_call(mut self, callback: impl Fn(&ExecutedToolCall) + Send + Sync + 'a) -> Self {
+        self.on_tool_call = Some(Box::new(callback));
+        self
+    }
+
+    /// Stream completions, passing text deltas and completed tool calls as they arrive
+    pub fn on_stream(mut self, callback: impl Fn(StreamEvent) + Send + Sync + 'a) -> Self {
+        self.on_stream = Some(Box::new(callback));
+        self
+    }
+
+    /// One completion, streamed when a stream callback is set
+    async fn complete(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, ProviderError> {
+        let cancel = &self.config.cancellation;
+        match self.on_stream {
+            Some(ref callback) => tokio::select! {
+                response = self.provider.complete_streaming(request, callback.as_ref()) => response,
+                _ = cancel.cancelled() => Err(ProviderError::Cancelled),
+            },
+            None => self.provider.complete_cancellable(request, cancel).await,
+        }
+    }
+
+    /// Run the loop starting from `request`; registry tools are attached to every request
+    pub async fn run(&self, request: CompletionRequest) -> Result<LoopResult, LoopError> {
+        // Stateful tools (scratchpad) get fresh instances that last for this run
+        let registry = self.registry.for_run();
+        let mut request = if self.config.tools_disabled {
+            if !registry.names().is_empty() {
+                warn!(tools = %registry.names().join(", "), "tools requested but disabled by --no-tools");
+            }
+            CompletionRequest {
+                tools: None,
+                tool_choice: None,
+                ..request
+            }
+        } else {
+            request.with_tools(registry.definitions())
+        };
+        let mut tool_calls_executed = Vec::new();
+        let mut last_content = None;
+        let mut usage = Usage::default();
+
+        for iteration in 1..=self.config.max_iterations {
+            if self.config.cancellation.is_cancelled() {
+                info!(iteration, "agent loop cancelled");
+                return Err(ProviderError::Cancelled.into());
+            }
+            if let Some(limit) = self.config.context_limit {
+                let removed = trim_history(&mut request.messages, limit);
+                if removed > 0 {
+                    info!(
+                        iteration,
+                        removed, limit, "trimmed oldest messages from history"
+                    );
+                }
+            }
+            debug!(
+                iteration,
+                messages = request.messages.len(),
+                "agent loop iteration"
+            );
+            let response = self.complete(request.clone()).await?;
+            usage += &response.usage;
+            response.check_refusal()?;
+
+            let tool_calls = response.tool_calls.unwrap_or_default();
+            if !tool_calls.is_empty() && self.config.tools_disabled {
+                warn!(
+                    iteration,
+                    count = tool_calls.len(),
+                    "ignoring tool calls, tools are disabled"
+                );
+            }
+            if tool_calls.is_empty() || self.config.tools_disabled {
+                debug!(iteration, "agent loop finished without tool calls");
+                return Ok(LoopResult {
+                    content: response.content.unwrap_or_default(),
+                    tool_calls_executed,
+                    iterations: iteration,
+                    stopped_early: false,
+                    budget_exhausted: false,
+                    usage,
+                });
+            }
+
+            if response.content.is_some() {
+                last_content.clone_from(&response.content);
+            }
+
+            if self
+                .config
+                .token_budget
+                .is_some_and(|budget| usage.total_tokens >= budget)
+            {
+                info!(
+                    iteration,
+                    total_tokens = usage.total_tokens,
+                    "token budget exhausted, stopping agent loop"
+                );
+                return Ok(LoopResult {
+                    content: last_content.unwrap_or_default(),
+                    tool_calls_executed,
+                    iterations: iteration,
+                    stopped_early: true,
+                    budget_exhausted: true,
+                    usage,
+                });
+            }
+            request.messages.push(Message::assistant_tool_calls(
+                response.content,
+                tool_calls.clone(),
+            ));
+
+            let names: Vec<&str> = tool_calls
+                .iter()
+                .map(|call| call.function.name.as_str())
+                .collect();
+            info!(iteration, tools = %names.join(", "), "executing tool calls");
+
+            let outputs = join_all(
+                tool_calls
+                    .iter()
+                    .map(|call| registry.execute(&call.function.name, &call.function.arguments)),
+            )
+            .await;
+
+            // A forced choice applies to the first round only, or the model could never answer
+            if request
+                .tool_choice
+                .as_ref()
+                .is_some_and(ToolChoice::forces_tool_call)
+            {
+                request.tool_choice = Some(ToolChoice::Auto);
+            }
+
+            for (call, outcome) in tool_calls.into_iter().zip(outputs) {
+                let (output, is_error) = match outcome {
+                    Ok(output) => (output, false),
+                    Err(e) => (format!("Error: {}", e), true),
+                };
+                request
+                    .messages
+                    .push(Message::tool_result(&call.id, &output));
+                let executed = ExecutedToolCall {
+                    iteration,
+                    id: call.id,
+                    name: call.function.name,
+                    arguments: call.function.arguments,
+                    output,
+                    is_error,
+                };
+                if let Some(ref callback) = self.on_tool_call {
+                    callback(&executed);
+                }
+                tool_calls_executed.push(executed);
+            }
+        }
+
+        info!(
+            max_iterations = self.config.max_iterations,
+            "agent loop reached max iterations"
+        );
+        if self.config.error_on_max_iterations {
+            return Err(LoopError::MaxIterationsExceeded(self.config.max_iterations));
+        }
+        Ok(LoopResult {
+            content: last_content.unwrap_or_default(),
+            tool_calls_executed,
+            iterations: self.config.max_iterations,
+            stopped_early: true,
+            budget_exhausted: false,
+            usage,
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::provider::{
+        CompletionResponse, FunctionCall, ResponsesRequest, ResponsesResponse, ToolCall, Usage,
+    };
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Provider that replays scripted responses and records requests
+    pub(crate) struct MockProvider {
+        responses: Mutex<Vec<CompletionResponse>>,
+        pub(crate) requests: Mutex<Vec<CompletionRequest>>,
+    }
+
+    impl MockProvider {
+        pub(crate) fn new(mut responses: Vec<CompletionResponse>) -> Self {
+            responses.reverse();
+            Self {
+                responses: Mutex::new(responses),
+                requests: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        async fn complete(
+            &self,
+            request: CompletionRequest,
+        ) -> Result<CompletionResponse, ProviderError> {
+            self.requests.lock().unwrap().push(request);
+            self.responses
+                .lock()
+                .unwrap()
+                .pop()
+                .ok_or_else(|| ProviderError::InvalidResponse("No scripted response".to_string()))
+        }
+
+        async fn create_response(
+            &self,
+            _request: ResponsesRequest,
+        ) -> Result<ResponsesResponse, ProviderError> {
+            unimplemented!("not used by the agent loop")
+        }
+    }
+
+    pub(crate) fn text_response(content: &str) -> CompletionResponse {
+        CompletionResponse {
+            content: Some(content.to_string()),
+            tool_calls: None,
+            finish_reason: "stop".to_string(),
+            usage: Usage::default(),
+            system_fingerprint: None,
+            logprobs: None,
+            choices: Vec::new(),
+            refusal: None,
+            model: None,
+        }
+    }
+
+    pub(crate) fn tool_call_response(calls: &[(&str, &str, &str)]) -> CompletionResponse {
+        CompletionResponse {
+            content: None,
+            tool_calls: Some(
+                calls
+                    .iter()
+                    .map(|(id, name, arguments)| ToolCall {
+                        id: id.to_string(),
+                        call_type: "function".to_string(),
+                        function: FunctionCall {
+                            name: name.to_string(),
+                            arguments: arguments.to_string(),
+                        },
+                    })
+                    .collect(),
+            ),
+            finish_reason: "tool_calls".to_string(),
+            usage: Usage::default(),
+            system_fingerprint: None,
+            logprobs: None,
+            choices: Vec::new(),
+            refusal: None,
+            model: None,
+        }
+    }
+
+    fn request() -> CompletionRequest {
+        CompletionRequest::new(vec![Message::user("What is 6 * 7?")])
+    }
+
+    #[tokio::test]
+    async fn test_run_without_tool_calls() {
+        let provider = MockProvider::new(vec![text_response("42")]);
+        let registry = ToolRegistry::with_builtins();
+        let result = AgentLoop::new(&provider, &registry, LoopConfig::default())
+            .run(request())
+            .await
+            .unwrap();
+        assert_eq!(result.content, "42");
+        assert_eq!(result.iterations, 1);
+        assert!(!result.stopped_early);
+        assert!(result.tool_calls_executed.is_empty());
+
+        let requests = provider.requests.lock().unwrap();
+        assert_eq!(
+            requests[0].tools.as_ref().unwrap().len(),
+            registry.names().len()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tools_disabled_sends_no_tools_and_ignores_calls() {
+        let mut response =
+            tool_call_response(&[("call_1", "calculate", r#"{"expression": "6 * 7"}"#)]);
+        response.content = Some("About 42".to_string());
+        let provider = MockProvider::new(vec![response, text_response("unused")]);
+        let registry = ToolRegistry::with_builtins();
+        let config = LoopConfig {
+            tools_disabled: true,
+            ..LoopConfig::default()
+        };
+        let result = AgentLoop::new(&provider, &registry, config)
+            .run(request().with_tool_choice(ToolChoice::Required))
+            .await
+            .unwrap();
+        assert_eq!(result.content, "About 42");
+        assert_eq!(result.iterations, 1);
+        assert!(result.tool_calls_executed.is_empty());
+
+        let requests = provider.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].tools.is_none());
+        assert!(requests[0].tool_choice.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_run_executes_tool_calls() {
+        let provider = MockProvider::new(vec![
+            tool_call_response(&[("call_1", "calculate", r#"{"expression": "6 * 7"}"#)]),
+            text_response("The answer is 42"),
+        ]);
+        let registry = ToolRegistry::with_builtins();
+        let result = AgentLoop::new(&provider, &registry, LoopConfig::default())
+            .run(request())
+            .await
+            .unwrap();
+
+        assert_eq!(result.content, "The answer is 42");
+        assert_eq!(result.iterations, 2);
+        assert_eq!(result.tool_calls_executed.len(), 1);
+        assert_eq!(result.tool_calls_executed[0].output, "42");
+        assert!(!result.tool_calls_executed[0].is_error);
+
+        // Second request carries the assistant tool call and the tool result
+        let requests = provider.requests.lock().unwrap();
+        let messages = &requests[1].messages;
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1].role, Role::Assistant);
+        assert_eq!(messages[2].role, Role::Tool);
+        assert_eq!(messages[2].tool_call_id.as_deref(), Some("call_1"));
+        assert_eq!(messages[2].text_content().as_deref(), Some("42"));
+    }
+
+    #[tokio::test]
+    async fn test_scratchpad_lasts_for_one_run() {
+        let write = r#"{"operation": "write", "key": "draft", "value": "v1"}"#;
+        let read = r#"{"operation": "read", "key": "draft"}"#;
+        let provider = MockProvider::new(vec![
+            tool_call_response(&[("call_1", "scratchpad", write)]),
+            tool_call_response(&[("call_2", "scratchpad", read)]),
+            text_response("done"),
+            tool_call_response(&[("call_3", "scratchpad", read)]),
+            text_response("done"),
+        ]);
+        let registry = ToolRegistry::with_builtins();
+        let agent = AgentLoop::new(&provider, &registry, LoopConfig::default());
+
+        let result = agent.run(request()).await.unwrap();
+        assert_eq!(result.tool_calls_executed[1].output, "v1");
+        // The next run starts with an empty scratchpad
+        let result = agent.run(request()).await.unwrap();
+        assert!(result.tool_calls_executed[0].is_error);
+    }
+
+    #[tokio::test]
+    async fn test_forced_tool_choice_applies_to_first_round_only() {
+        for (choice, later) in [
+            (ToolChoice::Required, ToolChoice::Auto),
+            (
+                ToolChoice::Function("calculate".to_string()),
+                ToolChoice::Auto,
+            ),
+            (ToolChoice::None, ToolChoice::None),
+        ] {
+            let provider = MockProvider::new(vec![
+                tool_call_response(&[("call_1", "calculate", r#"{"expression": "6 * 7"}"#)]),
+                text_response("42"),
+            ]);
+            let registry = ToolRegistry::with_builtins();
+            AgentLoop::new(&provider, &registry, LoopConfig::default())
+                .run(request().with_tool_choice(choice.clone()))
+                .await
+                .unwrap();
+
+            let requests = provider.requests.lock().unwrap();
+            assert_eq!(requests[0].tool_choice, Some(choice));
+            assert_eq!(requests[1].tool_choice, Some(later));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_traces_tool_calls_in_order() {
+        let provider = MockProvider::new(vec![
+            tool_call_response(&[("call_1", "calculate", r#"{"expression": "2 + 2"}"#)]),
+            tool_call_response(&[("call_2", "calculate", r#"{"expression": "3 * 3"}"#)]),
+            text_response("4 and 9"),
+        ]);
+        let registry = ToolRegistry::with_builtins();
+        let trace = Mutex::new(Vec::new());
+        let result = AgentLoop::new(&provider, &registry, LoopConfig::default())
+            .on_tool_call(|call| {
+                trace
+                    .lock()
+                    .unwrap()
+                    .push(serde_json::to_value(call).unwrap());
+            })
+            .run(request())
+            .await
+            .unwrap();
+
+        let trace = trace.into_inner().unwrap();
+        assert_eq!(trace.len(), 2);
+        assert_eq!(trace[0]["id"], "call_1");
+        assert_eq!(trace[0]["iteration"], 1);
+        assert_eq!(trace[0]["output"], "4");
+        assert_eq!(trace[1]["id"], "call_2");
+        assert_eq!(trace[1]["iteration"], 2);
+        assert_eq!(trace[1]["output"], "9");
+        assert_eq!(result.iterations, 3);
+        assert_eq!(result.tool_calls_executed.len(), 2);
+    }
+
+    /// Tool that sleeps before echoing its name
+    struct SlowTool(&'static str);
+
+    #[async_trait]
+    impl crate::tools::ToolExecutor for SlowTool {
+        fn definition(&self) -> crate::provider::Tool {
+            crate::provider::Tool::function(self.0, "Slow tool", serde_json::json!({}))
+        }
+
+        async fn execute(&self, _arguments: &str) -> Result<String, crate::tools::ToolError> {
+            tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+            Ok(self.0.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_executes_tool_calls_concurrently() {
+        let provider = MockProvider::new(vec![
+            tool_call_response(&[("call_1", "slow_a", "{}"), ("call_2", "slow_b", "{}")]),
+            text_response("done"),
+        ]);
+        let mut registry = ToolRegistry::new();
+        registry.register(SlowTool("slow_a"));
+        registry.register(SlowTool("slow_b"));
+
+        let started = std::time::Instant::now();
+        let result = AgentLoop::new(&provider, &registry, LoopConfig::default())
+            .run(request())
+            .await
+            .unwrap();
+        assert!(started.elapsed() < std::time::Duration::from_millis(600));
+
+        // Results keep the requested order
+        let outputs: Vec<_> = result
+            .tool_calls_executed
+            .iter()
+            .map(|c| (c.id.as_str(), c.output.as_str()))
+            .collect();
+        assert_eq!(outputs, vec![("call_1", "slow_a"), ("call_2", "slow_b")]);
+
+        let requests = provider.requests.lock().unwrap();
+        let messages = &requests[1].messages;
+        assert_eq!(messages[2].tool_call_id.as_deref(), Some("call_1"));
+        assert_eq!(messages[3].tool_call_id.as_deref(), Some("call_2"));
+    }
+
+    #[tokio::test]
+    async fn test_run_reports_tool_errors_to_model() {
+        let provider = MockProvider::new(vec![
+            tool_call_response(&[("call_1", "missing_tool", "{}")]),
+            text_response("Sorry"),
+        ]);
+        let registry = ToolRegistry::with_builtins();
+        let result = AgentLoop::new(&provider, &registry, LoopConfig::default())
+            .run(request())
+            .await
+            .unwrap();
+
+        assert!(result.tool_calls_executed[0].is_error);
+        assert!(result.tool_calls_executed[0]
+            .output
+            .contains("Unknown tool: missing_tool"));
+    }
+
+    /// Provider script that never stops calling tools
+    fn endless_tool_calls() -> MockProvider {
+        let mut partial = tool_call_response(&[("call_2", "calculate", r#"{"expression": "2"}"#)]);
+        partial.content = Some("Still working".to_string());
+        MockProvider::new(vec![
+            tool_call_response(&[("call_1", "calculate", r#"{"expression": "1"}"#)]),
+            partial,
+            tool_call_response(&[("call_3", "calculate", r#"{"expression": "3"}"#)]),
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_run_max_iterations_returns_partial_result() {
+        let provider = endless_tool_calls();
+        let registry = ToolRegistry::with_builtins();
+        let config = LoopConfig {
+            max_iterations: 3,
+            ..LoopConfig::default()
+        };
+        let result = AgentLoop::new(&provider, &registry, config)
+            .run(request())
+            .await
+            .unwrap();
+        assert!(result.stopped_early);
+        assert_eq!(result.iterations, 3);
+        assert_eq!(result.content, "Still working");
+        assert_eq!(result.tool_calls_executed.len(), 3);
+    }
+
+    fn with_usage(mut response: CompletionResponse, total_tokens: u32) -> CompletionResponse {
+        response.usage = Usage {
+            prompt_tokens: total_tokens - 10,
+            completion_tokens: 10,
+            total_tokens,
+        };
+        response
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_when_token_budget_exhausted() {
+        let mut second = tool_call_response(&[("call_2", "calculate", r#"{"expression": "2"}"#)]);
+        second.content = Some("Halfway".to_string());
+        let provider = MockProvider::new(vec![
+            with_usage(
+                tool_call_response(&[("call_1", "calculate", r#"{"expression": "1"}"#)]),
+                100,
+            ),
+            with_usage(second, 150),
+            with_usage(text_response("never requested"), 100),
+        ]);
+        let registry = ToolRegistry::with_builtins();
+        let config = LoopConfig {
+            token_budget: Some(200),
+            ..LoopConfig::default()
+        };
+        let result = AgentLoop::new(&provider, &registry, config)
+            .run(request())
+            .await
+            .unwrap();
+
+        assert!(result.budget_exhausted);
+        assert!(result.stopped_early);
+        assert_eq!(result.iterations, 2);
+        assert_eq!(result.content, "Halfway");
+        assert_eq!(result.usage.total_tokens, 250);
+        assert_eq!(result.usage.completion_tokens, 20);
+        assert_eq!(result.tool_calls_executed.len(), 1);
+        assert_eq!(provider.requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_run_accumulates_usage_within_budget() {
+        let provider = MockProvider::new(vec![
+            with_usage(
+                tool_call_response(&[("call_1", "calculate", r#"{"expression": "1"}"#)]),
+                50,
+            ),
+            with_usage(text_response("1"), 60),
+        ]);
+        let registry = ToolRegistry::with_builtins();
+        let config = LoopConfig {
+            token_budget: Some(200),
+            ..LoopConfig::default()
+        };
+        let result = AgentLoop::new(&provider, &registry, config)
+            .run(request())
+            .await
+            .unwrap();
+        assert!(!result.budget_exhausted);
+        assert_eq!(result.usage.total_tokens, 110);
+    }
+
+    #[tokio::test]
+    async fn test_run_max_iterations_exceeded() {
+        let provider = endless_tool_calls();
+        let registry = ToolRegistry::with_builtins();
+        let config = LoopConfig {
+            max_iterations: 2,
+            error_on_max_iterations: true,
+            ..LoopConfig::default()
+        };
+        let err = AgentLoop::new(&provider, &registry, config)
+            .run(request())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LoopError::MaxIterationsExceeded(2)));
+    }
+
+    fn texts(messages: &[Message]) -> Vec<String> {
+        messages
+            .iter()
+            .map(|m| m.text_content().unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn test_trim_history_keeps_system_and_tool_pairs() {
+        let call = tool_call_response(&[("call_1", "calculate", r#"{"expression": "1"}"#)]);
+        let mut messages = vec![
+            Message::system("Be brief."),
+            Message::user("First question, quite long"),
+            Message::assistant_tool_calls(None, call.tool_calls.unwrap()),
+            Message::tool_result("call_1", "1"),
+            Message::user("Second"),
+        ];
+
+        // Under the limit nothing is dropped
+        assert_eq!(trim_history(&mut messages.clone(), 1000), 0);
+
+        let removed = trim_history(&mut messages, 30);
+        assert_eq!(removed, 3);
+        assert_eq!(texts(&messages), vec!["Be brief.", "Second"]);
+
+        // The newest message is kept even when it alone exceeds the limit
+        assert_eq!(trim_history(&mut messages, 1), 0);
+        assert_eq!(messages.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_run_trims_oldest_messages_to_context_limit() {
+        let provider = MockProvider::new(vec![
+            tool_call_response(&[("call_1", "calculate", r#"{"expression": "6 * 7"}"#)]),
+            text_response("42"),
+        ]);
+        let registry = ToolRegistry::with_builtins();
+        let config = LoopConfig {
+            context_limit: Some(30),
+            ..LoopConfig::default()
+        };
+        let request = CompletionRequest::new(vec![
+            Message::system("Use tools."),
+            Message::user("An old question that no longer fits"),
+            Message::assistant("An old answer"),
+            Message::user("What is 6 * 7?"),
+        ]);
+        let result = AgentLoop::new(&provider, &registry, config)
+            .run(request)
+            .await
+            .unwrap();
+        assert_eq!(result.content, "42");
+
+        let requests = provider.requests.lock().unwrap();
+        assert_eq!(
+            texts(&requests[0].messages),
+            vec!["Use tools.", "What is 6 * 7?"]
+        );
+        // The system prompt survives; the tool call stays paired with its result
+        let second = &requests[1].messages;
+        assert_eq!(second[0].role, Role::System);
+        assert_eq!(second.last().unwrap().role, Role::Tool);
+        assert!(second[second.len() - 2].tool_calls.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_run_cancelled_stops_before_next_iteration() {
+        let provider = endless_tool_calls();
+        let registry = ToolRegistry::with_builtins();
+        let config = LoopConfig::default();
+        let cancellation = config.cancellation.clone();
+        let err = AgentLoop::new(&provider, &registry, config)
+            .on_tool_call(move |_| cancellation.cancel())
+            .run(request())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LoopError::Provider(ProviderError::Cancelled)));
+        assert_eq!(provider.requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_run_cancelled_aborts_pending_completion() {
+        struct HangingProvider;
+
+        #[async_trait]
+        impl Provider for HangingProvider {
+            async fn complete(
+                &self,
+                _request: CompletionRequest,
+            ) -> Result<CompletionResponse, ProviderError> {
+                std::future::pending().await
+            }
+
+            async fn create_response(
+                &self,
+                _request: ResponsesRequest,
+            ) -> Result<ResponsesResponse, ProviderError> {
+                unimplemented!("not used by the agent loop")
+            }
+        }
+
+        let registry = ToolRegistry::with_builtins();
+        let config = LoopConfig::default();
+        let cancellation = config.cancellation.clone();
+        let agent = AgentLoop::new(&HangingProvider, &registry, config);
+        let (result, _) = tokio::join!(agent.run(request()), async { cancellation.cancel() });
+        assert!(matches!(
+            result,
+            Err(LoopError::Provider(ProviderError::Cancelled))
+        ));
+    }
+}
@@ -1,2 +1,12 @@
+pub mod context;
+pub mod conversation;
+pub mod front_matter;
 pub mod generate;
 pub mod image;
+pub mod image_input;
+pub mod include;
+pub mod r#loop;
+pub mod remote;
+pub mod render;
+pub mod summarize;
+pub mod vars;
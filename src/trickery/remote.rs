@@ -0,0 +1,171 @@
+// Remote templates: an `http://` or `https://` input is fetched instead of read from disk.
+// The body is capped at MAX_TEMPLATE_BYTES and the request at the global --timeout, else
+// FETCH_TIMEOUT_SECS. Transport
+// errors are returned as ProviderError::Http so they get the formatter's network hints.
+// Includes are rejected: a fetched template must not pull local files into the prompt.
+
+use std::error::Error;
+use std::time::Duration;
+use tracing::debug;
+
+use super::include::has_includes;
+use crate::provider::ProviderError;
+
+/// Largest template body accepted from a URL
+pub const MAX_TEMPLATE_BYTES: usize = 1024 * 1024;
+const FETCH_TIMEOUT_SECS: u64 = 30;
+
+/// Whether the input names a remote template
+pub fn is_template_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+/// Fetch the template at `url`, giving up after `timeout` (default: 30s)
+pub async fn fetch_template(
+    url: &str,
+    timeout: Option<Duration>,
+) -> Result<String, Box<dyn Error>> {
+    let timeout = timeout.unwrap_or(Duration::from_secs(FETCH_TIMEOUT_SECS));
+    fetch(url, MAX_TEMPLATE_BYTES, timeout).await
+}
+
+async fn fetch(url: &str, max_bytes: usize, timeout: Duration) -> Result<String, Box<dyn Error>> {
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(ProviderError::Http)?;
+    let mut response = client.get(url).send().await.map_err(ProviderError::Http)?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!(
+            "Failed to fetch template '{}': HTTP {}",
+            url,
+            status.as_u16()
+        )
+        .into());
+    }
+    let too_large = || format!("Template at '{}' is larger than {} bytes", url, max_bytes);
+    if response
+        .content_length()
+        .is_some_and(|len| len > max_bytes as u64)
+    {
+        return Err(too_large().into());
+    }
+    // Content-Length may be missing or wrong; enforce the limit while reading
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(ProviderError::Http)? {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(too_large().into());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    let content =
+        String::from_utf8(body).map_err(|_| format!("Template at '{}' is not valid UTF-8", url))?;
+    if has_includes(&content) {
+        return Err(format!(
+            "Template at '{}' uses {{{{include}}}}, which is only supported for local files",
+            url
+        )
+        .into());
+    }
+    debug!(url, bytes = content.len(), "loaded remote template");
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Server;
+
+    #[test]
+    fn test_is_template_url() {
+        assert!(is_template_url("https://example.com/prompts/a.md"));
+        assert!(is_template_url("http://localhost:8080/a.md"));
+        assert!(!is_template_url("prompts/a.md"));
+        assert!(!is_template_url("Summarize https://example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_template() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/prompts/greet.md")
+            .with_body("---\nmodel: gpt-5.2\n---\nHello {{ name }}")
+            .create_async()
+            .await;
+
+        let content = fetch_template(&format!("{}/prompts/greet.md", server.url()), None)
+            .await
+            .unwrap();
+        assert_eq!(content, "---\nmodel: gpt-5.2\n---\nHello {{ name }}");
+    }
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[tokio::test]
+    async fn test_fetch_template_timeout() {
+        let mut server = Server::new_async().await;
+        let _slow = server
+            .mock("GET", "/slow.md")
+            .with_body_from_request(|_| {
+                std::thread::sleep(Duration::from_millis(500));
+                b"Hello".to_vec()
+            })
+            .create_async()
+            .await;
+
+        let err = fetch_template(
+            &format!("{}/slow.md", server.url()),
+            Some(Duration::from_millis(100)),
+        )
+        .await
+        .unwrap_err();
+        match err.downcast_ref::<ProviderError>() {
+            Some(ProviderError::Http(e)) => assert!(e.is_timeout()),
+            other => panic!("Expected Http timeout error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_template_errors() {
+        let mut server = Server::new_async().await;
+        let _missing = server
+            .mock("GET", "/missing.md")
+            .with_status(404)
+            .create_async()
+            .await;
+        let _large = server
+            .mock("GET", "/large.md")
+            .with_body("x".repeat(64))
+            .create_async()
+            .await;
+        let _include = server
+            .mock("GET", "/include.md")
+            .with_body(r#"{{include "/etc/passwd"}}"#)
+            .create_async()
+            .await;
+
+        let err = fetch(&format!("{}/missing.md", server.url()), 1024, SECOND)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().ends_with("HTTP 404"), "{}", err);
+
+        let err = fetch(&format!("{}/large.md", server.url()), 32, SECOND)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("larger than 32 bytes"), "{}", err);
+
+        let err = fetch(&format!("{}/include.md", server.url()), 1024, SECOND)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("only supported for local files"));
+
+        // Nothing listens on port 9 (discard); the failure is a network error
+        let err = fetch("http://127.0.0.1:9/a.md", 1024, SECOND)
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<ProviderError>().is_some());
+    }
+}
@@ -0,0 +1,169 @@
+// Markdown to plain text for generate --render plain.
+// A walk over pulldown-cmark events: headings, paragraphs and quotes become plain blocks,
+// list items are normalized to "- " (ordered ones keep their number), and emphasis markers
+// are dropped. Code (fenced blocks and inline spans) is kept verbatim; links keep their URL
+// in parentheses.
+
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+/// Flatten markdown: drop heading/quote markers, fences and rules, normalize bullets to "- ",
+/// and remove inline emphasis, code backticks and link syntax.
+pub fn markdown_to_plain(markdown: &str) -> String {
+    let mut plain = Plain::default();
+    for event in Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH) {
+        match event {
+            Event::Start(
+                Tag::Paragraph
+                | Tag::Heading { .. }
+                | Tag::BlockQuote(_)
+                | Tag::CodeBlock(_)
+                | Tag::HtmlBlock,
+            ) => plain.block(),
+            Event::Start(Tag::List(start)) => {
+                if plain.lists.is_empty() {
+                    plain.block();
+                }
+                plain.lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                plain.lists.pop();
+            }
+            Event::Start(Tag::Item) => plain.item(),
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                plain.links.push((dest_url.to_string(), plain.out.len()));
+            }
+            Event::End(TagEnd::Link) => {
+                if let Some((url, start)) = plain.links.pop() {
+                    let label = &plain.out[start..];
+                    if !url.is_empty() && label != url && label != url.trim_start_matches("mailto:")
+                    {
+                        plain.out.push_str(&format!(" ({})", url));
+                    }
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                let len = plain.out.trim_end().len();
+                plain.out.truncate(len);
+            }
+            Event::Text(text) | Event::Code(text) | Event::Html(text) | Event::InlineHtml(text) => {
+                plain.text(&text)
+            }
+            Event::SoftBreak | Event::HardBreak => plain.text("\n"),
+            _ => {}
+        }
+    }
+    plain.out.trim_end().to_string()
+}
+
+/// Output being built, with the open lists (next item number for ordered ones) and links
+#[derive(Default)]
+struct Plain {
+    out: String,
+    lists: Vec<Option<u64>>,
+    /// Destination and label start of each open link
+    links: Vec<(String, usize)>,
+    /// Right after a list marker, where the item's first block starts on the same line
+    item_start: bool,
+}
+
+impl Plain {
+    /// Start a block: a blank line between top-level blocks, a line break inside lists
+    fn block(&mut self) {
+        if std::mem::take(&mut self.item_start) || self.out.is_empty() {
+            return;
+        }
+        let len = self.out.trim_end().len();
+        self.out.truncate(len);
+        if self.lists.is_empty() {
+            self.out.push_str("\n\n");
+        } else {
+            self.out.push('\n');
+            self.out.push_str(&"  ".repeat(self.lists.len()));
+        }
+    }
+
+    /// Start a list item on its own line, indented by nesting depth
+    fn item(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+        self.out
+            .push_str(&"  ".repeat(self.lists.len().saturating_sub(1)));
+        match self.lists.last_mut() {
+            Some(Some(number)) => {
+                self.out.push_str(&format!("{}. ", number));
+                *number += 1;
+            }
+            _ => self.out.push_str("- "),
+        }
+        self.item_start = true;
+    }
+
+    /// Append text, indenting continuation lines to the enclosing list item
+    fn text(&mut self, text: &str) {
+        self.item_start = false;
+        let indent = format!("\n{}", "  ".repeat(self.lists.len()));
+        self.out.push_str(&text.replace('\n', &indent));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headings_and_rules() {
+        assert_eq!(
+            markdown_to_plain(
+                "# Title\n\nIntro text.\n\n---\n\n## Section ##\nBody\n\nSetext\n======"
+            ),
+            "Title\n\nIntro text.\n\nSection\n\nBody\n\nSetext"
+        );
+        // Not headings
+        assert_eq!(markdown_to_plain("#hashtag and #1"), "#hashtag and #1");
+    }
+
+    #[test]
+    fn test_lists() {
+        assert_eq!(
+            markdown_to_plain("* **One**\n* two\n  - nested `x`\n\n3. third\n4. fourth"),
+            "- One\n- two\n  - nested x\n\n3. third\n4. fourth"
+        );
+        // Loose items keep their continuation blocks under the marker
+        assert_eq!(
+            markdown_to_plain("- first\n\n  more\n\n  ```\n  code\n  ```\n- second"),
+            "- first\n  more\n  code\n- second"
+        );
+    }
+
+    #[test]
+    fn test_inline_code_and_fences() {
+        assert_eq!(
+            markdown_to_plain("Run `cargo test` or ``a ` b``.\n\n```rust\nlet x = *y;\n\n# not a heading\n```\nDone"),
+            "Run cargo test or a ` b.\n\nlet x = *y;\n\n# not a heading\n\nDone"
+        );
+    }
+
+    #[test]
+    fn test_emphasis() {
+        assert_eq!(
+            markdown_to_plain("*em*, _em_, **strong**, __strong__, ***both***, ~~gone~~"),
+            "em, em, strong, strong, both, gone"
+        );
+        // Left alone: arithmetic, snake_case, escapes, unclosed markers
+        assert_eq!(
+            markdown_to_plain("2 * 3 * 4 uses snake_case_name, \\*literal\\*, *open"),
+            "2 * 3 * 4 uses snake_case_name, *literal*, *open"
+        );
+    }
+
+    #[test]
+    fn test_links_and_quotes() {
+        assert_eq!(
+            markdown_to_plain(
+                "> See [the docs](https://example.com/docs \"Docs\") and <https://example.com>.\n> ![logo](logo.png) [https://a.b](https://a.b) [x]"
+            ),
+            "See the docs (https://example.com/docs) and https://example.com.\nlogo https://a.b [x]"
+        );
+    }
+}
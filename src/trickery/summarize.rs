@@ -0,0 +1,285 @@
+// Map-reduce summarization for inputs larger than the context window.
+// The text is split into chunks of at most `chunk_size` characters, each overlapping the
+// previous one by `overlap` characters so sentences cut at a boundary appear whole in one of
+// them. Chunks end at a paragraph, line or word break when one is in the second half of the
+// window. Each chunk is summarized (map), then the summaries are combined in one request
+// (reduce); input that fits one chunk is summarized directly. Chunks are summarized one after
+// another, which keeps request order and rate limits predictable.
+
+use crate::provider::{CompletionRequest, Message, Provider, Usage};
+use thiserror::Error;
+
+/// Default chunk size in characters (roughly 3,000 tokens of English text)
+pub const DEFAULT_CHUNK_SIZE: usize = 12_000;
+/// Default overlap between consecutive chunks in characters
+pub const DEFAULT_CHUNK_OVERLAP: usize = 500;
+
+const CHUNK_PROMPT: &str = "Summarize the following text. Keep key facts, names, numbers and \
+conclusions; answer with the summary only.";
+const PART_PROMPT: &str = "The following text is part {part} of {parts} of a longer document. \
+Summarize it, keeping key facts, names, numbers and conclusions; answer with the summary only.";
+const COMBINE_PROMPT: &str = "The following are summaries of consecutive parts of one document. \
+Combine them into a single coherent summary of the whole document, removing repetition; answer \
+with the summary only.";
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ChunkError {
+    #[error("Chunk size must be greater than 0")]
+    EmptyChunk,
+    #[error("Chunk overlap ({overlap}) must be smaller than the chunk size ({chunk_size})")]
+    OverlapTooLarge { overlap: usize, chunk_size: usize },
+}
+
+/// Settings for summarize
+#[derive(Debug, Clone)]
+pub struct SummarizeConfig {
+    pub model: Option<String>,
+    /// Maximum characters per chunk
+    pub chunk_size: usize,
+    /// Characters repeated from the end of the previous chunk
+    pub overlap: usize,
+    /// Extra instructions appended to every prompt (e.g. focus or length)
+    pub instructions: Option<String>,
+}
+
+impl Default for SummarizeConfig {
+    fn default() -> Self {
+        Self {
+            model: None,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            overlap: DEFAULT_CHUNK_OVERLAP,
+            instructions: None,
+        }
+    }
+}
+
+/// Final summary with the number of chunks and the tokens spent on all requests
+#[derive(Debug, Clone)]
+pub struct SummaryOutput {
+    pub summary: String,
+    pub chunks: usize,
+    pub usage: Usage,
+}
+
+/// Split `text` into chunks of at most `chunk_size` characters, consecutive chunks sharing
+/// `overlap` characters. Whitespace-only text gives no chunks.
+pub fn chunk_text(text: &str, chunk_size: usize, overlap: usize) -> Result<Vec<&str>, ChunkError> {
+    if chunk_size == 0 {
+        return Err(ChunkError::EmptyChunk);
+    }
+    if overlap >= chunk_size {
+        return Err(ChunkError::OverlapTooLarge {
+            overlap,
+            chunk_size,
+        });
+    }
+    let text = text.trim();
+    // Byte offsets of every char, plus the end, so windows are counted in characters
+    let offsets: Vec<usize> = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(text.len()))
+        .collect();
+    let chars = offsets.len() - 1;
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < chars {
+        let mut end = (start + chunk_size).min(chars);
+        if end < chars {
+            end = break_point(text, &offsets, start + chunk_size / 2, end).unwrap_or(end);
+        }
+        let chunk = text[offsets[start]..offsets[end]].trim();
+        if !chunk.is_empty() {
+            chunks.push(chunk);
+        }
+        if end == chars {
+            break;
+        }
+        // Always move forward, even when the break point left less than `overlap` behind
+        start = end.saturating_sub(overlap).max(start + 1);
+    }
+    Ok(chunks)
+}
+
+/// Char index just after the last paragraph, line or word break in `from..to`, preferring
+/// the coarsest break available
+fn break_point(text: &str, offsets: &[usize], from: usize, to: usize) -> Option<usize> {
+    let window = &text[offsets[from]..offsets[to]];
+    let byte = ["\n\n", "\n", " "]
+        .iter()
+        .find_map(|separator| window.rfind(separator).map(|i| i + separator.len()))?;
+    let byte = offsets[from] + byte;
+    offsets.binary_search(&byte).ok().filter(|&end| end > from)
+}
+
+/// Summarize `text`: one request when it fits a chunk, otherwise one per chunk and a final
+/// request combining their summaries
+pub async fn summarize(
+    provider: &dyn Provider,
+    text: &str,
+    config: &SummarizeConfig,
+) -> Result<SummaryOutput, Box<dyn std::error::Error>> {
+    let chunks = chunk_text(text, config.chunk_size, config.overlap)?;
+    if chunks.is_empty() {
+        return Err("Nothing to summarize: the input is empty".into());
+    }
+
+    let mut usage = Usage::default();
+    if chunks.len() == 1 {
+        let summary = complete(provider, config, CHUNK_PROMPT, chunks[0], &mut usage).await?;
+        return Ok(SummaryOutput {
+            summary,
+            chunks: 1,
+            usage,
+        });
+    }
+
+    let mut summaries = Vec::with_capacity(chunks.len());
+    for (index, chunk) in chunks.iter().enumerate() {
+        let prompt = PART_PROMPT
+            .replace("{part}", &(index + 1).to_string())
+            .replace("{parts}", &chunks.len().to_string());
+        summaries.push(complete(provider, config, &prompt, chunk, &mut usage).await?);
+    }
+    let combined = summaries
+        .iter()
+        .enumerate()
+        .map(|(index, summary)| format!("Part {}:\n{}", index + 1, summary))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let summary = complete(provider, config, COMBINE_PROMPT, &combined, &mut usage).await?;
+    Ok(SummaryOutput {
+        summary,
+        chunks: chunks.len(),
+        usage,
+    })
+}
+
+/// One summarization request: the instructions, then the text
+async fn complete(
+    provider: &dyn Provider,
+    config: &SummarizeConfig,
+    prompt: &str,
+    text: &str,
+    usage: &mut Usage,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut instructions = prompt.to_string();
+    if let Some(ref extra) = config.instructions {
+        instructions = format!("{}\n{}", instructions, extra);
+    }
+    let mut request =
+        CompletionRequest::new(vec![Message::user(format!("{}\n\n{}", instructions, text))]);
+    if let Some(ref model) = config.model {
+        request = request.with_model(model.clone());
+    }
+    let response = provider.complete(request).await?;
+    response.check_refusal()?;
+    *usage += &response.usage;
+    Ok(response.content.unwrap_or_default().trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trickery::r#loop::tests::{text_response, MockProvider};
+
+    #[test]
+    fn test_chunk_text_fits_one_chunk() {
+        assert_eq!(
+            chunk_text("  short text \n", 100, 10).unwrap(),
+            ["short text"]
+        );
+        assert_eq!(chunk_text("exactly10!", 10, 2).unwrap(), ["exactly10!"]);
+        assert!(chunk_text(" \n ", 10, 2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_chunk_text_boundaries_and_overlap() {
+        // Hard cuts without whitespace: windows of 4 chars, each repeating the last 1
+        assert_eq!(
+            chunk_text("abcdefghij", 4, 1).unwrap(),
+            ["abcd", "defg", "ghij"]
+        );
+        // No overlap: chunks tile the text
+        assert_eq!(chunk_text("abcdefgh", 4, 0).unwrap(), ["abcd", "efgh"]);
+        // Counted in characters, not bytes
+        assert_eq!(chunk_text("ééééé", 3, 0).unwrap(), ["ééé", "éé"]);
+    }
+
+    #[test]
+    fn test_chunk_text_prefers_breaks() {
+        let text = "one two three four five six";
+        let chunks = chunk_text(text, 12, 0).unwrap();
+        assert_eq!(chunks, ["one two", "three four", "five six"]);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 12));
+
+        // A paragraph break wins over a later space
+        let chunks = chunk_text("first para\n\nsecond para here", 20, 0).unwrap();
+        assert_eq!(chunks, ["first para", "second para here"]);
+    }
+
+    #[test]
+    fn test_chunk_text_rejects_bad_sizes() {
+        assert_eq!(chunk_text("text", 0, 0), Err(ChunkError::EmptyChunk));
+        assert_eq!(
+            chunk_text("text", 4, 4),
+            Err(ChunkError::OverlapTooLarge {
+                overlap: 4,
+                chunk_size: 4
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_summarize_single_chunk() {
+        let provider = MockProvider::new(vec![text_response("Short summary")]);
+        let output = summarize(&provider, "A short document.", &SummarizeConfig::default())
+            .await
+            .unwrap();
+
+        assert_eq!(output.summary, "Short summary");
+        assert_eq!(output.chunks, 1);
+        assert_eq!(provider.requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_summarize_map_reduce() {
+        let provider = MockProvider::new(vec![
+            text_response("S1"),
+            text_response("S2"),
+            text_response("S3"),
+            text_response("Final"),
+        ]);
+        let config = SummarizeConfig {
+            model: Some("gpt-5-nano".to_string()),
+            chunk_size: 10,
+            overlap: 0,
+            instructions: Some("Be brief.".to_string()),
+        };
+        let output = summarize(&provider, "aaaa bbbb cccc dddd eeee", &config)
+            .await
+            .unwrap();
+
+        assert_eq!(output.summary, "Final");
+        assert_eq!(output.chunks, 3);
+        let requests = provider.requests.lock().unwrap();
+        let prompts: Vec<String> = requests
+            .iter()
+            .map(|r| r.messages[0].text_content().unwrap_or_default())
+            .collect();
+        assert_eq!(prompts.len(), 4);
+        assert!(prompts[0].contains("part 1 of 3") && prompts[0].ends_with("aaaa bbbb"));
+        assert!(prompts[2].ends_with("eeee"));
+        // Exactly one reduce request, with every part summary
+        let reduce: Vec<_> = prompts
+            .iter()
+            .filter(|p| p.starts_with(COMBINE_PROMPT))
+            .collect();
+        assert_eq!(reduce.len(), 1);
+        assert!(reduce[0].contains("Be brief.\n\nPart 1:\nS1\n\nPart 2:\nS2\n\nPart 3:\nS3"));
+        assert!(requests
+            .iter()
+            .all(|r| r.model.as_deref() == Some("gpt-5-nano")));
+    }
+}
@@ -0,0 +1,185 @@
+// Template variables loaded from files and the command line.
+// A vars file is a flat JSON or YAML object (`.json` is parsed as JSON,
+// anything else as YAML). Values keep their types; `--var` flags override file entries.
+// `--var` values that parse as JSON (`5`, `true`, `[1, 2]`) keep their JSON type,
+// everything else is taken as a plain string. A `--jsonl` line is one JSON object of variables.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Parse a `--var KEY=VALUE` flag. Valid JSON values keep their type, others stay strings.
+pub fn parse_key_val(s: &str) -> Result<(String, Value), String> {
+    let (key, raw) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid KEY=VALUE: no `=` found in `{}`", s))?;
+    let value = serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()));
+    Ok((key.to_string(), value))
+}
+
+/// Load template variables from a JSON or YAML file containing a top-level object
+pub fn parse_vars_file(path: &Path) -> Result<HashMap<String, Value>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read vars file '{}': {}", path.display(), e))?;
+
+    let is_json = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+
+    let parsed: Result<HashMap<String, Value>, String> = if is_json {
+        serde_json::from_str(&content).map_err(|e| e.to_string())
+    } else if content.trim().is_empty() {
+        Ok(HashMap::new())
+    } else {
+        serde_yaml::from_str(&content).map_err(|e| e.to_string())
+    };
+
+    parsed.map_err(|e| {
+        format!(
+            "Invalid vars file '{}': expected a key/value object ({})",
+            path.display(),
+            e
+        )
+    })
+}
+
+/// Parse one `--jsonl` input line: a JSON object of variables
+pub fn parse_vars_line(line: &str) -> Result<HashMap<String, Value>, String> {
+    serde_json::from_str(line).map_err(|e| format!("expected a JSON object of variables ({})", e))
+}
+
+/// Combine variables from an optional vars file with `--var` flags.
+/// Flags take precedence over file entries.
+pub fn collect_variables(
+    vars_file: Option<&Path>,
+    cli_vars: &[(String, Value)],
+) -> Result<HashMap<String, Value>, String> {
+    let mut variables = match vars_file {
+        Some(path) => parse_vars_file(path)?,
+        None => HashMap::new(),
+    };
+    variables.extend(cli_vars.iter().cloned());
+    Ok(variables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn vars_file(suffix: &str, content: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::with_suffix(suffix).unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_parse_key_val_integer() {
+        assert_eq!(
+            parse_key_val("count=5").unwrap(),
+            ("count".to_string(), json!(5))
+        );
+    }
+
+    #[test]
+    fn test_parse_key_val_float() {
+        assert_eq!(
+            parse_key_val("ratio=0.75").unwrap(),
+            ("ratio".to_string(), json!(0.75))
+        );
+    }
+
+    #[test]
+    fn test_parse_key_val_bool() {
+        assert_eq!(
+            parse_key_val("enabled=true").unwrap(),
+            ("enabled".to_string(), json!(true))
+        );
+    }
+
+    #[test]
+    fn test_parse_key_val_string() {
+        assert_eq!(
+            parse_key_val("name=John Doe").unwrap(),
+            ("name".to_string(), json!("John Doe"))
+        );
+        // Not valid JSON numbers, kept verbatim
+        assert_eq!(parse_key_val("id=007").unwrap().1, json!("007"));
+        assert_eq!(parse_key_val("v=1.2.3").unwrap().1, json!("1.2.3"));
+        assert_eq!(parse_key_val("empty=").unwrap().1, json!(""));
+    }
+
+    #[test]
+    fn test_parse_vars_file_json() {
+        let file = vars_file(".json", r#"{"name": "Alice", "topic": "Q3 review"}"#);
+        let vars = parse_vars_file(file.path()).unwrap();
+        assert_eq!(vars["name"], json!("Alice"));
+        assert_eq!(vars["topic"], json!("Q3 review"));
+    }
+
+    #[test]
+    fn test_parse_vars_file_yaml() {
+        let file = vars_file(".yaml", "name: Bob\ntopic: release notes\n");
+        let vars = parse_vars_file(file.path()).unwrap();
+        assert_eq!(vars["name"], json!("Bob"));
+        assert_eq!(vars["topic"], json!("release notes"));
+    }
+
+    #[test]
+    fn test_parse_vars_file_preserves_types() {
+        let file = vars_file(".yml", "count: 5\nratio: 0.5\nenabled: true\n");
+        let vars = parse_vars_file(file.path()).unwrap();
+        assert_eq!(vars["count"], json!(5));
+        assert_eq!(vars["ratio"], json!(0.5));
+        assert_eq!(vars["enabled"], json!(true));
+
+        let file = vars_file(".json", r#"{"count": 5, "enabled": false}"#);
+        let vars = parse_vars_file(file.path()).unwrap();
+        assert_eq!(vars["count"], json!(5));
+        assert_eq!(vars["enabled"], json!(false));
+    }
+
+    #[test]
+    fn test_parse_vars_line() {
+        let vars = parse_vars_line(r#"{"name": "Ada", "age": 36}"#).unwrap();
+        assert_eq!(vars["name"], json!("Ada"));
+        assert_eq!(vars["age"], json!(36));
+        assert!(parse_vars_line(r#"["Ada"]"#).is_err());
+        assert!(parse_vars_line("{name: Ada}").is_err());
+    }
+
+    #[test]
+    fn test_parse_vars_file_rejects_non_object() {
+        let file = vars_file(".json", r#"["a", "b"]"#);
+        let err = parse_vars_file(file.path()).unwrap_err();
+        assert!(err.contains("Invalid vars file"));
+
+        let file = vars_file(".yaml", "- a\n- b\n");
+        assert!(parse_vars_file(file.path()).is_err());
+    }
+
+    #[test]
+    fn test_parse_vars_file_missing() {
+        let err = parse_vars_file(Path::new("/nonexistent/vars.json")).unwrap_err();
+        assert!(err.contains("Failed to read vars file"));
+    }
+
+    #[test]
+    fn test_collect_variables_cli_overrides_file() {
+        let file = vars_file(".yaml", "name: Bob\ntopic: release notes\n");
+        let cli_vars = vec![("name".to_string(), json!("Alice"))];
+        let vars = collect_variables(Some(file.path()), &cli_vars).unwrap();
+        assert_eq!(vars["name"], json!("Alice"));
+        assert_eq!(vars["topic"], json!("release notes"));
+    }
+
+    #[test]
+    fn test_collect_variables_without_file() {
+        let cli_vars = vec![("name".to_string(), json!("Alice"))];
+        let vars = collect_variables(None, &cli_vars).unwrap();
+        assert_eq!(vars.len(), 1);
+    }
+}